@@ -0,0 +1,76 @@
+//! Compares deserializing a large `CreateRequest` through a `Reader` over the aggregated body,
+//! as the JSON filters used to, with `serde_json::from_slice` on the collected bytes, and
+//! collecting a medium body chunk by chunk into a fresh `Vec` with collecting it into a
+//! `PooledBuffer`.
+//!
+//! `cargo bench --bench body_parsing` measures, `cargo test` runs it once.
+
+use bytes::buf::BufExt;
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion};
+use example_rust_json_input_validation::buffer_pool::PooledBuffer;
+use example_rust_json_input_validation::config::ValidationConfig;
+use example_rust_json_input_validation::filters::parse_json_body;
+use example_rust_json_input_validation::models::CreateRequest;
+use std::hint::black_box;
+
+fn payload(pets: usize) -> Bytes {
+    let pets: Vec<_> = (0..pets)
+        .map(|i| serde_json::json!({ "name": format!("pet number {}", i) }))
+        .collect();
+    let body = serde_json::json!({
+        "email": "chip@example.com",
+        "address": { "street": "warpstreet", "streetNo": 1 },
+        "pets": pets,
+    });
+    Bytes::from(serde_json::to_vec(&body).unwrap())
+}
+
+fn body_parsing(c: &mut Criterion) {
+    // Lists over `pets_max` are turned away while they're read.
+    ValidationConfig {
+        pets_max: 3000,
+        ..ValidationConfig::DEFAULT
+    }
+    .install();
+    let body = payload(3000);
+    let mut group = c.benchmark_group("create_request_3000_pets");
+    group.bench_function("aggregate_from_reader", |b| {
+        b.iter(|| {
+            let des = &mut serde_json::Deserializer::from_reader(body.clone().reader());
+            let request: CreateRequest = serde_path_to_error::deserialize(des).unwrap();
+            black_box(request)
+        })
+    });
+    group.bench_function("bytes_from_slice", |b| {
+        b.iter(|| black_box(parse_json_body::<CreateRequest>(&body).unwrap()))
+    });
+    group.finish();
+
+    // 100 pets in the 8 KiB chunks hyper reads bodies in.
+    let body = payload(100);
+    let chunks: Vec<&[u8]> = body.chunks(8 * 1024).collect();
+    let mut group = c.benchmark_group("collect_100_pets");
+    group.bench_function("fresh_vec", |b| {
+        b.iter(|| {
+            let mut buffer = Vec::new();
+            chunks
+                .iter()
+                .for_each(|chunk| buffer.extend_from_slice(chunk));
+            black_box(parse_json_body::<CreateRequest>(&buffer).unwrap())
+        })
+    });
+    group.bench_function("pooled_buffer", |b| {
+        b.iter(|| {
+            let mut buffer = PooledBuffer::take();
+            chunks
+                .iter()
+                .for_each(|chunk| buffer.extend_from_slice(chunk));
+            black_box(parse_json_body::<CreateRequest>(&buffer).unwrap())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, body_parsing);
+criterion_main!(benches);
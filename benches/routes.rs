@@ -0,0 +1,171 @@
+//! End-to-end latency of the create routes, driven through the same filters the server runs
+//! with `warp::test` instead of a TCP port, for each way of handling the body, across payload
+//! sizes, for a payload that fails validation and for one with far more pets than allowed, which
+//! every route must turn away while reading the list. Allocations per request are printed before
+//! the measurements, counted by the global allocator of this benchmark, after checking that
+//! every route accepts the valid payloads and turns away the others.
+//!
+//! `cargo bench --bench routes` measures, `cargo test` runs every case once.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use example_rust_json_input_validation::config::ValidationConfig;
+use example_rust_json_input_validation::error::DefaultErrorFormatter;
+use example_rust_json_input_validation::health::Readiness;
+use example_rust_json_input_validation::routes_with_state;
+use example_rust_json_input_validation::store::CreateStore;
+use example_rust_json_input_validation::validation::ValidationContext;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// The routes compared, by how they handle the body. `create-validator` is a dry run, so
+/// repeating the same email doesn't turn into a conflict.
+const ROUTES: &[(&str, &str)] = &[
+    ("body_json", "/create-basic"),
+    ("bytes_from_slice", "/create-path"),
+    ("negotiated_validated", "/create-validator?dry_run=true"),
+    ("bytes_validated", "/create-fast"),
+];
+
+const PET_COUNTS: &[usize] = &[1, 100, 5000];
+
+/// Twenty times `pets_max`, each pet only a few bytes.
+const OVERSIZED_PETS: usize = 100_000;
+
+/// A distinct valid pet name for every `i`, pet names take letters only.
+fn pet_name(mut i: usize) -> String {
+    let mut name = String::from("pet ");
+    loop {
+        name.push((b'a' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            return name;
+        }
+    }
+}
+
+fn payload(pets: usize, email: &str) -> Bytes {
+    let pets: Vec<_> = (0..pets)
+        .map(|i| serde_json::json!({ "name": pet_name(i) }))
+        .collect();
+    let body = serde_json::json!({
+        "email": email,
+        "address": { "street": "warpstreet", "streetNo": 1 },
+        "pets": pets,
+    });
+    Bytes::from(serde_json::to_vec(&body).unwrap())
+}
+
+async fn post<F>(routes: &F, path: &str, body: Bytes) -> warp::http::Response<Bytes>
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json")
+        .header("content-length", body.len())
+        .body(body)
+        .reply(routes)
+        .await
+}
+
+fn routes_benchmark(c: &mut Criterion) {
+    std::env::set_var("BODY_LIMIT_BYTES", "16777216");
+    std::env::set_var("DECODED_BODY_LIMIT_BYTES", "16777216");
+    std::env::set_var("RATE_LIMIT_PER_MINUTE", u32::MAX.to_string());
+    ValidationConfig {
+        pets_max: 5000,
+        request_max_string_bytes: usize::MAX,
+        ..ValidationConfig::DEFAULT
+    }
+    .install();
+    let routes = routes_with_state(
+        Arc::new(CreateStore::default()),
+        Arc::new(ValidationContext::from_env()),
+        Arc::new(Readiness::new(&[])),
+        Arc::new(DefaultErrorFormatter),
+    );
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    let mut cases = Vec::new();
+    for &pets in PET_COUNTS {
+        cases.push((
+            format!("{}_pets", pets),
+            payload(pets, "chip@example.com"),
+            true,
+        ));
+    }
+    cases.push(("invalid_100_pets".to_string(), payload(100, "chip"), false));
+    let oversized = payload(OVERSIZED_PETS, "chip@example.com");
+    for (name, path) in ROUTES {
+        let response = rt.block_on(post(&routes, path, oversized.clone()));
+        let body = String::from_utf8_lossy(response.body());
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "{}: {}",
+            name,
+            body
+        );
+        assert!(body.contains("at most `5000`"), "{}: {}", name, body);
+    }
+    cases.push((
+        format!("oversized_{}k_pets", OVERSIZED_PETS / 1000),
+        oversized,
+        false,
+    ));
+
+    for (case, body, valid) in &cases {
+        for (name, path) in ROUTES {
+            let status = rt.block_on(post(&routes, path, body.clone())).status();
+            assert_eq!(status.is_success(), *valid, "{} {}: {}", case, name, status);
+            let before = ALLOCATIONS.load(Ordering::Relaxed);
+            rt.block_on(post(&routes, path, body.clone()));
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+            println!(
+                "{:<18} {:<22} {} {:>8} allocations",
+                case, name, status, allocations
+            );
+        }
+    }
+
+    for (case, body, _) in &cases {
+        let mut group = c.benchmark_group(format!("routes/{}", case));
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        for (name, path) in ROUTES {
+            group.bench_with_input(BenchmarkId::from_parameter(name), path, |b, path| {
+                b.iter(|| black_box(rt.block_on(post(&routes, path, body.clone()))))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, routes_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+//! Buffers that request bodies are collected into, kept per worker thread and reused across
+//! requests, so a warm worker collects a body without allocating and without reallocating as the
+//! chunks come in.
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
+
+const DEFAULT_POOL_SIZE: usize = 16;
+const DEFAULT_MAX_BUFFER_BYTES: usize = 256 * 1024;
+
+/// How many buffers each worker thread keeps and how large they may be.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolLimits {
+    /// Buffers kept per thread, `0` turns pooling off.
+    pub buffers: usize,
+    /// Buffers that grew beyond this many bytes are dropped when they're returned, so a single
+    /// giant request doesn't pin its memory for the life of the worker.
+    pub max_buffer_bytes: usize,
+}
+
+impl PoolLimits {
+    /// Read once from `BODY_BUFFER_POOL_SIZE` (16 by default) and `BODY_BUFFER_MAX_BYTES`
+    /// (256 KiB by default).
+    pub fn get() -> Self {
+        static LIMITS: OnceLock<PoolLimits> = OnceLock::new();
+        *LIMITS.get_or_init(|| {
+            let var = |name: &str, default: usize| {
+                std::env::var(name)
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(default)
+            };
+            PoolLimits {
+                buffers: var("BODY_BUFFER_POOL_SIZE", DEFAULT_POOL_SIZE),
+                max_buffer_bytes: var("BODY_BUFFER_MAX_BYTES", DEFAULT_MAX_BUFFER_BYTES),
+            }
+        })
+    }
+}
+
+thread_local! {
+    static BUFFERS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A buffer of the pool, handed back when it's dropped. A request may move between worker
+/// threads while its body arrives, then the buffer goes to the pool of the thread that drops it.
+/// Nothing is ever shared between threads, so there's no lock to contend for.
+#[derive(Debug)]
+pub struct PooledBuffer {
+    buffer: Vec<u8>,
+}
+
+impl PooledBuffer {
+    /// An empty buffer from the pool of the current thread, or a fresh `Vec` if the pool is
+    /// empty, e.g. because more requests are in flight on this thread than it keeps buffers.
+    pub fn take() -> Self {
+        let buffer = BUFFERS
+            .try_with(|buffers| buffers.borrow_mut().pop())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        PooledBuffer { buffer }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let limits = PoolLimits::get();
+        if buffer.capacity() == 0 || buffer.capacity() > limits.max_buffer_bytes {
+            return;
+        }
+        buffer.clear();
+        // Threads that are shutting down have dropped their pool already, the buffer goes with it
+        let _ = BUFFERS.try_with(|buffers| {
+            let mut buffers = buffers.borrow_mut();
+            if buffers.len() < limits.buffers {
+                buffers.push(buffer);
+            }
+        });
+    }
+}
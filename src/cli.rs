@@ -0,0 +1,149 @@
+//! The `validate` command of the binary, which runs a file through the same deserialization and
+//! validation as `POST /create-validator` without starting the server, e.g. to check fixtures in
+//! CI.
+
+use crate::error::{error_response, Error, ErrorConfig, Language};
+use crate::filters::parse_and_validate_create;
+use crate::models::{ExplainQuery, LenientQuery};
+use crate::validation::ValidationContext;
+use std::io::Read;
+use warp::http::Method;
+
+/// The document deserialized and passed validation.
+pub const EXIT_VALID: i32 = 0;
+/// The document deserialized but failed validation.
+pub const EXIT_INVALID: i32 = 1;
+/// The document didn't deserialize, or the command couldn't read it.
+pub const EXIT_MALFORMED: i32 = 2;
+
+const USAGE: &str = "usage: validate [--type create|create-v2] (--stdin | <path>)";
+
+/// The request types `--type` selects, one per version of the create API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestType {
+    Create,
+    CreateV2,
+}
+
+impl RequestType {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "create" => Some(RequestType::Create),
+            "create-v2" => Some(RequestType::CreateV2),
+            _ => None,
+        }
+    }
+
+    /// The `X-Api-Version` this type is sent as.
+    fn api_version(self) -> &'static str {
+        match self {
+            RequestType::Create => "1",
+            RequestType::CreateV2 => "2",
+        }
+    }
+}
+
+/// What validating a document printed and how the command exits.
+#[derive(Debug)]
+pub struct Report {
+    pub exit_code: i32,
+    /// The validated input, or the `ErrorResponse` the server would have answered with.
+    pub output: String,
+}
+
+/// Validates `bytes` as a JSON `request_type`.
+pub async fn validate_document(
+    request_type: RequestType,
+    bytes: &[u8],
+    ctx: &ValidationContext,
+    config: ErrorConfig,
+) -> Report {
+    let result = parse_and_validate_create(
+        request_type.api_version(),
+        None,
+        ExplainQuery::default(),
+        LenientQuery::default(),
+        bytes,
+        ctx,
+    )
+    .await;
+    match result {
+        Ok((_, input)) => Report {
+            exit_code: EXIT_VALID,
+            output: serde_json::to_string(&input).expect("create input serializes"),
+        },
+        Err(err) => {
+            let exit_code = match err.find::<Error>() {
+                Some(Error::ValidationError(_)) | Some(Error::ExplainedValidationError(_)) => {
+                    EXIT_INVALID
+                }
+                _ => EXIT_MALFORMED,
+            };
+            let (_, response) = error_response(
+                &err,
+                &Method::POST,
+                Language::En,
+                config.detail_level,
+                "cli",
+                config,
+            );
+            Report {
+                exit_code,
+                output: serde_json::to_string(&response).expect("error response serializes"),
+            }
+        }
+    }
+}
+
+/// Runs the command with the arguments after `validate`, printing the report to stdout and
+/// usage errors to stderr, and returns the exit code.
+pub async fn run(args: impl IntoIterator<Item = String>) -> i32 {
+    let mut request_type = RequestType::Create;
+    let mut input = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--type" => match args.next().as_deref().and_then(RequestType::parse) {
+                Some(parsed) => request_type = parsed,
+                None => return usage_error("--type must be create or create-v2"),
+            },
+            "--stdin" if input.is_none() => input = Some(None),
+            path if input.is_none() && !path.starts_with("--") => {
+                input = Some(Some(path.to_string()))
+            }
+            other => return usage_error(&format!("unexpected argument {}", other)),
+        }
+    }
+    let bytes = match input {
+        Some(Some(path)) => std::fs::read(&path).map_err(|e| format!("can't read {}: {}", path, e)),
+        Some(None) => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map(|_| bytes)
+                .map_err(|e| format!("can't read stdin: {}", e))
+        }
+        None => return usage_error("missing --stdin or a file to validate"),
+    };
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_MALFORMED;
+        }
+    };
+    let report = validate_document(
+        request_type,
+        &bytes,
+        &ValidationContext::from_env(),
+        ErrorConfig::from_env(),
+    )
+    .await;
+    println!("{}", report.output);
+    report.exit_code
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n{}", message, USAGE);
+    EXIT_MALFORMED
+}
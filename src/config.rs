@@ -0,0 +1,320 @@
+//! Server and validation configuration read from the environment at startup.
+
+use rustls::internal::pemfile;
+use rustls::{NoClientAuth, PrivateKey, SignatureScheme};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use thiserror::Error;
+
+const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const DEFAULT_PORT: u16 = 8080;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("invalid APP_HOST `{0}`: expected an IP address like 127.0.0.1 or 0.0.0.0")]
+    InvalidHost(String),
+    #[error("invalid APP_PORT `{0}`: expected a port number between 0 and 65535")]
+    InvalidPort(String),
+    #[error("TLS_CERT_PATH and TLS_KEY_PATH have to be set together")]
+    IncompleteTls,
+    #[error("failed to read {path}: {reason}")]
+    UnreadableTlsFile { path: String, reason: String },
+    #[error("invalid TLS certificate or key: {0}")]
+    InvalidTls(String),
+    #[error("failed to read VALIDATION_CONFIG_FILE {path}: {reason}")]
+    UnreadableValidationConfig { path: String, reason: String },
+    #[error("invalid validation config in {path}: {reason}")]
+    InvalidValidationConfig { path: String, reason: String },
+    #[error("failed to read API_KEYS_FILE {path}: {reason}")]
+    UnreadableApiKeysFile { path: String, reason: String },
+}
+
+/// Where the server listens, set via `APP_HOST` and `APP_PORT`. Port 0 binds to a free port,
+/// which `crate::bind` reports back. With `TLS_CERT_PATH` and `TLS_KEY_PATH` set it serves
+/// HTTPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    pub tls: Option<TlsConfig>,
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut config = Self::parse(
+            std::env::var("APP_HOST").ok().as_deref(),
+            std::env::var("APP_PORT").ok().as_deref(),
+        )?;
+        config.tls = match (
+            std::env::var_os("TLS_CERT_PATH"),
+            std::env::var_os("TLS_KEY_PATH"),
+        ) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path: cert_path.into(),
+                key_path: key_path.into(),
+            }),
+            (None, None) => None,
+            _ => return Err(ConfigError::IncompleteTls),
+        };
+        Ok(config)
+    }
+
+    /// Parses the raw variable values, with unset variables falling back to `127.0.0.1:8080`.
+    pub fn parse(host: Option<&str>, port: Option<&str>) -> Result<Self, ConfigError> {
+        let host = match host {
+            Some(host) => host
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::InvalidHost(host.to_string()))?,
+            None => DEFAULT_HOST,
+        };
+        let port = match port {
+            Some(port) => port
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::InvalidPort(port.to_string()))?,
+            None => DEFAULT_PORT,
+        };
+        Ok(ServerConfig {
+            host,
+            port,
+            tls: None,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+/// The bounds the validators of `CreateRequest` and `CreateRequestV2` enforce and
+/// `GET /schema/create` publishes. Read from the JSON file named by `VALIDATION_CONFIG_FILE`,
+/// where every key is optional and defaults to the value in `ValidationConfig::DEFAULT`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    pub street_min_length: usize,
+    pub street_max_length: usize,
+    pub street_no_min: usize,
+    pub pet_name_min_length: usize,
+    pub pet_name_max_length: usize,
+    pub pets_min: usize,
+    pub pets_max: usize,
+    /// The most bytes a street may take UTF-8 encoded, on top of `street_max_length`.
+    pub street_max_bytes: usize,
+    pub pet_name_max_bytes: usize,
+    pub email_max_bytes: usize,
+    /// The most bytes of any other string field.
+    pub string_max_bytes: usize,
+    /// The most bytes all string fields of a request may take together.
+    pub request_max_string_bytes: usize,
+    /// Turns away numbers sent as strings, like `"streetNo": "4"`, instead of coercing them.
+    pub strict_numbers: bool,
+}
+
+static VALIDATION_CONFIG: RwLock<ValidationConfig> = RwLock::new(ValidationConfig::DEFAULT);
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig::DEFAULT
+    }
+}
+
+impl ValidationConfig {
+    pub const DEFAULT: ValidationConfig = ValidationConfig {
+        street_min_length: 2,
+        street_max_length: 10,
+        street_no_min: 1,
+        pet_name_min_length: 3,
+        pet_name_max_length: 20,
+        pets_min: 1,
+        pets_max: 10,
+        street_max_bytes: 256,
+        pet_name_max_bytes: 256,
+        email_max_bytes: 320,
+        string_max_bytes: 4096,
+        request_max_string_bytes: 16384,
+        strict_numbers: false,
+    };
+
+    /// Loads `VALIDATION_CONFIG_FILE`, or the defaults when it isn't set.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        match std::env::var_os("VALIDATION_CONFIG_FILE") {
+            Some(path) => Self::load(&PathBuf::from(path)),
+            None => Ok(ValidationConfig::DEFAULT),
+        }
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, ConfigError> {
+        let contents =
+            std::fs::read(path).map_err(|e| ConfigError::UnreadableValidationConfig {
+                path: path.display().to_string(),
+                reason: e.to_string(),
+            })?;
+        let invalid = |reason: String| ConfigError::InvalidValidationConfig {
+            path: path.display().to_string(),
+            reason,
+        };
+        let config: ValidationConfig =
+            serde_json::from_slice(&contents).map_err(|e| invalid(e.to_string()))?;
+        config.check().map_err(invalid)?;
+        Ok(config)
+    }
+
+    /// Rejects bounds no value could satisfy: a maximum of zero or below its minimum.
+    pub fn check(&self) -> Result<(), String> {
+        let bounds = [
+            ("street", self.street_min_length, self.street_max_length),
+            (
+                "pet_name",
+                self.pet_name_min_length,
+                self.pet_name_max_length,
+            ),
+        ];
+        for (name, min, max) in bounds {
+            if max == 0 {
+                return Err(format!("{}_max_length must be greater than 0", name));
+            }
+            if min > max {
+                return Err(format!(
+                    "{name}_min_length ({min}) is greater than {name}_max_length ({max})"
+                ));
+            }
+        }
+        if self.pets_max == 0 {
+            return Err("pets_max must be greater than 0".to_string());
+        }
+        if self.pets_min > self.pets_max {
+            return Err(format!(
+                "pets_min ({}) is greater than pets_max ({})",
+                self.pets_min, self.pets_max
+            ));
+        }
+        let byte_limits = [
+            ("street_max_bytes", self.street_max_bytes),
+            ("pet_name_max_bytes", self.pet_name_max_bytes),
+            ("email_max_bytes", self.email_max_bytes),
+            ("string_max_bytes", self.string_max_bytes),
+            ("request_max_string_bytes", self.request_max_string_bytes),
+        ];
+        for (name, max) in byte_limits {
+            if max == 0 {
+                return Err(format!("{} must be greater than 0", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The configuration the validators currently use.
+    pub fn current() -> Self {
+        *VALIDATION_CONFIG
+            .read()
+            .expect("validation config lock poisoned")
+    }
+
+    /// Makes this the configuration the validators use from now on. Done once at startup by the
+    /// binary; embedders can swap it at any time.
+    pub fn install(self) {
+        *VALIDATION_CONFIG
+            .write()
+            .expect("validation config lock poisoned") = self;
+    }
+}
+
+/// The PEM files of the certificate chain and private key to serve HTTPS with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// A certificate chain and private key that were checked to belong together, for `crate::bind_tls`.
+pub struct TlsIdentity {
+    pub(crate) cert: Vec<u8>,
+    pub(crate) key: Vec<u8>,
+}
+
+/// The schemes offered to the key when checking it against the certificate, with the webpki
+/// algorithm verifying each.
+const KEY_CHECK_SCHEMES: &[(SignatureScheme, &webpki::SignatureAlgorithm)] = &[
+    (
+        SignatureScheme::ECDSA_NISTP256_SHA256,
+        &webpki::ECDSA_P256_SHA256,
+    ),
+    (
+        SignatureScheme::ECDSA_NISTP384_SHA384,
+        &webpki::ECDSA_P384_SHA384,
+    ),
+    (SignatureScheme::ED25519, &webpki::ED25519),
+    (
+        SignatureScheme::RSA_PSS_SHA256,
+        &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    ),
+];
+
+impl TlsConfig {
+    /// Reads both files and checks what warp would otherwise only find out by panicking once it
+    /// binds: that they parse, and that the key is the one of the first certificate.
+    pub fn load(&self) -> Result<TlsIdentity, ConfigError> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path).map_err(|e| ConfigError::UnreadableTlsFile {
+                path: path.display().to_string(),
+                reason: e.to_string(),
+            })
+        };
+        let invalid = |reason: &str| ConfigError::InvalidTls(reason.to_string());
+        let cert = read(&self.cert_path)?;
+        let key = read(&self.key_path)?;
+
+        let certs = pemfile::certs(&mut cert.as_slice())
+            .map_err(|()| invalid("the certificate file is not PEM"))?;
+        let leaf = certs
+            .first()
+            .ok_or_else(|| invalid("the certificate file contains no certificate"))?;
+        let private_key = match pemfile::pkcs8_private_keys(&mut key.as_slice()) {
+            Ok(mut keys) if !keys.is_empty() => keys.remove(0),
+            _ => match pemfile::rsa_private_keys(&mut key.as_slice()) {
+                Ok(mut keys) if !keys.is_empty() => keys.remove(0),
+                _ => {
+                    return Err(invalid(
+                        "the key file contains no PKCS#8 or RSA private key",
+                    ))
+                }
+            },
+        };
+        check_key_matches(&leaf.0, &private_key)?;
+        rustls::ServerConfig::new(NoClientAuth::new())
+            .set_single_cert(certs, private_key)
+            .map_err(|e| ConfigError::InvalidTls(e.to_string()))?;
+        Ok(TlsIdentity { cert, key })
+    }
+}
+
+/// Signs a message with `key` and verifies the signature with the public key in `cert`.
+fn check_key_matches(cert: &[u8], key: &PrivateKey) -> Result<(), ConfigError> {
+    let invalid = |reason: &str| ConfigError::InvalidTls(reason.to_string());
+    let cert = webpki::EndEntityCert::from(cert)
+        .map_err(|e| ConfigError::InvalidTls(format!("the certificate doesn't parse: {:?}", e)))?;
+    let key = rustls::sign::any_supported_type(key)
+        .map_err(|()| invalid("the private key is of an unsupported type"))?;
+    let schemes: Vec<SignatureScheme> = KEY_CHECK_SCHEMES
+        .iter()
+        .map(|(scheme, _)| *scheme)
+        .collect();
+    let signer = key
+        .choose_scheme(&schemes)
+        .ok_or_else(|| invalid("the private key supports none of the checked signature schemes"))?;
+    let message = b"example-rust-json-input-validation key check";
+    let signature = signer
+        .sign(message)
+        .map_err(|e| ConfigError::InvalidTls(e.to_string()))?;
+    let algorithm = KEY_CHECK_SCHEMES
+        .iter()
+        .find(|(scheme, _)| *scheme == signer.get_scheme())
+        .map(|(_, algorithm)| *algorithm)
+        .expect("signers only pick offered schemes");
+    cert.verify_signature(algorithm, message, &signature)
+        .map_err(|_| invalid("the private key doesn't belong to the certificate"))
+}
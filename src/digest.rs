@@ -0,0 +1,188 @@
+//! Verification of request bodies against the `Digest` header of RFC 3230 and the legacy
+//! `Content-MD5`, for clients that sign what they send.
+
+use crate::error::{reject, Error};
+use crate::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha512};
+
+pub const DIGEST: &str = "Digest";
+pub const CONTENT_MD5: &str = "Content-MD5";
+
+/// Whether bodies have to come with a digest, set via `DIGEST_REQUIRED=true`.
+pub fn digest_required_from_env() -> bool {
+    std::env::var("DIGEST_REQUIRED").as_deref() == Ok("true")
+}
+
+/// The digest algorithms of the `Digest` header that are checked. Others are ignored, as RFC 3230
+/// asks for, as long as one of these is there too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Sha256,
+    Sha512,
+    Md5,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("sha-256") {
+            Some(Algorithm::Sha256)
+        } else if name.eq_ignore_ascii_case("sha-512") {
+            Some(Algorithm::Sha512)
+        } else if name.eq_ignore_ascii_case("md5") {
+            Some(Algorithm::Md5)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha-256",
+            Algorithm::Sha512 => "sha-512",
+            Algorithm::Md5 => "md5",
+        }
+    }
+
+    fn hash(self, body: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha256 => Sha256::digest(body).to_vec(),
+            Algorithm::Sha512 => Sha512::digest(body).to_vec(),
+            Algorithm::Md5 => md5(body).to_vec(),
+        }
+    }
+}
+
+/// Checks `body`, as it was received and before any `Content-Encoding` is undone, against every
+/// supported digest in the `Digest` header and against `Content-MD5`.
+///
+/// A digest that doesn't match is rejected with `Error::DigestMismatch`, a header that can't be
+/// read or names no supported algorithm with `Error::MalformedDigest`. Without either header the
+/// body passes unless `required`, then it's rejected with `Error::DigestRequired`.
+pub fn verify_body_digest(
+    digest: Option<&str>,
+    content_md5: Option<&str>,
+    body: &[u8],
+    required: bool,
+) -> Result<()> {
+    if digest.is_none() && content_md5.is_none() {
+        return match required {
+            true => Err(reject(Error::DigestRequired)),
+            false => Ok(()),
+        };
+    }
+    if let Some(header) = digest {
+        let mut checked = false;
+        for entry in header.split(',') {
+            let (name, value) = entry.trim().split_once('=').ok_or_else(|| {
+                malformed(
+                    DIGEST,
+                    header,
+                    "expected algorithm=digest pairs like sha-256=...",
+                )
+            })?;
+            if let Some(algorithm) = Algorithm::parse(name.trim()) {
+                verify(DIGEST, algorithm, value.trim(), body)?;
+                checked = true;
+            }
+        }
+        if !checked {
+            return Err(malformed(
+                DIGEST,
+                header,
+                "no supported algorithm, expected sha-256, sha-512 or md5",
+            ));
+        }
+    }
+    if let Some(value) = content_md5 {
+        verify(CONTENT_MD5, Algorithm::Md5, value.trim(), body)?;
+    }
+    Ok(())
+}
+
+fn verify(header: &'static str, algorithm: Algorithm, expected: &str, body: &[u8]) -> Result<()> {
+    let expected = STANDARD
+        .decode(expected)
+        .map_err(|_| malformed(header, expected, "the digest is not base64"))?;
+    let computed = algorithm.hash(body);
+    if expected.len() != computed.len() {
+        return Err(malformed(
+            header,
+            &STANDARD.encode(&expected),
+            &format!(
+                "a {} digest is {} bytes, not {}",
+                algorithm.name(),
+                computed.len(),
+                expected.len()
+            ),
+        ));
+    }
+    if expected != computed {
+        return Err(reject(Error::DigestMismatch {
+            header,
+            algorithm: algorithm.name(),
+            expected: STANDARD.encode(&expected),
+            computed: STANDARD.encode(&computed),
+        }));
+    }
+    Ok(())
+}
+
+fn malformed(header: &'static str, value: &str, reason: &str) -> warp::Rejection {
+    reject(Error::MalformedDigest {
+        header,
+        value: value.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+/// MD5 as of RFC 1321, only here for `Content-MD5`. It's long broken as a signature, but still
+/// catches a body that was cut off or mangled on the way.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (1..=64)
+        .map(|i| ((i as f64).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in message.chunks(64) {
+        let words: Vec<u32> = block
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(SHIFTS[i / 16 * 4 + i % 4]));
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 16];
+    for (bytes, word) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
@@ -0,0 +1,2678 @@
+//! The error type of the service and how rejections are turned into responses.
+
+use crate::filters::{Accept, JSON_MEDIA_TYPE};
+use crate::metrics::Metrics;
+use crate::models::{
+    create_request_validators, invalid_enum_value, FIELD_ORDER, NUMBER_ERRORS, TOO_MANY_ELEMENTS,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use thiserror::Error;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+use warp::http::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use warp::http::Method;
+use warp::path::FullPath;
+use warp::{http::StatusCode, Filter, Rejection};
+
+/// One step of the path to the offending value, e.g. `["pets", 1, "name"]` for `pets[1].name`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PathSegment {
+    /// A struct field or map key.
+    Key(String),
+    /// A position in a list.
+    Index(usize),
+    /// A segment serde could not name, serialized as `null`.
+    Unknown,
+}
+
+impl From<&serde_path_to_error::Segment> for PathSegment {
+    fn from(segment: &serde_path_to_error::Segment) -> Self {
+        use serde_path_to_error::Segment;
+        match segment {
+            Segment::Seq { index } => PathSegment::Index(*index),
+            Segment::Map { key } => PathSegment::Key(key.clone()),
+            Segment::Enum { variant } => PathSegment::Key(variant.clone()),
+            Segment::Unknown => PathSegment::Unknown,
+        }
+    }
+}
+
+/// Where and why serde_json gave up on a body, serialized into the error response as is.
+#[derive(Serialize, Debug, Clone)]
+pub struct JsonErrorDetail {
+    pub(crate) path: String,
+    /// `path` split into keys and list indices.
+    segments: Vec<PathSegment>,
+    /// Missing when deserializing from an already parsed `serde_json::Value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) column: Option<usize>,
+    /// serde_json's error category: `io`, `syntax`, `data` or `eof`.
+    kind: &'static str,
+    #[serde(skip)]
+    message: String,
+    /// serde_json's message without the location.
+    #[serde(skip)]
+    reason: String,
+}
+
+impl JsonErrorDetail {
+    /// `prefix` is prepended to the path for documents embedded in another one, like the
+    /// `payload` of an envelope.
+    fn new(prefix: &str, err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = match (prefix, err.path().to_string()) {
+            ("", path) => path,
+            (prefix, path) if path == "." => prefix.to_string(),
+            (prefix, path) => format!("{}.{}", prefix, path),
+        };
+        let segments = prefix
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .map(|key| PathSegment::Key(key.to_string()))
+            .chain(err.path().iter().map(PathSegment::from))
+            .collect();
+        let inner = err.inner();
+        let kind = match inner.classify() {
+            serde_json::error::Category::Io => "io",
+            serde_json::error::Category::Syntax => "syntax",
+            serde_json::error::Category::Data => "data",
+            serde_json::error::Category::Eof => "eof",
+        };
+        let message = if path == "." {
+            inner.to_string()
+        } else {
+            format!("{}: {}", path, inner)
+        };
+        let location = format!(" at line {} column {}", inner.line(), inner.column());
+        let reason = inner.to_string();
+        let reason = reason
+            .strip_suffix(&location)
+            .unwrap_or(&reason)
+            .to_string();
+        JsonErrorDetail {
+            path,
+            segments,
+            line: Some(inner.line()).filter(|line| *line > 0),
+            column: Some(inner.column()).filter(|column| *column > 0),
+            kind,
+            message,
+            reason,
+        }
+    }
+}
+
+/// Rejects with the `Error` a JSON document failed with, see `From` for
+/// `serde_path_to_error::Error`. `prefix` is prepended to the path as in `JsonErrorDetail::new`.
+pub fn json_rejection(
+    prefix: &str,
+    err: serde_path_to_error::Error<serde_json::Error>,
+) -> Rejection {
+    json_error(prefix, err).into_rejection()
+}
+
+/// `Error::JSONPathError`, except for unknown fields, unknown enum values, malformed dates, lists
+/// over the limit of a `BoundedVec` and the `NUMBER_ERRORS`, which are reported as a `FieldError` on the offending key like those
+/// found by `check_shape`.
+fn json_error(prefix: &str, err: serde_path_to_error::Error<serde_json::Error>) -> Error {
+    let detail = JsonErrorDetail::new(prefix, err);
+    if detail.kind == "data" && detail.reason.starts_with("unknown field `") {
+        return Error::DeserializationErrors(vec![FieldError {
+            field: detail.path,
+            severity: Severity::Error,
+            code: "unknown_field".to_string(),
+            field_errors: vec![format!("unknown_field: {}", detail.reason)],
+            params: serde_json::Map::new(),
+            rejected_value: serde_json::Value::Null,
+            docs_url: None,
+        }]);
+    }
+    if detail.kind == "data" && detail.reason.starts_with("unknown variant `") {
+        // serde lists the variants it expected quoted in backticks after the received value
+        let mut quoted = detail.reason.split('`').skip(1).step_by(2);
+        let value = quoted.next().unwrap_or_default();
+        let allowed: Vec<&str> = quoted.collect();
+        return Error::DeserializationErrors(vec![invalid_enum_value(
+            &detail.path,
+            value,
+            &allowed,
+        )]);
+    }
+    if detail.kind == "data" && detail.reason.starts_with("invalid date `") {
+        let value = detail.reason.split('`').nth(1).unwrap_or_default().into();
+        return Error::DeserializationErrors(vec![FieldError {
+            rejected_value: rejected_value(&detail.path, &value),
+            field: detail.path,
+            severity: Severity::Error,
+            code: "invalid_date".to_string(),
+            field_errors: vec![format!("invalid_date: {}", detail.reason)],
+            params: serde_json::Map::new(),
+            docs_url: None,
+        }]);
+    }
+    if detail.kind == "data" && detail.reason.starts_with(TOO_MANY_ELEMENTS) {
+        let max = detail.reason[TOO_MANY_ELEMENTS.len()..]
+            .split('`')
+            .next()
+            .and_then(|max| max.parse::<usize>().ok());
+        let mut params = serde_json::Map::new();
+        params.insert("max".to_string(), max.into());
+        return Error::DeserializationErrors(vec![FieldError {
+            field: detail.path,
+            severity: Severity::Error,
+            code: "too_many_elements".to_string(),
+            field_errors: vec![format!("too_many_elements: {}", detail.reason)],
+            params,
+            rejected_value: serde_json::Value::Null,
+            docs_url: None,
+        }]);
+    }
+    if detail.kind == "data" {
+        let number_error = NUMBER_ERRORS
+            .iter()
+            .find(|(prefix, _)| detail.reason.starts_with(prefix));
+        if let Some((_, code)) = number_error {
+            let number = detail.reason.split('`').nth(1).unwrap_or_default();
+            let value = serde_json::from_str(number).unwrap_or(serde_json::Value::Null);
+            return Error::DeserializationErrors(vec![FieldError {
+                rejected_value: rejected_value(&detail.path, &value),
+                field: detail.path,
+                severity: Severity::Error,
+                code: code.to_string(),
+                field_errors: vec![format!("{}: {}", code, detail.reason)],
+                params: serde_json::Map::new(),
+                docs_url: None,
+            }]);
+        }
+    }
+    Error::JSONPathError(detail)
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("JSON path error: {}", .0.message)]
+    JSONPathError(JsonErrorDetail),
+    #[error("request body is empty")]
+    EmptyBody,
+    #[error("expected {expected}, got {received}")]
+    InvalidBodyType {
+        expected: &'static str,
+        received: &'static str,
+    },
+    #[error("JSON nesting too deep (max {max})")]
+    JsonTooDeep { max: usize },
+    #[error("unexpected content after the JSON document at byte {offset}")]
+    TrailingContent { offset: usize },
+    #[error("the body is not valid UTF-8: invalid byte sequence at byte {offset}")]
+    InvalidUtf8 { offset: usize },
+    #[error("the body is encoded as {encoding}, JSON bodies must be UTF-8")]
+    UnsupportedCharset { encoding: &'static str },
+    #[error("form path error: {0}")]
+    FormPathError(String),
+    /// The message carries the parser's line and column.
+    #[error("YAML path error: {0}")]
+    YAMLPathError(String),
+    #[error("MessagePack path error: {0}")]
+    MsgPackPathError(String),
+    #[error("validation error: {0}")]
+    ValidationError(ValidationErrors),
+    #[error("validation error: {0}")]
+    ExplainedValidationError(ValidationErrors),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("missing X-Api-Key header")]
+    MissingApiKey,
+    #[error("unknown API key")]
+    UnknownApiKey,
+    #[error("origin {origin} is not allowed to call this API, see CORS_ALLOWED_ORIGINS")]
+    CorsForbidden { origin: String },
+    #[error("method {method} is not allowed for cross-origin requests, only GET, POST and PATCH")]
+    CorsMethodForbidden { method: String },
+    #[error("missing X-Client-Version header, clients need to be at least {minimum}")]
+    MissingClientVersion { minimum: String },
+    #[error("X-Client-Version {received:?} is not a semantic version like {minimum}")]
+    InvalidClientVersion { received: String, minimum: String },
+    #[error("client version {received} is too old, please upgrade to at least {minimum}")]
+    ClientTooOld { received: String, minimum: String },
+    #[error(
+        "unsupported media type {}, expected {}",
+        received.as_deref().unwrap_or("(none)"),
+        supported.join(" or ")
+    )]
+    UnsupportedMediaType {
+        received: Option<String>,
+        supported: &'static [&'static str],
+    },
+    #[error("unsupported Content-Encoding {0:?}, expected gzip, deflate or identity")]
+    UnsupportedContentEncoding(String),
+    #[error(
+        "not acceptable: Accept {received:?} rules out {}, the types this route responds with",
+        supported.join(" and ")
+    )]
+    NotAcceptable {
+        received: String,
+        supported: &'static [&'static str],
+    },
+    #[error("could not decode the {encoding} body: {reason}")]
+    UndecodableBody {
+        encoding: &'static str,
+        reason: String,
+    },
+    #[error("payload too large: the body exceeds the {limit} byte limit of /{route}")]
+    PayloadTooLarge { route: &'static str, limit: u64 },
+    #[error("too many rows: imports are limited to {limit} rows")]
+    TooManyRows { limit: usize },
+    #[error("too many requests, retry in {retry_after_secs} seconds")]
+    TooManyRequests { retry_after_secs: u64 },
+    #[error("request timeout: the request didn't complete within the {limit_ms} ms limit")]
+    Timeout { limit_ms: u64 },
+    #[error(
+        "the body doesn't match its {header} header: expected {algorithm} {expected}, computed {computed}"
+    )]
+    DigestMismatch {
+        header: &'static str,
+        algorithm: &'static str,
+        expected: String,
+        computed: String,
+    },
+    #[error("malformed {header} header: {reason}")]
+    MalformedDigest {
+        header: &'static str,
+        value: String,
+        reason: String,
+    },
+    #[error("missing Digest header, request bodies have to come with a digest like sha-256=...")]
+    DigestRequired,
+    #[error("unknown query parameters: {}", .0.join(", "))]
+    UnknownQueryParams(Vec<String>),
+    #[error(
+        "unsupported API version {received}, supported versions are {}",
+        supported.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    UnsupportedApiVersion {
+        received: String,
+        supported: &'static [u32],
+    },
+    #[error("invalid query string: {0}")]
+    InvalidQuery(String),
+    #[error("{resource} {id} not found")]
+    NotFound { resource: &'static str, id: String },
+    #[error("{field} {value} is already taken")]
+    Conflict { field: &'static str, value: String },
+    #[error("Idempotency-Key must be a UUID, got {0:?}")]
+    InvalidIdempotencyKey(String),
+    #[error("Idempotency-Key {0} was already used for a different request")]
+    IdempotencyKeyReused(uuid::Uuid),
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    #[error("unknown field path {0}, expected a field of a create request like address.street or pets[0].name")]
+    UnknownFieldPath(String),
+    #[error("{field} is only checked by rules on the whole request ({}), validate the whole request instead", .rules.join(", "))]
+    RequiresFullValidation {
+        field: String,
+        rules: &'static [&'static str],
+    },
+    #[error("invalid JSON schema: {0}")]
+    InvalidSchema(String),
+    #[error("data does not match the JSON schema")]
+    SchemaViolation(Vec<FieldError>),
+    #[error("deserialization errors")]
+    DeserializationErrors(Vec<FieldError>),
+    /// Query or path parameters that don't parse, like `?dry_run=yes`.
+    #[error("invalid parameters")]
+    ParameterErrors(Vec<FieldError>),
+}
+
+impl warp::reject::Reject for Error {}
+
+impl Error {
+    pub fn into_rejection(self) -> Rejection {
+        warp::reject::custom(self)
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for Error {
+    fn from(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        json_error("", err)
+    }
+}
+
+impl From<ValidationErrors> for Error {
+    fn from(errors: ValidationErrors) -> Self {
+        Error::ValidationError(errors)
+    }
+}
+
+/// Rejects with anything that converts into an `Error`, so fallible calls read
+/// `.map_err(reject)?`.
+pub fn reject(err: impl Into<Error>) -> Rejection {
+    err.into().into_rejection()
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    message: String,
+    #[serde(flatten)]
+    details: ErrorDetails,
+}
+
+/// An RFC 7807 `application/problem+json` error, for clients that ask for one with `Accept`.
+/// `detail` is the `message` of an `ErrorResponse` and its other members are extension members,
+/// so both formats carry the same information.
+#[derive(Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    #[serde(flatten)]
+    details: ErrorDetails,
+}
+
+/// Everything about an error beyond its message, shared by both error formats.
+#[derive(Serialize)]
+pub struct ErrorDetails {
+    errors: Option<ReportedErrors>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validators: Option<BTreeMap<&'static str, Vec<&'static str>>>,
+    /// The docs of the error as a whole, see `docs_url_for`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docs_url: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    failed_sections: Vec<String>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    json_error: Option<JsonErrorDetail>,
+    /// Quoted back to us to find the request in the logs. Empty without a request.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    request_id: String,
+}
+
+impl ErrorResponse {
+    /// A response listing `errors`, for `pipeline::ApiError`, which has no request to take a
+    /// request ID, language or detail level from.
+    pub(crate) fn without_request(message: String, mut errors: Vec<FieldError>) -> Self {
+        link_docs(&mut errors);
+        ErrorResponse {
+            message,
+            details: ErrorDetails {
+                failed_sections: failed_sections(&errors),
+                errors: Some(ReportedErrors::Full(errors)),
+                validators: None,
+                docs_url: None,
+                json_error: None,
+                request_id: String::new(),
+            },
+        }
+    }
+}
+
+/// The distinct top-level sections of the body (`email`, `address`, `pets`) that have errors, in
+/// the order they first appear in `errors`. Errors on the body as a whole, at the empty path or
+/// from a struct-level rule under `__all__`, are in the section `root`.
+fn failed_sections(errors: &[FieldError]) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for error in errors {
+        let section = match error.field.split(['.', '[']).next().unwrap_or_default() {
+            "" | "__all__" => ROOT_SECTION,
+            section => section,
+        }
+        .to_string();
+        if !sections.contains(&section) {
+            sections.push(section);
+        }
+    }
+    sections
+}
+
+/// The section of errors on the body as a whole.
+const ROOT_SECTION: &str = "root";
+
+const DEFAULT_DOCS_BASE_URL: &str = "/docs/validation";
+
+/// Base URL of the error docs, read once from `DOCS_BASE_URL`.
+fn docs_base_url() -> &'static str {
+    static DOCS_BASE_URL: OnceLock<String> = OnceLock::new();
+    DOCS_BASE_URL.get_or_init(|| {
+        std::env::var("DOCS_BASE_URL").unwrap_or_else(|_| DEFAULT_DOCS_BASE_URL.to_string())
+    })
+}
+
+/// The section of the docs for `code`, e.g. `/docs/validation#length` for `length_min`, or the
+/// docs as a whole for codes `ERROR_DOC_PAGES` has no section for.
+fn docs_url_for(code: &str) -> String {
+    match ERROR_DOC_PAGES
+        .iter()
+        .find(|(documented, _)| *documented == code)
+    {
+        Some((_, page)) => format!("{}#{}", docs_base_url(), page),
+        None => docs_base_url().to_string(),
+    }
+}
+
+/// Links each of `errors` to the docs of its code.
+fn link_docs(errors: &mut [FieldError]) {
+    for error in errors {
+        error.docs_url = Some(docs_url_for(&error.code));
+    }
+}
+
+/// The section of the docs on each stable error code: the codes of `Error::code`, those of field
+/// errors after `stable_code`, and the categories of warp's own rejections. Related codes can
+/// share a section.
+const ERROR_DOC_PAGES: &[(&str, &str)] = &[
+    // Bodies that don't parse
+    ("json_path_error", "malformed-body"),
+    ("form_path_error", "malformed-body"),
+    ("yaml_path_error", "malformed-body"),
+    ("msgpack_path_error", "malformed-body"),
+    ("malformed_body", "malformed-body"),
+    ("json.malformed", "malformed-body"),
+    ("csv.malformed", "malformed-body"),
+    ("json_too_deep", "json-too-deep"),
+    ("trailing_content", "malformed-body"),
+    ("invalid_encoding", "invalid-encoding"),
+    ("digest_mismatch", "digest"),
+    ("digest_malformed", "digest"),
+    ("digest_required", "digest"),
+    ("empty_body", "empty-body"),
+    ("invalid_body_type", "invalid-body-type"),
+    ("undecodable_body", "content-encoding"),
+    ("unsupported_content_encoding", "content-encoding"),
+    ("unsupported_media_type", "unsupported-media-type"),
+    ("not_acceptable", "not-acceptable"),
+    ("deserialization_errors", "deserialization"),
+    ("unknown_field", "unknown-field"),
+    ("duplicate_key", "duplicate-key"),
+    ("invalid_type", "invalid-type"),
+    ("required", "required"),
+    ("invalid_enum_value", "invalid-enum-value"),
+    ("invalid_date", "invalid-date"),
+    ("too_many_elements", "pets-count"),
+    // Validation
+    ("validation_error", "validation"),
+    ("explained_validation_error", "validation"),
+    ("length", "length"),
+    ("length_min", "length"),
+    ("length_max", "length"),
+    ("range", "range"),
+    ("range_min", "range"),
+    ("range_max", "range"),
+    ("email.invalid", "email"),
+    ("email_domain_blocked", "email"),
+    ("email_domain_in_pet_name", "pet-name"),
+    ("url.invalid", "url"),
+    ("url.not_an_image", "url"),
+    ("html.not_allowed", "html"),
+    ("pet_name.whitespace", "pet-name"),
+    ("pet_name.charset", "pet-name"),
+    ("pet_name.reserved", "pet-name"),
+    ("pets_count", "pets-count"),
+    ("phone.invalid", "phone"),
+    ("cannot_be_null", "partial-updates"),
+    ("invalid_number_string", "number-strings"),
+    ("negative_not_allowed", "numbers"),
+    ("not_an_integer", "numbers"),
+    ("number_out_of_range", "numbers"),
+    ("max_bytes_exceeded", "byte-limits"),
+    ("max_total_bytes_exceeded", "byte-limits"),
+    ("password_too_short", "password"),
+    ("password_too_weak", "password"),
+    ("password_contains_email", "password"),
+    ("birthdate.future", "birthdate"),
+    ("birthdate.too_old", "birthdate"),
+    ("required_when", "required-when"),
+    ("street_no_required", "street-no"),
+    ("schedule_not_chronological", "schedule"),
+    ("min_guests_greater_than_max_guests", "booking"),
+    ("min_guests_below_lower_bound", "booking"),
+    ("max_guests_above_upper_bound", "booking"),
+    ("card_number_required_for_card", "payment"),
+    ("paypal_email_required_for_paypal", "payment"),
+    ("unique", "unique"),
+    ("conflict", "unique"),
+    ("schema_violation", "schema"),
+    ("invalid_schema", "schema"),
+    // Requests the service turns away
+    ("bad_request", "bad-request"),
+    ("duplicate_part", "multipart"),
+    ("photo.too_large", "multipart"),
+    ("photo.unsupported_type", "multipart"),
+    ("unknown_field_path", "validate-field"),
+    ("requires_full_validation", "validate-field"),
+    ("invalid_id", "invalid-id"),
+    ("invalid_flag", "query"),
+    ("invalid_query", "query"),
+    ("parameter_errors", "query"),
+    ("unknown_query_params", "query"),
+    ("unsupported_api_version", "api-version"),
+    ("client_version_missing", "client-version"),
+    ("client_version_invalid", "client-version"),
+    ("client_version_too_old", "client-version"),
+    ("forbidden", "authorization"),
+    ("missing_api_key", "authorization"),
+    ("unknown_api_key", "authorization"),
+    ("cors_forbidden", "cors"),
+    ("cors_method_forbidden", "cors"),
+    ("invalid_idempotency_key", "idempotency"),
+    ("idempotency_key_reused", "idempotency"),
+    ("too_many_requests", "rate-limit"),
+    ("request_timeout", "timeout"),
+    ("payload_too_large", "payload-too-large"),
+    ("too_many_rows", "payload-too-large"),
+    ("length_required", "payload-too-large"),
+    ("not_found", "not-found"),
+    ("method_not_allowed", "method-not-allowed"),
+    ("missing_header", "headers"),
+    ("invalid_header", "headers"),
+    ("internal_error", "internal-error"),
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldError {
+    pub field: String,
+    pub severity: Severity,
+    /// Stable code of the first failing rule, see `stable_code`.
+    pub code: String,
+    pub field_errors: Vec<String>,
+    /// The params of the rule behind `code`, e.g. its `min` and `max`.
+    pub params: serde_json::Map<String, serde_json::Value>,
+    /// The submitted value, `null` if there is none or the field is sensitive.
+    pub rejected_value: serde_json::Value,
+    /// The docs of `code`, filled in by `error_response`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs_url: Option<String>,
+}
+
+/// A `FieldError` cut down to what `DetailLevel::Minimal` responses show.
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldErrorCode {
+    pub field: String,
+    pub code: String,
+}
+
+impl From<FieldError> for FieldErrorCode {
+    fn from(error: FieldError) -> Self {
+        FieldErrorCode {
+            field: error.field,
+            code: error.code,
+        }
+    }
+}
+
+/// The `errors` of a response at the detail level the client asked for.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ReportedErrors {
+    Full(Vec<FieldError>),
+    Minimal(Vec<FieldErrorCode>),
+}
+
+/// Fields whose values are never echoed back in an error.
+const SENSITIVE_FIELDS: &[&str] = &["password", "cardNumber"];
+
+/// `value` as it may be quoted back for the field at `path`: `null` for sensitive fields.
+pub(crate) fn rejected_value(path: &str, value: &serde_json::Value) -> serde_json::Value {
+    let name = path.rsplit('.').next().unwrap_or(path);
+    let name = name.split('[').next().unwrap_or(name);
+    if SENSITIVE_FIELDS.contains(&name) {
+        return serde_json::Value::Null;
+    }
+    value.clone()
+}
+
+/// Parts of field names, compared case-insensitively, whose values `redact_body` replaces
+/// entirely, so `newPassword` or `phoneNumber` are covered as soon as they exist.
+const REDACTED_FIELD_PARTS: &[&str] = &["password", "phone", "card", "secret", "token"];
+
+const DEFAULT_REJECTED_BODY_LOG_BYTES: usize = 1024;
+
+/// The debug logging of rejected bodies, enabled with `LOG_REJECTED_BODIES=true`.
+#[derive(Debug, Clone, Copy)]
+pub struct RejectedBodyLog {
+    /// How much of the redacted body is logged, from `LOG_REJECTED_BODY_BYTES` (1 KiB by
+    /// default).
+    pub max_bytes: usize,
+}
+
+impl RejectedBodyLog {
+    /// `None` unless `LOG_REJECTED_BODIES=true`.
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("LOG_REJECTED_BODIES").as_deref() != Ok("true") {
+            return None;
+        }
+        let max_bytes = std::env::var("LOG_REJECTED_BODY_BYTES")
+            .ok()
+            .and_then(|max_bytes| max_bytes.parse().ok())
+            .unwrap_or(DEFAULT_REJECTED_BODY_LOG_BYTES);
+        Some(RejectedBodyLog { max_bytes })
+    }
+
+    /// `rejection` with a redacted copy of the `body` it rejected, if it's a body that didn't
+    /// deserialize or validate. Anything else is passed through as is.
+    pub fn attach(self, rejection: Rejection, body: &[u8]) -> Rejection {
+        let rejects_body = rejection.find::<Error>().is_some_and(|e| {
+            e.is_parse_error()
+                || matches!(
+                    e,
+                    Error::ValidationError(_)
+                        | Error::ExplainedValidationError(_)
+                        | Error::SchemaViolation(_)
+                )
+        });
+        if !rejects_body {
+            return rejection;
+        }
+        let snippet = redact_body(body, self.max_bytes);
+        warp::reject::custom(RejectedBody { rejection, snippet })
+    }
+}
+
+/// A rejection that `RejectedBodyLog::attach` added the redacted body to. `with_request_handling`
+/// answers it like the rejection it wraps.
+#[derive(Debug)]
+pub struct RejectedBody {
+    pub rejection: Rejection,
+    pub snippet: String,
+}
+
+impl warp::reject::Reject for RejectedBody {}
+
+/// `body` as it may be logged: its JSON with the local part of emails masked, like
+/// `f***@example.com`, and the values of fields matching `SENSITIVE_FIELDS` or
+/// `REDACTED_FIELD_PARTS` replaced, cut to `max_bytes`. Redaction works on the parsed document,
+/// so a body that isn't JSON is only logged by its size.
+fn redact_body(body: &[u8], max_bytes: usize) -> String {
+    let mut value = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(value) => value,
+        Err(_) => return format!("<{} bytes, not JSON>", body.len()),
+    };
+    redact_value(&mut value);
+    let mut snippet = value.to_string();
+    if snippet.len() > max_bytes {
+        let mut end = max_bytes;
+        while !snippet.is_char_boundary(end) {
+            end -= 1;
+        }
+        snippet.truncate(end);
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                let lowercase = name.to_lowercase();
+                if SENSITIVE_FIELDS.contains(&name.as_str())
+                    || REDACTED_FIELD_PARTS
+                        .iter()
+                        .any(|part| lowercase.contains(part))
+                {
+                    *value = "[redacted]".into();
+                } else if lowercase.contains("email") {
+                    if let serde_json::Value::String(email) = value {
+                        *email = mask_email(email);
+                    } else {
+                        redact_value(value);
+                    }
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// `fred@example.com` as `f***@example.com`. Anything without a domain is masked completely.
+fn mask_email(email: &str) -> String {
+    match email.rsplit_once('@') {
+        Some((local, domain)) => match local.chars().next() {
+            Some(first) => format!("{}***@{}", first, domain),
+            None => format!("***@{}", domain),
+        },
+        None => "***".to_string(),
+    }
+}
+
+/// Params of struct-level rules that hold field paths, which are reported in wire names too.
+const PATH_PARAMS: &[&str] = &["field", "fields", "missing_field"];
+
+/// The params of `error` apart from the rejected `value`, which `FieldError` carries separately.
+fn error_params(error: &ValidationError) -> serde_json::Map<String, serde_json::Value> {
+    error
+        .params
+        .iter()
+        .filter(|(name, _)| *name != "value")
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(path) if PATH_PARAMS.contains(&name.as_ref()) => {
+                    wire_path(path).into()
+                }
+                serde_json::Value::Array(paths) if PATH_PARAMS.contains(&name.as_ref()) => paths
+                    .iter()
+                    .map(|path| match path {
+                        serde_json::Value::String(path) => wire_path(path).into(),
+                        other => other.clone(),
+                    })
+                    .collect(),
+                value => value.clone(),
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// The key clients send for the Rust field `field`. Every request struct is
+/// `#[serde(rename_all = "camelCase")]`, so this is serde's own renaming, `street_no` to
+/// `streetNo`, and validator's errors end up on the paths serde_path_to_error reports.
+pub(crate) fn wire_name(field: &str) -> String {
+    let mut name = String::with_capacity(field.len());
+    let mut capitalize = false;
+    for c in field.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            name.extend(c.to_uppercase());
+            capitalize = false;
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+/// `wire_name` applied to every key of a path like `pets[0].photo_url`.
+pub(crate) fn wire_path(path: &str) -> String {
+    path.split('.')
+        .map(|segment| match segment.split_once('[') {
+            Some((key, index)) => format!("{}[{}", wire_name(key), index),
+            None => wire_name(segment),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Blocking errors sort before warnings, so the derived `Ord` relies on the variant order.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Custom validators can flag a `ValidationError` as non-blocking by setting a `severity` param
+/// to `"warning"`, everything else counts as a blocking error.
+fn severity_of(error: &ValidationError) -> Severity {
+    match error.params.get("severity").and_then(|s| s.as_str()) {
+        Some("warning") => Severity::Warning,
+        _ => Severity::Error,
+    }
+}
+
+fn severity_of_kind(kind: &ValidationErrorsKind) -> Severity {
+    match kind {
+        ValidationErrorsKind::Field(field_errs) => field_errs
+            .iter()
+            .map(severity_of)
+            .min()
+            .unwrap_or(Severity::Error),
+        ValidationErrorsKind::Struct(struct_err) => severity_of_errors(struct_err),
+        ValidationErrorsKind::List(vec_errs) => vec_errs
+            .values()
+            .map(|ve| severity_of_errors(ve))
+            .min()
+            .unwrap_or(Severity::Error),
+    }
+}
+
+fn severity_of_errors(ve: &ValidationErrors) -> Severity {
+    ve.errors()
+        .values()
+        .map(severity_of_kind)
+        .min()
+        .unwrap_or(Severity::Error)
+}
+
+/// Languages validation messages are available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    En,
+    De,
+}
+
+impl Language {
+    /// Picks the supported language with the highest `q` weight from an `Accept-Language`
+    /// header, falling back to English for missing headers and unsupported languages.
+    fn from_accept_language(header: Option<&str>) -> Self {
+        let mut ranges: Vec<(&str, f32)> = header
+            .unwrap_or_default()
+            .split(',')
+            .map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next().unwrap_or_default().trim();
+                let q = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (tag, q)
+            })
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges
+            .into_iter()
+            .filter(|(_, q)| *q > 0.0)
+            .find_map(|(tag, _)| {
+                match tag
+                    .split('-')
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase()
+                    .as_str()
+                {
+                    "en" => Some(Language::En),
+                    "de" => Some(Language::De),
+                    _ => None,
+                }
+            })
+            .unwrap_or(Language::En)
+    }
+}
+
+/// How much an error response gives away, picked per request with `X-Error-Detail`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailLevel {
+    /// Messages, params, rejected values and serde's own wording on top of the codes.
+    Full,
+    /// Only field names and stable codes, for callers that shouldn't learn the constraints.
+    Minimal,
+}
+
+impl DetailLevel {
+    /// Parses `full` or `minimal`, ignoring case. Anything else is `None`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(DetailLevel::Full),
+            "minimal" => Some(DetailLevel::Minimal),
+            _ => None,
+        }
+    }
+}
+
+/// The format error responses are rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Json,
+    /// RFC 7807 `application/problem+json`.
+    Problem,
+}
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+impl ErrorFormat {
+    /// Picks `Problem` when the `Accept` header weighs `application/problem+json` at least as
+    /// high as `application/json`, `Json` otherwise, also when it accepts neither.
+    pub fn from_accept(header: Option<&str>) -> Self {
+        match Accept::parse(header).negotiate(&[JSON_MEDIA_TYPE, PROBLEM_JSON]) {
+            Some(PROBLEM_JSON) => ErrorFormat::Problem,
+            _ => ErrorFormat::Json,
+        }
+    }
+}
+
+fn with_error_format() -> impl Filter<Extract = (ErrorFormat,), Error = Infallible> + Clone {
+    warp::header::optional::<String>("accept")
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(|header: Option<String>| ErrorFormat::from_accept(header.as_deref()))
+}
+
+/// The `X-Error-Detail` the client asked for, `default` if it's missing or not understood.
+pub(crate) fn with_detail_level(
+    default: DetailLevel,
+) -> impl Filter<Extract = (DetailLevel,), Error = Infallible> + Clone {
+    warp::header::optional::<String>("x-error-detail")
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(move |header: Option<String>| {
+            header
+                .as_deref()
+                .and_then(DetailLevel::parse)
+                .unwrap_or(default)
+        })
+}
+
+pub(crate) fn with_language() -> impl Filter<Extract = (Language,), Error = Infallible> + Clone {
+    warp::header::optional::<String>("accept-language")
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(|header: Option<String>| Language::from_accept_language(header.as_deref()))
+}
+
+/// Takes the request ID from an `X-Request-Id` header a proxy already set, as long as it's a
+/// short token, and generates one otherwise.
+fn with_request_id() -> impl Filter<Extract = (String,), Error = Infallible> + Clone {
+    warp::header::optional::<String>("x-request-id")
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(|id: Option<String>| {
+            id.filter(|id| {
+                !id.is_empty()
+                    && id.len() <= 64
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            })
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+        })
+}
+
+/// How rejections are answered, read once at startup.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorConfig {
+    /// The status of requests that parsed but failed validation.
+    pub validation_status: StatusCode,
+    /// The detail level of requests without an `X-Error-Detail` header.
+    pub detail_level: DetailLevel,
+    /// Whether field errors and errors without field errors link to their docs. When off, only
+    /// validation errors do, on the response, from the code of their first field error.
+    pub docs_urls: bool,
+}
+
+impl Default for ErrorConfig {
+    fn default() -> Self {
+        ErrorConfig {
+            validation_status: StatusCode::BAD_REQUEST,
+            detail_level: DetailLevel::Full,
+            docs_urls: true,
+        }
+    }
+}
+
+impl ErrorConfig {
+    /// Reads the status for validation failures from `VALIDATION_ERROR_STATUS`, `400` or `422`.
+    /// Anything else keeps the default `400`. Bodies that don't parse are always a `400`.
+    ///
+    /// The default detail level comes from `ERROR_DETAIL`, `full` unless it says `minimal`.
+    ///
+    /// `ERROR_DOC_URLS=false` keeps `docs_url` off field errors and errors without field errors.
+    pub fn from_env() -> Self {
+        let validation_status = match std::env::var("VALIDATION_ERROR_STATUS").as_deref() {
+            Ok("422") => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        let detail_level = std::env::var("ERROR_DETAIL")
+            .ok()
+            .and_then(|value| DetailLevel::parse(&value))
+            .unwrap_or(DetailLevel::Full);
+        let docs_urls = std::env::var("ERROR_DOC_URLS").as_deref() != Ok("false");
+        ErrorConfig {
+            validation_status,
+            detail_level,
+            docs_urls,
+        }
+    }
+}
+
+/// Recovers rejections of `filter` through `handle_rejection` in the language and at the detail
+/// level the client asked for, which `recover` alone can't do since it only sees the rejection,
+/// not the request.
+///
+/// Every response carries the request ID in `X-Request-Id`, and every request is logged with its
+/// ID, method, path, status and latency and counted in `metrics`. Error bodies are shaped by
+/// `formatter`.
+pub fn with_request_handling<F>(
+    metrics: Arc<Metrics>,
+    config: ErrorConfig,
+    formatter: Arc<dyn ErrorFormatter>,
+    filter: F,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Rejection>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::any()
+        .map(Instant::now)
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(with_language())
+        .and(with_error_format())
+        .and(with_detail_level(config.detail_level))
+        .and(with_request_id())
+        .and(
+            filter
+                .map(Ok)
+                .recover(|err: Rejection| async move { Ok::<_, Infallible>(Err(err)) })
+                .unify(),
+        )
+        .and_then(
+            move |start: Instant,
+                  method: Method,
+                  path: FullPath,
+                  language: Language,
+                  format: ErrorFormat,
+                  detail_level: DetailLevel,
+                  request_id: String,
+                  result: std::result::Result<warp::reply::Response, Rejection>| {
+                let metrics = metrics.clone();
+                let formatter = formatter.clone();
+                async move {
+                    let mut error_code = None;
+                    let mut response = match result {
+                        Ok(response) => response,
+                        Err(err) => {
+                            let rejected_body = err.find::<RejectedBody>();
+                            let err = rejected_body.map_or(&err, |rejected| &rejected.rejection);
+                            error_code = err.find::<Error>().map(Error::code);
+                            metrics.record_rejection(err);
+                            let ctx = ErrorContext {
+                                rejection: err,
+                                method: &method,
+                                language,
+                                format,
+                                detail_level,
+                                request_id: &request_id,
+                                config,
+                                path: path.as_str(),
+                                rejected_body: rejected_body
+                                    .map(|rejected| rejected.snippet.as_str()),
+                            };
+                            handle_rejection(formatter.as_ref(), ctx).await?
+                        }
+                    };
+                    metrics.record_request(path.as_str());
+                    if let Ok(value) = HeaderValue::from_str(&request_id) {
+                        response.headers_mut().insert("x-request-id", value);
+                    }
+                    tracing::info!(
+                        request_id = %request_id,
+                        method = %method,
+                        path = path.as_str(),
+                        status = response.status().as_u16(),
+                        error = error_code,
+                        latency_ms = start.elapsed().as_millis() as u64,
+                        "request"
+                    );
+                    Ok::<_, Infallible>(response)
+                }
+            },
+        )
+}
+
+/// The message template for a validator code in `language`, with `{field}` and the names of the
+/// error's params as placeholders. Bounded validators get a template per combination of bounds.
+fn message_template(key: &str, language: Language) -> Option<&'static str> {
+    let template = match (key, language) {
+        ("length", Language::En) => "{field} must be between {min} and {max} characters",
+        ("length", Language::De) => "{field} muss zwischen {min} und {max} Zeichen lang sein",
+        ("length_min", Language::En) => "{field} must be at least {min} characters",
+        ("length_min", Language::De) => "{field} muss mindestens {min} Zeichen lang sein",
+        ("length_max", Language::En) => "{field} must be at most {max} characters",
+        ("length_max", Language::De) => "{field} darf höchstens {max} Zeichen lang sein",
+        ("range", Language::En) => "{field} must be between {min} and {max}",
+        ("range", Language::De) => "{field} muss zwischen {min} und {max} liegen",
+        ("range_min", Language::En) => "{field} must be at least {min}",
+        ("range_min", Language::De) => "{field} muss mindestens {min} sein",
+        ("range_max", Language::En) => "{field} must be at most {max}",
+        ("range_max", Language::De) => "{field} darf höchstens {max} sein",
+        ("email", Language::En) => "{field} must be a valid email address",
+        ("email", Language::De) => "{field} muss eine gültige E-Mail-Adresse sein",
+        ("invalid_url", Language::En) => "{field} must be a valid URL",
+        ("invalid_url", Language::De) => "{field} muss eine gültige URL sein",
+        ("not_an_image_url", Language::En) => "{field} must point to an image",
+        ("not_an_image_url", Language::De) => "{field} muss auf ein Bild verweisen",
+        ("html_not_allowed", Language::En) => "{field} must not contain HTML",
+        ("html_not_allowed", Language::De) => "{field} darf kein HTML enthalten",
+        ("pet_name_whitespace", Language::En) => {
+            "{field} must not start or end with whitespace"
+        }
+        ("pet_name_whitespace", Language::De) => {
+            "{field} darf nicht mit Leerzeichen beginnen oder enden"
+        }
+        ("pet_name_charset", Language::En) => {
+            "{field} may only contain letters, spaces and hyphens, not {invalid_chars}"
+        }
+        ("pet_name_charset", Language::De) => {
+            "{field} darf nur Buchstaben, Leerzeichen und Bindestriche enthalten, nicht {invalid_chars}"
+        }
+        ("pet_name_reserved", Language::En) => "{field} must not be {value}",
+        ("pet_name_reserved", Language::De) => "{field} darf nicht {value} sein",
+        ("invalid_phone", Language::En) => {
+            "{field} must be a phone number like +4915112345678 or 0151 12345678"
+        }
+        ("invalid_phone", Language::De) => {
+            "{field} muss eine Telefonnummer wie +4915112345678 oder 0151 12345678 sein"
+        }
+        ("birthdate_in_future", Language::En) => "{field} must not be in the future",
+        ("birthdate_in_future", Language::De) => "{field} darf nicht in der Zukunft liegen",
+        ("birthdate_too_old", Language::En) => {
+            "{field} must be at most {max_age_years} years ago"
+        }
+        ("birthdate_too_old", Language::De) => {
+            "{field} darf höchstens {max_age_years} Jahre zurückliegen"
+        }
+        ("email_domain_in_pet_name", Language::En) => {
+            "{field} must not contain the email domain {domain}"
+        }
+        ("email_domain_in_pet_name", Language::De) => {
+            "{field} darf die E-Mail-Domain {domain} nicht enthalten"
+        }
+        ("cannot_be_null", Language::En) => {
+            "{field} can't be cleared, leave it out to keep it unchanged"
+        }
+        ("cannot_be_null", Language::De) => {
+            "{field} kann nicht geleert werden, lass es weg, um es unverändert zu lassen"
+        }
+        ("invalid_number_string", Language::En) => "{field} must be a number, not {value}",
+        ("invalid_number_string", Language::De) => "{field} muss eine Zahl sein, nicht {value}",
+        ("max_bytes_exceeded", Language::En) => {
+            "{field} must be at most {max} bytes long, not {bytes}"
+        }
+        ("max_bytes_exceeded", Language::De) => {
+            "{field} darf höchstens {max} Bytes lang sein, nicht {bytes}"
+        }
+        ("max_total_bytes_exceeded", Language::En) => {
+            "the text fields of the request must be at most {max} bytes long together, not {bytes}"
+        }
+        ("max_total_bytes_exceeded", Language::De) => {
+            "die Textfelder der Anfrage dürfen zusammen höchstens {max} Bytes lang sein, nicht {bytes}"
+        }
+        ("password_too_short", Language::En) => "{field} must be at least {min} characters",
+        ("password_too_short", Language::De) => "{field} muss mindestens {min} Zeichen lang sein",
+        ("password_too_weak", Language::En) => {
+            "{field} must contain at least one letter and one digit"
+        }
+        ("password_too_weak", Language::De) => {
+            "{field} muss mindestens einen Buchstaben und eine Ziffer enthalten"
+        }
+        ("password_contains_email", Language::En) => {
+            "{field} must not contain the local part of the email"
+        }
+        ("password_contains_email", Language::De) => {
+            "{field} darf den lokalen Teil der E-Mail-Adresse nicht enthalten"
+        }
+        ("email_domain_blocked", Language::En) => "{field} uses the blocked domain {domain}",
+        ("email_domain_blocked", Language::De) => {
+            "{field} verwendet die gesperrte Domain {domain}"
+        }
+        ("pets_count", Language::En) => "{field} must contain between {min} and {max} pets",
+        ("pets_count", Language::De) => {
+            "{field} muss zwischen {min} und {max} Haustiere enthalten"
+        }
+        ("street_no_required", Language::En) => {
+            "{field} must be greater than 0 for more than {max_pets} pets"
+        }
+        ("street_no_required", Language::De) => {
+            "{field} muss bei mehr als {max_pets} Haustieren größer als 0 sein"
+        }
+        ("required_when", Language::En) => {
+            "{field} is required when contactMethod is {contact_method}"
+        }
+        ("required_when", Language::De) => {
+            "{field} ist erforderlich, wenn contactMethod {contact_method} ist"
+        }
+        _ => return None,
+    };
+    Some(template)
+}
+
+/// Renders `error` on `field` in `language`, interpolating `{field}` and the error's params.
+///
+/// The `message` set on a validator attribute is English, so it wins for English while other
+/// languages use their template when they have one. Codes without either get a generic sentence.
+fn localized_message(field: &str, error: &ValidationError, language: Language) -> String {
+    let key = match error.code.as_ref() {
+        code @ ("length" | "range") => {
+            match (
+                error.params.contains_key("min"),
+                error.params.contains_key("max"),
+            ) {
+                (true, false) => format!("{}_min", code),
+                (false, true) => format!("{}_max", code),
+                _ => code.to_string(),
+            }
+        }
+        code => code.to_string(),
+    };
+    let template = match (error.message.as_deref(), language) {
+        (Some(message), Language::En) => message,
+        (message, _) => message_template(&key, language)
+            .or(message)
+            .unwrap_or(match language {
+                Language::En => "{field} is invalid",
+                Language::De => "{field} ist ungültig",
+            }),
+    };
+    let mut message = template.replace("{field}", field);
+    for (name, value) in &error.params {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Number(n) => match n.as_f64() {
+                Some(f) if f.fract() == 0.0 => format!("{}", f as i64),
+                _ => n.to_string(),
+            },
+            other => other.to_string(),
+        };
+        message = message.replace(&format!("{{{}}}", name), &value);
+    }
+    message
+}
+
+/// The errors of a failed `validate()`, flattened to one `FieldError` per field path and
+/// sorted by severity, then path as `field_order_key` orders it, then code and messages. The
+/// order doesn't depend on the `HashMap`s inside `ValidationErrors`, so the same body always gets
+/// the same response.
+pub fn validation_field_errors(errors: &ValidationErrors, language: Language) -> Vec<FieldError> {
+    let mut out = Vec::new();
+    collect_field_errors("", errors, language, &mut out);
+    out.sort_by_cached_key(|error| {
+        (
+            error.severity,
+            field_order_key(&error.field),
+            error.code.clone(),
+            error.field_errors.clone(),
+        )
+    });
+    out
+}
+
+/// One segment of a path as `field_order_key` compares it.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum PathOrder {
+    /// A field by its position in `FIELD_ORDER`, then name, so undeclared fields come last in
+    /// alphabetical order.
+    Field(usize, String),
+    /// A list element by its index.
+    Index(usize),
+}
+
+/// Where `name` is declared in the struct at `struct_path`, `usize::MAX` if it isn't known.
+fn field_rank(struct_path: &str, name: &str) -> usize {
+    FIELD_ORDER
+        .iter()
+        .find(|(path, _)| *path == struct_path)
+        .and_then(|(_, fields)| fields.iter().position(|field| *field == name))
+        .unwrap_or(usize::MAX)
+}
+
+/// The sort key of a path like `pets[10].name`: fields in declaration order and list elements
+/// by index, so `email` comes before `address` and `pets[2]` before `pets[10]`.
+fn field_order_key(path: &str) -> Vec<PathOrder> {
+    let mut key = Vec::new();
+    let mut struct_path = String::new();
+    for part in path.split('.') {
+        let name_end = part.find('[').unwrap_or(part.len());
+        let name = &part[..name_end];
+        key.push(PathOrder::Field(
+            field_rank(&struct_path, name),
+            name.to_string(),
+        ));
+        if !struct_path.is_empty() {
+            struct_path.push('.');
+        }
+        struct_path.push_str(name);
+        for index in part[name_end..].split(['[', ']']).filter(|s| !s.is_empty()) {
+            key.push(PathOrder::Index(index.parse().unwrap_or(usize::MAX)));
+            struct_path.push_str("[]");
+        }
+    }
+    key
+}
+
+/// The status, message and field errors `handle_rejection` answers one of our errors with.
+pub fn error_status(
+    e: &Error,
+    language: Language,
+    config: ErrorConfig,
+) -> (StatusCode, String, Option<Vec<FieldError>>) {
+    (e.status(config), e.message(), e.field_errors(language))
+}
+
+/// What each variant is answered with. The matches have no catch-all arm on purpose, so a new
+/// variant doesn't compile until it says what it maps to, rather than ending up as a `500`.
+impl Error {
+    /// The response status. Only validation failures depend on `config`.
+    pub fn status(&self, config: ErrorConfig) -> StatusCode {
+        match self {
+            Error::JSONPathError(_)
+            | Error::FormPathError(_)
+            | Error::YAMLPathError(_)
+            | Error::MsgPackPathError(_)
+            | Error::JsonTooDeep { .. }
+            | Error::TrailingContent { .. }
+            | Error::InvalidUtf8 { .. }
+            | Error::UnsupportedCharset { .. }
+            | Error::EmptyBody
+            | Error::InvalidBodyType { .. }
+            | Error::DigestMismatch { .. }
+            | Error::MalformedDigest { .. }
+            | Error::DigestRequired
+            | Error::MissingClientVersion { .. }
+            | Error::InvalidClientVersion { .. }
+            | Error::UndecodableBody { .. }
+            | Error::UnknownQueryParams(_)
+            | Error::InvalidQuery(_)
+            | Error::UnsupportedApiVersion { .. }
+            | Error::BadRequest(_)
+            | Error::UnknownFieldPath(_)
+            | Error::RequiresFullValidation { .. }
+            | Error::InvalidIdempotencyKey(_)
+            | Error::InvalidSchema(_)
+            | Error::SchemaViolation(_)
+            | Error::DeserializationErrors(_)
+            | Error::ParameterErrors(_) => StatusCode::BAD_REQUEST,
+            Error::ValidationError(_) | Error::ExplainedValidationError(_) => {
+                config.validation_status
+            }
+            Error::Forbidden(_)
+            | Error::UnknownApiKey
+            | Error::CorsForbidden { .. }
+            | Error::CorsMethodForbidden { .. } => StatusCode::FORBIDDEN,
+            Error::MissingApiKey => StatusCode::UNAUTHORIZED,
+            Error::ClientTooOld { .. } => StatusCode::UPGRADE_REQUIRED,
+            Error::UnsupportedMediaType { .. } | Error::UnsupportedContentEncoding(_) => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            Error::NotAcceptable { .. } => StatusCode::NOT_ACCEPTABLE,
+            Error::PayloadTooLarge { .. } | Error::TooManyRows { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            // Rather than a 503: the limit is hit by clients that are slow to send their body far
+            // more often than by a slow service, and 408 tells them to retry with a faster one.
+            Error::Timeout { .. } => StatusCode::REQUEST_TIMEOUT,
+            Error::NotFound { .. } => StatusCode::NOT_FOUND,
+            Error::Conflict { .. } => StatusCode::CONFLICT,
+            Error::IdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// A stable name for the kind of error, for logs and metrics. Not part of the response.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::JSONPathError(_) => "json_path_error",
+            Error::JsonTooDeep { .. } => "json_too_deep",
+            Error::TrailingContent { .. } => "trailing_content",
+            Error::InvalidUtf8 { .. } | Error::UnsupportedCharset { .. } => "invalid_encoding",
+            Error::DigestMismatch { .. } => "digest_mismatch",
+            Error::MalformedDigest { .. } => "digest_malformed",
+            Error::DigestRequired => "digest_required",
+            Error::EmptyBody => "empty_body",
+            Error::InvalidBodyType { .. } => "invalid_body_type",
+            Error::FormPathError(_) => "form_path_error",
+            Error::YAMLPathError(_) => "yaml_path_error",
+            Error::MsgPackPathError(_) => "msgpack_path_error",
+            Error::ValidationError(_) => "validation_error",
+            Error::ExplainedValidationError(_) => "explained_validation_error",
+            Error::Forbidden(_) => "forbidden",
+            Error::MissingApiKey => "missing_api_key",
+            Error::UnknownApiKey => "unknown_api_key",
+            Error::CorsForbidden { .. } => "cors_forbidden",
+            Error::CorsMethodForbidden { .. } => "cors_method_forbidden",
+            Error::MissingClientVersion { .. } => "client_version_missing",
+            Error::InvalidClientVersion { .. } => "client_version_invalid",
+            Error::ClientTooOld { .. } => "client_version_too_old",
+            Error::UnsupportedMediaType { .. } => "unsupported_media_type",
+            Error::NotAcceptable { .. } => "not_acceptable",
+            Error::UnsupportedContentEncoding(_) => "unsupported_content_encoding",
+            Error::UndecodableBody { .. } => "undecodable_body",
+            Error::PayloadTooLarge { .. } => "payload_too_large",
+            Error::TooManyRows { .. } => "too_many_rows",
+            Error::TooManyRequests { .. } => "too_many_requests",
+            Error::Timeout { .. } => "request_timeout",
+            Error::UnknownQueryParams(_) => "unknown_query_params",
+            Error::UnsupportedApiVersion { .. } => "unsupported_api_version",
+            Error::InvalidQuery(_) => "invalid_query",
+            Error::NotFound { .. } => "not_found",
+            Error::Conflict { .. } => "conflict",
+            Error::InvalidIdempotencyKey(_) => "invalid_idempotency_key",
+            Error::IdempotencyKeyReused(_) => "idempotency_key_reused",
+            Error::BadRequest(_) => "bad_request",
+            Error::UnknownFieldPath(_) => "unknown_field_path",
+            Error::RequiresFullValidation { .. } => "requires_full_validation",
+            Error::InvalidSchema(_) => "invalid_schema",
+            Error::SchemaViolation(_) => "schema_violation",
+            Error::DeserializationErrors(_) => "deserialization_errors",
+            Error::ParameterErrors(_) => "parameter_errors",
+        }
+    }
+
+    /// Whether the body or query didn't deserialize, as opposed to deserializing into something
+    /// invalid or being turned away before it was read.
+    pub fn is_parse_error(&self) -> bool {
+        matches!(
+            self,
+            Error::JSONPathError(_)
+                | Error::EmptyBody
+                | Error::InvalidBodyType { .. }
+                | Error::JsonTooDeep { .. }
+                | Error::TrailingContent { .. }
+                | Error::InvalidUtf8 { .. }
+                | Error::UnsupportedCharset { .. }
+                | Error::FormPathError(_)
+                | Error::YAMLPathError(_)
+                | Error::MsgPackPathError(_)
+                | Error::UndecodableBody { .. }
+                | Error::InvalidQuery(_)
+                | Error::DeserializationErrors(_)
+                | Error::ParameterErrors(_)
+        )
+    }
+
+    /// Whether the field errors are about the body, rather than headers or query and path
+    /// parameters, which have no section in `failed_sections`.
+    fn reports_body_fields(&self) -> bool {
+        !matches!(
+            self,
+            Error::MissingClientVersion { .. }
+                | Error::InvalidClientVersion { .. }
+                | Error::ClientTooOld { .. }
+                | Error::DigestMismatch { .. }
+                | Error::MalformedDigest { .. }
+                | Error::DigestRequired
+                | Error::NotAcceptable { .. }
+                | Error::ParameterErrors(_)
+        )
+    }
+
+    /// The top-level message. Errors that list their fields leave the details to them.
+    pub fn message(&self) -> String {
+        match self {
+            Error::SchemaViolation(_)
+            | Error::DeserializationErrors(_)
+            | Error::ParameterErrors(_)
+            | Error::ValidationError(_)
+            | Error::ExplainedValidationError(_) => "field errors".to_string(),
+            e => e.to_string(),
+        }
+    }
+
+    /// A `FieldError` on the body as a whole, at the empty path, so the error's code and `params`
+    /// are reported like those of a field.
+    fn body_error(&self, params: serde_json::Map<String, serde_json::Value>) -> FieldError {
+        FieldError {
+            field: String::new(),
+            severity: Severity::Error,
+            code: self.code().to_string(),
+            field_errors: vec![self.to_string()],
+            params,
+            rejected_value: serde_json::Value::Null,
+            docs_url: None,
+        }
+    }
+
+    /// The field errors of the response, `None` for errors that aren't about a particular field
+    /// or the encoding of the body.
+    pub fn field_errors(&self, language: Language) -> Option<Vec<FieldError>> {
+        match self {
+            Error::InvalidBodyType { expected, received } => {
+                let mut params = serde_json::Map::new();
+                params.insert("expected".to_string(), (*expected).into());
+                params.insert("received".to_string(), (*received).into());
+                Some(vec![FieldError {
+                    field: String::new(),
+                    severity: Severity::Error,
+                    code: self.code().to_string(),
+                    field_errors: vec![format!("invalid_body_type: {}", self)],
+                    params,
+                    rejected_value: serde_json::Value::Null,
+                    docs_url: None,
+                }])
+            }
+            Error::MissingClientVersion { minimum } => {
+                Some(vec![client_version_error(self, None, minimum)])
+            }
+            Error::InvalidClientVersion { received, minimum }
+            | Error::ClientTooOld { received, minimum } => {
+                Some(vec![client_version_error(self, Some(received), minimum)])
+            }
+            Error::UnknownFieldPath(field) => Some(vec![FieldError {
+                field: field.clone(),
+                severity: Severity::Error,
+                code: self.code().to_string(),
+                field_errors: vec![self.to_string()],
+                params: serde_json::Map::new(),
+                rejected_value: serde_json::Value::Null,
+                docs_url: None,
+            }]),
+            Error::RequiresFullValidation { field, rules } => {
+                let mut params = serde_json::Map::new();
+                params.insert("rules".to_string(), (*rules).into());
+                Some(vec![FieldError {
+                    field: field.clone(),
+                    severity: Severity::Error,
+                    code: self.code().to_string(),
+                    field_errors: vec![self.to_string()],
+                    params,
+                    rejected_value: serde_json::Value::Null,
+                    docs_url: None,
+                }])
+            }
+            Error::InvalidUtf8 { offset } => {
+                let mut params = serde_json::Map::new();
+                params.insert("offset".to_string(), (*offset).into());
+                Some(vec![self.body_error(params)])
+            }
+            Error::UnsupportedCharset { encoding } => {
+                let mut params = serde_json::Map::new();
+                params.insert("encoding".to_string(), (*encoding).into());
+                Some(vec![self.body_error(params)])
+            }
+            Error::DigestMismatch {
+                header,
+                algorithm,
+                expected,
+                computed,
+            } => {
+                let mut params = serde_json::Map::new();
+                params.insert("algorithm".to_string(), (*algorithm).into());
+                params.insert("expected".to_string(), expected.as_str().into());
+                params.insert("computed".to_string(), computed.as_str().into());
+                Some(vec![header_error(self, header, params, None)])
+            }
+            Error::MalformedDigest { header, value, .. } => Some(vec![header_error(
+                self,
+                header,
+                serde_json::Map::new(),
+                Some(value),
+            )]),
+            Error::NotAcceptable {
+                received,
+                supported,
+            } => {
+                let mut params = serde_json::Map::new();
+                params.insert("supported".to_string(), (*supported).into());
+                Some(vec![header_error(self, "Accept", params, Some(received))])
+            }
+            Error::DigestRequired => Some(vec![header_error(
+                self,
+                crate::digest::DIGEST,
+                serde_json::Map::new(),
+                None,
+            )]),
+            Error::Conflict { field, value } => Some(vec![FieldError {
+                field: field.to_string(),
+                severity: Severity::Error,
+                code: "unique".to_string(),
+                field_errors: vec![format!("unique: {}", self)],
+                params: serde_json::Map::new(),
+                rejected_value: rejected_value(field, &value.as_str().into()),
+                docs_url: None,
+            }]),
+            Error::SchemaViolation(errors)
+            | Error::DeserializationErrors(errors)
+            | Error::ParameterErrors(errors) => Some(errors.clone()),
+            Error::ValidationError(val_errs) | Error::ExplainedValidationError(val_errs) => {
+                Some(validation_field_errors(val_errs, language))
+            }
+            Error::JSONPathError(_)
+            | Error::JsonTooDeep { .. }
+            | Error::TrailingContent { .. }
+            | Error::EmptyBody
+            | Error::FormPathError(_)
+            | Error::YAMLPathError(_)
+            | Error::MsgPackPathError(_)
+            | Error::Forbidden(_)
+            | Error::MissingApiKey
+            | Error::UnknownApiKey
+            | Error::CorsForbidden { .. }
+            | Error::CorsMethodForbidden { .. }
+            | Error::UnsupportedMediaType { .. }
+            | Error::UnsupportedContentEncoding(_)
+            | Error::UndecodableBody { .. }
+            | Error::PayloadTooLarge { .. }
+            | Error::TooManyRows { .. }
+            | Error::TooManyRequests { .. }
+            | Error::Timeout { .. }
+            | Error::UnknownQueryParams(_)
+            | Error::UnsupportedApiVersion { .. }
+            | Error::InvalidQuery(_)
+            | Error::NotFound { .. }
+            | Error::InvalidIdempotencyKey(_)
+            | Error::IdempotencyKeyReused(_)
+            | Error::BadRequest(_)
+            | Error::InvalidSchema(_) => None,
+        }
+    }
+}
+
+/// The status and message for warp's own rejections, or `None` for ones we don't know.
+pub fn builtin_rejection_status(err: &Rejection, method: &Method) -> Option<(StatusCode, String)> {
+    let status = if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not Found".to_string())
+    } else if let Some(e) = err.find::<warp::reject::PayloadTooLarge>() {
+        // `with_body_limit` answers oversized bodies itself, this only covers warp's own check
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::LengthRequired>() {
+        (StatusCode::LENGTH_REQUIRED, e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("method {} is not allowed on this route", method),
+        )
+    } else if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported media type".to_string(),
+        )
+    } else if err.find::<warp::reject::InvalidQuery>().is_some() {
+        (StatusCode::BAD_REQUEST, "invalid query string".to_string())
+    } else if let Some(e) = err.find::<warp::reject::MissingHeader>() {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("missing header {}", e.name()),
+        )
+    } else if let Some(e) = err.find::<warp::reject::InvalidHeader>() {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("invalid header {}", e.name()),
+        )
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (
+            StatusCode::BAD_REQUEST,
+            e.source()
+                .map(|cause| cause.to_string())
+                .unwrap_or_else(|| "BAD_REQUEST".to_string()),
+        )
+    } else {
+        return None;
+    };
+    Some(status)
+}
+
+/// The unknown or missing field of a `warp::body::json` rejection as a `FieldError`, like the
+/// routes deserializing with `serde_path_to_error` report it. warp only keeps serde's message, so
+/// this is a best effort: the field is the bare key serde names, without the path to it, and
+/// messages that name no key, like a wrong type, get no errors.
+fn body_deserialize_field_errors(err: &Rejection) -> Option<Vec<FieldError>> {
+    let cause = err
+        .find::<warp::filters::body::BodyDeserializeError>()?
+        .source()?
+        .to_string();
+    let reason = cause
+        .rsplit_once(" at line ")
+        .map_or(cause.as_str(), |(reason, _)| reason);
+    let (code, rest) = if let Some(rest) = reason.strip_prefix("unknown field `") {
+        ("unknown_field", rest)
+    } else if let Some(rest) = reason.strip_prefix("missing field `") {
+        ("required", rest)
+    } else {
+        return None;
+    };
+    let field = rest.split('`').next()?;
+    Some(vec![FieldError {
+        field: field.to_string(),
+        severity: Severity::Error,
+        code: code.to_string(),
+        field_errors: vec![format!("{}: {}", code, reason)],
+        params: serde_json::Map::new(),
+        rejected_value: serde_json::Value::Null,
+        docs_url: None,
+    }])
+}
+
+/// The stable code of `err` that `ERROR_DOC_PAGES` is keyed by: `Error::code` for our errors and
+/// a category for warp's own rejections.
+fn rejection_code(err: &Rejection) -> &'static str {
+    if let Some(e) = err.find::<Error>() {
+        e.code()
+    } else if err.is_not_found() {
+        "not_found"
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        "payload_too_large"
+    } else if err.find::<warp::reject::LengthRequired>().is_some() {
+        "length_required"
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        "method_not_allowed"
+    } else if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        "unsupported_media_type"
+    } else if err.find::<warp::reject::InvalidQuery>().is_some() {
+        "invalid_query"
+    } else if err.find::<warp::reject::MissingHeader>().is_some() {
+        "missing_header"
+    } else if err.find::<warp::reject::InvalidHeader>().is_some() {
+        "invalid_header"
+    } else if err
+        .find::<warp::filters::body::BodyDeserializeError>()
+        .is_some()
+    {
+        "malformed_body"
+    } else {
+        "internal_error"
+    }
+}
+
+/// The RFC 7807 `type` of the problem details for `err`.
+fn problem_type(err: &Rejection) -> &'static str {
+    match err.find::<Error>() {
+        _ if err.is_not_found() => "/problems/not-found",
+        Some(Error::NotFound { .. }) => "/problems/not-found",
+        Some(
+            Error::ValidationError(_)
+            | Error::ExplainedValidationError(_)
+            | Error::SchemaViolation(_)
+            | Error::DeserializationErrors(_)
+            | Error::ParameterErrors(_),
+        ) => "/problems/validation-error",
+        Some(
+            Error::JSONPathError(_)
+            | Error::FormPathError(_)
+            | Error::YAMLPathError(_)
+            | Error::MsgPackPathError(_)
+            | Error::JsonTooDeep { .. }
+            | Error::TrailingContent { .. }
+            | Error::InvalidUtf8 { .. }
+            | Error::UnsupportedCharset { .. }
+            | Error::EmptyBody
+            | Error::InvalidBodyType { .. }
+            | Error::UndecodableBody { .. },
+        ) => "/problems/parse-error",
+        _ if err
+            .find::<warp::filters::body::BodyDeserializeError>()
+            .is_some() =>
+        {
+            "/problems/parse-error"
+        }
+        _ => "about:blank",
+    }
+}
+
+/// What an `ErrorFormatter` knows about the request besides the error itself.
+pub struct ErrorContext<'a> {
+    pub rejection: &'a Rejection,
+    pub method: &'a Method,
+    pub language: Language,
+    /// The format the client asked for with `Accept`.
+    pub format: ErrorFormat,
+    pub detail_level: DetailLevel,
+    pub request_id: &'a str,
+    pub config: ErrorConfig,
+    pub path: &'a str,
+    /// The redacted body of the request, if `RejectedBodyLog` attached one to the rejection.
+    pub rejected_body: Option<&'a str>,
+}
+
+/// A rendered error body.
+pub struct ErrorBody {
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+impl ErrorBody {
+    /// `value` as `application/json`.
+    pub fn json(value: &impl Serialize) -> Self {
+        ErrorBody {
+            content_type: "application/json",
+            bytes: serde_json::to_vec(value).expect("error body serializes"),
+        }
+    }
+}
+
+/// Shapes the error responses of `with_request_handling`, for services with an error envelope
+/// of their own. `handle_rejection` picks the method by the kind of rejection; the headers it
+/// adds, like `Retry-After` and `X-Request-Id`, are set whichever formatter is installed.
+pub trait ErrorFormatter: Send + Sync {
+    /// `Error::ValidationError` and `Error::ExplainedValidationError`.
+    fn format_validation(
+        &self,
+        errors: &ValidationErrors,
+        ctx: &ErrorContext,
+    ) -> (StatusCode, ErrorBody);
+
+    /// Bodies and queries that didn't deserialize, see `Error::is_parse_error`.
+    fn format_parse(&self, error: &Error, ctx: &ErrorContext) -> (StatusCode, ErrorBody);
+
+    /// Every other rejection, ours and warp's.
+    fn format_other(&self, ctx: &ErrorContext) -> (StatusCode, ErrorBody);
+}
+
+/// The `ErrorResponse`, or `ProblemDetails` for clients that ask for them, of `error_response`.
+pub struct DefaultErrorFormatter;
+
+impl DefaultErrorFormatter {
+    fn format(&self, ctx: &ErrorContext) -> (StatusCode, ErrorBody) {
+        let (code, ErrorResponse { message, details }) = error_response(
+            ctx.rejection,
+            ctx.method,
+            ctx.language,
+            ctx.detail_level,
+            ctx.request_id,
+            ctx.config,
+        );
+        let body = match ctx.format {
+            ErrorFormat::Json => ErrorBody::json(&ErrorResponse { message, details }),
+            ErrorFormat::Problem => ErrorBody {
+                content_type: PROBLEM_JSON,
+                ..ErrorBody::json(&ProblemDetails {
+                    problem_type: problem_type(ctx.rejection),
+                    title: code.canonical_reason().unwrap_or("Error"),
+                    status: code.as_u16(),
+                    detail: message,
+                    details,
+                })
+            },
+        };
+        (code, body)
+    }
+}
+
+impl ErrorFormatter for DefaultErrorFormatter {
+    fn format_validation(
+        &self,
+        _: &ValidationErrors,
+        ctx: &ErrorContext,
+    ) -> (StatusCode, ErrorBody) {
+        self.format(ctx)
+    }
+
+    fn format_parse(&self, _: &Error, ctx: &ErrorContext) -> (StatusCode, ErrorBody) {
+        self.format(ctx)
+    }
+
+    fn format_other(&self, ctx: &ErrorContext) -> (StatusCode, ErrorBody) {
+        self.format(ctx)
+    }
+}
+
+/// `{"errors": {"pets[0].name": ["length: ..."]}}`, the messages of each field keyed by its
+/// path, sorted by field. Errors that aren't about a field are listed under `""`. The format
+/// the client asked for is ignored.
+pub struct FlatErrorFormatter;
+
+#[derive(Serialize)]
+struct FlatErrors {
+    errors: BTreeMap<String, Vec<String>>,
+}
+
+impl FlatErrors {
+    fn new(field_errors: Vec<FieldError>) -> Self {
+        let mut errors: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for error in field_errors {
+            errors
+                .entry(error.field)
+                .or_default()
+                .extend(error.field_errors);
+        }
+        FlatErrors { errors }
+    }
+
+    fn message(field: String, message: String) -> Self {
+        FlatErrors {
+            errors: std::iter::once((field, vec![message])).collect(),
+        }
+    }
+}
+
+impl ErrorFormatter for FlatErrorFormatter {
+    fn format_validation(
+        &self,
+        errors: &ValidationErrors,
+        ctx: &ErrorContext,
+    ) -> (StatusCode, ErrorBody) {
+        let errors = FlatErrors::new(validation_field_errors(errors, ctx.language));
+        (ctx.config.validation_status, ErrorBody::json(&errors))
+    }
+
+    fn format_parse(&self, error: &Error, ctx: &ErrorContext) -> (StatusCode, ErrorBody) {
+        let errors = match (error.field_errors(ctx.language), error) {
+            (Some(field_errors), _) => FlatErrors::new(field_errors),
+            (None, Error::JSONPathError(detail)) if detail.path != "." => {
+                FlatErrors::message(detail.path.clone(), error.to_string())
+            }
+            (None, _) => FlatErrors::message(String::new(), error.to_string()),
+        };
+        (error.status(ctx.config), ErrorBody::json(&errors))
+    }
+
+    fn format_other(&self, ctx: &ErrorContext) -> (StatusCode, ErrorBody) {
+        let (code, ErrorResponse { message, details }) = error_response(
+            ctx.rejection,
+            ctx.method,
+            ctx.language,
+            DetailLevel::Full,
+            ctx.request_id,
+            ctx.config,
+        );
+        let errors = match details.errors {
+            Some(ReportedErrors::Full(field_errors)) => FlatErrors::new(field_errors),
+            _ => FlatErrors::message(String::new(), message),
+        };
+        (code, ErrorBody::json(&errors))
+    }
+}
+
+/// The formatter named by `ERROR_FORMATTER`: `flat` for `FlatErrorFormatter`, anything else,
+/// or nothing, for `DefaultErrorFormatter`.
+pub fn error_formatter_from_env() -> Arc<dyn ErrorFormatter> {
+    match std::env::var("ERROR_FORMATTER").as_deref() {
+        Ok("flat") => Arc::new(FlatErrorFormatter),
+        _ => Arc::new(DefaultErrorFormatter),
+    }
+}
+
+/// Answers `ctx.rejection` with the method of `formatter` for its kind of error.
+///
+/// Rejections that came with a redacted body are logged at debug level, with the path, the error
+/// code and the fields that failed, so it can be seen what a client actually sent.
+pub async fn handle_rejection(
+    formatter: &dyn ErrorFormatter,
+    ctx: ErrorContext<'_>,
+) -> std::result::Result<warp::reply::Response, Infallible> {
+    if let Some(body) = ctx.rejected_body {
+        log_rejected_body(&ctx, body);
+    }
+    let (code, body) = match ctx.rejection.find::<Error>() {
+        Some(Error::ValidationError(errors)) | Some(Error::ExplainedValidationError(errors)) => {
+            formatter.format_validation(errors, &ctx)
+        }
+        Some(e) if e.is_parse_error() => formatter.format_parse(e, &ctx),
+        _ => formatter.format_other(&ctx),
+    };
+    let mut response = warp::http::Response::new(body.bytes.into());
+    *response.status_mut() = code;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(body.content_type));
+    if let Some(Error::TooManyRequests { retry_after_secs }) = ctx.rejection.find::<Error>() {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(*retry_after_secs));
+    }
+    Ok(response)
+}
+
+fn log_rejected_body(ctx: &ErrorContext<'_>, body: &str) {
+    let field_errors: Vec<FieldErrorCode> = ctx
+        .rejection
+        .find::<Error>()
+        .and_then(|e| error_status(e, Language::En, ctx.config).2)
+        .into_iter()
+        .flatten()
+        .map(FieldErrorCode::from)
+        .collect();
+    tracing::debug!(
+        request_id = %ctx.request_id,
+        path = ctx.path,
+        error = rejection_code(ctx.rejection),
+        field_errors = %serde_json::to_string(&field_errors).expect("field errors serialize"),
+        body = body,
+        "rejected body"
+    );
+}
+
+/// The status and `ErrorResponse` body `handle_rejection` answers `err` with, for callers that
+/// render it themselves, like the `validate` command of the binary.
+pub fn error_response(
+    err: &Rejection,
+    method: &Method,
+    language: Language,
+    detail_level: DetailLevel,
+    request_id: &str,
+    config: ErrorConfig,
+) -> (StatusCode, ErrorResponse) {
+    let (code, message, mut errors) = if let Some(e) = err.find::<Error>() {
+        error_status(e, language, config)
+    } else if let Some((code, message)) = builtin_rejection_status(err, method) {
+        (code, message, body_deserialize_field_errors(err))
+    } else {
+        tracing::error!(request_id = %request_id, "unhandled error: {:?}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal Server Error".to_string(),
+            None,
+        )
+    };
+    let validators = match err.find::<Error>() {
+        Some(Error::ExplainedValidationError(_)) if detail_level == DetailLevel::Full => {
+            Some(create_request_validators())
+        }
+        _ => None,
+    };
+    let failed_sections = match err.find::<Error>() {
+        Some(e) if !e.reports_body_fields() => Vec::new(),
+        _ => errors.as_deref().map(failed_sections).unwrap_or_default(),
+    };
+    if let Some(errors) = errors.as_mut().filter(|_| config.docs_urls) {
+        link_docs(errors);
+    }
+    // The docs of the error listed first, or of the error as a whole if it has no field errors
+    let docs_url = match (errors.as_deref(), err.find::<Error>()) {
+        (Some([first, ..]), _) if config.docs_urls => first.docs_url.clone(),
+        _ if config.docs_urls => Some(docs_url_for(rejection_code(err))),
+        (Some([first, ..]), Some(Error::ValidationError(_)))
+        | (Some([first, ..]), Some(Error::ExplainedValidationError(_))) => {
+            Some(docs_url_for(&first.code))
+        }
+        _ => None,
+    };
+    let json_error = match err.find::<Error>() {
+        Some(Error::JSONPathError(detail)) if detail_level == DetailLevel::Full => {
+            Some(detail.clone())
+        }
+        _ => None,
+    };
+    let (message, errors) = match detail_level {
+        DetailLevel::Full => (message, errors.map(ReportedErrors::Full)),
+        DetailLevel::Minimal => (
+            minimal_message(err).unwrap_or(message),
+            errors.map(|errors| {
+                ReportedErrors::Minimal(errors.into_iter().map(FieldErrorCode::from).collect())
+            }),
+        ),
+    };
+    let details = ErrorDetails {
+        errors,
+        validators,
+        docs_url,
+        failed_sections,
+        json_error,
+        request_id: request_id.to_string(),
+    };
+    (code, ErrorResponse { message, details })
+}
+
+/// The message of `DetailLevel::Minimal` responses for errors whose usual message quotes serde or
+/// the rejected value, or `None` to keep the usual one.
+fn minimal_message(err: &Rejection) -> Option<String> {
+    match err.find::<Error>() {
+        Some(Error::JSONPathError(detail)) => Some(format!("malformed JSON at {}", detail.path)),
+        Some(Error::FormPathError(_)) => Some("malformed form body".to_string()),
+        Some(Error::YAMLPathError(_)) => Some("malformed YAML".to_string()),
+        Some(Error::MsgPackPathError(_)) => Some("malformed MessagePack".to_string()),
+        Some(Error::Conflict { field, .. }) => Some(format!("{} is already taken", field)),
+        Some(_) => None,
+        None => err
+            .find::<warp::filters::body::BodyDeserializeError>()
+            .map(|_| "malformed JSON".to_string()),
+    }
+}
+
+/// The `FieldError` on the `X-Client-Version` header, with the minimum version in its params.
+fn client_version_error(e: &Error, received: Option<&String>, minimum: &str) -> FieldError {
+    let mut params = serde_json::Map::new();
+    params.insert("minimum".to_string(), minimum.into());
+    header_error(e, "X-Client-Version", params, received)
+}
+
+/// The `FieldError` on the request header `header`, with the value it was sent with, if any.
+fn header_error(
+    e: &Error,
+    header: &str,
+    params: serde_json::Map<String, serde_json::Value>,
+    received: Option<&String>,
+) -> FieldError {
+    FieldError {
+        field: header.to_string(),
+        severity: Severity::Error,
+        code: e.code().to_string(),
+        field_errors: vec![e.to_string()],
+        params,
+        rejected_value: received.cloned().into(),
+        docs_url: None,
+    }
+}
+
+/// Maps a validator error to the machine-readable code clients key translations off, e.g.
+/// `length_min` or `email.invalid`. Codes without a mapping are passed through as they are.
+fn stable_code(error: &ValidationError) -> String {
+    let param = |name: &str| error.params.get(name).and_then(serde_json::Value::as_f64);
+    let bound = |actual: Option<f64>| match actual {
+        Some(actual) if param("min").is_some_and(|min| actual < min) => "_min",
+        Some(actual) if param("max").is_some_and(|max| actual > max) => "_max",
+        _ => "",
+    };
+    match error.code.as_ref() {
+        "length" => {
+            let length = param("length").or_else(|| match error.params.get("value") {
+                Some(serde_json::Value::String(s)) => Some(s.chars().count() as f64),
+                Some(serde_json::Value::Array(items)) => Some(items.len() as f64),
+                _ => None,
+            });
+            format!("length{}", bound(length))
+        }
+        "range" => format!("range{}", bound(param("value"))),
+        "email" => "email.invalid".to_string(),
+        "url" | "invalid_url" => "url.invalid".to_string(),
+        "not_an_image_url" => "url.not_an_image".to_string(),
+        "html_not_allowed" => "html.not_allowed".to_string(),
+        "pet_name_whitespace" => "pet_name.whitespace".to_string(),
+        "pet_name_charset" => "pet_name.charset".to_string(),
+        "pet_name_reserved" => "pet_name.reserved".to_string(),
+        "invalid_phone" => "phone.invalid".to_string(),
+        "birthdate_in_future" => "birthdate.future".to_string(),
+        "birthdate_too_old" => "birthdate.too_old".to_string(),
+        code => code.to_string(),
+    }
+}
+
+/// Flattens `ve` into one `FieldError` per failing field, with nested structs reported under
+/// their full dotted path, e.g. `address.street`, and list elements under their index in the
+/// submitted array, e.g. `pets[2].name`. Structs and lists are walked to any depth, so a field of
+/// a struct inside a list inside a struct comes out as `owners[1].address.geo.lat`.
+pub fn collect_field_errors(
+    prefix: &str,
+    ve: &ValidationErrors,
+    language: Language,
+    out: &mut Vec<FieldError>,
+) {
+    for (field, kind) in ve.errors() {
+        let path = format!("{}{}", prefix, wire_name(field));
+        match kind {
+            // Struct-level rules name the field they belong on in their `field` param.
+            ValidationErrorsKind::Field(field_errs) if *field == "__all__" => {
+                for error in field_errs {
+                    let field = match error.params.get("field") {
+                        Some(serde_json::Value::String(field)) => wire_path(field),
+                        _ => field.to_string(),
+                    };
+                    out.push(FieldError {
+                        field: format!("{}{}", prefix, field),
+                        severity: severity_of(error),
+                        code: stable_code(error),
+                        field_errors: vec![localized_message(&field, error, language)],
+                        params: error_params(error),
+                        rejected_value: serde_json::Value::Null,
+                        docs_url: None,
+                    })
+                }
+            }
+            ValidationErrorsKind::Field(field_errs) => {
+                let mut field_errs: Vec<&ValidationError> = field_errs.iter().collect();
+                field_errs.sort_by_cached_key(|fe| {
+                    (
+                        stable_code(fe),
+                        localized_message(&wire_name(field), fe, language),
+                    )
+                });
+                let first = field_errs.first().copied();
+                let value = first.and_then(|fe| fe.params.get("value"));
+                let rejected_value =
+                    rejected_value(&path, value.unwrap_or(&serde_json::Value::Null));
+                // Messages may quote `{value}`, which mustn't leak what `rejected_value` hides.
+                let redacted = |fe: &ValidationError| match value {
+                    Some(_) if rejected_value.is_null() => {
+                        let mut fe = fe.clone();
+                        fe.add_param("value".into(), &"[redacted]");
+                        fe
+                    }
+                    _ => fe.clone(),
+                };
+                out.push(FieldError {
+                    severity: severity_of_kind(kind),
+                    code: first.map(stable_code).unwrap_or_default(),
+                    field_errors: field_errs
+                        .iter()
+                        .map(|fe| localized_message(&wire_name(field), &redacted(fe), language))
+                        .collect(),
+                    params: first.map(error_params).unwrap_or_default(),
+                    rejected_value,
+                    field: path,
+                    docs_url: None,
+                })
+            }
+            ValidationErrorsKind::Struct(struct_err) => {
+                collect_field_errors(&format!("{}.", path), struct_err, language, out)
+            }
+            ValidationErrorsKind::List(vec_errs) => {
+                for (index, element_errs) in vec_errs {
+                    collect_field_errors(
+                        &format!("{}[{}].", path, index),
+                        element_errs,
+                        language,
+                        out,
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use validator::Validate;
+
+    fn documented(code: &str) -> bool {
+        ERROR_DOC_PAGES
+            .iter()
+            .any(|(documented, _)| *documented == code)
+    }
+
+    /// One of each variant, a new variant needs adding here.
+    fn every_error() -> Vec<Error> {
+        let json = serde_path_to_error::deserialize::<_, serde_json::Value>(
+            &mut serde_json::Deserializer::from_str("{"),
+        )
+        .unwrap_err();
+        let text = String::new;
+        vec![
+            json_error("", json),
+            Error::EmptyBody,
+            Error::InvalidBodyType {
+                expected: "object",
+                received: "array",
+            },
+            Error::JsonTooDeep { max: 1 },
+            Error::TrailingContent { offset: 1 },
+            Error::InvalidUtf8 { offset: 1 },
+            Error::UnsupportedCharset { encoding: "UTF-16" },
+            Error::FormPathError(text()),
+            Error::YAMLPathError(text()),
+            Error::MsgPackPathError(text()),
+            Error::ValidationError(ValidationErrors::new()),
+            Error::ExplainedValidationError(ValidationErrors::new()),
+            Error::Forbidden(text()),
+            Error::MissingApiKey,
+            Error::UnknownApiKey,
+            Error::CorsForbidden { origin: text() },
+            Error::CorsMethodForbidden { method: text() },
+            Error::MissingClientVersion { minimum: text() },
+            Error::InvalidClientVersion {
+                received: text(),
+                minimum: text(),
+            },
+            Error::ClientTooOld {
+                received: text(),
+                minimum: text(),
+            },
+            Error::UnsupportedMediaType {
+                received: None,
+                supported: &[],
+            },
+            Error::UnsupportedContentEncoding(text()),
+            Error::NotAcceptable {
+                received: text(),
+                supported: &[],
+            },
+            Error::UndecodableBody {
+                encoding: "gzip",
+                reason: text(),
+            },
+            Error::PayloadTooLarge {
+                route: "create-basic",
+                limit: 1,
+            },
+            Error::TooManyRows { limit: 1 },
+            Error::TooManyRequests {
+                retry_after_secs: 1,
+            },
+            Error::Timeout { limit_ms: 1 },
+            Error::DigestMismatch {
+                header: "Digest",
+                algorithm: "sha-256",
+                expected: text(),
+                computed: text(),
+            },
+            Error::MalformedDigest {
+                header: "Digest",
+                value: text(),
+                reason: text(),
+            },
+            Error::DigestRequired,
+            Error::UnknownQueryParams(Vec::new()),
+            Error::UnsupportedApiVersion {
+                received: text(),
+                supported: &[],
+            },
+            Error::InvalidQuery(text()),
+            Error::NotFound {
+                resource: "create",
+                id: text(),
+            },
+            Error::Conflict {
+                field: "email",
+                value: text(),
+            },
+            Error::InvalidIdempotencyKey(text()),
+            Error::IdempotencyKeyReused(uuid::Uuid::nil()),
+            Error::BadRequest(text()),
+            Error::UnknownFieldPath(text()),
+            Error::RequiresFullValidation {
+                field: text(),
+                rules: &[],
+            },
+            Error::InvalidSchema(text()),
+            Error::SchemaViolation(Vec::new()),
+            Error::DeserializationErrors(Vec::new()),
+            Error::ParameterErrors(Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn every_error_code_has_a_docs_section() {
+        for error in every_error() {
+            assert!(documented(error.code()), "{} has no section", error.code());
+        }
+    }
+
+    #[test]
+    fn every_validator_code_has_a_docs_section() {
+        let rule_codes = crate::models::CREATE_RULES
+            .iter()
+            .flat_map(|(_, rules)| rules.iter())
+            .flat_map(|rule| rule.codes.iter().copied());
+        // Codes of the other routes' rules and of field errors built outside validator
+        let other_codes = [
+            "range",
+            "schedule_not_chronological",
+            "min_guests_greater_than_max_guests",
+            "min_guests_below_lower_bound",
+            "max_guests_above_upper_bound",
+            "card_number_required_for_card",
+            "paypal_email_required_for_paypal",
+            "cannot_be_null",
+            "invalid_number_string",
+            "negative_not_allowed",
+            "not_an_integer",
+            "number_out_of_range",
+            "unknown_field",
+            "duplicate_key",
+            "invalid_type",
+            "required",
+            "invalid_enum_value",
+            "invalid_date",
+            "json.malformed",
+            "csv.malformed",
+            "invalid_flag",
+            "invalid_id",
+            "unique",
+            "duplicate_part",
+        ];
+        for code in rule_codes.chain(other_codes) {
+            let code = stable_code(&ValidationError::new(code));
+            assert!(documented(&code), "{} has no section", code);
+        }
+    }
+
+    #[derive(Validate)]
+    struct Tag {
+        #[validate(length(min = 2))]
+        label: String,
+    }
+
+    #[derive(Validate)]
+    struct Owner {
+        #[validate(email)]
+        email: String,
+        #[validate]
+        tag: Tag,
+        #[validate]
+        tags: Vec<Tag>,
+    }
+
+    fn tag(label: &str) -> Tag {
+        Tag {
+            label: label.to_string(),
+        }
+    }
+
+    fn field_errors_of(owner: &Owner) -> serde_json::Value {
+        let errors = owner.validate().unwrap_err();
+        serde_json::to_value(validation_field_errors(&errors, Language::En)).unwrap()
+    }
+
+    #[test]
+    fn nested_struct_errors_are_reported_on_their_path() {
+        let owner = Owner {
+            email: "chip@example.com".to_string(),
+            tag: tag("a"),
+            tags: vec![],
+        };
+        assert_eq!(
+            field_errors_of(&owner),
+            serde_json::json!([{
+                "field": "tag.label",
+                "severity": "error",
+                "code": "length_min",
+                "field_errors": ["label must be at least 2 characters"],
+                "params": { "min": 2 },
+                "rejected_value": "a",
+            }])
+        );
+    }
+
+    #[test]
+    fn list_errors_are_reported_on_the_index_of_the_element() {
+        let owner = Owner {
+            email: "chip@example.com".to_string(),
+            tag: tag("ok"),
+            tags: vec![tag("ok"), tag("b"), tag("c")],
+        };
+        assert_eq!(
+            field_errors_of(&owner),
+            serde_json::json!([
+                {
+                    "field": "tags[1].label",
+                    "severity": "error",
+                    "code": "length_min",
+                    "field_errors": ["label must be at least 2 characters"],
+                    "params": { "min": 2 },
+                    "rejected_value": "b",
+                },
+                {
+                    "field": "tags[2].label",
+                    "severity": "error",
+                    "code": "length_min",
+                    "field_errors": ["label must be at least 2 characters"],
+                    "params": { "min": 2 },
+                    "rejected_value": "c",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn field_struct_and_list_errors_are_reported_together() {
+        let owner = Owner {
+            email: "chip".to_string(),
+            tag: tag("a"),
+            tags: vec![tag("b")],
+        };
+        let fields: Vec<_> = field_errors_of(&owner)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| (error["field"].clone(), error["code"].clone()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("email", "email.invalid"),
+                ("tag.label", "length_min"),
+                ("tags[0].label", "length_min"),
+            ]
+            .iter()
+            .map(|(field, code)| ((*field).into(), (*code).into()))
+            .collect::<Vec<(serde_json::Value, serde_json::Value)>>()
+        );
+    }
+
+    fn nudge(_: &str) -> std::result::Result<(), ValidationError> {
+        let mut error = ValidationError::new("nudge");
+        error.add_param("severity".into(), &"warning");
+        Err(error)
+    }
+
+    #[derive(Validate)]
+    struct Profile {
+        #[validate(custom = "nudge")]
+        zeta: String,
+        #[validate(length(min = 5))]
+        omega: String,
+        #[validate(custom = "nudge")]
+        beta: String,
+        #[validate(length(min = 2))]
+        alpha: String,
+    }
+
+    #[test]
+    fn errors_come_before_warnings_each_in_field_order() {
+        let profile = Profile {
+            zeta: String::new(),
+            omega: String::new(),
+            beta: String::new(),
+            alpha: String::new(),
+        };
+        let errors = validation_field_errors(&profile.validate().unwrap_err(), Language::En);
+        let order: Vec<_> = errors
+            .iter()
+            .map(|error| (error.severity, error.field.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (Severity::Error, "alpha"),
+                (Severity::Error, "omega"),
+                (Severity::Warning, "beta"),
+                (Severity::Warning, "zeta"),
+            ]
+        );
+    }
+
+    #[derive(Validate)]
+    struct GeoPoint {
+        #[validate(range(min = -90.0, max = 90.0))]
+        lat: f64,
+    }
+
+    #[derive(Validate)]
+    struct Location {
+        #[validate]
+        geo: GeoPoint,
+        #[validate]
+        addresses: Vec<crate::models::Address>,
+    }
+
+    #[derive(Validate)]
+    struct Household {
+        #[validate]
+        home: Location,
+    }
+
+    fn address(street: &str, street_no: usize) -> crate::models::Address {
+        serde_json::from_value(serde_json::json!({ "street": street, "streetNo": street_no }))
+            .unwrap()
+    }
+
+    #[test]
+    fn errors_three_levels_deep_keep_their_full_path() {
+        let household = Household {
+            home: Location {
+                geo: GeoPoint { lat: 91.0 },
+                addresses: vec![address("warpstreet", 1), address("w", 0)],
+            },
+        };
+        let errors = household.validate().unwrap_err();
+        let errors = validation_field_errors(&errors, Language::En);
+        let fields: Vec<_> = errors
+            .iter()
+            .map(|error| (error.field.as_str(), error.code.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("home.addresses[1].street", "length_min"),
+                ("home.addresses[1].streetNo", "range_min"),
+                ("home.geo.lat", "range_max"),
+            ]
+        );
+        assert_eq!(
+            errors[0].field_errors,
+            ["street must be between 2 and 10 characters"]
+        );
+        assert_eq!(errors[2].field_errors, ["lat must be between -90 and 90"]);
+        assert_eq!(errors[2].rejected_value, 91.0);
+    }
+
+    #[test]
+    fn not_found_is_answered_by_the_builtin_branch() {
+        let err = warp::reject::not_found();
+        assert_eq!(
+            builtin_rejection_status(&err, &Method::GET),
+            Some((StatusCode::NOT_FOUND, "Not Found".to_string()))
+        );
+        assert_eq!(rejection_code(&err), "not_found");
+        assert!(body_deserialize_field_errors(&err).is_none());
+    }
+
+    async fn json_body_rejection<T: serde::de::DeserializeOwned + Send + 'static>(
+        body: &str,
+    ) -> Rejection {
+        warp::test::request()
+            .body(body)
+            .filter(&warp::body::json::<T>())
+            .await
+            .err()
+            .expect("the body was read")
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Named {
+        #[allow(dead_code)]
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn body_deserialize_error_is_a_bad_request_with_serdes_message() {
+        let err = json_body_rejection::<serde_json::Value>("{").await;
+        assert_eq!(
+            builtin_rejection_status(&err, &Method::POST),
+            Some((
+                StatusCode::BAD_REQUEST,
+                "EOF while parsing an object at line 1 column 1".to_string()
+            ))
+        );
+        assert_eq!(rejection_code(&err), "malformed_body");
+        assert!(body_deserialize_field_errors(&err).is_none());
+    }
+
+    #[tokio::test]
+    async fn body_deserialize_error_names_a_missing_or_unknown_field() {
+        for (body, field, code, message) in &[
+            ("{}", "name", "required", "required: missing field `name`"),
+            (
+                r#"{"name":"Chip","age":3}"#,
+                "age",
+                "unknown_field",
+                "unknown_field: unknown field `age`, expected `name`",
+            ),
+        ] {
+            let err = json_body_rejection::<Named>(body).await;
+            assert_eq!(
+                serde_json::to_value(body_deserialize_field_errors(&err)).unwrap(),
+                serde_json::json!([{
+                    "field": field,
+                    "severity": "error",
+                    "code": code,
+                    "field_errors": [message],
+                    "params": {},
+                    "rejected_value": null,
+                }])
+            );
+        }
+    }
+
+    #[test]
+    fn docs_url_links_the_section_of_the_code() {
+        assert_eq!(docs_url_for("length_min"), "/docs/validation#length");
+        assert_eq!(
+            docs_url_for("required_when"),
+            "/docs/validation#required-when"
+        );
+        assert_eq!(docs_url_for("no_such_code"), "/docs/validation");
+    }
+}
@@ -0,0 +1,68 @@
+//! Entity tags of the stored creates and their list, so clients polling them can send
+//! `If-None-Match` and get a `304 Not Modified` instead of the same body again (RFC 7232).
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use warp::http::StatusCode;
+use warp::Reply;
+
+/// A strong tag of `body`, a hash of the bytes, so it changes with any change to them.
+pub fn strong_etag(body: &[u8]) -> String {
+    format!(
+        "\"{}\"",
+        URL_SAFE_NO_PAD.encode(&Sha256::digest(body)[..16])
+    )
+}
+
+/// A weak tag for `opaque`, for bodies that are tagged by a version rather than their bytes.
+pub fn weak_etag(opaque: &str) -> String {
+    format!("W/\"{}\"", opaque)
+}
+
+/// Whether the `If-None-Match` header lists `etag`. Tags are compared the weak way RFC 7232 asks
+/// for here, ignoring `W/`, and `*` matches any tag. A header that doesn't parse as a list of
+/// quoted tags matches nothing, so the request is answered in full.
+pub fn if_none_match(header: Option<&str>, etag: &str) -> bool {
+    let header = match header {
+        Some(header) => header.trim(),
+        None => return false,
+    };
+    if header == "*" {
+        return true;
+    }
+    let etag = opaque_tag(etag);
+    let mut matched = false;
+    for entry in header.split(',') {
+        match opaque_tag(entry.trim()) {
+            Some(tag) => matched |= Some(tag) == etag,
+            None => return false,
+        }
+    }
+    matched
+}
+
+/// The quoted part of `tag`, without a leading `W/`, if it's a well-formed entity tag.
+fn opaque_tag(tag: &str) -> Option<&str> {
+    let tag = tag.strip_prefix("W/").unwrap_or(tag);
+    let opaque = tag.strip_prefix('"')?.strip_suffix('"')?;
+    match opaque.contains('"') {
+        true => None,
+        false => Some(opaque),
+    }
+}
+
+/// `body`, a JSON document, with an `ETag` header of `etag`, or an empty `304 Not Modified`
+/// with the same header if `if_none_match` already lists it.
+pub fn tagged_json(
+    body: Vec<u8>,
+    etag: String,
+    if_none_match: Option<&str>,
+) -> warp::reply::Response {
+    if self::if_none_match(if_none_match, &etag) {
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        return warp::reply::with_header(reply, "etag", etag).into_response();
+    }
+    let reply = warp::reply::with_header(body, "content-type", "application/json");
+    warp::reply::with_header(reply, "etag", etag).into_response()
+}
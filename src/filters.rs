@@ -0,0 +1,1878 @@
+//! Filters that parse, limit and validate requests before they reach a handler.
+
+use crate::buffer_pool::PooledBuffer;
+use crate::config::{ConfigError, ValidationConfig};
+use crate::digest::{digest_required_from_env, verify_body_digest};
+use crate::error::{
+    collect_field_errors, json_rejection, reject, validation_field_errors, wire_name,
+    with_language, Error, FieldError, Language, PathSegment, RejectedBodyLog, Severity,
+    PROBLEM_JSON,
+};
+use crate::models::{
+    check_shape, fit_shape, json_type_name, parse_untyped, CreateInput, CreateRequest,
+    CreateRequestV2, DryRunQuery, ExpectedShape, ExplainQuery, FieldRules, LenientQuery, Normalize,
+    RejectedPet, WithPets, CREATE_API_VERSIONS, CREATE_FIELD_RULES,
+};
+use crate::pipeline::{
+    check_body_type, check_encoding, check_json_depth, parse_and_validate_body, parse_json,
+};
+use crate::store::CreateStore;
+use crate::validation::{AsyncValidate, ValidationContext};
+use crate::Result;
+use bytes::{Buf, Bytes, BytesMut};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use futures::{Stream, StreamExt};
+use semver::Version;
+use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+use warp::http::header::{
+    HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    CONTENT_TYPE, VARY,
+};
+use warp::http::{Method, StatusCode};
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+pub use crate::pipeline::MAX_JSON_DEPTH;
+
+/// Collects the body and deserializes it with `serde_path_to_error`, so a malformed body is
+/// rejected with `Error::JSONPathError` naming the offending field.
+pub fn with_json_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    with_decoded_body().and_then(|bytes: Bytes| async move { parse_json_body::<T>(&bytes) })
+}
+
+const DEFAULT_DECODED_BODY_LIMIT_BYTES: u64 = 1024 * 1024;
+
+/// Collects the body and decompresses it according to its `Content-Encoding`, `gzip` or
+/// `deflate`. The decompressed body may be at most `DECODED_BODY_LIMIT_BYTES` (1 MiB by
+/// default), so a small compressed body can't expand without bound. Undecodable bodies are
+/// rejected with `Error::UndecodableBody`, other encodings with
+/// `Error::UnsupportedContentEncoding`. A body that hasn't fully arrived within
+/// `REQUEST_TIMEOUT_MS` is rejected with `Error::Timeout`.
+///
+/// The body as it was received is checked against its `Digest` and `Content-MD5` headers
+/// first, see `verify_body_digest`. `DIGEST_REQUIRED=true` rejects bodies that have neither.
+pub fn with_decoded_body() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
+    let limit = std::env::var("DECODED_BODY_LIMIT_BYTES")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_DECODED_BODY_LIMIT_BYTES);
+    let digest_required = digest_required_from_env();
+    warp::header::optional::<String>("content-encoding")
+        .and(warp::header::optional::<String>("digest"))
+        .and(warp::header::optional::<String>("content-md5"))
+        .and(with_deadline())
+        .and(warp::body::stream())
+        .and_then(
+            move |encoding: Option<String>,
+                  digest: Option<String>,
+                  content_md5: Option<String>,
+                  deadline: Deadline,
+                  body| async move {
+                let bytes = deadline.run(collect_body(body)).await?;
+                verify_body_digest(
+                    digest.as_deref(),
+                    content_md5.as_deref(),
+                    &bytes,
+                    digest_required,
+                )?;
+                decode_body(encoding.as_deref(), bytes, limit)
+            },
+        )
+}
+
+/// Like `with_decoded_body`, but collects the body into a `PooledBuffer` of the worker thread
+/// instead of a fresh one, for the routes validating bodies that are done with them once the
+/// handler has its value. A compressed body is decompressed into the same buffer.
+pub fn with_pooled_body() -> impl Filter<Extract = (PooledBuffer,), Error = Rejection> + Clone {
+    let limit = std::env::var("DECODED_BODY_LIMIT_BYTES")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_DECODED_BODY_LIMIT_BYTES);
+    let digest_required = digest_required_from_env();
+    warp::header::optional::<String>("content-encoding")
+        .and(warp::header::optional::<String>("digest"))
+        .and(warp::header::optional::<String>("content-md5"))
+        .and(with_deadline())
+        .and(warp::body::stream())
+        .and_then(
+            move |encoding: Option<String>,
+                  digest: Option<String>,
+                  content_md5: Option<String>,
+                  deadline: Deadline,
+                  body| async move {
+                let mut buffer = PooledBuffer::take();
+                deadline.run(collect_body_into(body, &mut buffer)).await?;
+                verify_body_digest(
+                    digest.as_deref(),
+                    content_md5.as_deref(),
+                    &buffer,
+                    digest_required,
+                )?;
+                if let Some(decoded) = decompress(encoding.as_deref(), &buffer, limit)? {
+                    buffer.clear();
+                    buffer.extend_from_slice(&decoded);
+                }
+                Ok::<_, Rejection>(buffer)
+            },
+        )
+}
+
+async fn collect_body(
+    body: impl Stream<Item = std::result::Result<impl Buf, warp::Error>>,
+) -> Result<Bytes> {
+    futures::pin_mut!(body);
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = body.next().await {
+        bytes.extend_from_slice(read_chunk(chunk)?.bytes());
+    }
+    Ok(bytes.freeze())
+}
+
+async fn collect_body_into(
+    body: impl Stream<Item = std::result::Result<impl Buf, warp::Error>>,
+    buffer: &mut Vec<u8>,
+) -> Result<()> {
+    futures::pin_mut!(body);
+    while let Some(chunk) = body.next().await {
+        buffer.extend_from_slice(read_chunk(chunk)?.bytes());
+    }
+    Ok(())
+}
+
+fn read_chunk<B: Buf>(chunk: std::result::Result<B, warp::Error>) -> Result<B> {
+    chunk.map_err(|e| reject(Error::BadRequest(format!("failed to read the body: {}", e))))
+}
+
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+/// How long a request may take to arrive and be validated, set via `REQUEST_TIMEOUT_MS`.
+pub fn request_timeout_from_env() -> Duration {
+    let limit_ms = std::env::var("REQUEST_TIMEOUT_MS")
+        .ok()
+        .and_then(|limit_ms| limit_ms.parse().ok())
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS);
+    Duration::from_millis(limit_ms)
+}
+
+/// The time by which a request has to be done.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: tokio::time::Instant,
+    limit: Duration,
+}
+
+impl Deadline {
+    pub fn after(limit: Duration) -> Self {
+        Deadline {
+            at: tokio::time::Instant::now() + limit,
+            limit,
+        }
+    }
+
+    /// Runs `work` until the deadline and rejects with `Error::Timeout` if it isn't done by then.
+    /// `work` is dropped in that case, so whatever it was waiting on, the rest of a body or an
+    /// async validation, is cancelled rather than left running.
+    pub async fn run<T>(self, work: impl Future<Output = Result<T>>) -> Result<T> {
+        match tokio::time::timeout_at(self.at, work).await {
+            Ok(result) => result,
+            Err(_) => Err(reject(Error::Timeout {
+                limit_ms: self.limit.as_millis() as u64,
+            })),
+        }
+    }
+}
+
+/// A `Deadline` `request_timeout_from_env()` from now.
+pub fn with_deadline() -> impl Filter<Extract = (Deadline,), Error = Infallible> + Clone {
+    let limit = request_timeout_from_env();
+    warp::any().map(move || Deadline::after(limit))
+}
+
+fn decode_body(encoding: Option<&str>, bytes: Bytes, limit: u64) -> Result<Bytes> {
+    Ok(match decompress(encoding, &bytes, limit)? {
+        Some(decoded) => decoded.into(),
+        None => bytes,
+    })
+}
+
+/// `bytes` decompressed according to `encoding`, `None` if they aren't compressed.
+fn decompress(encoding: Option<&str>, bytes: &[u8], limit: u64) -> Result<Option<Vec<u8>>> {
+    let encoding = encoding.map(str::trim).unwrap_or_default();
+    let (encoding, decoder): (_, Box<dyn Read>) = if encoding.eq_ignore_ascii_case("gzip") {
+        ("gzip", Box::new(GzDecoder::new(bytes)))
+    } else if encoding.eq_ignore_ascii_case("deflate") {
+        ("deflate", Box::new(ZlibDecoder::new(bytes)))
+    } else if encoding.is_empty() || encoding.eq_ignore_ascii_case("identity") {
+        return Ok(None);
+    } else {
+        return Err(reject(Error::UnsupportedContentEncoding(
+            encoding.to_string(),
+        )));
+    };
+    let mut decoded = Vec::new();
+    decoder
+        .take(limit + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|e| {
+            reject(Error::UndecodableBody {
+                encoding,
+                reason: e.to_string(),
+            })
+        })?;
+    if decoded.len() as u64 > limit {
+        return Err(reject(Error::UndecodableBody {
+            encoding,
+            reason: format!("it decompresses to more than {} bytes", limit),
+        }));
+    }
+    Ok(Some(decoded))
+}
+
+/// Deserializes a JSON body with `pipeline::parse_json`, rejecting with its `Error`.
+///
+/// Deserializes from one contiguous buffer rather than through a `Reader` over the aggregated
+/// chunks, which copies every byte once more. `benches/body_parsing.rs` compares the two.
+pub fn parse_json_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    parse_json(bytes).map_err(reject)
+}
+
+/// Like `with_json_body`, but picks the format by `Content-Type`: bodies sent as
+/// `application/x-www-form-urlencoded` are deserialized from the form, with nested fields in
+/// bracket notation (`address[street]=Foo`, `pets[0][name]=nacho`), and `application/yaml` or
+/// `application/x-yaml` bodies as YAML and `application/msgpack` bodies as MessagePack. Any
+/// other content type is treated as JSON, which may contain comments and trailing commas if it
+/// is sent as `application/json5` or with `?lenient=true`. JSON objects with a key appearing
+/// twice are rejected, since serde would silently keep the last value.
+pub fn with_negotiated_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    warp::query::<LenientQuery>()
+        .and(warp::header::optional::<String>("content-type"))
+        .and(with_decoded_body())
+        .and_then(
+            |lenient: LenientQuery, content_type: Option<String>, bytes: Bytes| async move {
+                parse_negotiated_body::<T>(content_type.as_deref(), lenient, &bytes)
+            },
+        )
+}
+
+fn parse_negotiated_body<T: DeserializeOwned>(
+    content_type: Option<&str>,
+    lenient: LenientQuery,
+    bytes: &[u8],
+) -> Result<T> {
+    let content_type = content_type.map(media_type).unwrap_or_default();
+    if content_type.eq_ignore_ascii_case(FORM_MEDIA_TYPE) {
+        parse_form_body::<T>(bytes)
+    } else if YAML_MEDIA_TYPES
+        .iter()
+        .any(|yaml| content_type.eq_ignore_ascii_case(yaml))
+    {
+        parse_yaml_body::<T>(bytes)
+    } else if content_type.eq_ignore_ascii_case(MSGPACK_MEDIA_TYPE) {
+        parse_msgpack_body::<T>(bytes)
+    } else if lenient.lenient || content_type.eq_ignore_ascii_case(JSON5_MEDIA_TYPE) {
+        parse_unique_json_body::<T>(&strip_comments_and_trailing_commas(bytes))
+    } else {
+        parse_unique_json_body::<T>(bytes)
+    }
+}
+
+/// `parse_json_body`, rejecting bodies with duplicate keys with an `Error::DeserializationErrors`
+/// listing each duplicated key's path. Derived structs would reject the first duplicate field
+/// themselves, but not those in maps or `serde_json::Value`s, and not all of them at once.
+fn parse_unique_json_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let bytes = check_encoding(bytes).map_err(reject)?;
+    check_json_depth(bytes, MAX_JSON_DEPTH).map_err(reject)?;
+    let mut duplicates = Vec::new();
+    let scanned = KeyScan {
+        path: String::new(),
+        duplicates: &mut duplicates,
+    }
+    .deserialize(&mut serde_json::Deserializer::from_slice(bytes));
+    // Malformed bodies are left to `parse_json_body` to report.
+    if scanned.is_err() || duplicates.is_empty() {
+        return parse_json_body(bytes);
+    }
+    Err(reject(Error::DeserializationErrors(
+        duplicates
+            .into_iter()
+            .map(|path| FieldError {
+                field_errors: vec![format!("duplicate_key: `{}` appears more than once", path)],
+                field: path,
+                severity: Severity::Error,
+                code: "duplicate_key".to_string(),
+                params: serde_json::Map::new(),
+                rejected_value: serde_json::Value::Null,
+                docs_url: None,
+            })
+            .collect(),
+    )))
+}
+
+/// Walks a JSON document, collecting the paths of keys that appear more than once in their
+/// object, like `address.street` or `pets[0].name`.
+struct KeyScan<'a> {
+    path: String,
+    duplicates: &'a mut Vec<String>,
+}
+
+impl<'de> DeserializeSeed<'de> for KeyScan<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeyScan<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        let mut index = 0;
+        while seq
+            .next_element_seed(KeyScan {
+                path: format!("{}[{}]", self.path, index),
+                duplicates: &mut *self.duplicates,
+            })?
+            .is_some()
+        {
+            index += 1;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let path = if self.path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", self.path, key)
+            };
+            if !keys.insert(key) && !self.duplicates.contains(&path) {
+                self.duplicates.push(path.clone());
+            }
+            map.next_value_seed(KeyScan {
+                path,
+                duplicates: &mut *self.duplicates,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Blanks out `//` and `/* */` comments and trailing commas outside of strings. Everything is
+/// replaced by spaces, keeping line breaks, so the positions serde reports for the result are
+/// those of the original body.
+fn strip_comments_and_trailing_commas(bytes: &[u8]) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    let mut in_string = false;
+    // A comma only followed by whitespace and comments so far.
+    let mut pending_comma = None;
+    let mut i = 0;
+    while i < out.len() {
+        match out[i] {
+            b'\\' if in_string => i += 1,
+            b'"' => {
+                in_string = !in_string;
+                pending_comma = None;
+            }
+            _ if in_string => {}
+            b'/' if out.get(i + 1) == Some(&b'/') => {
+                while i < out.len() && out[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if out.get(i + 1) == Some(&b'*') => {
+                let end = out[i + 2..]
+                    .windows(2)
+                    .position(|window| window == b"*/")
+                    .map_or(out.len(), |position| i + 2 + position + 2);
+                for byte in &mut out[i..end] {
+                    if *byte != b'\n' {
+                        *byte = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            b',' => pending_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out[comma] = b' ';
+                }
+            }
+            byte if byte.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Like `with_negotiated_body` followed by `validate_explained_body`, but deserializes into the
+/// version of the create request named by `X-Api-Version` (1 if missing). Validation errors name
+/// the fields of that version, only then is the body turned into a `CreateInput`.
+///
+/// With `LOG_REJECTED_BODIES=true`, rejections carry a redacted copy of the body for the log.
+pub fn with_versioned_create_body(
+    ctx: Arc<ValidationContext>,
+) -> impl Filter<Extract = (ExplainQuery, CreateInput), Error = Rejection> + Clone {
+    with_create_body(ctx, false)
+        .map(|query, input, _: Vec<RejectedPet>| (query, input))
+        .untuple_one()
+}
+
+/// Like `with_versioned_create_body`, but with `X-Allow-Partial: true` a request whose only
+/// validation errors are on its pets goes through with just the valid pets. The pets that were
+/// dropped come along with their errors, none without the header or if all pets are valid.
+///
+/// What's left has to pass validation as a whole again, so `pets_count` applies to the pets that
+/// were kept. If they don't, or anything but the pets is invalid, the request is rejected with
+/// the errors of all of it, like without the header.
+pub fn with_partial_create_body(
+    ctx: Arc<ValidationContext>,
+) -> impl Filter<Extract = (ExplainQuery, CreateInput, Vec<RejectedPet>), Error = Rejection> + Clone
+{
+    with_create_body(ctx, true)
+}
+
+fn with_create_body(
+    ctx: Arc<ValidationContext>,
+    allow_partial: bool,
+) -> impl Filter<Extract = (ExplainQuery, CreateInput, Vec<RejectedPet>), Error = Rejection> + Clone
+{
+    let log = RejectedBodyLog::from_env();
+    warp::any()
+        .map(move || ctx.clone())
+        .and(with_language())
+        .and(warp::header::optional::<String>("x-allow-partial"))
+        .and(warp::header::optional::<String>("x-api-version"))
+        .and(warp::query::<ExplainQuery>())
+        .and(warp::query::<LenientQuery>())
+        .and(warp::header::optional::<String>("content-type"))
+        .and(with_deadline())
+        .and(with_pooled_body())
+        .and_then(
+            move |ctx: Arc<ValidationContext>,
+                  language: Language,
+                  partial: Option<String>,
+                  version: Option<String>,
+                  query: ExplainQuery,
+                  lenient: LenientQuery,
+                  content_type: Option<String>,
+                  deadline: Deadline,
+                  bytes: PooledBuffer| async move {
+                let partial = partial.as_deref().map(str::trim) == Some("true");
+                deadline
+                    .run(parse_and_validate_partial_create(
+                        version.as_deref().map(str::trim).unwrap_or("1"),
+                        content_type.as_deref(),
+                        query,
+                        lenient,
+                        &bytes,
+                        (allow_partial && partial).then_some(language),
+                        &ctx,
+                    ))
+                    .await
+                    .map_err(|err| match log {
+                        Some(log) => log.attach(err, &bytes),
+                        None => err,
+                    })
+            },
+        )
+        .untuple_one()
+}
+
+/// Deserializes and validates a create request of API `version` the way `/create-validator`
+/// does, without anything of the request but its body. The `validate` command of the binary
+/// runs files through it.
+pub async fn parse_and_validate_create(
+    version: &str,
+    content_type: Option<&str>,
+    query: ExplainQuery,
+    lenient: LenientQuery,
+    bytes: &[u8],
+    ctx: &ValidationContext,
+) -> Result<(ExplainQuery, CreateInput)> {
+    let (query, input, _) =
+        parse_and_validate_partial_create(version, content_type, query, lenient, bytes, None, ctx)
+            .await?;
+    Ok((query, input))
+}
+
+/// `parse_and_validate_create` that drops invalid pets as `with_partial_create_body` describes
+/// if `partial` names the language to report their errors in.
+async fn parse_and_validate_partial_create(
+    version: &str,
+    content_type: Option<&str>,
+    query: ExplainQuery,
+    lenient: LenientQuery,
+    bytes: &[u8],
+    partial: Option<Language>,
+    ctx: &ValidationContext,
+) -> Result<(ExplainQuery, CreateInput, Vec<RejectedPet>)> {
+    match version {
+        "1" => {
+            let body: CreateRequest = parse_negotiated_body(content_type, lenient, bytes)?;
+            validate_create_body(query, body, partial, ctx).await
+        }
+        "2" => {
+            let body: CreateRequestV2 = parse_negotiated_body(content_type, lenient, bytes)?;
+            validate_create_body(query, body, partial, ctx).await
+        }
+        received => Err(reject(Error::UnsupportedApiVersion {
+            received: received.to_string(),
+            supported: CREATE_API_VERSIONS,
+        })),
+    }
+}
+
+/// The errors of `result` and the field errors of `async_result` together.
+fn with_async_errors(
+    result: std::result::Result<(), ValidationErrors>,
+    async_result: std::result::Result<(), ValidationErrors>,
+) -> std::result::Result<(), ValidationErrors> {
+    match (result, async_result) {
+        (Err(mut errors), Err(async_errors)) => {
+            for (field, field_errors) in async_errors.field_errors() {
+                for error in field_errors {
+                    errors.add(field, error.clone());
+                }
+            }
+            Err(errors)
+        }
+        (Err(errors), Ok(())) | (Ok(()), Err(errors)) => Err(errors),
+        (Ok(()), Ok(())) => Ok(()),
+    }
+}
+
+/// Validates `value` as the field at `field` of a version 1 create request, e.g.
+/// `address.street` or `pets[2].name`, for `POST /validate-field`.
+///
+/// The value is put into a request that has nothing else and goes through the deserialization,
+/// normalization and validation of create-validator, of which only the errors on `field` are
+/// kept. So a field is checked by the rules attached to it and fails with the same errors it
+/// would in a full request. Paths missing from `CREATE_FIELD_RULES`, and list indices no valid
+/// request reaches, are rejected with `Error::UnknownFieldPath`, fields with only struct-level
+/// rules with `Error::RequiresFullValidation`.
+pub async fn validate_create_field(
+    field: &str,
+    value: serde_json::Value,
+    ctx: &ValidationContext,
+) -> Result<()> {
+    let unknown = || reject(Error::UnknownFieldPath(field.to_string()));
+    let path = parse_field_path(field).ok_or_else(unknown)?;
+    let pattern = field_path_pattern(&path);
+    match CREATE_FIELD_RULES
+        .iter()
+        .find(|(known, _)| *known == pattern)
+    {
+        Some((_, FieldRules::Own)) => {}
+        Some((_, FieldRules::StructLevel(rules))) => {
+            return Err(reject(Error::RequiresFullValidation {
+                field: field.to_string(),
+                rules,
+            }))
+        }
+        None => return Err(unknown()),
+    }
+    let pets_max = ValidationConfig::current().pets_max;
+    if path
+        .iter()
+        .any(|segment| matches!(segment, PathSegment::Index(i) if *i >= pets_max))
+    {
+        return Err(unknown());
+    }
+
+    let mut document = serde_json::json!({
+        "address": { "street": "", "streetNo": 0 },
+        "pets": [],
+    });
+    set_at(&mut document, &path, value);
+    let mut body: CreateRequest =
+        serde_path_to_error::deserialize(document).map_err(|e| json_rejection("", e))?;
+    body.normalize();
+    let result = body.validate();
+    let input = CreateInput::from(body);
+    let result = with_async_errors(result, input.validate_async(ctx).await);
+    match result.err().and_then(|errors| errors_at(errors, &path)) {
+        Some(errors) => Err(reject(Error::ValidationError(errors))),
+        None => Ok(()),
+    }
+}
+
+/// Splits a field path like `pets[2].name` into its keys and indices, `None` if it's malformed.
+fn parse_field_path(field: &str) -> Option<Vec<PathSegment>> {
+    let mut path = Vec::new();
+    for part in field.split('.') {
+        let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if key.is_empty() {
+            return None;
+        }
+        path.push(PathSegment::Key(key.to_string()));
+        while !indices.is_empty() {
+            let (index, rest) = indices.strip_prefix('[')?.split_once(']')?;
+            path.push(PathSegment::Index(index.parse().ok()?));
+            indices = rest;
+        }
+    }
+    Some(path)
+}
+
+/// `path` as it's listed in `CREATE_FIELD_RULES`, e.g. `pets[].name` for `pets[2].name`.
+fn field_path_pattern(path: &[PathSegment]) -> String {
+    let mut pattern = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if pattern.is_empty() => pattern.push_str(key),
+            PathSegment::Key(key) => {
+                pattern.push('.');
+                pattern.push_str(key);
+            }
+            PathSegment::Index(_) | PathSegment::Unknown => pattern.push_str("[]"),
+        }
+    }
+    pattern
+}
+
+/// Sets the value at `path` of `document`, padding lists with pets that have nothing but an
+/// empty name, since `pets` is the only list of a create request.
+fn set_at(document: &mut serde_json::Value, path: &[PathSegment], value: serde_json::Value) {
+    match (path, document) {
+        ([], document) => *document = value,
+        ([PathSegment::Key(key), rest @ ..], serde_json::Value::Object(map)) => {
+            let entry = map.entry(key.clone()).or_insert(serde_json::Value::Null);
+            set_at(entry, rest, value)
+        }
+        ([PathSegment::Index(i), rest @ ..], serde_json::Value::Array(list)) => {
+            while list.len() <= *i {
+                list.push(serde_json::json!({ "name": "" }));
+            }
+            set_at(&mut list[*i], rest, value)
+        }
+        _ => {}
+    }
+}
+
+/// Only the errors of `errors` on the field at `path`, nested the same way, or `None` if there
+/// are none.
+fn errors_at(errors: ValidationErrors, path: &[PathSegment]) -> Option<ValidationErrors> {
+    let (key, rest) = match path {
+        [PathSegment::Key(key), rest @ ..] => (key, rest),
+        _ => return None,
+    };
+    let (field, kind) = errors
+        .into_errors()
+        .into_iter()
+        .find(|(field, _)| wire_name(field) == *key)?;
+    match (kind, rest) {
+        (ValidationErrorsKind::Field(field_errors), []) => {
+            let mut only = ValidationErrors::new();
+            for error in field_errors {
+                only.add(field, error);
+            }
+            Some(only)
+        }
+        (ValidationErrorsKind::Struct(nested), rest) => {
+            let nested = errors_at(*nested, rest)?;
+            ValidationErrors::merge(Ok(()), field, Err(nested)).err()
+        }
+        (ValidationErrorsKind::List(mut elements), [PathSegment::Index(i), rest @ ..]) => {
+            let nested = errors_at(*elements.remove(i)?, rest)?;
+            // `merge_all` expects every element's errors nested under the field name, and takes
+            // an element's index from its position
+            let results = std::iter::repeat_with(|| Ok(()))
+                .take(*i)
+                .chain(std::iter::once(ValidationErrors::merge(
+                    Ok(()),
+                    field,
+                    Err(nested),
+                )))
+                .collect();
+            ValidationErrors::merge_all(Ok(()), field, results).err()
+        }
+        _ => None,
+    }
+}
+
+/// The media type of a `Content-Type` value without parameters such as `charset`.
+pub(crate) fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+pub(crate) const JSON_MEDIA_TYPE: &str = "application/json";
+pub(crate) const FORM_MEDIA_TYPE: &str = "application/x-www-form-urlencoded";
+pub(crate) const YAML_MEDIA_TYPES: &[&str] = &["application/yaml", "application/x-yaml"];
+pub(crate) const MSGPACK_MEDIA_TYPE: &str = "application/msgpack";
+pub(crate) const JSON5_MEDIA_TYPE: &str = "application/json5";
+pub(crate) const CSV_MEDIA_TYPE: &str = "text/csv";
+pub(crate) const NDJSON_MEDIA_TYPE: &str = "application/x-ndjson";
+pub(crate) const MULTIPART_MEDIA_TYPE: &str = "multipart/form-data";
+
+pub(crate) const JSON: &[&str] = &[JSON_MEDIA_TYPE];
+pub(crate) const CSV: &[&str] = &[CSV_MEDIA_TYPE];
+pub(crate) const NDJSON: &[&str] = &[NDJSON_MEDIA_TYPE];
+pub(crate) const MULTIPART: &[&str] = &[MULTIPART_MEDIA_TYPE];
+/// Everything `with_negotiated_body` can parse.
+pub(crate) const NEGOTIATED: &[&str] = &[
+    JSON_MEDIA_TYPE,
+    JSON5_MEDIA_TYPE,
+    FORM_MEDIA_TYPE,
+    "application/yaml",
+    "application/x-yaml",
+    MSGPACK_MEDIA_TYPE,
+];
+
+/// The media ranges of an `Accept` header with their `q` weights. A missing header and one that
+/// doesn't parse both accept anything, like `*/*`, rather than failing requests over a header
+/// most clients never set by hand.
+pub(crate) struct Accept {
+    ranges: Vec<(String, f32)>,
+}
+
+impl Accept {
+    pub(crate) fn parse(header: Option<&str>) -> Self {
+        let any = || Accept {
+            ranges: vec![("*/*".to_string(), 1.0)],
+        };
+        let Some(header) = header.filter(|header| !header.trim().is_empty()) else {
+            return any();
+        };
+        let mut ranges = Vec::new();
+        for range in header.split(',').filter(|range| !range.trim().is_empty()) {
+            let media_range = media_type(range).to_ascii_lowercase();
+            if !media_range
+                .split_once('/')
+                .is_some_and(|(ty, subtype)| !ty.is_empty() && !subtype.is_empty())
+            {
+                return any();
+            }
+            let q = match range
+                .split(';')
+                .skip(1)
+                .find_map(|param| param.trim().strip_prefix("q="))
+            {
+                Some(q) => match q.trim().parse::<f32>() {
+                    Ok(q) if (0.0..=1.0).contains(&q) => q,
+                    _ => return any(),
+                },
+                None => 1.0,
+            };
+            ranges.push((media_range, q));
+        }
+        Accept { ranges }
+    }
+
+    /// The weight of `media_type`, taken from the most specific range that matches it as RFC 7231
+    /// asks for, and whether that range named it exactly. `0` if no range matches.
+    fn quality(&self, media_type: &str) -> (f32, bool) {
+        let ty = media_type.split('/').next().unwrap_or_default();
+        self.ranges
+            .iter()
+            .filter_map(|(range, q)| {
+                let specificity = match range.split_once('/') {
+                    _ if range.eq_ignore_ascii_case(media_type) => 2,
+                    Some((range_ty, "*")) if range_ty.eq_ignore_ascii_case(ty) => 1,
+                    Some(("*", "*")) => 0,
+                    _ => return None,
+                };
+                Some((specificity, *q))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or((0.0, false), |(specificity, q)| (q, specificity == 2))
+    }
+
+    /// Whether any of `media_types` may be sent.
+    pub(crate) fn accepts_any(&self, media_types: &[&str]) -> bool {
+        media_types
+            .iter()
+            .any(|media_type| self.quality(media_type).0 > 0.0)
+    }
+
+    /// Picks the one of `media_types` with the highest weight, the first being the default. At the
+    /// same weight the default wins unless the client named another one exactly, so
+    /// `application/msgpack, application/json` still asks for MessagePack and `*/*` for JSON.
+    pub(crate) fn negotiate<'a>(&self, media_types: &[&'a str]) -> Option<&'a str> {
+        let mut best: Option<(&'a str, f32, bool)> = None;
+        for (i, media_type) in media_types.iter().enumerate() {
+            let (q, named) = self.quality(media_type);
+            let preferred = named && i > 0;
+            if q > 0.0
+                && best.is_none_or(|(_, best_q, best_preferred)| {
+                    q > best_q || (q == best_q && preferred && !best_preferred)
+                })
+            {
+                best = Some((media_type, q, preferred));
+            }
+        }
+        best.map(|(media_type, _, _)| media_type)
+    }
+}
+
+/// How a handler should encode its response, chosen by `with_response_format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseFormat {
+    Json,
+    MessagePack,
+}
+
+/// Picks MessagePack when the `Accept` header weighs `application/msgpack` highest, JSON
+/// otherwise. Requests that accept neither were already turned away by `api_route`.
+pub fn with_response_format() -> impl Filter<Extract = (ResponseFormat,), Error = Rejection> + Clone
+{
+    warp::header::optional::<String>("accept").map(|accept: Option<String>| {
+        match Accept::parse(accept.as_deref()).negotiate(&[JSON_MEDIA_TYPE, MSGPACK_MEDIA_TYPE]) {
+            Some(MSGPACK_MEDIA_TYPE) => ResponseFormat::MessagePack,
+            _ => ResponseFormat::Json,
+        }
+    })
+}
+
+/// Rejects requests whose `Accept` header rules out every one of `produced`, the types the
+/// route can answer with, with `Error::NotAcceptable`. Error responses can always be sent as
+/// `application/problem+json`, so accepting only that is fine too.
+pub(crate) fn with_acceptable(
+    produced: &'static [&'static str],
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("accept")
+        .and_then(move |accept: Option<String>| async move {
+            let acceptable = Accept::parse(accept.as_deref());
+            if acceptable.accepts_any(produced) || acceptable.accepts_any(&[PROBLEM_JSON]) {
+                Ok(())
+            } else {
+                Err(reject(Error::NotAcceptable {
+                    received: accept.unwrap_or_default(),
+                    supported: produced,
+                }))
+            }
+        })
+        .untuple_one()
+}
+
+/// Rejects requests whose `Content-Type` is missing or not one of `supported`, ignoring
+/// parameters like `charset=utf-8`, with `Error::UnsupportedMediaType`.
+pub fn with_content_type(
+    supported: &'static [&'static str],
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("content-type")
+        .and_then(move |content_type: Option<String>| async move {
+            match content_type {
+                Some(ref ct)
+                    if supported
+                        .iter()
+                        .any(|s| media_type(ct).eq_ignore_ascii_case(s)) =>
+                {
+                    Ok(())
+                }
+                received => Err(reject(Error::UnsupportedMediaType {
+                    received,
+                    supported,
+                })),
+            }
+        })
+        .untuple_one()
+}
+
+fn parse_form_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let des = serde_qs::Deserializer::new(bytes)
+        .map_err(|e| reject(Error::FormPathError(e.to_string())))?;
+    parse_untyped(|| serde_path_to_error::deserialize(des))
+        .map_err(|e| reject(Error::FormPathError(e.to_string())))
+}
+
+/// serde_yaml already names the offending field in its errors, so no `serde_path_to_error`.
+fn parse_yaml_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_yaml::from_slice(bytes).map_err(|e| reject(Error::YAMLPathError(e.to_string())))
+}
+
+fn parse_msgpack_body<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let des = &mut rmp_serde::Deserializer::from_read_ref(bytes);
+    serde_path_to_error::deserialize(des)
+        .map_err(|e| reject(Error::MsgPackPathError(e.to_string())))
+}
+
+/// Like `with_json_body`, but additionally normalizes and validates the body, rejecting with
+/// `Error::ValidationError`, so handlers only ever see valid requests. With
+/// `LOG_REJECTED_BODIES=true`, rejections carry a redacted copy of the body for the log.
+pub fn with_validated_json<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Validate + Normalize + Send + 'static,
+{
+    let log = RejectedBodyLog::from_env();
+    with_pooled_body().and_then(move |bytes: PooledBuffer| async move {
+        parse_and_validate_body::<T>(&bytes).map_err(|err| match log {
+            Some(log) => log.attach(reject(err), &bytes),
+            None => reject(err),
+        })
+    })
+}
+
+/// Deserializes and validates the query string, rejecting unparseable values with
+/// `Error::InvalidQuery` naming the parameter rather than warp's opaque `InvalidQuery`.
+pub fn with_validated_query<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Validate + Normalize + Send + 'static,
+{
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and_then(|query: String| async move {
+            let des = serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+            serde_path_to_error::deserialize::<_, T>(des)
+                .map_err(|e| reject(Error::InvalidQuery(e.to_string())))
+        })
+        .and_then(validate_body)
+}
+
+/// Like `with_validated_json`, but checks the parsed body against `T::SHAPE` first, so all
+/// missing fields and type mismatches are reported together as `Error::DeserializationErrors`.
+///
+/// The fields that did fit are validated as well and their errors reported alongside, so a client
+/// learns about `"streetNo": "five"` and a malformed email in one go. To get there, the misfits
+/// are replaced with placeholders by `fit_shape`, and validation errors on those placeholders are
+/// dropped again.
+pub fn with_collected_json<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: ExpectedShape + DeserializeOwned + Validate + Normalize + Send + 'static,
+{
+    with_language()
+        .and(with_json_body::<serde_json::Value>())
+        .and_then(
+            |language: Language, mut value: serde_json::Value| async move {
+                check_body_type::<T>(json_type_name(&value)).map_err(reject)?;
+                let mut errors = Vec::new();
+                check_shape(&value, &T::SHAPE, "", &mut errors);
+                if errors.is_empty() {
+                    return serde_path_to_error::deserialize::<_, T>(value).map_err(reject);
+                }
+
+                fit_shape(&mut value, &T::SHAPE);
+                let mut body: T = serde_path_to_error::deserialize(value).map_err(reject)?;
+                body.normalize();
+                if let Err(e) = body.validate() {
+                    let mut validation_errors = Vec::new();
+                    collect_field_errors("", &e, language, &mut validation_errors);
+                    let misfits: Vec<String> = errors.iter().map(|e| e.field.clone()).collect();
+                    errors.extend(validation_errors.into_iter().filter(|e| {
+                        !misfits.iter().any(|misfit| {
+                            e.field == *misfit
+                                || e.field.starts_with(&format!("{}.", misfit))
+                                || e.field.starts_with(&format!("{}[", misfit))
+                        })
+                    }));
+                }
+                errors.sort_by(|a, b| (a.severity, &a.field).cmp(&(b.severity, &b.field)));
+                Err(reject(Error::DeserializationErrors(errors)))
+            },
+        )
+        .and_then(validate_body)
+}
+
+pub async fn validate_body<T: Validate + Normalize>(mut body: T) -> Result<T> {
+    body.normalize();
+    body.validate().map_err(reject)?;
+    Ok(body)
+}
+
+/// `validate_body` for routes supporting `?explain=true`, where validation errors are rejected
+/// with `Error::ExplainedValidationError` so the response lists the validators per field.
+pub async fn validate_explained_body<T: Validate + Normalize>(
+    query: ExplainQuery,
+    mut body: T,
+) -> Result<(ExplainQuery, T)> {
+    body.normalize();
+    body.validate().map_err(|e| {
+        if query.explain {
+            reject(Error::ExplainedValidationError(e))
+        } else {
+            reject(Error::ValidationError(e))
+        }
+    })?;
+    Ok((query, body))
+}
+
+/// `validate_explained_body` followed by the `AsyncValidate` checks of the resulting
+/// `CreateInput`. Both run even if the first fails, and their errors are rejected together.
+async fn validate_create_body<T>(
+    query: ExplainQuery,
+    mut body: T,
+    partial: Option<Language>,
+    ctx: &ValidationContext,
+) -> Result<(ExplainQuery, CreateInput, Vec<RejectedPet>)>
+where
+    T: Validate + Normalize + WithPets,
+    CreateInput: From<T>,
+{
+    body.normalize();
+    let mut result = body.validate();
+    let mut rejected = Vec::new();
+    if let (Err(errors), Some(language)) = (&result, partial) {
+        if let Some(pets) = rejected_pets(errors, language) {
+            let kept = std::mem::take(body.pets_mut())
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| !pets.iter().any(|pet| pet.index == *index))
+                .map(|(_, pet)| pet)
+                .collect();
+            *body.pets_mut() = kept;
+            // Otherwise the request is rejected with the original errors, so it doesn't matter
+            // that the pets are gone
+            if body.validate().is_ok() {
+                result = Ok(());
+                rejected = pets;
+            }
+        }
+    }
+    let input = CreateInput::from(body);
+    let result = with_async_errors(result, input.validate_async(ctx).await);
+    result.map_err(|e| {
+        if query.explain {
+            reject(Error::ExplainedValidationError(e))
+        } else {
+            reject(Error::ValidationError(e))
+        }
+    })?;
+    Ok((query, input, rejected))
+}
+
+/// The errors of `errors` grouped by the pet they are on, if there are no others. `pets_count`
+/// doesn't count as another, it's checked again once the invalid pets are dropped.
+fn rejected_pets(errors: &ValidationErrors, language: Language) -> Option<Vec<RejectedPet>> {
+    let mut rejected: Vec<RejectedPet> = Vec::new();
+    for error in validation_field_errors(errors, language) {
+        if error.field == "pets" && error.code == "pets_count" {
+            continue;
+        }
+        let index = error
+            .field
+            .strip_prefix("pets[")
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(index, _)| index.parse().ok())?;
+        match rejected.iter_mut().find(|pet| pet.index == index) {
+            Some(pet) => pet.errors.push(error),
+            None => rejected.push(RejectedPet {
+                index,
+                errors: vec![error],
+            }),
+        }
+    }
+    rejected.sort_by_key(|pet| pet.index);
+    Some(rejected)
+}
+
+const DEFAULT_BODY_LIMIT_BYTES: u64 = 64 * 1024;
+
+/// Maximum accepted request body size per route, in bytes.
+///
+/// `BODY_LIMIT_BYTES` overrides the default for every route, and a route specific variable like
+/// `BODY_LIMIT_BYTES_CREATE_VALIDATOR` overrides it for just that route.
+pub struct BodyLimits {
+    default: u64,
+    per_route: HashMap<String, u64>,
+}
+
+impl BodyLimits {
+    pub fn from_env() -> Self {
+        let default = std::env::var("BODY_LIMIT_BYTES")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_BODY_LIMIT_BYTES);
+        let per_route = std::env::vars()
+            .filter_map(|(key, value)| {
+                let route = key.strip_prefix("BODY_LIMIT_BYTES_")?;
+                let limit = value.parse().ok()?;
+                Some((route.to_lowercase().replace('_', "-"), limit))
+            })
+            .collect();
+        BodyLimits { default, per_route }
+    }
+
+    pub fn for_route(&self, route: &str) -> u64 {
+        self.per_route.get(route).copied().unwrap_or(self.default)
+    }
+}
+
+/// Rejects bodies above `limit` with an error naming the route and its limit. Requests without a
+/// `Content-Length` are left to warp's `content_length_limit`, which answers them with a 411.
+pub fn with_body_limit(
+    route: &'static str,
+    limit: u64,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<u64>("content-length")
+        .and_then(move |length: Option<u64>| async move {
+            match length {
+                Some(length) if length > limit => {
+                    Err(reject(Error::PayloadTooLarge { route, limit }))
+                }
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+        .and(warp::body::content_length_limit(limit))
+}
+
+/// What to do with query parameters a route doesn't know about, set via `UNKNOWN_QUERY_PARAMS`.
+///
+/// Tracking parameters (`utm_*`) are always ignored, since clients append them without meaning
+/// anything by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownQueryPolicy {
+    Ignore,
+    Warn,
+    Reject,
+}
+
+impl UnknownQueryPolicy {
+    pub fn from_env() -> Self {
+        match std::env::var("UNKNOWN_QUERY_PARAMS").as_deref() {
+            Ok("warn") => UnknownQueryPolicy::Warn,
+            Ok("reject") => UnknownQueryPolicy::Reject,
+            _ => UnknownQueryPolicy::Ignore,
+        }
+    }
+}
+
+pub fn with_query_policy(
+    policy: UnknownQueryPolicy,
+    known: &'static [&'static str],
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and(warp::path::full())
+        .and_then(move |query: String, path: FullPath| async move {
+            check_query_params(policy, known, &query, path.as_str())
+        })
+        .untuple_one()
+}
+
+fn check_query_params(
+    policy: UnknownQueryPolicy,
+    known: &[&str],
+    query: &str,
+    path: &str,
+) -> Result<()> {
+    if policy == UnknownQueryPolicy::Ignore {
+        return Ok(());
+    }
+
+    let params: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+    let mut unknown: Vec<String> = params
+        .into_iter()
+        .map(|(key, _)| key)
+        .filter(|key| !key.starts_with("utm_") && !known.contains(&key.as_str()))
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    match policy {
+        UnknownQueryPolicy::Reject => Err(reject(Error::UnknownQueryParams(unknown))),
+        _ => {
+            tracing::warn!(
+                path = %path,
+                "unknown query parameters: {}",
+                unknown.join(", ")
+            );
+            Ok(())
+        }
+    }
+}
+
+const DEFAULT_USER_AGENT_DENYLIST: &[&str] = &["sqlmap", "nikto", "masscan"];
+
+/// Reads a comma-separated list of `User-Agent` substrings from `USER_AGENT_DENYLIST`,
+/// falling back to a few well-known scanners if the variable isn't set.
+pub fn user_agent_denylist_from_env() -> Arc<Vec<String>> {
+    let denylist = match std::env::var("USER_AGENT_DENYLIST") {
+        Ok(list) => list
+            .split(',')
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_USER_AGENT_DENYLIST
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect(),
+    };
+    Arc::new(denylist)
+}
+
+/// Rejects requests whose `User-Agent` contains one of the denylisted substrings.
+///
+/// This is a simple heuristic for keeping the noisiest bots away from a public demo, the header
+/// is trivially spoofed, so it's no replacement for real access control.
+pub fn with_user_agent_check(
+    denylist: Arc<Vec<String>>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("user-agent")
+        .and(warp::any().map(move || denylist.clone()))
+        .and_then(check_user_agent)
+        .untuple_one()
+}
+
+async fn check_user_agent(user_agent: Option<String>, denylist: Arc<Vec<String>>) -> Result<()> {
+    let user_agent = match user_agent {
+        Some(user_agent) => user_agent.to_lowercase(),
+        None => return Ok(()),
+    };
+    match denylist
+        .iter()
+        .find(|entry| user_agent.contains(entry.as_str()))
+    {
+        Some(entry) => Err(reject(Error::Forbidden(format!(
+            "user agent matches denylisted pattern '{}'",
+            entry
+        )))),
+        None => Ok(()),
+    }
+}
+
+/// The API keys allowed to call the create routes, loaded at startup from `API_KEYS`
+/// (comma-separated) and `API_KEYS_FILE` (one key per line). Without any keys the create routes
+/// stay open.
+pub struct ApiKeys(Vec<String>);
+
+impl ApiKeys {
+    /// Fails with `ConfigError::UnreadableApiKeysFile` if `API_KEYS_FILE` is set but can't be
+    /// read.
+    pub fn from_env() -> std::result::Result<Self, ConfigError> {
+        let mut keys: Vec<String> = std::env::var("API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .collect();
+        if let Ok(path) = std::env::var("API_KEYS_FILE") {
+            let file =
+                std::fs::read_to_string(&path).map_err(|e| ConfigError::UnreadableApiKeysFile {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                })?;
+            keys.extend(file.lines().map(|key| key.trim().to_string()));
+        }
+        keys.retain(|key| !key.is_empty());
+        Ok(ApiKeys(keys))
+    }
+
+    /// Compares against every key in constant time, so response times don't reveal how much of
+    /// a key was right.
+    fn contains(&self, candidate: &str) -> bool {
+        self.0.iter().fold(false, |found, key| {
+            let same = key.len() == candidate.len()
+                && key
+                    .bytes()
+                    .zip(candidate.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0;
+            found | same
+        })
+    }
+}
+
+/// Rejects requests without an `X-Api-Key` with `Error::MissingApiKey` and those with an unknown
+/// one with `Error::UnknownApiKey`, unless no keys are configured.
+pub fn with_api_key(keys: Arc<ApiKeys>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-api-key")
+        .and_then(move |key: Option<String>| {
+            let keys = keys.clone();
+            async move {
+                match key {
+                    _ if keys.0.is_empty() => Ok(()),
+                    None => Err(reject(Error::MissingApiKey)),
+                    Some(key) if keys.contains(&key) => Ok(()),
+                    Some(_) => Err(reject(Error::UnknownApiKey)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Who a request is attributed to for rate limiting.
+///
+/// Requests presenting a bearer token or an `X-Api-Key` that is one of the `ApiKeys` are bucketed
+/// by that key, all others by the client IP, so a caller can't pick a fresh subject by sending a
+/// made-up key. The IP is the socket's peer address unless `X-Forwarded-For` is trusted, in which
+/// case it's the last address in the header, the one our own proxy appended.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Subject {
+    Key(String),
+    Ip(IpAddr),
+    Anonymous,
+}
+
+impl Subject {
+    fn from_request(
+        keys: &ApiKeys,
+        authorization: Option<String>,
+        api_key: Option<String>,
+        forwarded_for: Option<String>,
+        remote: Option<SocketAddr>,
+    ) -> Self {
+        let bearer = authorization.and_then(|value| {
+            value
+                .strip_prefix("Bearer ")
+                .map(|token| token.trim().to_string())
+        });
+        match bearer.or(api_key).filter(|key| keys.contains(key)) {
+            Some(key) => Subject::Key(key),
+            None => forwarded_for
+                .and_then(|header| header.rsplit(',').next()?.trim().parse().ok())
+                .or_else(|| remote.map(|addr| addr.ip()))
+                .map_or(Subject::Anonymous, Subject::Ip),
+        }
+    }
+}
+
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+pub struct RateWindow {
+    started: Instant,
+    count: u32,
+}
+
+struct RateWindows {
+    by_subject: HashMap<Subject, RateWindow>,
+    last_sweep: Instant,
+}
+
+/// Fixed window rate limiter allowing `limit` requests per subject and window.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    trust_forwarded_for: bool,
+    windows: Mutex<RateWindows>,
+}
+
+impl RateLimiter {
+    fn new(limit: u32, window: Duration, trust_forwarded_for: bool) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            trust_forwarded_for,
+            windows: Mutex::new(RateWindows {
+                by_subject: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// Reads the allowed requests per minute from `RATE_LIMIT_PER_MINUTE`, and whether to take
+    /// the client IP from `X-Forwarded-For` from `RATE_LIMIT_TRUST_FORWARDED_FOR=true`. Only turn
+    /// that on behind a proxy that sets the header, anyone else can put whatever they like in it.
+    pub fn from_env() -> Self {
+        let limit = std::env::var("RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+        let trust_forwarded_for =
+            std::env::var("RATE_LIMIT_TRUST_FORWARDED_FOR").is_ok_and(|value| value == "true");
+        RateLimiter::new(limit, Duration::from_secs(60), trust_forwarded_for)
+    }
+
+    /// Counts a request for `subject`, returning how long to wait if its window is exhausted.
+    ///
+    /// Once per window, subjects whose window has run out are dropped, so the state only holds
+    /// recently seen subjects.
+    fn check(&self, subject: Subject) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().expect("rate limiter lock poisoned");
+        if now.duration_since(windows.last_sweep) >= self.window {
+            let window = self.window;
+            windows
+                .by_subject
+                .retain(|_, w| now.duration_since(w.started) < window);
+            windows.last_sweep = now;
+        }
+        let window = windows.by_subject.entry(subject).or_insert(RateWindow {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.count = 0;
+        }
+        if window.count >= self.limit {
+            return Err(self.window - now.duration_since(window.started));
+        }
+        window.count += 1;
+        Ok(())
+    }
+}
+
+/// Rejects requests whose subject used up its requests of the window with
+/// `Error::TooManyRequests`. Credentials count as a subject only if they are one of `keys`.
+pub fn with_rate_limit(
+    limiter: Arc<RateLimiter>,
+    keys: Arc<ApiKeys>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::any()
+        .map(move || keys.clone())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("x-api-key"))
+        .and(warp::header::optional::<String>("x-forwarded-for"))
+        .and(warp::addr::remote())
+        .and(warp::any().map(move || limiter.clone()))
+        .and_then(check_rate_limit)
+        .untuple_one()
+}
+
+async fn check_rate_limit(
+    keys: Arc<ApiKeys>,
+    authorization: Option<String>,
+    api_key: Option<String>,
+    forwarded_for: Option<String>,
+    remote: Option<SocketAddr>,
+    limiter: Arc<RateLimiter>,
+) -> Result<()> {
+    let forwarded_for = forwarded_for.filter(|_| limiter.trust_forwarded_for);
+    let subject = Subject::from_request(&keys, authorization, api_key, forwarded_for, remote);
+    limiter.check(subject).map_err(|retry_after| {
+        reject(Error::TooManyRequests {
+            retry_after_secs: retry_after.as_secs().max(1),
+        })
+    })
+}
+
+/// The oldest `X-Client-Version` accepted, read from `MIN_CLIENT_VERSION`. Without it, or with
+/// a value that isn't a semantic version, every client is let through.
+pub fn min_client_version_from_env() -> Option<Version> {
+    let minimum = std::env::var("MIN_CLIENT_VERSION").ok()?;
+    match Version::parse(minimum.trim()) {
+        Ok(minimum) => Some(minimum),
+        Err(e) => {
+            tracing::warn!("ignoring MIN_CLIENT_VERSION {:?}: {}", minimum, e);
+            None
+        }
+    }
+}
+
+/// Rejects clients whose `X-Client-Version` is older than `minimum` with `Error::ClientTooOld`,
+/// and those sending none or one that isn't a semantic version like `1.4.0` with
+/// `Error::MissingClientVersion` and `Error::InvalidClientVersion`.
+pub fn with_client_version(
+    minimum: Option<Version>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-client-version")
+        .and_then(move |received: Option<String>| {
+            let minimum = minimum.clone();
+            async move { check_client_version(received, minimum) }
+        })
+        .untuple_one()
+}
+
+fn check_client_version(received: Option<String>, minimum: Option<Version>) -> Result<()> {
+    let minimum = match minimum {
+        Some(minimum) => minimum,
+        None => return Ok(()),
+    };
+    let received = received.ok_or_else(|| {
+        reject(Error::MissingClientVersion {
+            minimum: minimum.to_string(),
+        })
+    })?;
+    let version = Version::parse(received.trim()).map_err(|_| {
+        reject(Error::InvalidClientVersion {
+            received: received.clone(),
+            minimum: minimum.to_string(),
+        })
+    })?;
+    if version < minimum {
+        return Err(reject(Error::ClientTooOld {
+            received,
+            minimum: minimum.to_string(),
+        }));
+    }
+    Ok(())
+}
+
+const DEFAULT_IDEMPOTENCY_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// A response remembered for an `Idempotency-Key`, with a hash of the request it answered.
+struct StoredResponse {
+    fingerprint: [u8; 32],
+    status: StatusCode,
+    content_type: &'static str,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+/// The successful responses of the last `ttl`, by the `Idempotency-Key` they were sent with.
+pub struct IdempotencyStore {
+    ttl: Duration,
+    responses: Mutex<HashMap<Uuid, StoredResponse>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyStore {
+            ttl,
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reads how long responses are kept, in seconds, from `IDEMPOTENCY_TTL_SECS`, a day by
+    /// default.
+    pub fn from_env() -> Self {
+        let ttl_secs = std::env::var("IDEMPOTENCY_TTL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_IDEMPOTENCY_TTL_SECS);
+        IdempotencyStore::new(Duration::from_secs(ttl_secs))
+    }
+}
+
+/// The `Idempotency-Key` of a request, if it sent one, and where to look it up.
+pub struct Idempotency {
+    store: Arc<IdempotencyStore>,
+    key: Option<Uuid>,
+}
+
+impl Idempotency {
+    /// The stored response for this key, marked with `Idempotency-Replayed: true`. A key that
+    /// was used for a request with another fingerprint is rejected with
+    /// `Error::IdempotencyKeyReused`.
+    pub fn replay(&self, fingerprint: &[u8; 32]) -> Result<Option<warp::reply::Response>> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let now = Instant::now();
+        let mut responses = self
+            .store
+            .responses
+            .lock()
+            .expect("idempotency lock poisoned");
+        let ttl = self.store.ttl;
+        responses.retain(|_, stored| now.duration_since(stored.stored_at) < ttl);
+        let stored = match responses.get(&key) {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if &stored.fingerprint != fingerprint {
+            return Err(reject(Error::IdempotencyKeyReused(key)));
+        }
+        let mut response = warp::reply::Response::new(stored.body.clone().into());
+        *response.status_mut() = stored.status;
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(stored.content_type));
+        headers.insert("idempotency-replayed", HeaderValue::from_static("true"));
+        Ok(Some(response))
+    }
+
+    /// Keeps a successful response so retries with the same key get it again. Failed requests
+    /// aren't remembered, a client can fix its request and retry with the same key.
+    pub fn remember(
+        &self,
+        fingerprint: [u8; 32],
+        status: StatusCode,
+        content_type: &'static str,
+        body: Bytes,
+    ) {
+        if let Some(key) = self.key {
+            let stored = StoredResponse {
+                fingerprint,
+                status,
+                content_type,
+                body,
+                stored_at: Instant::now(),
+            };
+            self.store
+                .responses
+                .lock()
+                .expect("idempotency lock poisoned")
+                .insert(key, stored);
+        }
+    }
+}
+
+/// Extracts the request's `Idempotency-Key`, rejecting keys that aren't UUIDs with
+/// `Error::InvalidIdempotencyKey`. Requests without the header aren't deduplicated.
+pub fn with_idempotency_key(
+    store: Arc<IdempotencyStore>,
+) -> impl Filter<Extract = (Idempotency,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("idempotency-key").and_then(move |key: Option<String>| {
+        let store = store.clone();
+        async move {
+            let key = match key {
+                Some(key) => Some(
+                    Uuid::parse_str(key.trim())
+                        .map_err(|_| reject(Error::InvalidIdempotencyKey(key)))?,
+                ),
+                None => None,
+            };
+            Ok::<_, Rejection>(Idempotency { store, key })
+        }
+    })
+}
+
+/// Reads `?dry_run=`, which has to be `true` or `false`. Anything else, like `1` or `yes`, is
+/// rejected with a field error on `dry_run` rather than guessed at.
+pub fn with_dry_run() -> impl Filter<Extract = (DryRunQuery,), Error = Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and_then(|query: String| async move {
+            let value = form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == "dry_run")
+                .map(|(_, value)| value.into_owned());
+            let dry_run = match value.as_deref() {
+                None | Some("false") => false,
+                Some("true") => true,
+                Some(value) => {
+                    let mut params = serde_json::Map::new();
+                    params.insert("expected".to_string(), "true or false".into());
+                    return Err(reject(Error::ParameterErrors(vec![FieldError {
+                        field: "dry_run".to_string(),
+                        severity: Severity::Error,
+                        code: "invalid_flag".to_string(),
+                        field_errors: vec![format!(
+                            "invalid_flag: dry_run must be true or false, got `{}`",
+                            value
+                        )],
+                        params,
+                        rejected_value: value.into(),
+                        docs_url: None,
+                    }])));
+                }
+            };
+            Ok::<_, Rejection>(DryRunQuery { dry_run })
+        })
+}
+
+/// The `If-None-Match` header of the GET routes that tag their bodies, `None` if it's missing or
+/// not a string, which `crate::etag::if_none_match` treats the same.
+pub fn with_if_none_match() -> impl Filter<Extract = (Option<String>,), Error = Infallible> + Clone
+{
+    warp::header::optional::<String>("if-none-match")
+        .or(warp::any().map(|| None))
+        .unify()
+}
+
+/// Hands the handlers the store the creates are kept in.
+pub fn with_store(
+    store: Arc<CreateStore>,
+) -> impl Filter<Extract = (Arc<CreateStore>,), Error = Infallible> + Clone {
+    warp::any().map(move || store.clone())
+}
+
+const DEFAULT_CORS_MAX_AGE_SECS: u64 = 600;
+
+/// Which browser origins may call the API, read from `CORS_ALLOWED_ORIGINS` (comma-separated,
+/// empty by default, `*` allows any origin) and `CORS_MAX_AGE_SECS`.
+pub struct CorsConfig {
+    allowed_origins: Vec<String>,
+    max_age_secs: u64,
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Self {
+        let allowed_origins = std::env::var("CORS_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        let max_age_secs = std::env::var("CORS_MAX_AGE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_CORS_MAX_AGE_SECS);
+        CorsConfig {
+            allowed_origins,
+            max_age_secs,
+        }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    }
+}
+
+/// The methods preflights may ask for, those of the routes in `API_ROUTES`.
+const CORS_ALLOWED_METHODS: &[&str] = &["GET", "POST", "PATCH"];
+
+/// The request headers preflights may ask for: every header the filters read that browsers
+/// don't allow on their own.
+const CORS_ALLOWED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-encoding",
+    "content-md5",
+    "digest",
+    "accept-language",
+    "authorization",
+    "x-api-key",
+    "x-api-version",
+    "x-allow-partial",
+    "x-error-detail",
+    "x-request-id",
+    "x-client-version",
+    "idempotency-key",
+    "if-none-match",
+];
+
+/// Rejects requests from a browser origin `CORS_ALLOWED_ORIGINS` doesn't list with a `403`, so
+/// the frontend gets an error it can read in the network tab instead of a response the browser
+/// hides, and so are preflights asking for a method outside `CORS_ALLOWED_METHODS`. Requests
+/// without an `Origin`, and every request while no origins are configured, pass.
+pub fn with_origin_check(
+    cors: Arc<CorsConfig>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::method())
+        .and(warp::header::optional::<String>(
+            "access-control-request-method",
+        ))
+        .and(warp::any().map(move || cors.clone()))
+        .and_then(check_origin)
+        .untuple_one()
+}
+
+async fn check_origin(
+    origin: Option<String>,
+    method: Method,
+    requested_method: Option<String>,
+    cors: Arc<CorsConfig>,
+) -> Result<()> {
+    if cors.allowed_origins.is_empty() {
+        return Ok(());
+    }
+    match (origin, requested_method) {
+        (Some(origin), _) if !cors.allows(&origin) => Err(reject(Error::CorsForbidden { origin })),
+        (Some(_), Some(requested))
+            if method == Method::OPTIONS && !cors_allows_method(&requested) =>
+        {
+            Err(reject(Error::CorsMethodForbidden { method: requested }))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn cors_allows_method(method: &str) -> bool {
+    CORS_ALLOWED_METHODS.contains(&method)
+}
+
+/// Answers CORS preflights from allowed origins and adds the CORS headers to every response of
+/// `filter` whose `Origin` is allowed, including the error responses `with_request_handling`
+/// produces, so browsers let the frontend read them. Preflights from other origins are left to
+/// `filter`, whose `with_origin_check` turns them away like their requests, as are preflights
+/// asking for a method no route has.
+pub fn with_cors<F>(
+    cors: Arc<CorsConfig>,
+    filter: F,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + Send + Sync,
+{
+    let allowed = cors.clone();
+    let preflight = warp::options()
+        .and(warp::header::<String>("access-control-request-method"))
+        .and(warp::header::<String>("origin"))
+        .and_then(move |method: String, origin: String| {
+            let allowed = allowed.allows(&origin) && cors_allows_method(&method);
+            async move {
+                if allowed {
+                    Ok(())
+                } else {
+                    Err(warp::reject())
+                }
+            }
+        })
+        .map(|()| {
+            let mut response = warp::reply::Response::default();
+            *response.status_mut() = StatusCode::NO_CONTENT;
+            (true, response)
+        });
+    warp::header::optional::<String>("origin")
+        .or(warp::any().map(|| None))
+        .unify()
+        .and(
+            preflight
+                .or(filter.map(|response| (false, response)))
+                .unify(),
+        )
+        .map(
+            move |origin: Option<String>,
+                  (preflight, mut response): (bool, warp::reply::Response)| {
+                let headers = response.headers_mut();
+                headers.append(VARY, HeaderValue::from_static("origin"));
+                let origin = match origin {
+                    Some(origin) if cors.allows(&origin) => origin,
+                    _ => return response,
+                };
+                if let Ok(origin) = HeaderValue::from_str(&origin) {
+                    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+                }
+                if preflight {
+                    headers.insert(
+                        ACCESS_CONTROL_ALLOW_METHODS,
+                        HeaderValue::from_str(&CORS_ALLOWED_METHODS.join(", "))
+                            .expect("method names are valid header values"),
+                    );
+                    headers.insert(
+                        ACCESS_CONTROL_ALLOW_HEADERS,
+                        HeaderValue::from_str(&CORS_ALLOWED_HEADERS.join(", "))
+                            .expect("header names are valid header values"),
+                    );
+                    headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(cors.max_age_secs));
+                } else {
+                    headers.insert(
+                        ACCESS_CONTROL_EXPOSE_HEADERS,
+                        HeaderValue::from_static("x-request-id, retry-after, idempotency-replayed, location, etag, x-coercions"),
+                    );
+                }
+                response
+            },
+        )
+}
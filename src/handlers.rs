@@ -0,0 +1,987 @@
+//! Route handlers, called with requests that already passed the route's filters.
+
+use crate::error::{
+    collect_field_errors, error_response, json_rejection, reject, validation_field_errors,
+    DetailLevel, Error, ErrorConfig, FieldError, Language, Severity,
+};
+use crate::etag::{strong_etag, tagged_json, weak_etag};
+use crate::filters::{
+    media_type, parse_and_validate_create, parse_json_body, validate_body, validate_create_field,
+    Idempotency, ResponseFormat, JSON, JSON_MEDIA_TYPE, NDJSON_MEDIA_TYPE,
+};
+use crate::health::{HealthStatus, Readiness};
+use crate::models::*;
+use crate::store::CreateStore;
+use crate::validation::ValidationContext;
+use crate::Result;
+use bytes::buf::{Buf, BufExt};
+use bytes::Bytes;
+use futures::{SinkExt, Stream, StreamExt};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use warp::filters::multipart::{FormData, Part};
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::http::{HeaderValue, Method};
+use warp::{http::StatusCode, Reply};
+
+fn created(body: impl Serialize) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&body), StatusCode::CREATED).into_response()
+}
+
+/// `body` encoded in `format`, with the content type to send it as.
+fn encode_as(format: ResponseFormat, body: impl Serialize) -> (&'static str, Bytes) {
+    match format {
+        ResponseFormat::Json => (
+            "application/json",
+            serde_json::to_vec(&body)
+                .expect("responses serialize to JSON")
+                .into(),
+        ),
+        // Field names are kept, so the document reads like the JSON one.
+        ResponseFormat::MessagePack => (
+            "application/msgpack",
+            rmp_serde::to_vec_named(&body)
+                .expect("responses serialize to MessagePack")
+                .into(),
+        ),
+    }
+}
+
+/// A hash of everything that decides a create's response, so a retry can be told apart from a
+/// different request reusing its `Idempotency-Key`.
+fn fingerprint(request: impl Serialize) -> [u8; 32] {
+    let bytes = serde_json::to_vec(&request).expect("requests serialize to JSON");
+    Sha256::digest(&bytes).into()
+}
+
+pub async fn create_handler(body: CreateRequest) -> Result<impl Reply> {
+    Ok(created(CreateResponse::from(body)))
+}
+
+pub async fn create_handler_path(body: CreateRequest) -> Result<impl Reply> {
+    Ok(created(CreateResponse::from(body)))
+}
+
+pub async fn update_handler(body: UpdateRequest) -> Result<impl Reply> {
+    Ok(format!("called with: {:?}", body))
+}
+
+pub async fn create_handler_validator(
+    dry_run: DryRunQuery,
+    query: ExplainQuery,
+    body: CreateInput,
+    rejected: Vec<RejectedPet>,
+    format: ResponseFormat,
+    idempotency: Idempotency,
+    store: Arc<CreateStore>,
+) -> Result<impl Reply> {
+    // Pets dropped by `X-Allow-Partial` turn the answer into a `207 Multi-Status`
+    let partial = !rejected.is_empty();
+    let pets = body.pets.len();
+    let coercions = body.coercions();
+    // A dry run reports a conflict like the real create would, but neither stores anything nor
+    // replays or remembers an idempotency key.
+    if dry_run.dry_run {
+        store.check_insert(&body)?;
+        let (content_type, bytes) =
+            encode_partial(format, DryRunResponse { valid: true }, pets, rejected);
+        let status = if partial {
+            StatusCode::MULTI_STATUS
+        } else {
+            StatusCode::OK
+        };
+        let reply = warp::reply::with_header(bytes.to_vec(), "content-type", content_type);
+        let response = warp::reply::with_status(reply, status).into_response();
+        return Ok(with_coercions(response, &coercions));
+    }
+    let fingerprint = fingerprint((query.explain, &body));
+    if let Some(replayed) = idempotency.replay(&fingerprint)? {
+        return Ok(replayed);
+    }
+    let stored = store.insert(body)?;
+    let location = format!("/creates/{}", stored.id);
+    let response = CreateResponse::from(&stored);
+    let (content_type, bytes) = if query.explain {
+        encode_partial(
+            format,
+            ExplainResponse {
+                created: response,
+                validators: create_request_validators(),
+            },
+            pets,
+            rejected,
+        )
+    } else {
+        encode_partial(format, response, pets, rejected)
+    };
+    let status = if partial {
+        StatusCode::MULTI_STATUS
+    } else {
+        StatusCode::CREATED
+    };
+    idempotency.remember(fingerprint, status, content_type, bytes.clone());
+    let reply = warp::reply::with_header(bytes.to_vec(), "content-type", content_type);
+    let reply = warp::reply::with_header(reply, "location", location);
+    let response = warp::reply::with_status(reply, status).into_response();
+    Ok(with_coercions(response, &coercions))
+}
+
+/// `response` with the fields that were sent as strings and coerced in `X-Coercions`, like
+/// `X-Coercions: address.streetNo`, so clients learn what to fix. Without any it's unchanged.
+fn with_coercions(
+    mut response: warp::reply::Response,
+    coercions: &[&str],
+) -> warp::reply::Response {
+    if !coercions.is_empty() {
+        let fields = HeaderValue::from_str(&coercions.join(", ")).expect("field paths are ASCII");
+        response.headers_mut().insert("x-coercions", fields);
+    }
+    response
+}
+
+/// Encodes `result` as `format`, as a `PartialCreateResponse` if any pets were `rejected`.
+/// `accepted` is the number of pets that were kept.
+fn encode_partial(
+    format: ResponseFormat,
+    result: impl Serialize,
+    accepted: usize,
+    rejected: Vec<RejectedPet>,
+) -> (&'static str, Bytes) {
+    if rejected.is_empty() {
+        return encode_as(format, result);
+    }
+    let accepted = (0..accepted + rejected.len())
+        .filter(|index| !rejected.iter().any(|pet| pet.index == *index))
+        .collect();
+    encode_as(
+        format,
+        PartialCreateResponse {
+            result,
+            accepted,
+            rejected,
+        },
+    )
+}
+
+/// Reads back a create stored by `POST /create-validator`. Ids that aren't UUIDs are rejected
+/// with an `invalid_id` field error.
+pub async fn get_create_handler(
+    id: String,
+    if_none_match: Option<String>,
+    store: Arc<CreateStore>,
+) -> Result<impl Reply> {
+    let uuid = Uuid::parse_str(&id).map_err(|_| {
+        reject(Error::ParameterErrors(vec![FieldError {
+            field: "id".to_string(),
+            severity: Severity::Error,
+            code: "invalid_id".to_string(),
+            field_errors: vec![format!("invalid_id: id must be a UUID, got `{}`", id)],
+            params: serde_json::Map::new(),
+            rejected_value: id.clone().into(),
+            docs_url: None,
+        }]))
+    })?;
+    match store.get(&uuid) {
+        Some(stored) => {
+            let body = serde_json::to_vec(&stored).expect("stored creates serialize to JSON");
+            let etag = strong_etag(&body);
+            Ok(tagged_json(body, etag, if_none_match.as_deref()))
+        }
+        None => Err(reject(Error::NotFound {
+            resource: "create",
+            id,
+        })),
+    }
+}
+
+/// Tagged with the version of the store and the page, since a list can't change without the
+/// store changing.
+pub async fn list_creates_handler(
+    query: ListCreatesQuery,
+    if_none_match: Option<String>,
+    store: Arc<CreateStore>,
+) -> Result<impl Reply> {
+    let etag = weak_etag(&format!(
+        "{}-{}-{}",
+        store.version(),
+        query.offset,
+        query.limit
+    ));
+    let body = serde_json::to_vec(&store.list(query.offset, query.limit))
+        .expect("stored creates serialize to JSON");
+    Ok(tagged_json(body, etag, if_none_match.as_deref()))
+}
+
+pub async fn list_pets_handler(query: ListPetsQuery) -> Result<impl Reply> {
+    let pets: Vec<Pet> = DEMO_PET_NAMES
+        .iter()
+        .filter(|name| {
+            query
+                .name_contains
+                .as_ref()
+                .is_none_or(|filter| name.contains(filter.as_str()))
+        })
+        .skip(query.offset)
+        .take(query.limit)
+        .map(|name| Pet {
+            name: name.to_string(),
+            photo_url: None,
+            species: None,
+            birthdate: None,
+        })
+        .collect();
+    Ok(warp::reply::json(&pets))
+}
+
+/// Looks up one of the demo pets by id, its position in the list `GET /pets` returns counting
+/// from 1. Ids that aren't positive integers are rejected with an `invalid_id` field error.
+pub async fn get_pet_handler(id: String) -> Result<impl Reply> {
+    let index = match id.parse::<usize>() {
+        Ok(index) if index >= 1 => index,
+        _ => {
+            return Err(reject(Error::ParameterErrors(vec![FieldError {
+                field: "id".to_string(),
+                severity: Severity::Error,
+                code: "invalid_id".to_string(),
+                field_errors: vec![format!(
+                    "invalid_id: id must be a positive integer, got `{}`",
+                    id
+                )],
+                params: serde_json::Map::new(),
+                rejected_value: id.into(),
+                docs_url: None,
+            }])));
+        }
+    };
+    match DEMO_PET_NAMES.get(index - 1) {
+        Some(name) => Ok(warp::reply::json(&Pet {
+            name: name.to_string(),
+            photo_url: None,
+            species: None,
+            birthdate: None,
+        })),
+        None => Err(reject(Error::NotFound {
+            resource: "pet",
+            id,
+        })),
+    }
+}
+
+pub async fn create_handler_collect(body: CreateRequest) -> Result<impl Reply> {
+    Ok(created(CreateResponse::from(body)))
+}
+
+const DEFAULT_BATCH_MAX_ITEMS: usize = 100;
+
+/// The most elements `/create-batch` accepts, set via `BATCH_MAX_ITEMS`.
+pub fn batch_max_items_from_env() -> usize {
+    std::env::var("BATCH_MAX_ITEMS")
+        .ok()
+        .and_then(|max_items| max_items.parse().ok())
+        .unwrap_or(DEFAULT_BATCH_MAX_ITEMS)
+}
+
+/// Validates every element on its own and answers with `207 Multi-Status`, listing each element
+/// as created or with its errors on paths like `[2].address.street`.
+pub async fn create_batch_handler(
+    max_items: usize,
+    language: Language,
+    body: Vec<CreateRequest>,
+) -> Result<impl Reply> {
+    if body.len() > max_items {
+        return Err(reject(Error::BadRequest(format!(
+            "batch of {} items exceeds the limit of {}",
+            body.len(),
+            max_items
+        ))));
+    }
+    let results: Vec<BatchItemResult> = body
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut request)| {
+            request.normalize();
+            match request.validate() {
+                Ok(()) => BatchItemResult::Created {
+                    index,
+                    created: CreateResponse::from(request),
+                },
+                Err(e) => {
+                    let mut errors = Vec::new();
+                    collect_field_errors(&format!("[{}].", index), &e, language, &mut errors);
+                    BatchItemResult::Invalid { index, errors }
+                }
+            }
+        })
+        .collect();
+    Ok(warp::reply::with_status(
+        warp::reply::json(&results),
+        StatusCode::MULTI_STATUS,
+    ))
+}
+
+const DEFAULT_STREAM_MAX_LINE_BYTES: usize = 64 * 1024;
+const DEFAULT_STREAM_MAX_LINES: usize = 10_000;
+
+/// How long a line of `/create-stream` may be and how many lines it may have, set via
+/// `STREAM_MAX_LINE_BYTES` and `STREAM_MAX_LINES`.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamLimits {
+    pub max_line_bytes: usize,
+    pub max_lines: usize,
+}
+
+impl StreamLimits {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        StreamLimits {
+            max_line_bytes: var("STREAM_MAX_LINE_BYTES", DEFAULT_STREAM_MAX_LINE_BYTES),
+            max_lines: var("STREAM_MAX_LINES", DEFAULT_STREAM_MAX_LINES),
+        }
+    }
+}
+
+/// Reads newline-delimited create requests as they arrive and answers with one NDJSON record per
+/// input line. Bad lines are reported and skipped; a line over the length limit, one past the
+/// line limit, or a broken body stream end the response with an `aborted` record.
+pub async fn create_stream_handler(
+    limits: StreamLimits,
+    language: Language,
+    body: impl Stream<Item = std::result::Result<impl Buf, warp::Error>>,
+) -> Result<impl Reply> {
+    futures::pin_mut!(body);
+    let mut output = Vec::new();
+    let mut pending = Vec::new();
+    let mut line = 0;
+    let aborted = 'read: loop {
+        let chunk = match body.next().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => break format!("failed to read the body: {}", e),
+            None => break 'read String::new(),
+        };
+        pending.extend_from_slice(chunk.bytes());
+        while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+            line += 1;
+            if line > limits.max_lines {
+                break 'read format!("the stream exceeds the limit of {} lines", limits.max_lines);
+            }
+            let record: Vec<u8> = pending.drain(..=end).collect();
+            if record.len() - 1 > limits.max_line_bytes {
+                break 'read format!("line exceeds the limit of {} bytes", limits.max_line_bytes);
+            }
+            write_stream_line(&mut output, stream_line_result(line, &record, language));
+        }
+        if pending.len() > limits.max_line_bytes {
+            line += 1;
+            break format!("line exceeds the limit of {} bytes", limits.max_line_bytes);
+        }
+    };
+    if !aborted.is_empty() {
+        write_stream_line(
+            &mut output,
+            StreamLineResult::Aborted {
+                line,
+                message: aborted,
+            },
+        );
+    } else if !pending.iter().all(u8::is_ascii_whitespace) {
+        // The last line doesn't need a trailing newline.
+        line += 1;
+        let result = if line > limits.max_lines {
+            StreamLineResult::Aborted {
+                line,
+                message: format!("the stream exceeds the limit of {} lines", limits.max_lines),
+            }
+        } else {
+            stream_line_result(line, &pending, language)
+        };
+        write_stream_line(&mut output, result);
+    }
+    Ok(warp::reply::with_header(
+        output,
+        "content-type",
+        NDJSON_MEDIA_TYPE,
+    ))
+}
+
+fn stream_line_result(line: usize, record: &[u8], language: Language) -> StreamLineResult {
+    let record = record.strip_suffix(b"\n").unwrap_or(record);
+    let record = record.strip_suffix(b"\r").unwrap_or(record);
+    let mut des = serde_json::Deserializer::from_slice(record);
+    let parsed = match serde_path_to_error::deserialize::<_, CreateRequest>(&mut des) {
+        Ok(request) => des.end().map(|()| request).map_err(|e| (String::new(), e)),
+        Err(e) => {
+            let path = e.path().to_string();
+            let path = if path == "." { String::new() } else { path };
+            Err((path, e.into_inner()))
+        }
+    };
+    let mut request = match parsed {
+        Ok(request) => request,
+        Err((path, e)) => {
+            return StreamLineResult::Invalid {
+                line,
+                errors: vec![FieldError {
+                    field: path,
+                    severity: Severity::Error,
+                    code: "json.malformed".to_string(),
+                    field_errors: vec![e.to_string()],
+                    params: serde_json::Map::new(),
+                    rejected_value: serde_json::Value::Null,
+                    docs_url: None,
+                }],
+            };
+        }
+    };
+    request.normalize();
+    match request.validate() {
+        Ok(()) => StreamLineResult::Created {
+            line,
+            created: CreateResponse::from(request),
+        },
+        Err(e) => StreamLineResult::Invalid {
+            line,
+            errors: validation_field_errors(&e, language),
+        },
+    }
+}
+
+fn write_stream_line(output: &mut Vec<u8>, result: StreamLineResult) {
+    serde_json::to_writer(&mut *output, &result).expect("stream results serialize to JSON");
+    output.push(b'\n');
+}
+
+pub fn liveness_handler() -> impl Reply {
+    warp::reply::json(&HealthStatus::ok())
+}
+
+pub fn readiness_handler(readiness: Arc<Readiness>) -> impl Reply {
+    let (status, body) = readiness.status();
+    warp::reply::with_status(warp::reply::json(&body), status)
+}
+
+const DEFAULT_WS_MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+/// The longest text message `/ws/create` accepts, set via `WS_MAX_MESSAGE_BYTES`. Longer ones
+/// are answered with an error instead of closing the socket.
+pub fn ws_max_message_bytes_from_env() -> usize {
+    std::env::var("WS_MAX_MESSAGE_BYTES")
+        .ok()
+        .and_then(|max_bytes| max_bytes.parse().ok())
+        .unwrap_or(DEFAULT_WS_MAX_MESSAGE_BYTES)
+}
+
+/// Upgrades to a WebSocket on which every text message is a create request, see
+/// `create_ws_session`.
+pub fn create_ws_handler(
+    ws: Ws,
+    max_message_bytes: usize,
+    language: Language,
+    detail_level: DetailLevel,
+    store: Arc<CreateStore>,
+    ctx: Arc<ValidationContext>,
+    config: ErrorConfig,
+) -> impl Reply {
+    ws.on_upgrade(move |socket| async move {
+        let session = WsSession {
+            max_message_bytes,
+            language,
+            detail_level,
+            store,
+            ctx,
+            config,
+            id: Uuid::new_v4().to_string(),
+        };
+        session.run(socket).await
+    })
+}
+
+/// What a `/ws/create` connection needs to answer its messages. `id` stands in for the request
+/// ID in error replies and logs.
+struct WsSession {
+    max_message_bytes: usize,
+    language: Language,
+    detail_level: DetailLevel,
+    store: Arc<CreateStore>,
+    ctx: Arc<ValidationContext>,
+    config: ErrorConfig,
+    id: String,
+}
+
+impl WsSession {
+    /// Answers every text message in order with `{"ok":true,"id":...}` or the `ErrorResponse`
+    /// `/create-validator` would have sent. Binary and oversized messages get an error reply too,
+    /// while pings and closes are answered by the WebSocket layer itself.
+    async fn run(self, socket: WebSocket) {
+        tracing::info!(request_id = %self.id, "websocket opened");
+        let (mut replies, mut messages) = socket.split();
+        while let Some(message) = messages.next().await {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::warn!(request_id = %self.id, "websocket failed: {}", e);
+                    break;
+                }
+            };
+            let result = if message.is_text() {
+                self.create(message.as_bytes()).await
+            } else if message.is_binary() {
+                Err(reject(Error::UnsupportedMediaType {
+                    received: Some("binary message".to_string()),
+                    supported: JSON,
+                }))
+            } else {
+                // Reading on after a close lets the WebSocket layer send the closing handshake,
+                // after which the stream ends.
+                continue;
+            };
+            let reply = match result {
+                Ok(id) => serde_json::json!({ "ok": true, "id": id }).to_string(),
+                Err(err) => {
+                    let (_, response) = error_response(
+                        &err,
+                        &Method::GET,
+                        self.language,
+                        self.detail_level,
+                        &self.id,
+                        self.config,
+                    );
+                    serde_json::to_string(&response).expect("error responses serialize to JSON")
+                }
+            };
+            if replies.send(Message::text(reply)).await.is_err() {
+                break;
+            }
+        }
+        tracing::info!(request_id = %self.id, "websocket closed");
+    }
+
+    async fn create(&self, message: &[u8]) -> Result<Uuid> {
+        if message.len() > self.max_message_bytes {
+            return Err(reject(Error::PayloadTooLarge {
+                route: "ws/create",
+                limit: self.max_message_bytes as u64,
+            }));
+        }
+        let (_, input) = parse_and_validate_create(
+            "1",
+            None,
+            ExplainQuery::default(),
+            LenientQuery::default(),
+            message,
+            &self.ctx,
+        )
+        .await?;
+        Ok(self.store.insert(input)?.id)
+    }
+}
+
+const DEFAULT_PHOTO_MAX_BYTES: usize = 32 * 1024;
+
+/// The largest `photo` part `/create-multipart` accepts, set via `PHOTO_MAX_BYTES`. The whole
+/// body is still subject to the route's body limit.
+pub fn photo_max_bytes_from_env() -> usize {
+    std::env::var("PHOTO_MAX_BYTES")
+        .ok()
+        .and_then(|max_bytes| max_bytes.parse().ok())
+        .unwrap_or(DEFAULT_PHOTO_MAX_BYTES)
+}
+
+/// The content types a `photo` part may have.
+pub const PHOTO_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg"];
+
+/// Creates from a `multipart/form-data` body with the create request as JSON in a `payload`
+/// part and optionally a PNG or JPEG `photo` of the first pet, which is answered with its size
+/// but not kept.
+///
+/// A missing `payload`, a part sent twice, an unknown part, and a photo that is too large or of
+/// another type are rejected together as field errors on the part. Only then is the payload
+/// deserialized and validated like a version 1 body of `/create-validator`, with errors under the
+/// same paths.
+pub async fn create_multipart_handler(
+    ctx: Arc<ValidationContext>,
+    photo_max_bytes: usize,
+    mut form: FormData,
+) -> Result<impl Reply> {
+    let mut payload = None;
+    let mut photo = None;
+    let mut errors = Vec::new();
+    while let Some(part) = form.next().await {
+        let part = part.map_err(|e| {
+            reject(Error::BadRequest(format!(
+                "malformed multipart body: {}",
+                e
+            )))
+        })?;
+        let name = part.name().to_string();
+        let seen = match name.as_str() {
+            "payload" => payload.is_some(),
+            "photo" => photo.is_some(),
+            _ => {
+                errors.push(part_error(
+                    &name,
+                    "unknown_field",
+                    format!("unknown part `{}`, expected `payload` or `photo`", name),
+                    serde_json::Map::new(),
+                ));
+                continue;
+            }
+        };
+        if seen {
+            errors.push(part_error(
+                &name,
+                "duplicate_part",
+                format!("the {} part is sent more than once", name),
+                serde_json::Map::new(),
+            ));
+            continue;
+        }
+        if name == "payload" {
+            // Without a limit of its own, the route's body limit bounds it.
+            payload = read_part(part, usize::MAX).await?.ok();
+            continue;
+        }
+        let content_type = part.content_type().map(str::to_string);
+        let allowed = content_type.as_deref().is_some_and(|content_type| {
+            PHOTO_MEDIA_TYPES
+                .iter()
+                .any(|allowed| media_type(content_type).eq_ignore_ascii_case(allowed))
+        });
+        if !allowed {
+            let mut params = serde_json::Map::new();
+            params.insert("received".to_string(), content_type.clone().into());
+            params.insert("allowed".to_string(), PHOTO_MEDIA_TYPES.into());
+            errors.push(part_error(
+                "photo",
+                "photo.unsupported_type",
+                format!(
+                    "photo must be one of {}, got {}",
+                    PHOTO_MEDIA_TYPES.join(", "),
+                    content_type.as_deref().unwrap_or("no content type")
+                ),
+                params,
+            ));
+            // Still remembered, so a second photo part is reported as a duplicate.
+            photo = Some((content_type, 0));
+            continue;
+        }
+        match read_part(part, photo_max_bytes).await? {
+            Ok(bytes) => photo = Some((content_type, bytes.len())),
+            Err(size) => {
+                let mut params = serde_json::Map::new();
+                params.insert("limit".to_string(), photo_max_bytes.into());
+                params.insert("size".to_string(), size.into());
+                errors.push(part_error(
+                    "photo",
+                    "photo.too_large",
+                    format!("photo exceeds the limit of {} bytes", photo_max_bytes),
+                    params,
+                ));
+                photo = Some((content_type, size));
+            }
+        }
+    }
+    if payload.is_none() {
+        errors.push(part_error(
+            "payload",
+            "required",
+            "the payload part with the create request is missing".to_string(),
+            serde_json::Map::new(),
+        ));
+    }
+    let payload = match payload {
+        Some(payload) if errors.is_empty() => payload,
+        _ => return Err(reject(Error::DeserializationErrors(errors))),
+    };
+    let (_, input) = parse_and_validate_create(
+        "1",
+        Some(JSON_MEDIA_TYPE),
+        ExplainQuery::default(),
+        LenientQuery::default(),
+        &payload,
+        &ctx,
+    )
+    .await?;
+    let (content_type, size) = match photo {
+        Some((content_type, size)) => (content_type, Some(size)),
+        None => (None, None),
+    };
+    Ok(created(MultipartCreateResponse {
+        created: CreateResponse::from(input),
+        photo: PhotoReceipt {
+            received: size.is_some(),
+            size,
+            content_type,
+        },
+    }))
+}
+
+/// Reads `part`, or as much of it as it takes to tell it's over `limit` bytes, whose size so far
+/// is returned as the error.
+async fn read_part(part: Part, limit: usize) -> Result<std::result::Result<Vec<u8>, usize>> {
+    let stream = part.stream();
+    futures::pin_mut!(stream);
+    let mut bytes = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| {
+            reject(Error::BadRequest(format!(
+                "malformed multipart body: {}",
+                e
+            )))
+        })?;
+        bytes.extend_from_slice(chunk.bytes());
+        if bytes.len() > limit {
+            return Ok(Err(bytes.len()));
+        }
+    }
+    Ok(Ok(bytes))
+}
+
+fn part_error(
+    part: &str,
+    code: &str,
+    message: String,
+    params: serde_json::Map<String, serde_json::Value>,
+) -> FieldError {
+    FieldError {
+        field: part.to_string(),
+        severity: Severity::Error,
+        code: code.to_string(),
+        field_errors: vec![format!("{}: {}", code, message)],
+        params,
+        rejected_value: serde_json::Value::Null,
+        docs_url: None,
+    }
+}
+
+const DEFAULT_PET_IMPORT_MAX_ROWS: usize = 1000;
+
+/// The most rows `/pets/import` accepts, set via `PET_IMPORT_MAX_ROWS`.
+pub fn pet_import_max_rows_from_env() -> usize {
+    std::env::var("PET_IMPORT_MAX_ROWS")
+        .ok()
+        .and_then(|max_rows| max_rows.parse().ok())
+        .unwrap_or(DEFAULT_PET_IMPORT_MAX_ROWS)
+}
+
+/// Validates every row of a CSV with a `name` header on its own. Invalid and malformed rows end
+/// up in the summary as errors on `row[n]`, counting data rows from 1, instead of failing the
+/// whole import.
+pub async fn import_pets_handler(
+    max_rows: usize,
+    language: Language,
+    buf: impl Buf,
+) -> Result<impl Reply> {
+    let mut summary = ImportSummary {
+        valid: 0,
+        errors: Vec::new(),
+    };
+    let mut reader = csv::Reader::from_reader(buf.reader());
+    for (index, record) in reader.deserialize::<Pet>().enumerate() {
+        let row = format!("row[{}]", index + 1);
+        if index >= max_rows {
+            return Err(reject(Error::TooManyRows { limit: max_rows }));
+        }
+        match record {
+            Ok(mut pet) => {
+                pet.normalize();
+                match pet.validate() {
+                    Ok(()) => summary.valid += 1,
+                    Err(e) => collect_field_errors(
+                        &format!("{}.", row),
+                        &e,
+                        language,
+                        &mut summary.errors,
+                    ),
+                }
+            }
+            Err(e) => summary.errors.push(FieldError {
+                field: row,
+                severity: Severity::Error,
+                code: "csv.malformed".to_string(),
+                field_errors: vec![e.to_string()],
+                params: serde_json::Map::new(),
+                rejected_value: serde_json::Value::Null,
+                docs_url: None,
+            }),
+        }
+    }
+    Ok(warp::reply::json(&summary))
+}
+
+pub async fn create_draft_handler(body: DraftRequest) -> Result<impl Reply> {
+    Ok(format!("called with: {:?}", body))
+}
+
+pub async fn create_booking_handler(body: BookingRequest) -> Result<impl Reply> {
+    Ok(format!("called with: {:?}", body))
+}
+
+pub async fn create_payment_handler(body: PaymentRequest) -> Result<impl Reply> {
+    Ok(format!("called with: {:?}", body))
+}
+
+pub async fn create_schedule_handler(body: ScheduleRequest) -> Result<impl Reply> {
+    Ok(format!("called with: {:?}", body))
+}
+
+/// Checks the envelope version before the payload is even deserialized, so clients on an
+/// unsupported version learn about that instead of about payload fields that moved.
+pub async fn create_envelope_handler(bytes: Bytes) -> Result<impl Reply> {
+    let envelope: Envelope<serde_json::Value> = parse_json_body(&bytes)?;
+
+    if !SUPPORTED_ENVELOPE_VERSIONS.contains(&envelope.version) {
+        return Err(reject(Error::BadRequest(format!(
+            "unsupported envelope version {}",
+            envelope.version
+        ))));
+    }
+
+    let body: CreateRequest = serde_path_to_error::deserialize(envelope.payload)
+        .map_err(|e| json_rejection("payload", e))?;
+    let body = validate_body(body).await?;
+    Ok(created(CreateResponse::from(body)))
+}
+
+pub async fn validate_against_schema_handler(body: SchemaValidationRequest) -> Result<impl Reply> {
+    let validator = jsonschema::validator_for(&body.schema)
+        .map_err(|e| reject(Error::InvalidSchema(e.to_string())))?;
+
+    let mut field_errors: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for err in validator.iter_errors(&body.data) {
+        let mut field = json_pointer_to_field_path(err.instance_path().as_str());
+        if let jsonschema::error::ValidationErrorKind::Required { property } = err.kind() {
+            let property = property.as_str().map(String::from).unwrap_or_default();
+            field = if field.is_empty() {
+                property
+            } else {
+                format!("{}.{}", field, property)
+            };
+        }
+        field_errors
+            .entry(field)
+            .or_insert_with(|| (format!("schema.{}", err.kind().keyword()), Vec::new()))
+            .1
+            .push(format!("{}: {}", err.kind().keyword(), err));
+    }
+
+    if !field_errors.is_empty() {
+        let errors = field_errors
+            .into_iter()
+            .map(|(field, (code, field_errors))| FieldError {
+                field,
+                severity: Severity::Error,
+                code,
+                field_errors,
+                params: serde_json::Map::new(),
+                rejected_value: serde_json::Value::Null,
+                docs_url: None,
+            })
+            .collect();
+        return Err(reject(Error::SchemaViolation(errors)));
+    }
+    Ok(format!("called with: {:?}", body.data))
+}
+
+#[derive(Serialize)]
+pub struct CanonicalResponse {
+    canonical: String,
+    sha256: String,
+}
+
+/// Validates a `CreateRequest` like create-validator and echoes its canonical JSON form together
+/// with the SHA-256 digest of it, so clients signing bodies can compare against what we'd sign.
+pub async fn canonical_handler(body: CreateRequest) -> Result<impl Reply> {
+    let value = serde_json::to_value(&body).expect("CreateRequest serializes to JSON");
+    let mut canonical = String::new();
+    write_canonical_json(&value, &mut canonical);
+    let sha256 = Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok(warp::reply::json(&CanonicalResponse { canonical, sha256 }))
+}
+
+/// Answers `{"valid": true}` if the value passes the rules of its field, see
+/// `validate_create_field`, for forms that validate a field at a time as users fill them in.
+pub async fn validate_field_handler(
+    ctx: Arc<ValidationContext>,
+    body: ValidateFieldRequest,
+) -> Result<impl Reply> {
+    validate_create_field(&body.field, body.value, &ctx).await?;
+    Ok(warp::reply::json(&DryRunResponse { valid: true }))
+}
+
+/// Echoes a create request that made it through the deserialization, normalization and
+/// validation of create-validator as canonical JSON, with the defaults of omitted fields filled
+/// in, so clients can diff what we understood against what they sent.
+pub async fn echo_handler(_: ExplainQuery, body: CreateInput) -> Result<impl Reply> {
+    let value = serde_json::to_value(&body).expect("CreateInput serializes to JSON");
+    let mut canonical = String::new();
+    write_canonical_json(&value, &mut canonical);
+    Ok(warp::reply::with_header(
+        canonical,
+        "content-type",
+        "application/json",
+    ))
+}
+
+/// Writes `value` with object keys sorted by their UTF-8 bytes and no insignificant whitespace.
+///
+/// Sorting is done here rather than relying on `serde_json::Map` being a `BTreeMap`, which would
+/// silently change if a dependency enabled serde_json's `preserve_order` feature.
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(value, out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(value, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Turns a JSON pointer like `/pets/0/name` into the `pets[0].name` notation used for field
+/// paths in error responses.
+fn json_pointer_to_field_path(pointer: &str) -> String {
+    let mut path = String::new();
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        if segment.parse::<usize>().is_ok() {
+            path.push_str(&format!("[{}]", segment));
+        } else {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(&segment);
+        }
+    }
+    path
+}
@@ -0,0 +1,76 @@
+//! The state behind `GET /health/ready`: the startup tasks that haven't finished yet.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use warp::http::StatusCode;
+
+/// Parsing the configuration from the environment, done once the routes are built.
+pub const CONFIG: &str = "config";
+/// The first read of the email domain deny-list file, if there is one.
+pub const EMAIL_DOMAIN_DENYLIST: &str = "email_domain_denylist";
+
+/// The components `routes` waits for before it reports ready.
+pub const STARTUP_COMPONENTS: &[&str] = &[CONFIG, EMAIL_DOMAIN_DENYLIST];
+
+/// The components that are still starting up. Ready once all of them are marked ready.
+pub struct Readiness {
+    unready: Mutex<BTreeSet<&'static str>>,
+}
+
+/// The body of both health endpoints.
+#[derive(Serialize, Debug)]
+pub struct HealthStatus {
+    /// `ok`, or `unavailable` while components are starting up.
+    pub status: &'static str,
+    /// The components that are still starting up, sorted by name. Left out once there are none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unready: Vec<&'static str>,
+}
+
+impl Readiness {
+    pub fn new(components: &[&'static str]) -> Self {
+        Readiness {
+            unready: Mutex::new(components.iter().copied().collect()),
+        }
+    }
+
+    /// Marks `component` ready. Unknown components are ignored.
+    pub fn mark_ready(&self, component: &str) {
+        self.unready
+            .lock()
+            .expect("readiness lock poisoned")
+            .remove(component);
+    }
+
+    /// `200` once every component is ready, `503` listing the others until then.
+    pub fn status(&self) -> (StatusCode, HealthStatus) {
+        let unready: Vec<&'static str> = self
+            .unready
+            .lock()
+            .expect("readiness lock poisoned")
+            .iter()
+            .copied()
+            .collect();
+        if unready.is_empty() {
+            (StatusCode::OK, HealthStatus::ok())
+        } else {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                HealthStatus {
+                    status: "unavailable",
+                    unready,
+                },
+            )
+        }
+    }
+}
+
+impl HealthStatus {
+    pub fn ok() -> Self {
+        HealthStatus {
+            status: "ok",
+            unready: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate validator_derive;
+
+pub mod buffer_pool;
+pub mod cli;
+pub mod config;
+pub mod digest;
+pub mod error;
+pub mod etag;
+pub mod filters;
+pub mod handlers;
+pub mod health;
+pub mod metrics;
+pub mod models;
+pub mod pipeline;
+mod routes;
+pub mod store;
+pub mod validation;
+
+pub use routes::{routes, routes_with_state};
+
+use config::TlsIdentity;
+use std::future::Future;
+use std::net::SocketAddr;
+
+type Result<T> = std::result::Result<T, warp::Rejection>;
+
+/// Binds `routes()` to `addr`, returning the address actually bound, which differs from `addr`
+/// for port 0, and the future running the server. Once `shutdown` completes the server stops
+/// accepting connections and the future resolves after in-flight requests have been answered.
+pub fn bind(
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::result::Result<(SocketAddr, impl Future<Output = ()>), warp::Error> {
+    warp::serve(routes()).try_bind_with_graceful_shutdown(addr, shutdown)
+}
+
+/// Like `bind`, but serving HTTPS with `identity`. warp panics if `addr` can't be bound.
+pub fn bind_tls(
+    addr: SocketAddr,
+    identity: TlsIdentity,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> (SocketAddr, impl Future<Output = ()>) {
+    warp::serve(routes())
+        .tls()
+        .cert(identity.cert)
+        .key(identity.key)
+        .bind_with_graceful_shutdown(addr, shutdown)
+}
+
+/// Completes on Ctrl-C, or on SIGTERM on unix, which is what orchestrators like Kubernetes send.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                eprintln!("failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    println!("Shutting down, waiting for in-flight requests");
+}
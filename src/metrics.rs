@@ -0,0 +1,107 @@
+//! Request and failure counters, exposed in Prometheus text format at `GET /metrics`.
+
+use crate::error::{collect_field_errors, Error, Language};
+use crate::routes::route_template;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use warp::Rejection;
+
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    requests: BTreeMap<String, u64>,
+    deserialization_failures: u64,
+    validation_failures: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    /// Counts a request by the route it was sent to, e.g. `/creates/{id}`, not its literal path,
+    /// so IDs can't grow the map without bound. Paths of no route, like those scanners probe, are
+    /// counted together.
+    pub fn record_request(&self, path: &str) {
+        let endpoint = route_template(path).unwrap_or("unmatched");
+        let mut counters = self.counters.lock().expect("metrics lock poisoned");
+        *counters.requests.entry(endpoint.to_string()).or_default() += 1;
+    }
+
+    /// Counts bodies and queries that couldn't be deserialized, and validation failures per
+    /// field with list indices collapsed, e.g. `pets[*].name`.
+    pub fn record_rejection(&self, err: &Rejection) {
+        let mut counters = self.counters.lock().expect("metrics lock poisoned");
+        if err
+            .find::<warp::filters::body::BodyDeserializeError>()
+            .is_some()
+        {
+            counters.deserialization_failures += 1;
+        }
+        match err.find::<Error>() {
+            Some(
+                Error::JSONPathError(_)
+                | Error::FormPathError(_)
+                | Error::YAMLPathError(_)
+                | Error::MsgPackPathError(_)
+                | Error::InvalidQuery(_)
+                | Error::DeserializationErrors(_)
+                | Error::ParameterErrors(_),
+            ) => counters.deserialization_failures += 1,
+            Some(Error::ValidationError(val_errs) | Error::ExplainedValidationError(val_errs)) => {
+                let mut errors = Vec::new();
+                collect_field_errors("", val_errs, Language::En, &mut errors);
+                for error in errors {
+                    *counters
+                        .validation_failures
+                        .entry(collapse_indices(&error.field))
+                        .or_default() += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().expect("metrics lock poisoned");
+        let mut out = String::new();
+        out.push_str("# TYPE requests_total counter\n");
+        for (endpoint, count) in &counters.requests {
+            let _ = writeln!(out, "requests_total{{endpoint=\"{}\"}} {}", endpoint, count);
+        }
+        out.push_str("# TYPE deserialization_failures_total counter\n");
+        let _ = writeln!(
+            out,
+            "deserialization_failures_total {}",
+            counters.deserialization_failures
+        );
+        out.push_str("# TYPE validation_failures_total counter\n");
+        for (field, count) in &counters.validation_failures {
+            let _ = writeln!(
+                out,
+                "validation_failures_total{{field=\"{}\"}} {}",
+                field, count
+            );
+        }
+        out
+    }
+}
+
+/// `pets[2].name` becomes `pets[*].name`.
+fn collapse_indices(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                out.push_str("[*]");
+            }
+            ']' => in_index = false,
+            _ if in_index => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
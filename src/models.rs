@@ -0,0 +1,2492 @@
+//! Request types and the validation rules that apply to them.
+
+use crate::config::ValidationConfig;
+use crate::error::{rejected_value, FieldError, Severity};
+use crate::store::StoredCreate;
+use chrono::{Months, NaiveDate, Utc};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
+use strum::VariantNames;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+use validator::{validate_email, validate_url, Validate, ValidationError, ValidationErrors};
+
+/// A request to create an owner with their address and pets. `Validate` is implemented by hand
+/// rather than derived, so every struct-level rule that fails is reported, also next to field
+/// errors, where validator's derive only runs one schema function and skips it on field errors.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct CreateRequest {
+    /// How to reach the owner, which decides whether `email` or `phone` is required.
+    pub contact_method: Option<ContactMethod>,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Address,
+    #[serde(deserialize_with = "deserialize_bounded::<PetsMax, _, _>")]
+    pub pets: Vec<Pet>,
+    pub bio: Option<String>,
+    /// The owner's birthdate.
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub birthdate: Option<NaiveDate>,
+    pub password: Option<Secret>,
+}
+
+const EMAIL_MESSAGE: &str = "{value} is not a valid email address";
+const PHONE_MESSAGE: &str = "phone must be a phone number like +4915112345678 or 0151 12345678";
+const BIO_MESSAGE: &str = "bio must not contain HTML";
+
+impl Validate for CreateRequest {
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if let Some(email) = &self.email {
+            add_error(
+                &mut errors,
+                "email",
+                validate_email_format(email),
+                email,
+                Some(EMAIL_MESSAGE),
+            );
+            add_error(
+                &mut errors,
+                "email",
+                validate_email_bytes(email),
+                email,
+                None,
+            );
+        }
+        validate_contact_fields(&mut errors, self.phone.as_ref(), self.bio.as_ref());
+        if let Some(birthdate) = &self.birthdate {
+            add_error(
+                &mut errors,
+                "birthdate",
+                validate_owner_birthdate(birthdate),
+                birthdate,
+                None,
+            );
+        }
+        if let Some(password) = &self.password {
+            add_error(
+                &mut errors,
+                "password",
+                validate_password(password),
+                password,
+                None,
+            );
+            add_error(
+                &mut errors,
+                "password",
+                validate_password_bytes(password),
+                password,
+                None,
+            );
+        }
+        for err in validate_create_request(self) {
+            errors.add("__all__", err);
+        }
+        with_nested_errors(errors, &self.address, &self.pets)
+    }
+}
+
+/// Adds the error of a field rule to `errors` the way validator's derive does, with the
+/// checked `value` as the `value` param and `message`, if any, replacing the rule's own.
+fn add_error<V: Serialize + ?Sized>(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    result: std::result::Result<(), ValidationError>,
+    value: &V,
+    message: Option<&'static str>,
+) {
+    if let Err(mut err) = result {
+        if let Some(message) = message {
+            err.message = Some(message.into());
+        }
+        err.add_param("value".into(), &value);
+        errors.add(field, err);
+    }
+}
+
+/// validator's `email` rule as a function, for `add_error`.
+fn validate_email_format(email: &str) -> std::result::Result<(), ValidationError> {
+    match validate_email(email) {
+        true => Ok(()),
+        false => Err(ValidationError::new("email")),
+    }
+}
+
+/// The rules of `phone` and `bio`, which every version of the create request shares.
+fn validate_contact_fields(
+    errors: &mut ValidationErrors,
+    phone: Option<&String>,
+    bio: Option<&String>,
+) {
+    if let Some(phone) = phone {
+        add_error(
+            errors,
+            "phone",
+            validate_phone(phone),
+            phone,
+            Some(PHONE_MESSAGE),
+        );
+        add_error(errors, "phone", validate_string_bytes(phone), phone, None);
+    }
+    if let Some(bio) = bio {
+        add_error(errors, "bio", no_html(bio), bio, Some(BIO_MESSAGE));
+        add_error(errors, "bio", validate_string_bytes(bio), bio, None);
+    }
+}
+
+/// `errors` with those of `address` and of each of `pets` nested under their paths, like
+/// validator's derive nests `#[validate]` fields.
+fn with_nested_errors(
+    errors: ValidationErrors,
+    address: &impl Validate,
+    pets: &[Pet],
+) -> std::result::Result<(), ValidationErrors> {
+    let mut result = if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    };
+    result = ValidationErrors::merge(result, "address", address.validate());
+    // `merge_all` expects every element's errors nested under the field name
+    let pets = pets
+        .iter()
+        .map(|pet| ValidationErrors::merge(Ok(()), "pets", pet.validate()))
+        .collect();
+    ValidationErrors::merge_all(result, "pets", pets)
+}
+
+/// A string that is never shown: `Debug` and `Serialize` write `"***"`, so it can't end up in a
+/// log line, a response or the `value` param of a validation error. Only `expose` reads it.
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("***")
+    }
+}
+
+pub const PASSWORD_MIN_LENGTH: usize = 12;
+
+/// Requires `PASSWORD_MIN_LENGTH` characters with at least one letter and one digit. The errors
+/// have no params describing the password, so nothing of it is reported back.
+fn validate_password(password: &Secret) -> std::result::Result<(), ValidationError> {
+    let password = password.expose();
+    if password.chars().count() < PASSWORD_MIN_LENGTH {
+        let mut err = ValidationError::new("password_too_short");
+        err.add_param("min".into(), &PASSWORD_MIN_LENGTH);
+        return Err(err);
+    }
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    if !(has_letter && has_digit) {
+        return Err(ValidationError::new("password_too_weak"));
+    }
+    Ok(())
+}
+
+/// Rejects passwords that are the local part of the email or contain it, ignoring case.
+fn validate_password_not_email(
+    password: &Secret,
+    email: &str,
+) -> std::result::Result<(), ValidationError> {
+    let local = match email.rsplit_once('@') {
+        Some((local, _)) if !local.is_empty() => local.to_lowercase(),
+        _ => return Ok(()),
+    };
+    if !password.expose().to_lowercase().contains(&local) {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("password_contains_email");
+    err.add_param("field".into(), &"password");
+    err.add_param("fields".into(), &["password", "email"]);
+    Err(err)
+}
+
+/// The channel an owner wants to be contacted on, `email` unless the request says otherwise.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default, strum::VariantNames)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ContactMethod {
+    #[default]
+    Email,
+    Phone,
+}
+
+/// The rules on a `CreateRequest` as a whole, every one that fails rather than only the first.
+fn validate_create_request(request: &CreateRequest) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    errors.extend(validate_contact_method(request).err());
+    errors.extend(validate_pets_count(&request.pets).err());
+    if let Some(email) = request.email.as_deref() {
+        errors.extend(validate_email_domain_not_in_pet_names(email, &request.pets).err());
+        if let Some(password) = &request.password {
+            errors.extend(validate_password_not_email(password, email).err());
+        }
+    }
+    errors.extend(
+        validate_street_no_for_pets(
+            "address.streetNo",
+            request.address.street_no.value().copied(),
+            &request.pets,
+        )
+        .err(),
+    );
+    let strings = vec![
+        request.email.as_deref(),
+        request.phone.as_deref(),
+        Some(request.address.street.as_str()),
+        request.address.phone.as_deref(),
+        request.bio.as_deref(),
+        request.password.as_ref().map(Secret::expose),
+    ];
+    errors.extend(validate_total_string_bytes(strings.into_iter().flatten(), &request.pets).err());
+    errors
+}
+
+/// Requires at least `pets_min` pets of the `ValidationConfig`. This is a struct-level rule since
+/// validator can't report a `length` error on `pets` next to the errors of its elements. The
+/// elements are still validated, so a list that is too short also reports its invalid pets.
+/// `pets_max` is enforced while the list is deserialized, by `BoundedVec`.
+fn validate_pets_count(pets: &[Pet]) -> std::result::Result<(), ValidationError> {
+    let limits = ValidationConfig::current();
+    if pets.len() >= limits.pets_min {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("pets_count");
+    err.add_param("field".into(), &"pets");
+    err.add_param("count".into(), &pets.len());
+    err.add_param("min".into(), &limits.pets_min);
+    err.add_param("max".into(), &limits.pets_max);
+    Err(err)
+}
+
+/// Requires the field of the chosen `contact_method`. `Normalize` turns blank values into
+/// `None`, so an empty `phone` is reported as missing rather than as a malformed number.
+fn validate_contact_method(request: &CreateRequest) -> std::result::Result<(), ValidationError> {
+    let contact_method = request.contact_method.unwrap_or_default();
+    let missing = match contact_method {
+        ContactMethod::Email if request.email.is_none() => "email",
+        ContactMethod::Phone if request.phone.is_none() => "phone",
+        _ => return Ok(()),
+    };
+    let mut err = ValidationError::new("required_when");
+    err.add_param("field".into(), &missing);
+    err.add_param("fields".into(), &["contact_method", missing]);
+    err.add_param("contact_method".into(), &contact_method);
+    Err(err)
+}
+
+/// The rules on a `CreateRequestV2` as a whole, every one that fails rather than only the first.
+fn validate_create_request_v2(request: &CreateRequestV2) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    errors.extend(validate_pets_count(&request.pets).err());
+    errors.extend(validate_email_domain_not_in_pet_names(&request.email, &request.pets).err());
+    errors.extend(
+        validate_street_no_for_pets(
+            "address.houseNumber",
+            Some(request.address.house_number),
+            &request.pets,
+        )
+        .err(),
+    );
+    let strings = vec![
+        Some(request.email.as_str()),
+        request.phone.as_deref(),
+        Some(request.address.street.as_str()),
+        request.bio.as_deref(),
+    ];
+    errors.extend(validate_total_string_bytes(strings.into_iter().flatten(), &request.pets).err());
+    errors
+}
+
+/// More pets than this need a street number greater than zero.
+pub const STREET_NO_REQUIRED_ABOVE_PETS: usize = 3;
+
+/// Requires a street number greater than zero at `field` for requests with more than
+/// `STREET_NO_REQUIRED_ABOVE_PETS` pets, even where `street_no_min` allows 0. `street_no` is
+/// `None` for one that isn't a number at all, which is already `invalid_number_string`.
+fn validate_street_no_for_pets(
+    field: &str,
+    street_no: Option<usize>,
+    pets: &[Pet],
+) -> std::result::Result<(), ValidationError> {
+    if pets.len() <= STREET_NO_REQUIRED_ABOVE_PETS || street_no != Some(0) {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("street_no_required");
+    err.add_param("field".into(), &field);
+    err.add_param("fields".into(), &["pets", field]);
+    err.add_param("pets".into(), &pets.len());
+    err.add_param("max_pets".into(), &STREET_NO_REQUIRED_ABOVE_PETS);
+    Err(err)
+}
+
+/// Rejects requests whose string fields, `strings` and those of `pets`, take more than
+/// `request_max_string_bytes` together, however each of them fares on its own. The error is on
+/// the request as a whole, at the empty path.
+fn validate_total_string_bytes<'a>(
+    strings: impl Iterator<Item = &'a str>,
+    pets: &'a [Pet],
+) -> std::result::Result<(), ValidationError> {
+    let max = ValidationConfig::current().request_max_string_bytes;
+    let pet_strings = pets
+        .iter()
+        .flat_map(|pet| std::iter::once(pet.name.as_str()).chain(pet.photo_url.as_deref()));
+    let bytes: usize = strings.chain(pet_strings).map(str::len).sum();
+    if bytes <= max {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("max_total_bytes_exceeded");
+    err.add_param("field".into(), &"");
+    err.add_param("max".into(), &max);
+    err.add_param("bytes".into(), &bytes);
+    Err(err)
+}
+
+/// Rejects pets named after the owner's email domain, `chip@nacho.com` can't have a pet called
+/// `Nacho`. Only the first label of the domain is compared, the dots of a full domain never make
+/// it past the pet name charset anyway.
+///
+/// Like every struct-level rule on a create request the error carries the path it belongs on in
+/// its `field` param, and every field involved in `fields`.
+fn validate_email_domain_not_in_pet_names(
+    email: &str,
+    pets: &[Pet],
+) -> std::result::Result<(), ValidationError> {
+    let domain = match email.rsplit_once('@') {
+        Some((_, domain)) => domain.split('.').next().unwrap_or_default().to_lowercase(),
+        None => return Ok(()),
+    };
+    if domain.is_empty() {
+        return Ok(());
+    }
+    let clash = pets
+        .iter()
+        .position(|pet| pet.name.to_lowercase().contains(&domain));
+    match clash {
+        Some(i) => {
+            let field = format!("pets[{}].name", i);
+            let mut err = ValidationError::new("email_domain_in_pet_name");
+            err.add_param("field".into(), &field);
+            err.add_param("fields".into(), &["email", field.as_str()]);
+            err.add_param("domain".into(), &domain);
+            Err(err)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Rejects text that looks like it contains HTML markup.
+///
+/// The heuristic only flags a `<` that is directly followed by something tag-like: a letter
+/// (`<script`), a closing slash plus letter (`</div`) or `!` (`<!--`, `<!DOCTYPE`). A `<` followed
+/// by whitespace, a digit or punctuation, as in `a < b` or `x <3`, is accepted, while `a<b` is
+/// still rejected since it can't be told apart from an opening tag.
+fn no_html(text: &str) -> std::result::Result<(), ValidationError> {
+    let chars: Vec<char> = text.chars().collect();
+    let looks_like_tag = chars.windows(2).enumerate().any(|(i, pair)| {
+        pair[0] == '<'
+            && (pair[1].is_ascii_alphabetic()
+                || pair[1] == '!'
+                || (pair[1] == '/' && chars.get(i + 2).is_some_and(|c| c.is_ascii_alphabetic())))
+    });
+    if looks_like_tag {
+        return Err(ValidationError::new("html_not_allowed"));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Address {
+    #[validate(
+        custom(
+            function = "validate_street_length",
+            message = "street must be between {min} and {max} characters"
+        ),
+        custom = "validate_street_bytes"
+    )]
+    pub street: String,
+    #[validate(
+        custom = "validate_number_string",
+        custom(
+            function = "validate_lenient_street_no",
+            message = "street number must be at least {min}"
+        )
+    )]
+    pub street_no: Lenient<usize>,
+    #[validate(
+        custom(
+            function = "validate_phone",
+            message = "phone must be a phone number like +4915112345678 or 0151 12345678"
+        ),
+        custom = "validate_string_bytes"
+    )]
+    pub phone: Option<String>,
+}
+
+/// How string lengths are counted by the length validators, set via `LENGTH_MODE`.
+///
+/// `graphemes` (the default) counts extended grapheme clusters, what users perceive as a
+/// character: `🐶🐶🐶` and three `👨‍👩‍👧` ZWJ families are both 3 long, and so is `Üb` written
+/// with a combining diaeresis, 2. `chars` counts code points like validator's own `length`, which
+/// makes each family 5 long, and `bytes` matches storage limits on UTF-8 encoded columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthMode {
+    Bytes,
+    Chars,
+    Graphemes,
+}
+
+impl LengthMode {
+    fn from_env() -> Self {
+        match std::env::var("LENGTH_MODE").as_deref() {
+            Ok("bytes") => LengthMode::Bytes,
+            Ok("chars") => LengthMode::Chars,
+            _ => LengthMode::Graphemes,
+        }
+    }
+
+    fn count(self, value: &str) -> usize {
+        match self {
+            LengthMode::Bytes => value.len(),
+            LengthMode::Chars => value.chars().count(),
+            LengthMode::Graphemes => value.graphemes(true).count(),
+        }
+    }
+}
+
+fn length_mode() -> LengthMode {
+    static LENGTH_MODE: OnceLock<LengthMode> = OnceLock::new();
+    *LENGTH_MODE.get_or_init(LengthMode::from_env)
+}
+
+/// Stand-in for validator's `length` that counts according to the configured `LengthMode`.
+fn validate_length_in(
+    value: &str,
+    min: usize,
+    max: usize,
+) -> std::result::Result<(), ValidationError> {
+    let mode = length_mode();
+    let length = mode.count(value);
+    if length >= min && length <= max {
+        return Ok(());
+    }
+
+    let mut err = ValidationError::new("length");
+    err.add_param("min".into(), &min);
+    err.add_param("max".into(), &max);
+    err.add_param("length".into(), &length);
+    err.add_param("mode".into(), &mode);
+    Err(err)
+}
+
+pub const PET_MAX_AGE_YEARS: u32 = 50;
+
+// The street, street number, pet name and pet count bounds come from `ValidationConfig` rather
+// than attribute literals, so they can change without a rebuild and `create_request_schema`
+// can publish the same values.
+fn validate_street_length(street: &str) -> std::result::Result<(), ValidationError> {
+    let limits = ValidationConfig::current();
+    validate_length_in(street, limits.street_min_length, limits.street_max_length)
+}
+
+/// validator's `range` only takes literals, this reports a `range` error like it would.
+fn validate_street_no(street_no: usize) -> std::result::Result<(), ValidationError> {
+    let min = ValidationConfig::current().street_no_min;
+    if street_no >= min {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("range");
+    err.add_param("min".into(), &min);
+    Err(err)
+}
+
+fn validate_lenient_street_no(
+    street_no: &Lenient<usize>,
+) -> std::result::Result<(), ValidationError> {
+    street_no
+        .value()
+        .map_or(Ok(()), |&street_no| validate_street_no(street_no))
+}
+
+fn validate_pet_name_length(name: &str) -> std::result::Result<(), ValidationError> {
+    let limits = ValidationConfig::current();
+    validate_length_in(name, limits.pet_name_min_length, limits.pet_name_max_length)
+}
+
+/// Rejects strings taking more than `max` bytes UTF-8 encoded with `max_bytes_exceeded`.
+///
+/// Length rules count graphemes or chars, so a name of 20 letters each carrying thousands of
+/// combining marks passes them at megabytes in size. Every string field of a create request
+/// gets this as a rule of its own next to its length rule, so a value can fail both.
+fn validate_max_bytes(value: &str, max: usize) -> std::result::Result<(), ValidationError> {
+    let bytes = value.len();
+    if bytes <= max {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("max_bytes_exceeded");
+    err.add_param("max".into(), &max);
+    err.add_param("bytes".into(), &bytes);
+    Err(err)
+}
+
+fn validate_street_bytes(street: &str) -> std::result::Result<(), ValidationError> {
+    validate_max_bytes(street, ValidationConfig::current().street_max_bytes)
+}
+
+fn validate_pet_name_bytes(name: &str) -> std::result::Result<(), ValidationError> {
+    validate_max_bytes(name, ValidationConfig::current().pet_name_max_bytes)
+}
+
+fn validate_email_bytes(email: &str) -> std::result::Result<(), ValidationError> {
+    validate_max_bytes(email, ValidationConfig::current().email_max_bytes)
+}
+
+fn validate_string_bytes(value: &str) -> std::result::Result<(), ValidationError> {
+    validate_max_bytes(value, ValidationConfig::current().string_max_bytes)
+}
+
+fn validate_password_bytes(password: &Secret) -> std::result::Result<(), ValidationError> {
+    validate_string_bytes(password.expose())
+}
+
+/// Reads an optional ISO 8601 calendar date like `2020-01-31`. A string that isn't one fails
+/// with an `invalid date` message that the `From` conversion into `Error` turns into an
+/// `invalid_date` field error.
+fn deserialize_date<'de, D>(deserializer: D) -> std::result::Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let date: Option<String> = Option::deserialize(deserializer)?;
+    date.map(|date| {
+        NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+            serde::de::Error::custom(format!(
+                "invalid date `{}`, expected an ISO 8601 date like 2020-01-31",
+                date
+            ))
+        })
+    })
+    .transpose()
+}
+
+/// The messages `deserialize_count` fails with, by the code of the field error the `From`
+/// conversion into `Error` turns them into. Each quotes the number it got in backticks.
+pub(crate) const NUMBER_ERRORS: &[(&str, &str)] = &[
+    ("negative number `", "negative_not_allowed"),
+    ("fractional number `", "not_an_integer"),
+    ("out of range number `", "number_out_of_range"),
+];
+
+/// Reads a non-negative integer like a `usize` would, but fails negative, fractional and too
+/// large numbers with one of the `NUMBER_ERRORS` instead of serde's `invalid value` message.
+/// A float without a fractional part, like `2.0`, is taken as the integer it is.
+fn deserialize_count<'de, D>(deserializer: D) -> std::result::Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_u64(CountVisitor)
+}
+
+struct CountVisitor;
+
+impl serde::de::Visitor<'_> for CountVisitor {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a non-negative integer")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<usize, E> {
+        usize::try_from(value).map_err(|_| {
+            E::custom(format!(
+                "out of range number `{}`, expected at most {}",
+                value,
+                usize::MAX
+            ))
+        })
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> std::result::Result<usize, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::custom(format!(
+                "negative number `{}`, expected a non-negative integer",
+                value
+            ))),
+        }
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> std::result::Result<usize, E> {
+        if value.fract() != 0.0 {
+            Err(E::custom(format!(
+                "fractional number `{}`, expected an integer",
+                value
+            )))
+        } else if value < 0.0 {
+            Err(E::custom(format!(
+                "negative number `{}`, expected a non-negative integer",
+                value
+            )))
+        } else if value >= usize::MAX as f64 {
+            // Integers too large for a `u64` are parsed as floats
+            Err(E::custom(format!(
+                "out of range number `{}`, expected at most {}",
+                value,
+                usize::MAX
+            )))
+        } else {
+            Ok(value as usize)
+        }
+    }
+}
+
+/// A value that may also be sent as a string containing it, like `"streetNo": "4"` or `" 4 "`,
+/// for clients that quote their numbers. Serialized as the value.
+///
+/// A string that doesn't parse, like `"four"`, doesn't fail deserialization. It's kept as
+/// `Invalid` and reported by `validate_number_string` as an `invalid_number_string` error next
+/// to the other validation errors. With `strict_numbers` in the `ValidationConfig`, strings fail
+/// deserialization like any other value of the wrong type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lenient<T> {
+    /// Sent as the type itself.
+    Value(T),
+    /// Parsed from a string.
+    Coerced(T),
+    Invalid(String),
+}
+
+impl<T> Lenient<T> {
+    /// The value, unless it was an `Invalid` string.
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Lenient::Value(value) | Lenient::Coerced(value) => Some(value),
+            Lenient::Invalid(_) => None,
+        }
+    }
+
+    pub fn is_coerced(&self) -> bool {
+        matches!(self, Lenient::Coerced(_))
+    }
+}
+
+impl<T: Serialize> Serialize for Lenient<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Lenient::Value(value) | Lenient::Coerced(value) => value.serialize(serializer),
+            Lenient::Invalid(value) => value.serialize(serializer),
+        }
+    }
+}
+
+/// Reads a count like `deserialize_count`, or a string holding one.
+impl<'de> Deserialize<'de> for Lenient<usize> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LenientCountVisitor;
+
+        impl serde::de::Visitor<'_> for LenientCountVisitor {
+            type Value = Lenient<usize>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a non-negative integer, or a string holding one")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                CountVisitor.visit_u64(value).map(Lenient::Value)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                CountVisitor.visit_i64(value).map(Lenient::Value)
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Self::Value, E> {
+                CountVisitor.visit_f64(value).map(Lenient::Value)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                let parsed = value.trim().parse().ok();
+                if UNTYPED_FORMAT.with(Cell::get) {
+                    return Ok(
+                        parsed.map_or_else(|| Lenient::Invalid(value.to_string()), Lenient::Value)
+                    );
+                }
+                if ValidationConfig::current().strict_numbers {
+                    return Err(E::invalid_type(
+                        serde::de::Unexpected::Str(value),
+                        &CountVisitor,
+                    ));
+                }
+                Ok(parsed.map_or_else(|| Lenient::Invalid(value.to_string()), Lenient::Coerced))
+            }
+        }
+
+        deserializer.deserialize_any(LenientCountVisitor)
+    }
+}
+
+thread_local! {
+    static UNTYPED_FORMAT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `parse` for a format that sends every value as a string, like form bodies. Their
+/// `Lenient` values are sent as strings because there is no other way, so they aren't counted as
+/// coerced, and `strict_numbers` doesn't turn them away. Deserialization doesn't `await`, so the
+/// thread it starts on is the one it ends on.
+pub(crate) fn parse_untyped<R>(parse: impl FnOnce() -> R) -> R {
+    UNTYPED_FORMAT.with(|untyped| untyped.set(true));
+    let result = parse();
+    UNTYPED_FORMAT.with(|untyped| untyped.set(false));
+    result
+}
+
+/// Reports an `Invalid` `Lenient` as `invalid_number_string`, with the string it got.
+fn validate_number_string<T>(value: &Lenient<T>) -> std::result::Result<(), ValidationError> {
+    match value {
+        Lenient::Invalid(value) => {
+            let mut err = ValidationError::new("invalid_number_string");
+            err.add_param("value".into(), value);
+            Err(err)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The most elements a `BoundedVec` with this limit takes.
+pub trait ElementLimit {
+    fn max() -> usize;
+}
+
+/// `pets_max` of the `ValidationConfig`.
+#[derive(Debug, Clone, Copy)]
+pub struct PetsMax;
+
+impl ElementLimit for PetsMax {
+    fn max() -> usize {
+        ValidationConfig::current().pets_max
+    }
+}
+
+/// The message a `BoundedVec` fails with, followed by its limit and a backtick. The `From`
+/// conversion into `Error` turns it into a `too_many_elements` field error on the list.
+pub(crate) const TOO_MANY_ELEMENTS: &str = "too many elements, expected at most `";
+
+/// A list of at most `L::max()` elements. Deserialization fails as soon as the list has one
+/// more, without deserializing that element or reading anything after it, so a body with a
+/// million tiny pets is turned away before any of them are built past the limit instead of
+/// being parsed and validated in full. Serialized as the list.
+#[derive(Debug, Clone)]
+pub struct BoundedVec<T, L> {
+    elements: Vec<T>,
+    limit: PhantomData<L>,
+}
+
+impl<T, L> BoundedVec<T, L> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.elements
+    }
+}
+
+impl<T, L> Deref for BoundedVec<T, L> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.elements
+    }
+}
+
+impl<T, L> DerefMut for BoundedVec<T, L> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.elements
+    }
+}
+
+impl<T: Serialize, L> Serialize for BoundedVec<T, L> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.elements.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, L: ElementLimit> Deserialize<'de> for BoundedVec<T, L> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BoundedVisitor<T, L>(PhantomData<(T, L)>);
+
+        impl<'de, T: Deserialize<'de>, L: ElementLimit> serde::de::Visitor<'de> for BoundedVisitor<T, L> {
+            type Value = BoundedVec<T, L>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a list of at most {} elements", L::max())
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let max = L::max();
+                let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(max));
+                while elements.len() < max {
+                    match seq.next_element()? {
+                        Some(element) => elements.push(element),
+                        None => break,
+                    }
+                }
+                if elements.len() == max && seq.next_element::<IgnoredAny>()?.is_some() {
+                    return Err(serde::de::Error::custom(format!(
+                        "{}{}`",
+                        TOO_MANY_ELEMENTS, max
+                    )));
+                }
+                Ok(BoundedVec {
+                    elements,
+                    limit: PhantomData,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(BoundedVisitor(PhantomData))
+    }
+}
+
+/// Reads a `Vec` through a `BoundedVec`, for `#[validate]` fields: validator only validates
+/// the elements of fields whose type is spelled `Vec<...>`.
+fn deserialize_bounded<'de, L, T, D>(deserializer: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    L: ElementLimit,
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    BoundedVec::<T, L>::deserialize(deserializer).map(BoundedVec::into_inner)
+}
+
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// Checks a birthdate against `today`, which is a parameter so the rule doesn't depend on the
+/// clock. Dates more than `max_age_years` before `today` are rejected when a limit is given.
+pub fn check_birthdate(
+    birthdate: NaiveDate,
+    today: NaiveDate,
+    max_age_years: Option<u32>,
+) -> std::result::Result<(), ValidationError> {
+    if birthdate > today {
+        let mut err = ValidationError::new("birthdate_in_future");
+        err.add_param("value".into(), &birthdate);
+        return Err(err);
+    }
+    let Some(max_age_years) = max_age_years else {
+        return Ok(());
+    };
+    let earliest = today.checked_sub_months(Months::new(max_age_years * 12));
+    if earliest.is_some_and(|earliest| birthdate < earliest) {
+        let mut err = ValidationError::new("birthdate_too_old");
+        err.add_param("value".into(), &birthdate);
+        err.add_param("max_age_years".into(), &max_age_years);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn validate_owner_birthdate(birthdate: &NaiveDate) -> std::result::Result<(), ValidationError> {
+    check_birthdate(*birthdate, today(), None)
+}
+
+fn validate_pet_birthdate(birthdate: &NaiveDate) -> std::result::Result<(), ValidationError> {
+    check_birthdate(*birthdate, today(), Some(PET_MAX_AGE_YEARS))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Pet {
+    // `validate_pet_name` and `validate_photo_url` report several codes, a single message would
+    // hide which rule failed, so they rely on the templates in `error.rs`.
+    #[validate(
+        custom(
+            function = "validate_pet_name_length",
+            message = "pet name must be between {min} and {max} characters"
+        ),
+        custom = "validate_pet_name",
+        custom = "validate_pet_name_bytes"
+    )]
+    pub name: String,
+    #[validate(custom = "validate_photo_url", custom = "validate_string_bytes")]
+    pub photo_url: Option<String>,
+    pub species: Option<Species>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    #[validate(custom = "validate_pet_birthdate")]
+    pub birthdate: Option<NaiveDate>,
+}
+
+/// Species are matched exactly, `"DOG"` is rejected like any other unknown value rather than
+/// guessed at. The lowercase names clients send are listed in `Species::VARIANTS`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, strum::VariantNames)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Species {
+    Dog,
+    Cat,
+    Hamster,
+}
+
+/// Outcome of a `/pets/import`.
+#[derive(Serialize, Debug)]
+pub struct ImportSummary {
+    /// Number of rows that passed validation.
+    pub valid: usize,
+    pub errors: Vec<FieldError>,
+}
+
+pub const RESERVED_PET_NAMES: &[&str] = &["null", "undefined", "none", "nan", "true", "false"];
+
+/// Allows letters, spaces and hyphens only, without surrounding whitespace, and rejects names
+/// that are easily confused with placeholder values, compared case-insensitively.
+///
+/// `Normalize` already trims names, the whitespace check only matters when validating without it.
+fn validate_pet_name(name: &str) -> std::result::Result<(), ValidationError> {
+    if name.trim() != name {
+        return Err(ValidationError::new("pet_name_whitespace"));
+    }
+
+    let invalid_chars: String = name
+        .chars()
+        .filter(|c| !(c.is_alphabetic() || *c == ' ' || *c == '-'))
+        .collect();
+    if !invalid_chars.is_empty() {
+        let mut err = ValidationError::new("pet_name_charset");
+        err.add_param("invalid_chars".into(), &invalid_chars);
+        return Err(err);
+    }
+
+    if RESERVED_PET_NAMES
+        .iter()
+        .any(|reserved| name.eq_ignore_ascii_case(reserved))
+    {
+        let mut err = ValidationError::new("pet_name_reserved");
+        err.add_param("reserved".into(), &RESERVED_PET_NAMES);
+        return Err(err);
+    }
+    Ok(())
+}
+
+pub const IMAGE_EXTENSIONS: &[&str] = &[".jpg", ".png", ".webp"];
+
+fn validate_photo_url(photo_url: &str) -> std::result::Result<(), ValidationError> {
+    if !validate_url(photo_url) {
+        return Err(ValidationError::new("invalid_url"));
+    }
+
+    let path = photo_url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+        let mut err = ValidationError::new("not_an_image_url");
+        err.add_param("allowed_extensions".into(), &IMAGE_EXTENSIONS);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Version 2 of `CreateRequest`, sent with `X-Api-Version: 2`. It adds `phone` and calls the
+/// street number `house_number`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct CreateRequestV2 {
+    pub email: String,
+    pub phone: Option<String>,
+    pub address: AddressV2,
+    #[serde(deserialize_with = "deserialize_bounded::<PetsMax, _, _>")]
+    pub pets: Vec<Pet>,
+    pub bio: Option<String>,
+}
+
+/// By hand for the same reason as `CreateRequest`.
+impl Validate for CreateRequestV2 {
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        let email = &self.email;
+        add_error(
+            &mut errors,
+            "email",
+            validate_email_format(email),
+            email,
+            Some(EMAIL_MESSAGE),
+        );
+        add_error(
+            &mut errors,
+            "email",
+            validate_email_bytes(email),
+            email,
+            None,
+        );
+        validate_contact_fields(&mut errors, self.phone.as_ref(), self.bio.as_ref());
+        for err in validate_create_request_v2(self) {
+            errors.add("__all__", err);
+        }
+        with_nested_errors(errors, &self.address, &self.pets)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct AddressV2 {
+    #[validate(
+        custom(
+            function = "validate_street_length",
+            message = "street must be between {min} and {max} characters"
+        ),
+        custom = "validate_street_bytes"
+    )]
+    pub street: String,
+    #[serde(deserialize_with = "deserialize_count")]
+    #[validate(custom(
+        function = "validate_street_no",
+        message = "street number must be at least {min}"
+    ))]
+    pub house_number: usize,
+}
+
+/// Accepts E.164 numbers, a `+` followed by 8 to 15 digits, and national numbers, a `0` trunk
+/// prefix followed by 6 to 14 digits. `Normalize` strips the spaces and dashes of formats like
+/// `0151 1234-5678` beforehand.
+fn validate_phone(phone: &str) -> std::result::Result<(), ValidationError> {
+    let digits = |digits: &str, lengths: std::ops::RangeInclusive<usize>| {
+        lengths.contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let valid = match (phone.strip_prefix('+'), phone.strip_prefix('0')) {
+        (Some(international), _) => digits(international, 8..=15),
+        (_, Some(national)) => digits(national, 6..=14),
+        _ => false,
+    };
+    if valid {
+        return Ok(());
+    }
+    Err(ValidationError::new("invalid_phone"))
+}
+
+/// What every version of the create request boils down to once it's valid, so handlers don't
+/// care which version a client sent.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInput {
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Address,
+    pub pets: Vec<Pet>,
+    pub bio: Option<String>,
+    pub password: Option<Secret>,
+}
+
+impl From<CreateRequest> for CreateInput {
+    fn from(request: CreateRequest) -> Self {
+        CreateInput {
+            email: request.email,
+            phone: request.phone,
+            address: request.address,
+            pets: request.pets,
+            bio: request.bio,
+            password: request.password,
+        }
+    }
+}
+
+impl From<CreateRequestV2> for CreateInput {
+    fn from(request: CreateRequestV2) -> Self {
+        CreateInput {
+            email: Some(request.email),
+            phone: request.phone,
+            address: Address {
+                street: request.address.street,
+                street_no: Lenient::Value(request.address.house_number),
+                phone: None,
+            },
+            pets: request.pets,
+            bio: request.bio,
+            password: None,
+        }
+    }
+}
+
+/// The versions of the create request that come with a list of pets, so `X-Allow-Partial` can
+/// drop the invalid ones.
+pub trait WithPets {
+    fn pets_mut(&mut self) -> &mut Vec<Pet>;
+}
+
+impl WithPets for CreateRequest {
+    fn pets_mut(&mut self) -> &mut Vec<Pet> {
+        &mut self.pets
+    }
+}
+
+impl WithPets for CreateRequestV2 {
+    fn pets_mut(&mut self) -> &mut Vec<Pet> {
+        &mut self.pets
+    }
+}
+
+impl CreateInput {
+    /// The paths of the fields that were sent as strings and coerced, for `X-Coercions`.
+    pub fn coercions(&self) -> Vec<&'static str> {
+        let mut coerced = Vec::new();
+        if self.address.street_no.is_coerced() {
+            coerced.push("address.streetNo");
+        }
+        coerced
+    }
+}
+
+/// The `X-Api-Version`s of the create request `with_versioned_create_body` understands.
+pub const CREATE_API_VERSIONS: &[u32] = &[1, 2];
+
+/// Cleans up a request after deserialization and before validation, so validation, the handler
+/// and the response all see the cleaned values.
+///
+/// All string fields are brought into Unicode Normalization Form C. The default `LengthMode`
+/// counts graphemes, which doesn't care, but in `chars` and `bytes` mode a decomposed
+/// `"e\u{301}"` would be two characters and three bytes long where the precomposed `"é"` is one
+/// and two. After normalizing, both modes count the NFC form, so the same text has the same
+/// length however the client composed it.
+///
+/// On top of that, emails are trimmed and lowercased, pet names trimmed and streets trimmed with
+/// inner runs of whitespace collapsed to one space, so `" ab  c "` is validated as `"ab c"`.
+///
+/// Request types without free text can opt out with an empty impl.
+pub trait Normalize {
+    fn normalize(&mut self) {}
+}
+
+fn to_nfc(s: &mut String) {
+    if !is_nfc(s) {
+        *s = s.nfc().collect();
+    }
+}
+
+fn trim_in_place(s: &mut String) {
+    if s.trim().len() != s.len() {
+        *s = s.trim().to_string();
+    }
+}
+
+fn normalize_email_field(email: &mut String) {
+    to_nfc(email);
+    trim_in_place(email);
+    *email = email.to_lowercase();
+}
+
+fn normalize_phone(phone: &mut String) {
+    phone.retain(|c| !(c.is_whitespace() || c == '-'));
+}
+
+/// Drops a value that is empty once normalized, so it counts as not sent.
+fn none_if_empty(value: &mut Option<String>) {
+    if value.as_deref() == Some("") {
+        *value = None;
+    }
+}
+
+fn normalize_street(street: &mut String) {
+    to_nfc(street);
+    *street = street.split_whitespace().collect::<Vec<_>>().join(" ");
+}
+
+impl Normalize for CreateRequest {
+    fn normalize(&mut self) {
+        if let Some(email) = self.email.as_mut() {
+            normalize_email_field(email);
+        }
+        none_if_empty(&mut self.email);
+        if let Some(phone) = self.phone.as_mut() {
+            normalize_phone(phone);
+        }
+        none_if_empty(&mut self.phone);
+        self.address.normalize();
+        self.pets.iter_mut().for_each(Pet::normalize);
+        if let Some(bio) = self.bio.as_mut() {
+            to_nfc(bio);
+        }
+    }
+}
+
+impl Normalize for CreateRequestV2 {
+    fn normalize(&mut self) {
+        normalize_email_field(&mut self.email);
+        if let Some(phone) = self.phone.as_mut() {
+            normalize_phone(phone);
+        }
+        normalize_street(&mut self.address.street);
+        self.pets.iter_mut().for_each(Pet::normalize);
+        if let Some(bio) = self.bio.as_mut() {
+            to_nfc(bio);
+        }
+    }
+}
+
+impl Normalize for Address {
+    fn normalize(&mut self) {
+        normalize_street(&mut self.street);
+        if let Some(phone) = self.phone.as_mut() {
+            normalize_phone(phone);
+        }
+    }
+}
+
+impl Normalize for Pet {
+    fn normalize(&mut self) {
+        to_nfc(&mut self.name);
+        trim_in_place(&mut self.name);
+        if let Some(photo_url) = self.photo_url.as_mut() {
+            to_nfc(photo_url);
+        }
+    }
+}
+
+/// A field of a partial update, which tells a key that was left out from one sent as `null`.
+/// Fields need `#[serde(default)]` for a missing key to be `Absent`, and
+/// `skip_serializing_if = "Maybe::is_absent"` to be left out again when serialized.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Maybe<T> {
+    /// The key was left out, keep the field as it is.
+    #[default]
+    Absent,
+    /// The key was sent as `null`, clear the field.
+    Null,
+    Value(T),
+}
+
+impl<T> Maybe<T> {
+    pub fn is_absent(&self) -> bool {
+        matches!(self, Maybe::Absent)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Maybe::Null)
+    }
+
+    /// The value, if one was sent.
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Maybe::Value(value) => Some(value),
+            Maybe::Absent | Maybe::Null => None,
+        }
+    }
+
+    pub fn value_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Maybe::Value(value) => Some(value),
+            Maybe::Absent | Maybe::Null => None,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Maybe<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        Ok(Option::<T>::deserialize(deserializer)?.map_or(Maybe::Null, Maybe::Value))
+    }
+}
+
+/// `null` for `Null`, and for `Absent` fields that aren't skipped.
+impl<T: Serialize> Serialize for Maybe<T> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Maybe::Value(value) => value.serialize(serializer),
+            Maybe::Absent | Maybe::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+/// A partial `CreateRequest` for `PATCH /update`, only the fields that are present are validated.
+/// `bio` may be sent as `null` to clear it. The other fields are required by `CreateRequest`, so
+/// for them `null` fails validation with `cannot_be_null`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct UpdateRequest {
+    #[serde(default, skip_serializing_if = "Maybe::is_absent")]
+    pub email: Maybe<String>,
+    #[serde(default, skip_serializing_if = "Maybe::is_absent")]
+    pub address: Maybe<Address>,
+    #[serde(default, skip_serializing_if = "Maybe::is_absent")]
+    pub pets: Maybe<BoundedVec<Pet, PetsMax>>,
+    #[serde(default, skip_serializing_if = "Maybe::is_absent")]
+    pub bio: Maybe<String>,
+}
+
+impl Validate for UpdateRequest {
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        let nulls: Vec<&'static str> = [
+            ("email", self.email.is_null()),
+            ("address", self.address.is_null()),
+            ("pets", self.pets.is_null()),
+        ]
+        .iter()
+        .filter_map(|&(field, null)| null.then_some(field))
+        .collect();
+        validate_partial_create(
+            self.email.value(),
+            self.address.value(),
+            self.pets.value().map(|pets| pets.as_slice()),
+            self.bio.value(),
+            &nulls,
+        )
+    }
+}
+
+impl Normalize for UpdateRequest {
+    fn normalize(&mut self) {
+        normalize_partial_create(
+            self.email.value_mut(),
+            self.address.value_mut(),
+            self.pets.value_mut().map(DerefMut::deref_mut),
+            self.bio.value_mut(),
+        );
+    }
+}
+
+/// A `CreateRequest` saved before it's complete, for `POST /create-draft`. Unlike an
+/// `UpdateRequest` it takes `null` for a field it doesn't have yet, the same as leaving it out.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct DraftRequest {
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub address: Option<Address>,
+    /// An empty list is present, and fails `pets_count` like it would on a `CreateRequest`.
+    #[serde(default)]
+    pub pets: Option<BoundedVec<Pet, PetsMax>>,
+    #[serde(default)]
+    pub bio: Option<String>,
+}
+
+impl Validate for DraftRequest {
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        validate_partial_create(
+            self.email.as_ref(),
+            self.address.as_ref(),
+            self.pets.as_deref().map(Vec::as_slice),
+            self.bio.as_ref(),
+            &[],
+        )
+    }
+}
+
+impl Normalize for DraftRequest {
+    fn normalize(&mut self) {
+        normalize_partial_create(
+            self.email.as_mut(),
+            self.address.as_mut(),
+            self.pets.as_deref_mut(),
+            self.bio.as_mut(),
+        );
+    }
+}
+
+/// Validates the fields of a `CreateRequest` that are present by its rules, reporting errors
+/// under the same paths. Written by hand since validator's derive can't validate the elements of
+/// an `Option<Vec<_>>`. The fields in `nulls` were sent as `null` but can't be cleared, and fail
+/// with `cannot_be_null`.
+fn validate_partial_create(
+    email: Option<&String>,
+    address: Option<&Address>,
+    pets: Option<&[Pet]>,
+    bio: Option<&String>,
+    nulls: &[&'static str],
+) -> std::result::Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    for &field in nulls {
+        errors.add(field, ValidationError::new("cannot_be_null"));
+    }
+    if let Some(email) = email {
+        add_error(
+            &mut errors,
+            "email",
+            validate_email_format(email),
+            email,
+            Some(EMAIL_MESSAGE),
+        );
+        add_error(
+            &mut errors,
+            "email",
+            validate_email_bytes(email),
+            email,
+            None,
+        );
+    }
+    // Partial requests have no `phone`
+    validate_contact_fields(&mut errors, None, bio);
+    if let Some(Err(err)) = pets.map(validate_pets_count) {
+        errors.add("__all__", err);
+    }
+
+    let mut result = if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    };
+    if let Some(address) = address {
+        result = ValidationErrors::merge(result, "address", address.validate());
+    }
+    if let Some(pets) = pets {
+        // `merge_all` expects every element's errors nested under the field name
+        let results = pets
+            .iter()
+            .map(|pet| ValidationErrors::merge(Ok(()), "pets", pet.validate()))
+            .collect();
+        result = ValidationErrors::merge_all(result, "pets", results);
+    }
+    result
+}
+
+fn normalize_partial_create(
+    email: Option<&mut String>,
+    address: Option<&mut Address>,
+    pets: Option<&mut Vec<Pet>>,
+    bio: Option<&mut String>,
+) {
+    if let Some(email) = email {
+        normalize_email_field(email);
+    }
+    if let Some(address) = address {
+        address.normalize();
+    }
+    if let Some(pets) = pets {
+        pets.iter_mut().for_each(Pet::normalize);
+    }
+    if let Some(bio) = bio {
+        to_nfc(bio);
+    }
+}
+
+pub const MIN_GUESTS: usize = 1;
+pub const MAX_GUESTS: usize = 20;
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_guest_range", skip_on_field_errors = false))]
+pub struct BookingRequest {
+    #[validate(length(min = 2, max = 30))]
+    pub name: String,
+    pub min_guests: usize,
+    pub max_guests: usize,
+}
+
+fn validate_guest_range(booking: &BookingRequest) -> std::result::Result<(), ValidationError> {
+    let code = if booking.min_guests > booking.max_guests {
+        "min_guests_greater_than_max_guests"
+    } else if booking.min_guests < MIN_GUESTS {
+        "min_guests_below_lower_bound"
+    } else if booking.max_guests > MAX_GUESTS {
+        "max_guests_above_upper_bound"
+    } else {
+        return Ok(());
+    };
+
+    let mut err = ValidationError::new(code);
+    err.add_param("min_guests".into(), &booking.min_guests);
+    err.add_param("max_guests".into(), &booking.max_guests);
+    err.add_param("lower_bound".into(), &MIN_GUESTS);
+    err.add_param("upper_bound".into(), &MAX_GUESTS);
+    Err(err)
+}
+
+impl Normalize for BookingRequest {
+    fn normalize(&mut self) {
+        to_nfc(&mut self.name);
+    }
+}
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequest {
+    #[validate]
+    pub payment: Payment,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentMethod {
+    Card,
+    Paypal,
+}
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(
+    function = "validate_payment_method_fields",
+    skip_on_field_errors = false
+))]
+pub struct Payment {
+    pub method: PaymentMethod,
+    #[validate(length(min = 12, max = 19))]
+    pub card_number: Option<String>,
+    #[validate(email)]
+    pub paypal_email: Option<String>,
+}
+
+/// Enforces the fields required by the chosen payment `method`.
+fn validate_payment_method_fields(payment: &Payment) -> std::result::Result<(), ValidationError> {
+    let (missing, code) = match payment.method {
+        PaymentMethod::Card if payment.card_number.is_none() => {
+            ("card_number", "card_number_required_for_card")
+        }
+        PaymentMethod::Paypal if payment.paypal_email.is_none() => {
+            ("paypal_email", "paypal_email_required_for_paypal")
+        }
+        _ => return Ok(()),
+    };
+
+    let mut err = ValidationError::new(code);
+    err.add_param("missing_field".into(), &missing);
+    err.add_param("method".into(), &payment.method);
+    Err(err)
+}
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+#[validate(schema(function = "validate_schedule_order", skip_on_field_errors = false))]
+pub struct ScheduleRequest {
+    #[validate]
+    pub schedule: Vec<Slot>,
+}
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct Slot {
+    /// Start of the slot as a unix timestamp in seconds.
+    pub starts_at: u64,
+    #[validate(range(min = 5, max = 480))]
+    pub duration_minutes: u32,
+}
+
+/// Requires the slots to be in chronological order, reporting the first slot that starts
+/// before its predecessor. Equal start times are allowed.
+fn validate_schedule_order(request: &ScheduleRequest) -> std::result::Result<(), ValidationError> {
+    let out_of_order = request
+        .schedule
+        .windows(2)
+        .position(|pair| pair[1].starts_at < pair[0].starts_at);
+    match out_of_order {
+        Some(i) => {
+            let mut err = ValidationError::new("schedule_not_chronological");
+            err.add_param("index".into(), &(i + 1));
+            err.add_param("starts_at".into(), &request.schedule[i + 1].starts_at);
+            err.add_param("previous_starts_at".into(), &request.schedule[i].starts_at);
+            Err(err)
+        }
+        None => Ok(()),
+    }
+}
+
+pub(crate) const SUPPORTED_ENVELOPE_VERSIONS: &[u32] = &[1];
+
+/// A versioned wrapper around a request payload, `{ "version": 1, "payload": {...} }`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub payload: T,
+}
+
+impl Normalize for PaymentRequest {}
+
+impl Normalize for ScheduleRequest {}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaValidationRequest {
+    pub schema: serde_json::Value,
+    pub data: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ExplainQuery {
+    #[serde(default)]
+    pub explain: bool,
+}
+
+/// `?lenient=true`, accepting comments and trailing commas in JSON bodies.
+#[derive(Deserialize, Debug, Default)]
+pub struct LenientQuery {
+    #[serde(default)]
+    pub lenient: bool,
+}
+
+/// `?dry_run=true`, validating a create without storing it. Parsed by `with_dry_run`, which
+/// takes only `true` and `false`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DryRunQuery {
+    pub dry_run: bool,
+}
+
+/// The body of `POST /validate-field`: a value for one field of a `CreateRequest`, e.g.
+/// `{"field": "pets[0].name", "value": "Chip"}`. A missing `value` is `null`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ValidateFieldRequest {
+    pub field: String,
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+/// Whether a field of `CREATE_FIELD_RULES` can be validated on its own.
+#[derive(Debug, Clone, Copy)]
+pub enum FieldRules {
+    /// The field has rules on its value alone.
+    Own,
+    /// The field's only rules are struct-level ones, named here, which compare it with other
+    /// fields.
+    StructLevel(&'static [&'static str]),
+}
+
+/// The fields of a `CreateRequest` that `POST /validate-field` knows, with list indices as `[]`.
+/// `email` and `pets[].name` also take part in `email_domain_in_pet_name`, and `email` and
+/// `password` in `password_contains_email`, which only a full validation checks.
+pub const CREATE_FIELD_RULES: &[(&str, FieldRules)] = &[
+    ("contactMethod", FieldRules::StructLevel(&["required_when"])),
+    ("email", FieldRules::Own),
+    ("phone", FieldRules::Own),
+    ("address.street", FieldRules::Own),
+    ("address.streetNo", FieldRules::Own),
+    ("address.phone", FieldRules::Own),
+    ("pets", FieldRules::StructLevel(&["pets_count"])),
+    ("pets[].name", FieldRules::Own),
+    ("pets[].photoUrl", FieldRules::Own),
+    ("pets[].species", FieldRules::Own),
+    ("pets[].birthdate", FieldRules::Own),
+    ("bio", FieldRules::Own),
+    ("birthdate", FieldRules::Own),
+    ("password", FieldRules::Own),
+];
+
+/// What a dry run that passed validation answers with.
+#[derive(Serialize, Debug)]
+pub struct DryRunResponse {
+    pub valid: bool,
+}
+
+/// What `/create-multipart` answers with, the usual `CreateResponse` and the photo it got.
+#[derive(Serialize, Debug)]
+pub struct MultipartCreateResponse {
+    #[serde(flatten)]
+    pub created: CreateResponse,
+    pub photo: PhotoReceipt,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoReceipt {
+    pub received: bool,
+    /// The size of the photo in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+/// What the create routes answer with, `201 Created`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateResponse {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    pub pets: Vec<Pet>,
+}
+
+impl From<CreateInput> for CreateResponse {
+    fn from(input: CreateInput) -> Self {
+        CreateResponse {
+            id: uuid::Uuid::new_v4().to_string(),
+            email: input.email,
+            phone: input.phone,
+            pets: input.pets,
+        }
+    }
+}
+
+impl From<CreateRequest> for CreateResponse {
+    fn from(request: CreateRequest) -> Self {
+        CreateInput::from(request).into()
+    }
+}
+
+impl From<&StoredCreate> for CreateResponse {
+    fn from(stored: &StoredCreate) -> Self {
+        CreateResponse {
+            id: stored.id.to_string(),
+            email: stored.email.clone(),
+            phone: stored.phone.clone(),
+            pets: stored.pets.clone(),
+        }
+    }
+}
+
+/// The outcome for one line of a `/create-stream` request, counting lines from 1. `Aborted` is
+/// the last record of a stream that hit a limit.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum StreamLineResult {
+    Created {
+        line: usize,
+        #[serde(flatten)]
+        created: CreateResponse,
+    },
+    Invalid {
+        line: usize,
+        errors: Vec<FieldError>,
+    },
+    Aborted {
+        line: usize,
+        message: String,
+    },
+}
+
+/// The outcome for one element of a `/create-batch` request.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BatchItemResult {
+    Created {
+        index: usize,
+        #[serde(flatten)]
+        created: CreateResponse,
+    },
+    Invalid {
+        index: usize,
+        errors: Vec<FieldError>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct ExplainResponse {
+    #[serde(flatten)]
+    pub created: CreateResponse,
+    pub validators: BTreeMap<&'static str, Vec<&'static str>>,
+}
+
+/// A pet that `X-Allow-Partial` dropped from a create request, by its index in the request, and
+/// the errors that got it dropped.
+#[derive(Serialize, Debug)]
+pub struct RejectedPet {
+    pub index: usize,
+    pub errors: Vec<FieldError>,
+}
+
+/// What create-validator answers with, `207 Multi-Status`, when `X-Allow-Partial` dropped some
+/// of the pets: the usual response of the pets that were kept, which of the request they were,
+/// and the ones that were dropped.
+#[derive(Serialize, Debug)]
+pub struct PartialCreateResponse<T> {
+    #[serde(flatten)]
+    pub result: T,
+    /// The indices in the request of the accepted pets, in the order they are stored in.
+    pub accepted: Vec<usize>,
+    pub rejected: Vec<RejectedPet>,
+}
+
+/// The fields of every struct of a create request in declaration order, keyed by the struct's
+/// path with list indices as `[]`. Errors are listed in this order, see
+/// `validation_field_errors`. `houseNumber` is the `streetNo` of a `CreateRequestV2`.
+pub(crate) const FIELD_ORDER: &[(&str, &[&str])] = &[
+    (
+        "",
+        &[
+            "contactMethod",
+            "email",
+            "phone",
+            "address",
+            "pets",
+            "bio",
+            "birthdate",
+            "password",
+        ],
+    ),
+    ("address", &["street", "streetNo", "houseNumber", "phone"]),
+    ("pets[]", &["name", "photoUrl", "species", "birthdate"]),
+];
+
+/// A rule of `CREATE_RULES`, what `GET /rules/create` reports of it and which validator of
+/// `CreateRequest`'s `Validate` impl enforces it.
+pub(crate) struct FieldRule {
+    /// The function that checks the rule, `validate_create_request` for struct-level rules.
+    pub validator: &'static str,
+    pub rule: &'static str,
+    /// The codes of the errors the rule fails with, as clients get them.
+    pub codes: &'static [&'static str],
+    pub description: &'static str,
+    /// The bounds the validator checks against, read from the same `ValidationConfig`.
+    pub params: fn(&ValidationConfig) -> serde_json::Value,
+}
+
+fn no_params(_: &ValidationConfig) -> serde_json::Value {
+    serde_json::json!({})
+}
+
+const EMAIL_BYTES: FieldRule = FieldRule {
+    validator: "validate_email_bytes",
+    rule: "max_bytes",
+    codes: &["max_bytes_exceeded"],
+    description: "at most max bytes UTF-8 encoded",
+    params: |limits| serde_json::json!({ "max": limits.email_max_bytes }),
+};
+
+const STRING_BYTES: FieldRule = FieldRule {
+    validator: "validate_string_bytes",
+    rule: "max_bytes",
+    codes: &["max_bytes_exceeded"],
+    description: "at most max bytes UTF-8 encoded",
+    params: |limits| serde_json::json!({ "max": limits.string_max_bytes }),
+};
+
+const PHONE: FieldRule = FieldRule {
+    validator: "validate_phone",
+    rule: "phone",
+    codes: &["phone.invalid"],
+    description: "a + followed by 8 to 15 digits, or a 0 followed by 6 to 14 digits, once spaces and dashes are removed",
+    params: no_params,
+};
+
+/// The rules of every field of a `CreateRequest`, keyed by field path with list indices as
+/// `[]`, in the order they are checked. Struct-level rules are listed under the field they
+/// report their errors on, the request as a whole is the empty path. `required_when` is listed
+/// under `contactMethod`, its errors are on the field the contact method requires.
+///
+/// `validator` doesn't expose which rules a `Validate` impl runs, so this has to be kept in sync
+/// with the impl of `CreateRequest` by hand. Both `GET /rules/create` and the
+/// validators listed by `?explain=true` are built from it.
+pub(crate) const CREATE_RULES: &[(&str, &[FieldRule])] = &[
+    (
+        "",
+        &[FieldRule {
+            validator: "validate_create_request",
+            rule: "max_total_bytes",
+            codes: &["max_total_bytes_exceeded"],
+            description: "all string fields together take at most max bytes UTF-8 encoded",
+            params: |limits| serde_json::json!({ "max": limits.request_max_string_bytes }),
+        }],
+    ),
+    (
+        "contactMethod",
+        &[FieldRule {
+            validator: "validate_create_request",
+            rule: "required_when",
+            codes: &["required_when"],
+            description: "the field of the contact method, email by default, is required",
+            params: |_| serde_json::json!({ "values": ContactMethod::VARIANTS }),
+        }],
+    ),
+    (
+        "email",
+        &[
+            FieldRule {
+                validator: "email",
+                rule: "email",
+                codes: &["email.invalid"],
+                description: "an email address",
+                params: no_params,
+            },
+            EMAIL_BYTES,
+        ],
+    ),
+    ("phone", &[PHONE, STRING_BYTES]),
+    (
+        "address.street",
+        &[
+            FieldRule {
+                validator: "validate_street_length",
+                rule: "length",
+                codes: &["length_min", "length_max"],
+                description: "between min and max long, counted in mode",
+                params: |limits| {
+                    serde_json::json!({
+                        "min": limits.street_min_length,
+                        "max": limits.street_max_length,
+                        "mode": length_mode(),
+                    })
+                },
+            },
+            FieldRule {
+                validator: "validate_street_bytes",
+                rule: "max_bytes",
+                codes: &["max_bytes_exceeded"],
+                description: "at most max bytes UTF-8 encoded",
+                params: |limits| serde_json::json!({ "max": limits.street_max_bytes }),
+            },
+        ],
+    ),
+    (
+        "address.streetNo",
+        &[
+            FieldRule {
+                validator: "validate_number_string",
+                rule: "number_string",
+                codes: &["invalid_number_string"],
+                description: "if sent as a string, the number and nothing but whitespace around it, unless strict",
+                params: |limits| serde_json::json!({ "strict": limits.strict_numbers }),
+            },
+            FieldRule {
+                validator: "validate_lenient_street_no",
+                rule: "range",
+                codes: &["range_min"],
+                description: "at least min",
+                params: |limits| serde_json::json!({ "min": limits.street_no_min }),
+            },
+            FieldRule {
+                validator: "validate_create_request",
+                rule: "required_for_pets",
+                codes: &["street_no_required"],
+                description: "greater than 0 with more than max_pets pets, whatever min allows",
+                params: |_| serde_json::json!({ "max_pets": STREET_NO_REQUIRED_ABOVE_PETS }),
+            },
+        ],
+    ),
+    ("address.phone", &[PHONE, STRING_BYTES]),
+    (
+        "pets",
+        &[FieldRule {
+            validator: "validate_create_request",
+            rule: "count",
+            codes: &["pets_count"],
+            description: "at least min pets",
+            params: |limits| serde_json::json!({ "min": limits.pets_min, "max": limits.pets_max }),
+        },
+        FieldRule {
+            validator: "deserialize_bounded",
+            rule: "max_elements",
+            codes: &["too_many_elements"],
+            description: "at most max pets, checked while the body is read",
+            params: |limits| serde_json::json!({ "max": limits.pets_max }),
+        }],
+    ),
+    (
+        "pets[].name",
+        &[
+            FieldRule {
+                validator: "validate_pet_name_length",
+                rule: "length",
+                codes: &["length_min", "length_max"],
+                description: "between min and max long, counted in mode",
+                params: |limits| {
+                    serde_json::json!({
+                        "min": limits.pet_name_min_length,
+                        "max": limits.pet_name_max_length,
+                        "mode": length_mode(),
+                    })
+                },
+            },
+            FieldRule {
+                validator: "validate_pet_name",
+                rule: "pet_name",
+                codes: &[
+                    "pet_name.whitespace",
+                    "pet_name.charset",
+                    "pet_name.reserved",
+                ],
+                description: "letters, spaces and hyphens without surrounding whitespace, and none of the reserved names ignoring case",
+                params: |_| serde_json::json!({ "reserved": RESERVED_PET_NAMES }),
+            },
+            FieldRule {
+                validator: "validate_pet_name_bytes",
+                rule: "max_bytes",
+                codes: &["max_bytes_exceeded"],
+                description: "at most max bytes UTF-8 encoded",
+                params: |limits| serde_json::json!({ "max": limits.pet_name_max_bytes }),
+            },
+            FieldRule {
+                validator: "validate_create_request",
+                rule: "email_domain_not_in_pet_name",
+                codes: &["email_domain_in_pet_name"],
+                description: "doesn't contain the first label of the email's domain, ignoring case",
+                params: no_params,
+            },
+        ],
+    ),
+    (
+        "pets[].photoUrl",
+        &[
+            FieldRule {
+                validator: "validate_photo_url",
+                rule: "image_url",
+                codes: &["url.invalid", "url.not_an_image"],
+                description: "a URL whose path ends in one of the extensions, ignoring case",
+                params: |_| serde_json::json!({ "allowed_extensions": IMAGE_EXTENSIONS }),
+            },
+            STRING_BYTES,
+        ],
+    ),
+    (
+        "pets[].birthdate",
+        &[FieldRule {
+            validator: "validate_pet_birthdate",
+            rule: "birthdate",
+            codes: &["birthdate.future", "birthdate.too_old"],
+            description: "not in the future and at most max_age_years ago",
+            params: |_| serde_json::json!({ "max_age_years": PET_MAX_AGE_YEARS }),
+        }],
+    ),
+    (
+        "bio",
+        &[
+            FieldRule {
+                validator: "no_html",
+                rule: "no_html",
+                codes: &["html.not_allowed"],
+                description: "no < directly followed by a letter, ! or / and a letter",
+                params: no_params,
+            },
+            STRING_BYTES,
+        ],
+    ),
+    (
+        "birthdate",
+        &[FieldRule {
+            validator: "validate_owner_birthdate",
+            rule: "birthdate",
+            codes: &["birthdate.future"],
+            description: "not in the future",
+            params: no_params,
+        }],
+    ),
+    (
+        "password",
+        &[
+            FieldRule {
+                validator: "validate_password",
+                rule: "password",
+                codes: &["password_too_short", "password_too_weak"],
+                description: "at least min characters with at least one letter and one digit",
+                params: |_| serde_json::json!({ "min": PASSWORD_MIN_LENGTH }),
+            },
+            FieldRule {
+                validator: "validate_password_bytes",
+                rule: "max_bytes",
+                codes: &["max_bytes_exceeded"],
+                description: "at most max bytes UTF-8 encoded",
+                params: |limits| serde_json::json!({ "max": limits.string_max_bytes }),
+            },
+            FieldRule {
+                validator: "validate_create_request",
+                rule: "password_not_email",
+                codes: &["password_contains_email"],
+                description: "doesn't contain the local part of the email, ignoring case",
+                params: no_params,
+            },
+        ],
+    ),
+];
+
+/// The rules of `CREATE_RULES` as `GET /rules/create` publishes them, for clients that mirror
+/// validation offline: per field path, the rules with their codes, a description and the current
+/// bounds, like `{"rule": "length", "min": 3, "max": 20, ...}`.
+pub(crate) fn create_request_rules() -> serde_json::Value {
+    let limits = ValidationConfig::current();
+    let fields = CREATE_RULES
+        .iter()
+        .map(|&(field, rules)| {
+            let rules = rules
+                .iter()
+                .map(|rule| {
+                    let mut entry = serde_json::json!({
+                        "rule": rule.rule,
+                        "codes": rule.codes,
+                        "description": rule.description,
+                    });
+                    if let (Some(entry), serde_json::Value::Object(params)) =
+                        (entry.as_object_mut(), (rule.params)(&limits))
+                    {
+                        entry.extend(params);
+                    }
+                    entry
+                })
+                .collect();
+            (field.to_string(), serde_json::Value::Array(rules))
+        })
+        .collect();
+    serde_json::Value::Object(fields)
+}
+
+/// The validators evaluated for every field of a `CreateRequest`, keyed by field path, from
+/// `CREATE_RULES` without the struct-level ones.
+pub(crate) fn create_request_validators() -> BTreeMap<&'static str, Vec<&'static str>> {
+    let mut validators = BTreeMap::new();
+    for &(field, rules) in CREATE_RULES {
+        for rule in rules {
+            if rule.validator == "validate_create_request" {
+                continue;
+            }
+            let names: &mut Vec<_> = validators.entry(field).or_default();
+            if !names.contains(&rule.validator) {
+                names.push(rule.validator);
+            }
+        }
+    }
+    validators
+}
+
+/// A JSON Schema for `CreateRequest`, published at `GET /schema/create` so clients can validate
+/// before sending. The bounds come from the `ValidationConfig` the validators use. JSON Schema counts
+/// string lengths in code points, which matches `LENGTH_MODE=chars`; with the default
+/// `graphemes` the schema can reject strings of emoji or combining marks the server accepts.
+pub(crate) fn create_request_schema() -> serde_json::Value {
+    let limits = ValidationConfig::current();
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "CreateRequest",
+        "type": "object",
+        "required": ["address", "pets"],
+        "additionalProperties": false,
+        "properties": {
+            "contactMethod": { "type": "string", "enum": ContactMethod::VARIANTS },
+            "email": { "type": "string", "format": "email" },
+            "phone": { "type": "string" },
+            "address": {
+                "type": "object",
+                "required": ["street", "streetNo"],
+                "additionalProperties": false,
+                "properties": {
+                    "street": {
+                        "type": "string",
+                        "minLength": limits.street_min_length,
+                        "maxLength": limits.street_max_length,
+                    },
+                    "streetNo": { "type": "integer", "minimum": limits.street_no_min },
+                    "phone": { "type": "string" },
+                },
+            },
+            "pets": {
+                "type": "array",
+                "minItems": limits.pets_min,
+                "maxItems": limits.pets_max,
+                "items": {
+                    "type": "object",
+                    "required": ["name"],
+                    "additionalProperties": false,
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "minLength": limits.pet_name_min_length,
+                            "maxLength": limits.pet_name_max_length,
+                        },
+                        "photoUrl": { "type": "string", "format": "uri" },
+                        "species": { "type": "string", "enum": Species::VARIANTS },
+                        "birthdate": { "type": "string", "format": "date" },
+                    },
+                },
+            },
+            "bio": { "type": "string" },
+            "birthdate": { "type": "string", "format": "date" },
+            "password": {
+                "type": "string",
+                "minLength": PASSWORD_MIN_LENGTH,
+                "writeOnly": true,
+            },
+        },
+    })
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+/// The filter is `nameContains` like every other camelCase key, its errors are reported under
+/// that name. `name_contains` is still accepted.
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPetsQuery {
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: usize,
+    #[serde(default)]
+    #[validate(range(max = 10000))]
+    pub offset: usize,
+    #[serde(alias = "name_contains")]
+    #[validate(length(min = 1, max = 20))]
+    pub name_contains: Option<String>,
+}
+
+impl Normalize for ListPetsQuery {
+    fn normalize(&mut self) {
+        if let Some(name_contains) = self.name_contains.as_mut() {
+            to_nfc(name_contains);
+        }
+    }
+}
+
+/// The pagination of `GET /creates`.
+#[derive(Deserialize, Debug, Validate)]
+pub struct ListCreatesQuery {
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: usize,
+    #[serde(default)]
+    #[validate(range(max = 10000))]
+    pub offset: usize,
+}
+
+impl Normalize for ListCreatesQuery {}
+
+pub(crate) const DEMO_PET_NAMES: &[&str] = &["nacho", "chip", "whiskers", "rex", "bella", "nala"];
+
+/// The JSON shape a request type expects, used to report every missing field and type mismatch
+/// at once instead of stopping at the first one like serde does.
+pub enum Shape {
+    String,
+    /// A string that has to be one of the listed values.
+    Enum(&'static [&'static str]),
+    Unsigned,
+    /// A `Lenient` count, which may also be a string.
+    LenientUnsigned,
+    Object(&'static [(&'static str, Shape, Presence)]),
+    /// A list of at most the limit's elements, like a `BoundedVec`. Only the elements up to the
+    /// one over the limit are checked, since deserializing stops there anyway.
+    Array(&'static Shape, fn() -> usize),
+}
+
+#[derive(PartialEq)]
+pub enum Presence {
+    Required,
+    Optional,
+}
+
+pub trait ExpectedShape {
+    const SHAPE: Shape;
+}
+
+const PET_SHAPE: Shape = Shape::Object(&[
+    ("name", Shape::String, Presence::Required),
+    ("photoUrl", Shape::String, Presence::Optional),
+    (
+        "species",
+        Shape::Enum(Species::VARIANTS),
+        Presence::Optional,
+    ),
+    ("birthdate", Shape::String, Presence::Optional),
+]);
+
+impl ExpectedShape for CreateRequest {
+    const SHAPE: Shape = Shape::Object(&[
+        (
+            "contactMethod",
+            Shape::Enum(ContactMethod::VARIANTS),
+            Presence::Optional,
+        ),
+        ("email", Shape::String, Presence::Optional),
+        ("phone", Shape::String, Presence::Optional),
+        (
+            "address",
+            Shape::Object(&[
+                ("street", Shape::String, Presence::Required),
+                ("streetNo", Shape::LenientUnsigned, Presence::Required),
+                ("phone", Shape::String, Presence::Optional),
+            ]),
+            Presence::Required,
+        ),
+        (
+            "pets",
+            Shape::Array(&PET_SHAPE, PetsMax::max),
+            Presence::Required,
+        ),
+        ("bio", Shape::String, Presence::Optional),
+        ("birthdate", Shape::String, Presence::Optional),
+        ("password", Shape::String, Presence::Optional),
+    ]);
+}
+
+pub(crate) fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// Makes `value` fit `shape` after `check_shape` found it doesn't: values of the wrong type and
+/// missing required fields are replaced with empty placeholders of the right type and unknown
+/// fields are removed. The placeholders are unlikely to pass validation, so errors on their paths
+/// have to be ignored.
+pub(crate) fn fit_shape(value: &mut serde_json::Value, shape: &Shape) {
+    match (shape, &mut *value) {
+        (Shape::String, serde_json::Value::String(_)) => {}
+        (Shape::String, _) => *value = serde_json::Value::String(String::new()),
+        (Shape::Enum(values), serde_json::Value::String(s)) if values.contains(&s.as_str()) => {}
+        (Shape::Enum(values), _) => *value = serde_json::Value::from(values[0]),
+        (Shape::Unsigned | Shape::LenientUnsigned, serde_json::Value::Number(n)) if n.is_u64() => {}
+        (Shape::LenientUnsigned, serde_json::Value::String(_))
+            if !ValidationConfig::current().strict_numbers => {}
+        (Shape::Unsigned | Shape::LenientUnsigned, _) => *value = serde_json::Value::from(0),
+        (Shape::Object(fields), serde_json::Value::Object(map)) => {
+            map.retain(|key, _| fields.iter().any(|(name, _, _)| name == key));
+            for (name, field_shape, presence) in fields.iter() {
+                match map.get_mut(*name) {
+                    None | Some(serde_json::Value::Null) if *presence == Presence::Optional => {}
+                    Some(field_value) => fit_shape(field_value, field_shape),
+                    None => {
+                        let mut field_value = serde_json::Value::Null;
+                        fit_shape(&mut field_value, field_shape);
+                        map.insert(name.to_string(), field_value);
+                    }
+                }
+            }
+        }
+        (Shape::Object(_), _) => {
+            *value = serde_json::Value::Object(serde_json::Map::new());
+            fit_shape(value, shape);
+        }
+        (Shape::Array(item_shape, max), serde_json::Value::Array(items)) => {
+            items.truncate(max() + 1);
+            items
+                .iter_mut()
+                .for_each(|item| fit_shape(item, item_shape));
+        }
+        (Shape::Array(..), _) => *value = serde_json::Value::Array(Vec::new()),
+    }
+}
+
+/// The `FieldError` for a `value` that isn't one of the `allowed` values of an enum.
+pub(crate) fn invalid_enum_value(field: &str, value: &str, allowed: &[&str]) -> FieldError {
+    let mut params = serde_json::Map::new();
+    params.insert("allowed".to_string(), allowed.into());
+    FieldError {
+        field: field.to_string(),
+        severity: Severity::Error,
+        code: "invalid_enum_value".to_string(),
+        field_errors: vec![format!(
+            "invalid_enum_value: `{}` is not one of {}",
+            value,
+            allowed.join(", ")
+        )],
+        params,
+        rejected_value: rejected_value(field, &value.into()),
+        docs_url: None,
+    }
+}
+
+/// Walks `value` along `shape`, pushing a `FieldError` for every missing required field, every
+/// value of the wrong type and every field the shape doesn't know.
+pub(crate) fn check_shape(
+    value: &serde_json::Value,
+    shape: &Shape,
+    path: &str,
+    out: &mut Vec<FieldError>,
+) {
+    let mut mismatch = |expected: &str| {
+        let mut params = serde_json::Map::new();
+        params.insert("expected".to_string(), expected.into());
+        out.push(FieldError {
+            field: path.to_string(),
+            severity: Severity::Error,
+            code: "invalid_type".to_string(),
+            field_errors: vec![format!(
+                "invalid_type: expected {}, got {}",
+                expected,
+                json_type_name(value)
+            )],
+            params,
+            rejected_value: rejected_value(path, value),
+            docs_url: None,
+        })
+    };
+    match (shape, value) {
+        (Shape::String, serde_json::Value::String(_)) => {}
+        (Shape::String, _) => mismatch("string"),
+        (Shape::Enum(values), serde_json::Value::String(s)) => {
+            if !values.contains(&s.as_str()) {
+                out.push(invalid_enum_value(path, s, values));
+            }
+        }
+        (Shape::Enum(_), _) => mismatch("string"),
+        (Shape::Unsigned | Shape::LenientUnsigned, serde_json::Value::Number(n)) if n.is_u64() => {}
+        (Shape::LenientUnsigned, serde_json::Value::String(_))
+            if !ValidationConfig::current().strict_numbers => {}
+        (Shape::Unsigned | Shape::LenientUnsigned, _) => mismatch("unsigned integer"),
+        (Shape::Object(fields), serde_json::Value::Object(map)) => {
+            for (name, field_shape, presence) in fields.iter() {
+                let field_path = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                match map.get(*name) {
+                    None | Some(serde_json::Value::Null) if *presence == Presence::Optional => {}
+                    None => out.push(FieldError {
+                        field: field_path,
+                        severity: Severity::Error,
+                        code: "required".to_string(),
+                        field_errors: vec!["required: field is missing".to_string()],
+                        params: serde_json::Map::new(),
+                        rejected_value: serde_json::Value::Null,
+                        docs_url: None,
+                    }),
+                    Some(field_value) => check_shape(field_value, field_shape, &field_path, out),
+                }
+            }
+            for key in map.keys() {
+                if fields.iter().any(|(name, _, _)| name == key) {
+                    continue;
+                }
+                let expected: Vec<String> = fields
+                    .iter()
+                    .map(|(name, _, _)| format!("`{}`", name))
+                    .collect();
+                let field = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                out.push(FieldError {
+                    rejected_value: rejected_value(&field, &map[key]),
+                    field,
+                    severity: Severity::Error,
+                    code: "unknown_field".to_string(),
+                    field_errors: vec![format!(
+                        "unknown_field: unknown field `{}`, expected one of {}",
+                        key,
+                        expected.join(", ")
+                    )],
+                    params: serde_json::Map::new(),
+                    docs_url: None,
+                });
+            }
+        }
+        (Shape::Object(_), _) => mismatch("object"),
+        (Shape::Array(item_shape, max), serde_json::Value::Array(items)) => {
+            for (i, item) in items.iter().enumerate().take(max() + 1) {
+                check_shape(item, item_shape, &format!("{}[{}]", path, i), out);
+            }
+        }
+        (Shape::Array(..), _) => mismatch("array"),
+    }
+}
@@ -0,0 +1,316 @@
+//! The deserialize, validate and report pipeline of the JSON routes without any web framework,
+//! for services built on something other than warp. `filters` is a thin warp layer over it.
+
+// Everything here fails with an `Error` by value, which is large, but only ever on its way into
+// an `ApiError` or a `Rejection`, which boxes it.
+#![allow(clippy::result_large_err)]
+
+use crate::error::{Error, ErrorConfig, ErrorResponse, FieldError, Language, Severity};
+use crate::models::Normalize;
+use serde::de::{DeserializeOwned, Deserializer, IgnoredAny, Visitor};
+use validator::Validate;
+
+/// Why `parse_and_validate` turned a body down.
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// The body isn't a JSON document `T` deserializes from. `path` is where serde gave up, like
+    /// `pets[0].name`, and empty for errors about the body as a whole. `line` and `column` are
+    /// known for syntax errors and type mismatches. `code` is a stable code like
+    /// `json_path_error`, `empty_body` or `unknown_field`.
+    Parse {
+        code: String,
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    /// The body deserialized, but broke the rules of `T`, one `FieldError` per field in English.
+    Validation(Vec<FieldError>),
+}
+
+impl ApiError {
+    /// `400` for both, like the warp routes with the default `ErrorConfig`.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ApiError::Parse { .. } => 400,
+            ApiError::Validation(_) => ErrorConfig::default().validation_status.as_u16(),
+        }
+    }
+
+    /// The `ErrorResponse` of the warp routes, without the parts that need a request like its
+    /// `request_id`. Parse errors are listed as a `FieldError` at their `path`, with `line` and
+    /// `column` in its `params`.
+    pub fn to_error_response(&self) -> ErrorResponse {
+        match self {
+            ApiError::Parse {
+                code,
+                path,
+                line,
+                column,
+                message,
+            } => {
+                let mut params = serde_json::Map::new();
+                if let Some(line) = line {
+                    params.insert("line".to_string(), (*line).into());
+                }
+                if let Some(column) = column {
+                    params.insert("column".to_string(), (*column).into());
+                }
+                let error = FieldError {
+                    field: path.clone(),
+                    severity: Severity::Error,
+                    code: code.clone(),
+                    field_errors: vec![message.clone()],
+                    params,
+                    rejected_value: serde_json::Value::Null,
+                    docs_url: None,
+                };
+                ErrorResponse::without_request(message.clone(), vec![error])
+            }
+            ApiError::Validation(errors) => {
+                ErrorResponse::without_request("field errors".to_string(), errors.clone())
+            }
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::ValidationError(_) | Error::ExplainedValidationError(_) => {
+                ApiError::Validation(e.field_errors(Language::En).unwrap_or_default())
+            }
+            Error::JSONPathError(ref detail) => ApiError::Parse {
+                code: e.code().to_string(),
+                path: detail.path.clone(),
+                line: detail.line,
+                column: detail.column,
+                message: e.message(),
+            },
+            // serde stops at the first unknown field or bad value, so there's only ever one
+            Error::DeserializationErrors(mut errors) if !errors.is_empty() => {
+                let error = errors.swap_remove(0);
+                ApiError::Parse {
+                    code: error.code,
+                    path: error.field,
+                    line: None,
+                    column: None,
+                    message: error.field_errors.join(", "),
+                }
+            }
+            e => ApiError::Parse {
+                code: e.code().to_string(),
+                path: String::new(),
+                line: None,
+                column: None,
+                message: e.message(),
+            },
+        }
+    }
+}
+
+/// Deserializes `body` with `parse_json`, normalizes it and validates it, the pipeline of
+/// `filters::with_validated_json`, without a request around it.
+///
+/// `T` needs a `Normalize` impl, which may be empty to leave the body as it was sent.
+pub fn parse_and_validate<T>(body: &[u8]) -> Result<T, ApiError>
+where
+    T: DeserializeOwned + Validate + Normalize,
+{
+    parse_and_validate_body(body).map_err(ApiError::from)
+}
+
+/// `parse_and_validate` with the `Error` the warp filters reject with, which carries more than
+/// an `ApiError`, like the path segments of a JSON error and the validator's own error codes.
+pub(crate) fn parse_and_validate_body<T>(body: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned + Validate + Normalize,
+{
+    let mut body: T = parse_json(body)?;
+    body.normalize();
+    body.validate()?;
+    Ok(body)
+}
+
+/// How deeply arrays and objects may nest in a JSON body. A create request nests 3 levels.
+pub const MAX_JSON_DEPTH: usize = 128;
+
+/// Deserializes a JSON body into a `T`.
+///
+/// Bodies nesting deeper than `MAX_JSON_DEPTH` are rejected with `Error::JsonTooDeep` before
+/// serde recurses into them, and anything but whitespace after the document with
+/// `Error::TrailingContent`. Empty bodies and documents of the wrong type altogether, like an
+/// array where an object belongs, get `Error::EmptyBody` and `Error::InvalidBodyType` instead
+/// of serde's messages, and bodies that aren't UTF-8 the errors of `check_encoding`.
+pub fn parse_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    let bytes = check_encoding(bytes)?;
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::EmptyBody);
+    }
+    check_json_depth(bytes, MAX_JSON_DEPTH)?;
+    check_body_type::<T>(document_type(bytes))?;
+    let mut des = serde_json::Deserializer::from_slice(bytes);
+    let body = serde_path_to_error::deserialize(&mut des)?;
+    if des.end().is_err() {
+        return Err(Error::TrailingContent {
+            offset: trailing_content_offset(bytes),
+        });
+    }
+    Ok(body)
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// `bytes` without a leading UTF-8 byte order mark, which some Windows clients send and serde
+/// would report as an unexpected character at column 1.
+///
+/// Bodies that aren't UTF-8 are rejected before serde gets to them: UTF-16 and UTF-32, told by
+/// their byte order mark or by the zero bytes around the first ASCII character JSON starts with,
+/// with `Error::UnsupportedCharset`, anything else with `Error::InvalidUtf8` at the first invalid
+/// byte, counted like the body was sent.
+pub(crate) fn check_encoding(bytes: &[u8]) -> Result<&[u8], Error> {
+    if let Some(encoding) = wide_encoding(bytes) {
+        return Err(Error::UnsupportedCharset { encoding });
+    }
+    let (offset, bytes) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(rest) => {
+            tracing::debug!("stripped the UTF-8 byte order mark of a JSON body");
+            (UTF8_BOM.len(), rest)
+        }
+        None => (0, bytes),
+    };
+    std::str::from_utf8(bytes).map_err(|e| Error::InvalidUtf8 {
+        offset: offset + e.valid_up_to(),
+    })?;
+    Ok(bytes)
+}
+
+/// The UTF-16 or UTF-32 encoding `bytes` look like they're in, if any.
+fn wide_encoding(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0, 0, 0xFE, 0xFF, ..] => Some("UTF-32BE"),
+        [0xFF, 0xFE, 0, 0, ..] => Some("UTF-32LE"),
+        [0xFE, 0xFF, ..] => Some("UTF-16BE"),
+        [0xFF, 0xFE, ..] => Some("UTF-16LE"),
+        [0, 0, 0, _, ..] => Some("UTF-32BE"),
+        [_, 0, 0, 0, ..] => Some("UTF-32LE"),
+        [0, _, ..] => Some("UTF-16BE"),
+        [_, 0, ..] => Some("UTF-16LE"),
+        _ => None,
+    }
+}
+
+/// Rejects a document whose top-level JSON type isn't the one `T` deserializes from with
+/// `Error::InvalidBodyType`. Checked up front, because derived structs also accept an array of
+/// their fields in declaration order and would report something about its elements instead.
+pub(crate) fn check_body_type<T: DeserializeOwned>(received: &'static str) -> Result<(), Error> {
+    match expected_json_type::<T>() {
+        Some(expected) if expected != received => {
+            Err(Error::InvalidBodyType { expected, received })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The JSON type of the document at the start of `bytes`, going by its first character. Not
+/// parsing it is fine as anything else is a syntax error serde reports once it gets there.
+fn document_type(bytes: &[u8]) -> &'static str {
+    match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        Some(b'{') => "object",
+        Some(b'[') => "array",
+        Some(b'"') => "string",
+        Some(b't') | Some(b'f') => "boolean",
+        Some(b'n') => "null",
+        _ => "number",
+    }
+}
+
+/// `object` for types that deserialize from a map, like derived structs, `array` for sequences,
+/// and `None` for everything else, including `Option`s and `serde_json::Value`, which take any
+/// type. Found by letting `T` ask a deserializer that has no data for what it wants.
+fn expected_json_type<T: DeserializeOwned>() -> Option<&'static str> {
+    let probe = TypeProbe::default();
+    let _ = T::deserialize(&probe);
+    probe.expected.get()
+}
+
+#[derive(Default)]
+struct TypeProbe {
+    expected: std::cell::Cell<Option<&'static str>>,
+}
+
+impl TypeProbe {
+    fn found(&self, expected: Option<&'static str>) -> serde::de::value::Error {
+        self.expected.set(expected);
+        serde::de::Error::custom("type probe")
+    }
+}
+
+impl<'de> Deserializer<'de> for &TypeProbe {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(self.found(None))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(self.found(Some("object")))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(self.found(Some("object")))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(self.found(Some("array")))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, _: V) -> Result<V::Value, Self::Error> {
+        Err(self.found(Some("array")))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct tuple_struct enum identifier ignored_any
+    }
+}
+
+/// The byte offset of the first non-whitespace after the JSON document at the start of `bytes`.
+fn trailing_content_offset(bytes: &[u8]) -> usize {
+    let mut documents = serde_json::Deserializer::from_slice(bytes).into_iter::<IgnoredAny>();
+    let _ = documents.next();
+    let end = documents.byte_offset();
+    bytes[end..]
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .map_or(bytes.len(), |position| end + position)
+}
+
+/// Counts the open arrays and objects outside of strings, without parsing anything else.
+pub(crate) fn check_json_depth(bytes: &[u8], max: usize) -> Result<(), Error> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in bytes {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => in_string = !in_string,
+            _ if in_string => {}
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max {
+                    return Err(Error::JsonTooDeep { max });
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1050 @@
+//! The routes of the service and the OpenAPI document describing them.
+
+use crate::error::{
+    error_formatter_from_env, with_detail_level, with_language, with_request_handling, ErrorConfig,
+    ErrorFormatter, PROBLEM_JSON,
+};
+use crate::filters::*;
+use crate::handlers::*;
+use crate::health::{self, Readiness};
+use crate::metrics::Metrics;
+use crate::models::{create_request_rules, create_request_schema};
+use crate::store::CreateStore;
+use crate::validation::ValidationContext;
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::http::{Method, StatusCode};
+use warp::{Filter, Reply};
+
+/// All routes with their rejections recovered and CORS applied, configured from the environment.
+pub fn routes() -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone {
+    routes_with_state(
+        Arc::new(CreateStore::default()),
+        Arc::new(ValidationContext::from_env()),
+        Arc::new(Readiness::new(health::STARTUP_COMPONENTS)),
+        error_formatter_from_env(),
+    )
+}
+
+/// Like `routes`, but keeping creates in `store`, which may already hold some, and validating
+/// against `validation`, whose deny-list the caller can swap while the server runs.
+///
+/// `GET /health/ready` answers from `readiness`. The routes mark `health::CONFIG` ready once
+/// they are built and `health::EMAIL_DOMAIN_DENYLIST` after the deny-list file was first read,
+/// which is done in a task spawned on the current runtime. Callers can add components of their
+/// own and mark them ready when they are.
+///
+/// Error responses are shaped by `formatter`, `DefaultErrorFormatter` for the usual
+/// `ErrorResponse`.
+///
+/// Panics if the API keys can't be loaded, which the server binary checks with
+/// `ApiKeys::from_env` before it binds.
+pub fn routes_with_state(
+    store: Arc<CreateStore>,
+    validation: Arc<ValidationContext>,
+    readiness: Arc<Readiness>,
+    formatter: Arc<dyn ErrorFormatter>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone {
+    let body_limits = BodyLimits::from_env();
+    let metrics = Arc::new(Metrics::default());
+    let query_policy = UnknownQueryPolicy::from_env();
+    let pet_import_max_rows = pet_import_max_rows_from_env();
+    let photo_max_bytes = photo_max_bytes_from_env();
+    let api_keys = Arc::new(ApiKeys::from_env().unwrap_or_else(|e| panic!("{}", e)));
+    let batch_max_items = batch_max_items_from_env();
+    let stream_limits = StreamLimits::from_env();
+    let ws_max_message_bytes = ws_max_message_bytes_from_env();
+    let error_config = ErrorConfig::from_env();
+    let min_client_version = min_client_version_from_env();
+    let idempotency = Arc::new(IdempotencyStore::from_env());
+
+    if validation.has_denylist_file() {
+        let (validation, readiness) = (validation.clone(), readiness.clone());
+        tokio::spawn(async move {
+            validation.refresh().await;
+            readiness.mark_ready(health::EMAIL_DOMAIN_DENYLIST);
+        });
+    } else {
+        readiness.mark_ready(health::EMAIL_DOMAIN_DENYLIST);
+    }
+
+    let basic = api_route(Method::POST, "/create-basic")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-basic",
+            body_limits.for_route("create-basic"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::body::json())
+        .and_then(validate_body)
+        .and_then(create_handler);
+
+    let basic_path = api_route(Method::POST, "/create-path")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-path",
+            body_limits.for_route("create-path"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_json_body())
+        .and_then(validate_body)
+        .and_then(create_handler_path);
+
+    let basic_path_validator = api_route(Method::POST, "/create-validator")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(NEGOTIATED))
+        .and(with_body_limit(
+            "create-validator",
+            body_limits.for_route("create-validator"),
+        ))
+        .and(with_query_policy(
+            query_policy,
+            &["explain", "lenient", "dry_run"],
+        ))
+        .and(with_dry_run())
+        .and(with_partial_create_body(validation.clone()))
+        .and(with_response_format())
+        .and(with_idempotency_key(idempotency))
+        .and(with_store(store.clone()))
+        .and_then(create_handler_validator);
+
+    // The filters of create-validator up to the handler, so invalid requests get the same errors.
+    let echo = api_route(Method::POST, "/echo")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(NEGOTIATED))
+        .and(with_body_limit("echo", body_limits.for_route("echo")))
+        .and(with_query_policy(query_policy, &["explain", "lenient"]))
+        .and(with_versioned_create_body(validation.clone()))
+        .and_then(echo_handler);
+
+    let list_creates = api_route(Method::GET, "/creates")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_query_policy(query_policy, &["limit", "offset"]))
+        .and(with_validated_query())
+        .and(with_if_none_match())
+        .and(with_store(store.clone()))
+        .and_then(list_creates_handler);
+
+    let get_create = api_route_with_param(Method::GET, "/creates/{id}")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_if_none_match())
+        .and(with_store(store.clone()))
+        .and_then(get_create_handler);
+
+    let update = api_route(Method::PATCH, "/update")
+        .and(with_content_type(JSON))
+        .and(with_body_limit("update", body_limits.for_route("update")))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(update_handler);
+
+    let draft = api_route(Method::POST, "/create-draft")
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-draft",
+            body_limits.for_route("create-draft"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(create_draft_handler);
+
+    let create_schema = api_route(Method::GET, "/schema/create")
+        .map(|| warp::reply::json(&create_request_schema()));
+
+    let create_rules =
+        api_route(Method::GET, "/rules/create").map(|| warp::reply::json(&create_request_rules()));
+
+    let openapi = api_route(Method::GET, "/openapi.json")
+        .map(move || warp::reply::json(&openapi_document(error_config)));
+
+    let metrics_route = {
+        let metrics = metrics.clone();
+        api_route(Method::GET, "/metrics").map(move || metrics.render())
+    };
+
+    let list_pets = api_route(Method::GET, "/pets")
+        .and(with_query_policy(
+            query_policy,
+            &["limit", "offset", "nameContains", "name_contains"],
+        ))
+        .and(with_validated_query())
+        .and_then(list_pets_handler);
+
+    let get_pet = api_route_with_param(Method::GET, "/pets/{id}")
+        .and(with_query_policy(query_policy, &[]))
+        .and_then(get_pet_handler);
+
+    // What the benchmarks in `benches/routes.rs` found to be the fastest validated route: the
+    // collected bytes go straight to `from_slice`, without the duplicate key scan, the content
+    // negotiation and the async checks of create-validator.
+    let fast = api_route(Method::POST, "/create-fast")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-fast",
+            body_limits.for_route("create-fast"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(create_handler);
+
+    let collect = api_route(Method::POST, "/create-collect")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-collect",
+            body_limits.for_route("create-collect"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_collected_json())
+        .and_then(create_handler_collect);
+
+    let import_pets = api_route(Method::POST, "/pets/import")
+        .and(with_content_type(CSV))
+        .and(with_body_limit(
+            "pets-import",
+            body_limits.for_route("pets-import"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::any().map(move || pet_import_max_rows))
+        .and(with_language())
+        .and(warp::body::aggregate())
+        .and_then(import_pets_handler);
+
+    let booking = api_route(Method::POST, "/create-booking")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-booking",
+            body_limits.for_route("create-booking"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(create_booking_handler);
+
+    let payment = api_route(Method::POST, "/create-payment")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-payment",
+            body_limits.for_route("create-payment"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(create_payment_handler);
+
+    let envelope = api_route(Method::POST, "/create-envelope")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-envelope",
+            body_limits.for_route("create-envelope"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_decoded_body())
+        .and_then(create_envelope_handler);
+
+    let batch = api_route(Method::POST, "/create-batch")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-batch",
+            body_limits.for_route("create-batch"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::any().map(move || batch_max_items))
+        .and(with_language())
+        .and(with_json_body())
+        .and_then(create_batch_handler);
+
+    let stream = api_route(Method::POST, "/create-stream")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(NDJSON))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::any().map(move || stream_limits))
+        .and(with_language())
+        .and(warp::body::stream())
+        .and_then(create_stream_handler);
+
+    let multipart = api_route(Method::POST, "/create-multipart")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(MULTIPART))
+        .and(with_body_limit(
+            "create-multipart",
+            body_limits.for_route("create-multipart"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::any().map({
+            let validation = validation.clone();
+            move || validation.clone()
+        }))
+        .and(warp::any().map(move || photo_max_bytes))
+        .and(warp::multipart::form().max_length(body_limits.for_route("create-multipart")))
+        .and_then(create_multipart_handler);
+
+    let validate_field = api_route(Method::POST, "/validate-field")
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "validate-field",
+            body_limits.for_route("validate-field"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(warp::any().map({
+            let validation = validation.clone();
+            move || validation.clone()
+        }))
+        .and(with_json_body())
+        .and_then(validate_field_handler);
+
+    let ws_create = api_route(Method::GET, "/ws/create")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(warp::ws())
+        .and(warp::any().map(move || ws_max_message_bytes))
+        .and(with_language())
+        .and(with_detail_level(error_config.detail_level))
+        .and(with_store(store))
+        .and(warp::any().map(move || validation.clone()))
+        .and(warp::any().map(move || error_config))
+        .map(create_ws_handler);
+
+    let schedule = api_route(Method::POST, "/create-schedule")
+        .and(with_api_key(api_keys.clone()))
+        .and(with_client_version(min_client_version.clone()))
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "create-schedule",
+            body_limits.for_route("create-schedule"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(create_schedule_handler);
+
+    let against_schema = api_route(Method::POST, "/validate-against-schema")
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "validate-against-schema",
+            body_limits.for_route("validate-against-schema"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_json_body())
+        .and_then(validate_against_schema_handler);
+
+    let canonical = api_route(Method::POST, "/canonical")
+        .and(with_content_type(JSON))
+        .and(with_body_limit(
+            "canonical",
+            body_limits.for_route("canonical"),
+        ))
+        .and(with_query_policy(query_policy, &[]))
+        .and(with_validated_json())
+        .and_then(canonical_handler);
+
+    // Boxed in two halves so the future of the whole chain stays within the compiler's layout
+    // depth limit, which release builds hit first.
+    let resource_routes = basic
+        .or(basic_path)
+        .or(basic_path_validator)
+        .or(list_creates)
+        .or(get_create)
+        .or(fast)
+        .or(collect)
+        .or(update)
+        .or(draft)
+        .or(create_schema)
+        .or(create_rules)
+        .boxed();
+    let create_routes = resource_routes
+        .or(openapi)
+        .or(metrics_route)
+        .or(list_pets)
+        .or(get_pet)
+        .or(import_pets)
+        .or(booking)
+        .or(payment)
+        .or(envelope)
+        .or(batch)
+        .or(stream)
+        .or(ws_create)
+        .or(schedule)
+        .or(against_schema)
+        .or(canonical)
+        .or(echo)
+        .or(validate_field)
+        .or(multipart)
+        .boxed();
+
+    let rate_limiter = Arc::new(RateLimiter::from_env());
+    let cors = Arc::new(CorsConfig::from_env());
+    let user_agent_denylist = user_agent_denylist_from_env();
+
+    readiness.mark_ready(health::CONFIG);
+
+    // Outside the user agent check and the rate limit, so probes are never turned away.
+    let health_routes = api_route(Method::GET, "/health/live")
+        .map(liveness_handler)
+        .or(api_route(Method::GET, "/health/ready")
+            .and(warp::any().map(move || readiness.clone()))
+            .map(readiness_handler));
+
+    with_cors(
+        cors.clone(),
+        with_request_handling(
+            metrics,
+            error_config,
+            formatter,
+            with_origin_check(cors).and(
+                health_routes
+                    .map(Reply::into_response)
+                    .or(with_user_agent_check(user_agent_denylist)
+                        .and(with_rate_limit(rate_limiter, api_keys))
+                        .and(create_routes)
+                        .map(Reply::into_response))
+                    .unify(),
+            ),
+        ),
+    )
+}
+
+/// A route as documented in the OpenAPI spec at `GET /openapi.json`.
+pub struct ApiRoute {
+    method: Method,
+    path: &'static str,
+    summary: &'static str,
+    /// Whether the route is behind `with_api_key`.
+    api_key: bool,
+    /// Name of the component schema of the JSON body, if the route takes one, with a `[]` suffix
+    /// for an array of them.
+    request_schema: Option<&'static str>,
+    success_status: u16,
+    /// Content types of the success response, the first being the default. Requests whose
+    /// `Accept` header rules out all of them are turned away with a `406`.
+    response_types: &'static [&'static str],
+    /// Name of the component schema of the success response, if it has one.
+    response_schema: Option<&'static str>,
+}
+
+/// Every route the server answers. Routes are built from this list by `api_route`, so a route
+/// can't be added without being documented.
+pub(crate) const API_ROUTES: &[ApiRoute] = &[
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-basic",
+        summary: "Create, deserializing with warp::body::json",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-path",
+        summary: "Create, reporting the path of deserialization errors",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-validator",
+        summary: "Create with full validation, JSON, form encoded, YAML or MessagePack",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 201,
+        response_types: &["application/json", "application/msgpack"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-fast",
+        summary: "Create, validating a JSON body deserialized straight from its bytes",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-collect",
+        summary: "Create, reporting all shape errors at once",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::PATCH,
+        path: "/update",
+        summary: "Validate a partial CreateRequest",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-draft",
+        summary: "Validate an incomplete CreateRequest, whose fields may be left out or null",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/schema/create",
+        summary: "JSON Schema of CreateRequest",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/rules/create",
+        summary: "Validation rules of CreateRequest per field, for clients that mirror them",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/openapi.json",
+        summary: "This document",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/health/live",
+        summary: "Liveness probe, answered as soon as the server runs",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: Some("HealthStatus"),
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/health/ready",
+        summary: "Readiness probe, a 503 listing the unready components until startup completes",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: Some("HealthStatus"),
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/metrics",
+        summary: "Request and failure counters in Prometheus text format",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/creates",
+        summary: "List the stored creates, oldest first, paginated",
+        api_key: true,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/creates/{id}",
+        summary: "Get a stored create by its UUID",
+        api_key: true,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/pets",
+        summary: "List pets, paginated and filtered by name",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/pets/{id}",
+        summary: "Get a pet by its positive integer id",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/pets/import",
+        summary: "Validate a CSV of pets row by row",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: Some("ImportSummary"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-booking",
+        summary: "Create a booking",
+        api_key: true,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-payment",
+        summary: "Create a payment",
+        api_key: true,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-envelope",
+        summary: "Create from a versioned envelope",
+        api_key: true,
+        request_schema: None,
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: Some("CreateResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-batch",
+        summary: "Create several at once, validating each on its own",
+        api_key: true,
+        request_schema: Some("CreateRequest[]"),
+        success_status: 207,
+        response_types: &["application/json"],
+        response_schema: Some("BatchResponse"),
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-stream",
+        summary: "Create from newline-delimited JSON, answering with one record per line",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 200,
+        response_types: &["application/x-ndjson"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::GET,
+        path: "/ws/create",
+        summary: "Create over a WebSocket, answering every text message with its outcome",
+        api_key: true,
+        request_schema: None,
+        success_status: 101,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-schedule",
+        summary: "Create a schedule",
+        api_key: true,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/validate-against-schema",
+        summary: "Validate data against a JSON Schema from the request",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["text/plain"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/canonical",
+        summary: "Canonical JSON form of a CreateRequest and its SHA-256",
+        api_key: false,
+        request_schema: Some("CreateRequest"),
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/create-multipart",
+        summary: "Create from a multipart body with a JSON payload part and an optional photo",
+        api_key: true,
+        request_schema: None,
+        success_status: 201,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/validate-field",
+        summary: "Validate one field of a CreateRequest",
+        api_key: false,
+        request_schema: None,
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+    ApiRoute {
+        method: Method::POST,
+        path: "/echo",
+        summary: "The validated and normalized create request as canonical JSON",
+        api_key: true,
+        request_schema: Some("CreateRequest"),
+        success_status: 200,
+        response_types: &["application/json"],
+        response_schema: None,
+    },
+];
+
+/// Matches the method and path of the documented route, panicking at startup for routes missing
+/// from `API_ROUTES`.
+pub(crate) fn api_route(method: Method, path: &'static str) -> BoxedFilter<()> {
+    let route = documented_route(&method, path);
+    path_segments(route.path)
+        .and(warp::path::end())
+        .and(method_filter(&route.method))
+        .and(with_acceptable(route.response_types))
+        .boxed()
+}
+
+/// Like `api_route`, for a path ending in a `{param}` segment. The segment is extracted as it
+/// was sent, so the handler can tell the client why a malformed value is rejected instead of the
+/// route just not matching.
+pub(crate) fn api_route_with_param(method: Method, path: &'static str) -> BoxedFilter<(String,)> {
+    let route = documented_route(&method, path);
+    let prefix = match route.path.rsplit_once('/') {
+        Some((prefix, param)) if param.starts_with('{') && param.ends_with('}') => prefix,
+        _ => panic!("{} doesn't end in a {{param}} segment", path),
+    };
+    path_segments(prefix)
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(method_filter(&route.method))
+        .and(with_acceptable(route.response_types))
+        .boxed()
+}
+
+/// The path of the documented route `path` was sent to, e.g. `/creates/{id}` for
+/// `/creates/6f2c…`. Literal segments win over `{param}` ones, so `/pets/import` is its own route.
+pub(crate) fn route_template(path: &str) -> Option<&'static str> {
+    let segments = |path: &'static str| path.trim_start_matches('/').split('/');
+    let sent: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let matches = |template: &'static str, params: bool| {
+        segments(template).count() == sent.len()
+            && segments(template).zip(&sent).all(|(segment, sent)| {
+                segment == *sent || (params && segment.starts_with('{') && !sent.is_empty())
+            })
+    };
+    [false, true].iter().find_map(|&params| {
+        API_ROUTES
+            .iter()
+            .map(|route| route.path)
+            .find(|&template| matches(template, params))
+    })
+}
+
+fn documented_route(method: &Method, path: &str) -> &'static ApiRoute {
+    API_ROUTES
+        .iter()
+        .find(|route| route.method == method && route.path == path)
+        .unwrap_or_else(|| panic!("{} {} is missing from API_ROUTES", method, path))
+}
+
+fn path_segments(path: &'static str) -> BoxedFilter<()> {
+    path.trim_start_matches('/')
+        .split('/')
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment)).boxed()
+        })
+}
+
+fn method_filter(method: &Method) -> BoxedFilter<()> {
+    match *method {
+        Method::GET => warp::get().boxed(),
+        Method::POST => warp::post().boxed(),
+        Method::PATCH => warp::patch().boxed(),
+        ref other => panic!("no filter for method {}", other),
+    }
+}
+
+/// The content of an error response, in the format the client picked with `Accept`.
+fn error_content() -> serde_json::Value {
+    serde_json::json!({
+        "application/json": {
+            "schema": { "$ref": "#/components/schemas/ErrorResponse" },
+        },
+        PROBLEM_JSON: {
+            "schema": { "$ref": "#/components/schemas/ProblemDetails" },
+        },
+    })
+}
+
+fn openapi_document(error_config: ErrorConfig) -> serde_json::Value {
+    let mut paths = serde_json::Map::new();
+    for route in API_ROUTES {
+        let response_schema = match route.response_schema {
+            Some(name) => serde_json::json!({ "$ref": format!("#/components/schemas/{}", name) }),
+            None if route.response_types[0] == "application/json" => serde_json::json!({}),
+            None => serde_json::json!({ "type": "string" }),
+        };
+        let mut operation = serde_json::json!({
+            "summary": route.summary,
+            "responses": {
+                route.success_status.to_string(): {
+                    "description": match route.success_status {
+                        201 => "Created",
+                        101 => "Switching Protocols",
+                        207 => "Multi-Status",
+                        _ => "OK",
+                    },
+                    "content": route
+                        .response_types
+                        .iter()
+                        .map(|&response_type| {
+                            (response_type.to_string(), serde_json::json!({ "schema": response_schema }))
+                        })
+                        .collect::<serde_json::Map<_, _>>(),
+                },
+                "400": {
+                    "description": "Malformed or invalid request",
+                    "content": error_content(),
+                },
+                "406": {
+                    "description": "The Accept header rules out every response type",
+                    "content": error_content(),
+                },
+            },
+        });
+        let path_params: Vec<serde_json::Value> = route
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        if !path_params.is_empty() {
+            operation["parameters"] = path_params.into();
+            operation["responses"]["404"] = serde_json::json!({
+                "description": "Not found",
+                "content": error_content(),
+            });
+        }
+        if error_config.validation_status != StatusCode::BAD_REQUEST {
+            operation["responses"]["400"]["description"] = "Malformed request".into();
+            operation["responses"][error_config.validation_status.as_str()] = serde_json::json!({
+                "description": "Invalid request",
+                "content": error_content(),
+            });
+        }
+        if route.api_key {
+            operation["security"] = serde_json::json!([{ "ApiKey": [] }]);
+            operation["responses"]["401"] = serde_json::json!({
+                "description": "Missing API key",
+                "content": error_content(),
+            });
+            operation["responses"]["403"] = serde_json::json!({
+                "description": "Unknown API key",
+                "content": error_content(),
+            });
+        }
+        if route.method != Method::GET {
+            let schema = match route.request_schema {
+                Some(name) => match name.strip_suffix("[]") {
+                    Some(name) => serde_json::json!({
+                        "type": "array",
+                        "items": { "$ref": format!("#/components/schemas/{}", name) },
+                    }),
+                    None => serde_json::json!({ "$ref": format!("#/components/schemas/{}", name) }),
+                },
+                None => serde_json::json!({ "type": "object" }),
+            };
+            operation["requestBody"] = serde_json::json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } },
+            });
+        }
+        let item = paths
+            .entry(route.path)
+            .or_insert_with(|| serde_json::json!({}));
+        item[route.method.as_str().to_ascii_lowercase()] = operation;
+    }
+
+    let mut create_request = create_request_schema();
+    if let Some(schema) = create_request.as_object_mut() {
+        schema.remove("$schema");
+    }
+    let error_response = serde_json::json!({
+        "type": "object",
+        "required": ["message", "errors", "request_id"],
+        "properties": {
+            "message": { "type": "string" },
+            "errors": {
+                "type": ["array", "null"],
+                "items": { "$ref": "#/components/schemas/FieldError" },
+            },
+            "validators": {
+                "type": "object",
+                "additionalProperties": { "type": "array", "items": { "type": "string" } },
+            },
+            "docs_url": { "type": "string" },
+            "failed_sections": { "type": "array", "items": { "type": "string" } },
+            "path": { "type": "string" },
+            "segments": {
+                "type": "array",
+                "items": { "type": ["string", "integer", "null"] },
+            },
+            "line": { "type": "integer" },
+            "column": { "type": "integer" },
+            "kind": { "type": "string", "enum": ["io", "syntax", "data", "eof"] },
+            "request_id": { "type": "string" },
+        },
+    });
+    // The same members, with `message` as `detail` next to the RFC 7807 ones.
+    let mut problem_details = error_response.clone();
+    problem_details["required"] =
+        serde_json::json!(["type", "title", "status", "detail", "errors", "request_id"]);
+    if let Some(properties) = problem_details["properties"].as_object_mut() {
+        properties.remove("message");
+        properties.insert(
+            "type".into(),
+            serde_json::json!({ "type": "string", "format": "uri-reference" }),
+        );
+        properties.insert("title".into(), serde_json::json!({ "type": "string" }));
+        properties.insert("status".into(), serde_json::json!({ "type": "integer" }));
+        properties.insert("detail".into(), serde_json::json!({ "type": "string" }));
+    }
+    serde_json::json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "example-rust-json-input-validation",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "ApiKey": { "type": "apiKey", "in": "header", "name": "X-Api-Key" },
+            },
+            "schemas": {
+                "CreateRequest": create_request,
+                "HealthStatus": {
+                    "type": "object",
+                    "required": ["status"],
+                    "properties": {
+                        "status": { "type": "string", "enum": ["ok", "unavailable"] },
+                        "unready": { "type": "array", "items": { "type": "string" } },
+                    },
+                },
+                "CreateResponse": {
+                    "type": "object",
+                    "required": ["id", "pets"],
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "email": { "type": "string", "format": "email" },
+                        "phone": { "type": "string" },
+                        "pets": { "type": "array", "items": create_request["properties"]["pets"]["items"] },
+                        "validators": {
+                            "description": "Only with ?explain=true",
+                            "type": "object",
+                            "additionalProperties": { "type": "array", "items": { "type": "string" } },
+                        },
+                    },
+                },
+                "BatchResponse": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["status", "index"],
+                        "properties": {
+                            "status": { "type": "string", "enum": ["created", "invalid"] },
+                            "index": { "type": "integer" },
+                            "id": { "type": "string", "format": "uuid" },
+                            "email": { "type": "string", "format": "email" },
+                            "pets": { "type": "array", "items": create_request["properties"]["pets"]["items"] },
+                            "errors": { "type": "array", "items": { "$ref": "#/components/schemas/FieldError" } },
+                        },
+                    },
+                },
+                "ImportSummary": {
+                    "type": "object",
+                    "required": ["valid", "errors"],
+                    "properties": {
+                        "valid": { "type": "integer" },
+                        "errors": { "type": "array", "items": { "$ref": "#/components/schemas/FieldError" } },
+                    },
+                },
+                "ErrorResponse": error_response,
+                "ProblemDetails": problem_details,
+                "FieldError": {
+                    "type": "object",
+                    "required": ["field", "severity", "code", "field_errors", "params", "rejected_value"],
+                    "properties": {
+                        "field": { "type": "string" },
+                        "severity": { "type": "string", "enum": ["error", "warning"] },
+                        "code": { "type": "string" },
+                        "field_errors": { "type": "array", "items": { "type": "string" } },
+                        "params": { "type": "object" },
+                        "rejected_value": {
+                            "description": "null for sensitive fields like passwords",
+                        },
+                        "docs_url": { "type": "string" },
+                    },
+                },
+            },
+        },
+    })
+}
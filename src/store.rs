@@ -0,0 +1,113 @@
+//! The creates accepted by `POST /create-validator`, kept in memory and read back through
+//! `GET /creates`.
+
+use crate::error::{reject, Error};
+use crate::models::{Address, CreateInput, Pet};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+/// A validated create request as it was stored.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCreate {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    pub address: Address,
+    pub pets: Vec<Pet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+}
+
+/// Every stored create by its id. Shared behind an `Arc` by the routes, so a server or test can
+/// hand `crate::routes_with_state` a store it seeded beforehand.
+#[derive(Default)]
+pub struct CreateStore {
+    creates: RwLock<HashMap<Uuid, StoredCreate>>,
+    /// Counts the changes to `creates`, for the tag of `GET /creates`.
+    version: AtomicU64,
+}
+
+impl CreateStore {
+    /// Stores `input` under a fresh id, returning the stored record. Fails with
+    /// `Error::Conflict` if a stored create already has its email, as `Normalize` left it; the
+    /// check and the insert happen under one write lock, so of two concurrent creates with the
+    /// same email only one gets through.
+    pub fn insert(&self, input: CreateInput) -> Result<StoredCreate> {
+        let stored = StoredCreate {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            email: input.email,
+            phone: input.phone,
+            address: input.address,
+            pets: input.pets,
+            bio: input.bio,
+        };
+        let mut creates = self.creates.write().expect("create store lock poisoned");
+        check_unique(&creates, stored.email.as_deref())?;
+        creates.insert(stored.id, stored.clone());
+        self.version.fetch_add(1, Ordering::Relaxed);
+        Ok(stored)
+    }
+
+    /// Fails like `insert` would if `input` were stored now, without storing it.
+    pub fn check_insert(&self, input: &CreateInput) -> Result<()> {
+        check_unique(
+            &self.creates.read().expect("create store lock poisoned"),
+            input.email.as_deref(),
+        )
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<StoredCreate> {
+        self.creates
+            .read()
+            .expect("create store lock poisoned")
+            .get(id)
+            .cloned()
+    }
+
+    /// How often the store has changed. Read it before `list`, then a list is never tagged with a
+    /// version newer than itself.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// One page of the stored creates, oldest first.
+    pub fn list(&self, offset: usize, limit: usize) -> Vec<StoredCreate> {
+        let mut creates: Vec<StoredCreate> = self
+            .creates
+            .read()
+            .expect("create store lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        creates.sort_by_key(|create| (create.created_at, create.id));
+        creates.into_iter().skip(offset).take(limit).collect()
+    }
+}
+
+/// `Error::Conflict` if one of `creates` already has `email`.
+fn check_unique(creates: &HashMap<Uuid, StoredCreate>, email: Option<&str>) -> Result<()> {
+    let email = match email {
+        Some(email) => email,
+        None => return Ok(()),
+    };
+    if creates
+        .values()
+        .any(|create| create.email.as_deref() == Some(email))
+    {
+        return Err(reject(Error::Conflict {
+            field: "email",
+            value: email.to_string(),
+        }));
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Validation that depends on shared state loaded at runtime, run asynchronously after the
+//! derived `Validate` checks have passed or failed.
+
+use crate::models::CreateInput;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use validator::{ValidationError, ValidationErrors};
+
+/// Checks that need a `ValidationContext`. Errors are reported like those of `Validate`, so the
+/// filters can merge both into one rejection.
+pub trait AsyncValidate {
+    fn validate_async(
+        &self,
+        ctx: &ValidationContext,
+    ) -> impl Future<Output = Result<(), ValidationErrors>> + Send;
+}
+
+const DEFAULT_DENYLIST_REFRESH_SECS: u64 = 60;
+
+/// The state async validation checks against: the email domains that are turned away.
+///
+/// The deny-list is read from the file named by `EMAIL_DOMAIN_DENYLIST_FILE`, one domain per
+/// line, and reread once it is older than `EMAIL_DOMAIN_DENYLIST_REFRESH_SECS` (60 by default).
+/// `set_blocked_email_domains` replaces it at runtime.
+#[derive(Default)]
+pub struct ValidationContext {
+    blocked_email_domains: RwLock<HashSet<String>>,
+    denylist_file: Option<DenyListFile>,
+}
+
+struct DenyListFile {
+    path: PathBuf,
+    refresh_every: Duration,
+    loaded_at: Mutex<Option<Instant>>,
+}
+
+impl ValidationContext {
+    pub fn from_env() -> Self {
+        let refresh_every = std::env::var("EMAIL_DOMAIN_DENYLIST_REFRESH_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_DENYLIST_REFRESH_SECS));
+        ValidationContext {
+            blocked_email_domains: RwLock::default(),
+            denylist_file: std::env::var_os("EMAIL_DOMAIN_DENYLIST_FILE").map(|path| {
+                DenyListFile {
+                    path: path.into(),
+                    refresh_every,
+                    loaded_at: Mutex::new(None),
+                }
+            }),
+        }
+    }
+
+    /// Replaces the deny-list, matched case-insensitively against the part after the `@`.
+    pub fn set_blocked_email_domains(&self, domains: impl IntoIterator<Item = String>) {
+        let domains = domains
+            .into_iter()
+            .map(|domain| domain.trim().to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        *self
+            .blocked_email_domains
+            .write()
+            .expect("deny-list lock poisoned") = domains;
+    }
+
+    pub fn is_email_domain_blocked(&self, domain: &str) -> bool {
+        self.blocked_email_domains
+            .read()
+            .expect("deny-list lock poisoned")
+            .contains(&domain.to_lowercase())
+    }
+
+    /// Whether the deny-list is read from `EMAIL_DOMAIN_DENYLIST_FILE`.
+    pub fn has_denylist_file(&self) -> bool {
+        self.denylist_file.is_some()
+    }
+
+    /// Rereads the deny-list file if it is due. A file that can't be read leaves the current
+    /// deny-list in place until the next refresh.
+    pub async fn refresh(&self) {
+        let file = match &self.denylist_file {
+            Some(file) => file,
+            None => return,
+        };
+        {
+            let mut loaded_at = file.loaded_at.lock().expect("deny-list lock poisoned");
+            if loaded_at.is_some_and(|loaded_at| loaded_at.elapsed() < file.refresh_every) {
+                return;
+            }
+            // Claimed before reading, so concurrent requests keep using the current list
+            // instead of all rereading the file.
+            *loaded_at = Some(Instant::now());
+        }
+        match tokio::fs::read_to_string(&file.path).await {
+            Ok(contents) => {
+                self.set_blocked_email_domains(contents.lines().map(str::to_string));
+            }
+            Err(e) => tracing::warn!(
+                "failed to read email domain deny-list {}: {}",
+                file.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+impl AsyncValidate for CreateInput {
+    async fn validate_async(&self, ctx: &ValidationContext) -> Result<(), ValidationErrors> {
+        ctx.refresh().await;
+        let mut errors = ValidationErrors::new();
+        if let Some(email) = &self.email {
+            let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+            if !domain.is_empty() && ctx.is_email_domain_blocked(domain) {
+                let mut error = ValidationError::new("email_domain_blocked");
+                error.add_param("domain".into(), &domain);
+                error.add_param("value".into(), &email);
+                errors.add("email", error);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! `Accept` negotiation: requests whose `Accept` header rules out everything a route responds
+//! with get a `406`, still as JSON, naming what the route can produce.
+
+mod common;
+
+use bytes::Bytes;
+use common::{field_codes, json_body, pairs, routes, send, valid_create};
+use serde_json::json;
+use warp::http::{Response, StatusCode};
+
+async fn create(accept: Option<&str>) -> Response<Bytes> {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .body(valid_create().to_string());
+    let request = match accept {
+        Some(accept) => request.header("accept", accept),
+        None => request,
+    };
+    send(&routes, request).await
+}
+
+async fn get(path: &str, accept: &str) -> Response<Bytes> {
+    let request = warp::test::request().path(path).header("accept", accept);
+    send(&routes(), request).await
+}
+
+#[tokio::test]
+async fn absent_header_is_answered_with_json() {
+    let response = create(None).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], "application/json");
+}
+
+#[tokio::test]
+async fn json_and_wildcards_are_answered_with_json() {
+    for accept in &[
+        "application/json",
+        "*/*",
+        "application/*",
+        "application/json;q=0.9, */*;q=0.1",
+        "text/html, application/json;q=0.2",
+    ] {
+        let response = create(Some(accept)).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", accept);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/json",
+            "{}",
+            accept
+        );
+    }
+}
+
+#[tokio::test]
+async fn xml_only_is_not_acceptable_with_a_json_body() {
+    let response = create(Some("application/xml")).await;
+    assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "not acceptable: Accept \"application/xml\" rules out application/json and \
+         application/msgpack, the types this route responds with"
+    );
+    assert_eq!(field_codes(&body), pairs(&[("Accept", "not_acceptable")]));
+    let error = &body["errors"][0];
+    assert_eq!(
+        error["params"]["supported"],
+        json!(["application/json", "application/msgpack"])
+    );
+    assert_eq!(error["rejected_value"], "application/xml");
+}
+
+#[tokio::test]
+async fn json_weighted_zero_is_not_acceptable() {
+    for accept in &[
+        "application/json;q=0",
+        "application/xml, */*;q=0",
+        "text/html, application/xml;q=0.9",
+    ] {
+        let response = create(Some(accept)).await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE, "{}", accept);
+    }
+}
+
+/// A header that can't be parsed is taken as `*/*`, rather than turning the request away.
+#[tokio::test]
+async fn malformed_header_is_taken_as_anything() {
+    for accept in &[
+        "garbage;;;",
+        "application/json;q=abc",
+        "application/json;q=2",
+    ] {
+        let response = create(Some(accept)).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", accept);
+        assert_eq!(response.headers()["content-type"], "application/json");
+    }
+}
+
+#[tokio::test]
+async fn every_route_checks_against_its_own_types() {
+    for path in &["/health/live", "/openapi.json", "/creates"] {
+        let response = get(path, "application/msgpack").await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE, "{}", path);
+        assert_eq!(
+            json_body(&response)["errors"][0]["params"]["supported"],
+            json!(["application/json"]),
+            "{}",
+            path
+        );
+        assert_eq!(get(path, "application/json").await.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn problem_json_only_is_acceptable_for_errors() {
+    let response = get("/creates", "application/problem+json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .header("accept", "application/problem+json")
+        .body(request.to_string());
+    let response = send(&routes(), request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+}
+
+#[tokio::test]
+async fn msgpack_is_picked_when_weighted_highest() {
+    let response = create(Some("application/json;q=0.5, application/msgpack")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], "application/msgpack");
+}
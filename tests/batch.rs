@@ -0,0 +1,65 @@
+//! `POST /create-batch`: every element is validated on its own and reported by its index.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, post_raw, routes, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn invalid_element_is_reported_between_created_ones() {
+    let routes = routes();
+    let mut invalid = valid_create();
+    invalid["address"]["street"] = "w".into();
+    let batch = json!([valid_create(), invalid, valid_create()]);
+    let response = post_json(&routes, "/create-batch", &batch).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = json_body(&response);
+    let results = body.as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    for index in &[0, 2] {
+        let created = &results[*index];
+        assert_eq!(created["status"], "created");
+        assert_eq!(created["index"], *index);
+        assert!(created["id"].is_string());
+        assert_eq!(created["email"], "chip@example.com");
+        assert!(created.get("errors").is_none());
+    }
+    let rejected = &results[1];
+    assert_eq!(rejected["status"], "invalid");
+    assert_eq!(rejected["index"], 1);
+    assert!(rejected.get("id").is_none());
+    assert_eq!(
+        field_codes(rejected),
+        pairs(&[("[1].address.street", "length_min")])
+    );
+    assert_eq!(
+        rejected["errors"][0]["field_errors"][0],
+        "street must be between 2 and 10 characters"
+    );
+}
+
+#[tokio::test]
+async fn batch_over_the_limit_is_rejected_whole() {
+    let routes = common::routes_with_env(&[("BATCH_MAX_ITEMS", "2")]);
+    let batch = json!([valid_create(), valid_create(), valid_create()]);
+    let response = post_json(&routes, "/create-batch", &batch).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "bad request: batch of 3 items exceeds the limit of 2"
+    );
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn unparseable_element_names_its_index() {
+    let routes = routes();
+    let response = post_raw(&routes, "/create-batch", "[{}, 1]").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["path"], "[0]");
+    assert_eq!(body["segments"], json!([0]));
+    assert_eq!(body["kind"], "data");
+}
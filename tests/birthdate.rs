@@ -0,0 +1,94 @@
+//! Birthdates of pets and owners: ISO 8601 dates, not in the future and, for pets, at most 50
+//! years ago.
+
+mod common;
+
+use chrono::NaiveDate;
+use common::{error_for, field_codes, json_body, post_json, routes, valid_create};
+use example_rust_json_input_validation::models::check_birthdate;
+use serde_json::Value;
+use warp::http::StatusCode;
+
+fn date(date: &str) -> NaiveDate {
+    date.parse().unwrap()
+}
+
+#[test]
+fn birthdate_is_checked_against_the_given_day() {
+    let today = date("2024-06-15");
+    assert!(check_birthdate(date("2024-06-15"), today, Some(50)).is_ok());
+    assert!(check_birthdate(date("1974-06-15"), today, Some(50)).is_ok());
+    assert_eq!(
+        check_birthdate(date("2024-06-16"), today, Some(50))
+            .unwrap_err()
+            .code,
+        "birthdate_in_future"
+    );
+    let too_old = check_birthdate(date("1974-06-14"), today, Some(50)).unwrap_err();
+    assert_eq!(too_old.code, "birthdate_too_old");
+    assert_eq!(too_old.params["max_age_years"], 50);
+    assert!(check_birthdate(date("1900-01-01"), today, None).is_ok());
+}
+
+fn with_pet_birthdate(birthdate: &str) -> Value {
+    let mut request = valid_create();
+    request["pets"][0]["birthdate"] = birthdate.into();
+    request
+}
+
+/// The single error of creating `request`, which has to be on `field`.
+async fn only_error(request: &Value, field: &str) -> Value {
+    let routes = routes();
+    let response = post_json(&routes, "/create-path", request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body).len(), 1, "{}", body);
+    error_for(&body, field).clone()
+}
+
+#[tokio::test]
+async fn valid_pet_birthdate_is_accepted() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-path", &with_pet_birthdate("2020-02-29")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["pets"][0]["birthdate"], "2020-02-29");
+}
+
+#[tokio::test]
+async fn future_pet_birthdate_is_rejected() {
+    let error = only_error(&with_pet_birthdate("2999-01-01"), "pets[0].birthdate").await;
+    assert_eq!(error["code"], "birthdate.future");
+    assert_eq!(
+        error["field_errors"][0],
+        "birthdate must not be in the future"
+    );
+}
+
+#[tokio::test]
+async fn ancient_pet_birthdate_is_rejected() {
+    let error = only_error(&with_pet_birthdate("1900-01-01"), "pets[0].birthdate").await;
+    assert_eq!(error["code"], "birthdate.too_old");
+    assert_eq!(error["params"]["max_age_years"], 50);
+}
+
+#[tokio::test]
+async fn garbage_pet_birthdate_is_an_invalid_date_on_its_path() {
+    for garbage in &["yesterday", "2023-13-45"] {
+        let error = only_error(&with_pet_birthdate(garbage), "pets[0].birthdate").await;
+        assert_eq!(error["code"], "invalid_date", "{}", garbage);
+        assert_eq!(error["rejected_value"], *garbage);
+    }
+}
+
+#[tokio::test]
+async fn owner_birthdate_may_be_old_but_not_in_the_future() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["birthdate"] = "1900-01-01".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    request["birthdate"] = "2999-01-01".into();
+    let error = only_error(&request, "birthdate").await;
+    assert_eq!(error["code"], "birthdate.future");
+}
@@ -0,0 +1,80 @@
+//! Bodies that are empty or not the JSON type a route reads get their own errors, before serde
+//! gets to report on them.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_raw, routes, valid_create};
+use warp::http::StatusCode;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator"];
+
+#[tokio::test]
+async fn empty_and_blank_bodies_are_empty() {
+    let routes = routes();
+    for body in &["", "  \r\n\t"] {
+        for path in ROUTES {
+            let response = post_raw(&routes, path, body).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+            let body = json_body(&response);
+            assert_eq!(body["message"], "request body is empty", "{}", path);
+            assert_eq!(body["docs_url"], "/docs/validation#empty-body", "{}", path);
+            assert!(body["errors"].is_null(), "{}", path);
+        }
+    }
+}
+
+#[tokio::test]
+async fn non_objects_are_invalid_body_types_naming_what_was_received() {
+    let routes = routes();
+    for (body, received) in &[
+        ("null", "null"),
+        (r#""hello""#, "string"),
+        ("[1, 2]", "array"),
+        ("42", "number"),
+        ("true", "boolean"),
+    ] {
+        for path in ROUTES {
+            let response = post_raw(&routes, path, body).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+            let body = json_body(&response);
+            assert_eq!(
+                body["message"],
+                format!("expected object, got {}", received),
+                "{}",
+                path
+            );
+            assert_eq!(field_codes(&body), pairs(&[("", "invalid_body_type")]));
+            assert_eq!(body["errors"][0]["params"]["expected"], "object");
+            assert_eq!(body["errors"][0]["params"]["received"], *received);
+        }
+    }
+}
+
+#[tokio::test]
+async fn batch_route_expects_an_array() {
+    let routes = routes();
+    let response = post_raw(&routes, "/create-batch", &valid_create().to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "expected array, got object");
+    assert_eq!(field_codes(&body), pairs(&[("", "invalid_body_type")]));
+
+    let response = post_raw(&routes, "/create-batch", "").await;
+    assert_eq!(json_body(&response)["message"], "request body is empty");
+}
+
+#[tokio::test]
+async fn invalid_object_still_gets_the_path_error() {
+    let routes = routes();
+    for path in ROUTES {
+        let response = post_raw(&routes, path, r#"{"email":"chip"}"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            body["message"],
+            "JSON path error: missing field `address` at line 1 column 16"
+        );
+        assert_eq!(body["kind"], "data");
+        assert!(body["errors"].is_null());
+    }
+}
@@ -0,0 +1,176 @@
+//! Lists over their limit are turned away while they're read: 100 000 pets get one
+//! `too_many_elements` error with the limit on every route taking a create request, and
+//! `BoundedVec` reads nothing past the element over it.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, routes_with_env, send, valid_create};
+use example_rust_json_input_validation::models::{BoundedVec, ElementLimit, Pet, PetsMax};
+use std::io::Read;
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+
+const PETS: usize = 100_000;
+
+/// Large enough for the 1.5 MB of `PETS` pets.
+const BODY_LIMITS: &[(&str, &str)] = &[
+    ("BODY_LIMIT_BYTES", "16777216"),
+    ("DECODED_BODY_LIMIT_BYTES", "16777216"),
+];
+
+/// `PETS` tiny pets as a JSON list.
+fn many_pets() -> String {
+    format!("[{}]", vec![r#"{"name":"Pet"}"#; PETS].join(","))
+}
+
+/// The body of `valid_create` with `pets` spliced in.
+fn create_with_pets(pets: &str) -> String {
+    let mut request = valid_create();
+    request["pets"] = serde_json::json!([]);
+    request
+        .to_string()
+        .replace(r#""pets":[]"#, &format!(r#""pets":{}"#, pets))
+}
+
+#[tokio::test]
+async fn hundred_thousand_pets_get_too_many_elements_with_the_limit_on_every_route() {
+    let routes = routes_with_env(BODY_LIMITS);
+    let pets = many_pets();
+    let create = create_with_pets(&pets);
+    let update = format!(r#"{{"pets":{}}}"#, pets);
+    let batch = format!("[{}]", create);
+    let requests = [
+        ("POST", "/create-path", &create, "pets"),
+        ("POST", "/create-validator", &create, "pets"),
+        ("POST", "/create-fast", &create, "pets"),
+        ("POST", "/create-collect", &create, "pets"),
+        ("POST", "/echo", &create, "pets"),
+        ("POST", "/create-draft", &create, "pets"),
+        ("PATCH", "/update", &update, "pets"),
+        ("POST", "/create-batch", &batch, "[0].pets"),
+    ];
+    for &(method, path, body, field) in requests.iter() {
+        let started = Instant::now();
+        let request = warp::test::request()
+            .method(method)
+            .path(path)
+            .header("content-type", "application/json")
+            .body(body.as_str());
+        let response = send(&routes, request).await;
+        // Generous for a debug build, parsing and validating the pets would take far longer
+        assert!(started.elapsed() < Duration::from_secs(10), "{}", path);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[(field, "too_many_elements")]),
+            "{}",
+            path
+        );
+        let error = error_for(&body, field);
+        assert_eq!(error["params"]["max"], 10, "{}", path);
+        assert_eq!(
+            error["field_errors"][0],
+            "too_many_elements: too many elements, expected at most `10`"
+        );
+    }
+}
+
+/// `/create-collect` checks the shape of the pets up to the one over the limit, not of the pets
+/// deserializing never gets to.
+#[tokio::test]
+async fn collected_create_checks_no_pets_past_the_limit() {
+    let routes = routes_with_env(BODY_LIMITS);
+    let mut pets = vec![r#"{"name":"Pet"}"#; PETS];
+    pets[PetsMax::max() + 1] = r#"{"name":1}"#;
+    let pets = format!("[{}]", pets.join(","));
+    let response = common::post_raw(&routes, "/create-collect", &create_with_pets(&pets)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets", "too_many_elements")]));
+}
+
+/// `warp::body::json` only hands on serde's message, create-basic has no field error for it.
+#[tokio::test]
+async fn hundred_thousand_pets_are_turned_away_by_create_basic_with_the_limit() {
+    let routes = routes_with_env(BODY_LIMITS);
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("content-type", "application/json")
+        .body(create_with_pets(&many_pets()));
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let message = json_body(&response)["message"].as_str().unwrap().to_owned();
+    assert!(
+        message.starts_with("too many elements, expected at most `10`"),
+        "{}",
+        message
+    );
+}
+
+/// A reader of `[1,1,1,...]` that never ends and counts the bytes read from it.
+struct EndlessList {
+    read: usize,
+}
+
+impl Read for EndlessList {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        for byte in buf.iter_mut() {
+            *byte = match self.read {
+                0 => b'[',
+                n if n % 2 == 1 => b'1',
+                _ => b',',
+            };
+            self.read += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[derive(Debug)]
+struct Three;
+
+impl ElementLimit for Three {
+    fn max() -> usize {
+        3
+    }
+}
+
+#[test]
+fn bounded_vec_stops_reading_after_the_element_over_its_limit() {
+    let mut list = EndlessList { read: 0 };
+    let error = serde_json::from_reader::<_, BoundedVec<u32, Three>>(
+        std::io::BufReader::with_capacity(1, &mut list),
+    )
+    .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("too many elements, expected at most `3`"),
+        "{}",
+        error
+    );
+    // `[1,1,1,1` and the comma after it
+    assert!(list.read <= 10, "read {} bytes", list.read);
+}
+
+#[test]
+fn bounded_vec_takes_lists_up_to_its_limit() {
+    let list: BoundedVec<u32, Three> = serde_json::from_str("[1, 2, 3]").unwrap();
+    assert_eq!(*list, [1, 2, 3]);
+    assert_eq!(serde_json::to_string(&list).unwrap(), "[1,2,3]");
+    let empty: BoundedVec<u32, Three> = serde_json::from_str("[]").unwrap();
+    assert!(empty.is_empty());
+    assert!(serde_json::from_str::<BoundedVec<u32, Three>>("[1, 2, 3, 4]").is_err());
+}
+
+#[test]
+fn pets_max_bounds_pets() {
+    assert_eq!(PetsMax::max(), 10);
+    let ten = format!("[{}]", [r#"{"name":"Pet"}"#; 10].join(","));
+    let pets: BoundedVec<Pet, PetsMax> = serde_json::from_str(&ten).unwrap();
+    assert_eq!(pets.len(), 10);
+    let eleven = format!("[{}]", [r#"{"name":"Pet"}"#; 11].join(","));
+    assert!(serde_json::from_str::<BoundedVec<Pet, PetsMax>>(&eleven).is_err());
+}
@@ -0,0 +1,155 @@
+//! The per-thread pool of body buffers: buffers are reused with their capacity, oversized ones
+//! aren't kept, an exhausted pool hands out fresh ones, and threads never see each other's.
+//!
+//! Each check runs on a thread of its own, so it starts with an empty pool.
+
+mod common;
+
+use common::{json_body, post_json, routes_with_env, valid_create};
+use example_rust_json_input_validation::buffer_pool::{PoolLimits, PooledBuffer};
+use std::sync::{Arc, Barrier};
+use warp::http::StatusCode;
+
+fn on_a_fresh_thread(check: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(check).join().unwrap();
+}
+
+#[test]
+fn limits_are_the_defaults() {
+    let limits = PoolLimits::get();
+    assert_eq!(limits.buffers, 16);
+    assert_eq!(limits.max_buffer_bytes, 256 * 1024);
+}
+
+#[test]
+fn returned_buffer_is_reused_empty_with_its_capacity() {
+    on_a_fresh_thread(|| {
+        let mut buffer = PooledBuffer::take();
+        assert_eq!(buffer.capacity(), 0);
+        buffer.extend_from_slice(&[7; 1000]);
+        let (pointer, capacity) = (buffer.as_ptr(), buffer.capacity());
+        drop(buffer);
+
+        let buffer = PooledBuffer::take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_ptr(), pointer);
+        assert_eq!(buffer.capacity(), capacity);
+    });
+}
+
+#[test]
+fn oversized_buffer_is_dropped_instead_of_kept() {
+    on_a_fresh_thread(|| {
+        let mut buffer = PooledBuffer::take();
+        buffer.resize(PoolLimits::get().max_buffer_bytes + 1, 0);
+        drop(buffer);
+        assert_eq!(PooledBuffer::take().capacity(), 0);
+
+        let mut buffer = PooledBuffer::take();
+        buffer.resize(PoolLimits::get().max_buffer_bytes, 0);
+        drop(buffer);
+        assert!(PooledBuffer::take().capacity() >= PoolLimits::get().max_buffer_bytes);
+    });
+}
+
+#[test]
+fn exhausted_pool_falls_back_to_fresh_buffers_and_keeps_at_most_its_size() {
+    on_a_fresh_thread(|| {
+        let size = PoolLimits::get().buffers;
+        let held: Vec<PooledBuffer> = (0..size + 4)
+            .map(|_| {
+                let mut buffer = PooledBuffer::take();
+                buffer.push(1);
+                buffer
+            })
+            .collect();
+        drop(held);
+
+        let taken: Vec<PooledBuffer> = (0..size + 4).map(|_| PooledBuffer::take()).collect();
+        let reused = taken.iter().filter(|buffer| buffer.capacity() > 0).count();
+        assert_eq!(reused, size);
+        assert!(taken.iter().all(|buffer| buffer.is_empty()));
+    });
+}
+
+#[test]
+fn buffer_goes_back_to_the_thread_that_drops_it() {
+    on_a_fresh_thread(|| {
+        let mut buffer = PooledBuffer::take();
+        buffer.extend_from_slice(b"moved");
+        let pointer = buffer.as_ptr() as usize;
+        std::thread::spawn(move || {
+            drop(buffer);
+            assert_eq!(PooledBuffer::take().as_ptr() as usize, pointer);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(PooledBuffer::take().capacity(), 0);
+    });
+}
+
+#[test]
+fn threads_filling_buffers_at_once_never_see_each_others_bytes() {
+    let threads = 8;
+    let barrier = Arc::new(Barrier::new(threads));
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                for round in 0..200 {
+                    let mut buffer = PooledBuffer::take();
+                    assert!(buffer.is_empty());
+                    barrier.wait();
+                    buffer.resize(64 + round, i as u8);
+                    barrier.wait();
+                    assert!(buffer.iter().all(|&byte| byte == i as u8));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+/// A pet name telling request `i` apart, names are letters only.
+fn pet_name(i: usize) -> String {
+    format!(
+        "Chip {}{}",
+        (b'a' + (i / 26) as u8) as char,
+        (b'a' + (i % 26) as u8) as char
+    )
+}
+
+/// Concurrent requests on a runtime with several workers each get an answer for their own body.
+#[tokio::test(threaded_scheduler)]
+async fn concurrent_requests_are_answered_like_without_a_pool() {
+    let routes = routes_with_env(&[("RATE_LIMIT_PER_MINUTE", "1000")]);
+    let requests = (0..64).map(|i| {
+        let routes = routes.clone();
+        tokio::spawn(async move {
+            let mut request = valid_create();
+            request["email"] = format!("chip{}@example.com", i).into();
+            request["pets"][0]["name"] = if i % 5 == 4 {
+                "C".to_owned()
+            } else {
+                pet_name(i)
+            }
+            .into();
+            let response = post_json(&routes, "/create-fast", &request).await;
+            (i, response)
+        })
+    });
+    for request in futures::future::join_all(requests).await {
+        let (i, response) = request.unwrap();
+        if i % 5 == 4 {
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", i);
+            assert_eq!(json_body(&response)["errors"][0]["field"], "pets[0].name");
+            continue;
+        }
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", i);
+        let body = json_body(&response);
+        assert_eq!(body["email"], format!("chip{}@example.com", i));
+        assert_eq!(body["pets"][0]["name"], pet_name(i));
+    }
+}
@@ -0,0 +1,51 @@
+//! The canonical JSON form of a create request, for clients signing or comparing bodies.
+
+mod common;
+
+use common::{json_body, post_raw, routes};
+use sha2::{Digest, Sha256};
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn key_order_and_whitespace_do_not_change_the_canonical_form() {
+    let routes = routes();
+    let compact = r#"{"email":"chip@example.com","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let reordered = r#"{
+        "pets": [ { "name": "Chip" } ],
+        "address": { "streetNo": 1, "street": "warpstreet" },
+        "email": "chip@example.com"
+    }"#;
+    let first = post_raw(&routes, "/canonical", compact).await;
+    let second = post_raw(&routes, "/canonical", reordered).await;
+    assert_eq!(first.status(), StatusCode::OK);
+    let (first, second) = (json_body(&first), json_body(&second));
+    assert_eq!(first["canonical"], second["canonical"]);
+    assert_eq!(first["sha256"], second["sha256"]);
+}
+
+#[tokio::test]
+async fn canonical_form_sorts_keys_and_spells_out_omitted_fields() {
+    let routes = routes();
+    let compact = r#"{"email":"chip@example.com","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let body = json_body(&post_raw(&routes, "/canonical", compact).await);
+    let canonical = concat!(
+        r#"{"address":{"phone":null,"street":"warpstreet","streetNo":1},"bio":null,"#,
+        r#""birthdate":null,"contactMethod":null,"email":"chip@example.com","password":null,"#,
+        r#""pets":[{"birthdate":null,"name":"Chip","photoUrl":null,"species":null}],"phone":null}"#,
+    );
+    assert_eq!(body["canonical"], canonical);
+    let digest: String = Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(body["sha256"], digest);
+}
+
+#[tokio::test]
+async fn invalid_request_has_no_canonical_form() {
+    let routes = routes();
+    let invalid = r#"{"email":"chip","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let response = post_raw(&routes, "/canonical", invalid).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(&response)["errors"][0]["field"], "email");
+}
@@ -0,0 +1,152 @@
+//! The `validate` command: the same deserialization, validation and error responses as
+//! `POST /create-validator`, reported on stdout with an exit code.
+
+use example_rust_json_input_validation::cli::{
+    validate_document, RequestType, EXIT_INVALID, EXIT_MALFORMED, EXIT_VALID,
+};
+use example_rust_json_input_validation::error::ErrorConfig;
+use example_rust_json_input_validation::validation::ValidationContext;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Validates the fixture `name` as `request_type`, returning the exit code and the output.
+async fn validate(request_type: RequestType, name: &str) -> (i32, Value) {
+    let bytes = std::fs::read(fixture(name)).unwrap();
+    let report = validate_document(
+        request_type,
+        &bytes,
+        &ValidationContext::from_env(),
+        ErrorConfig::default(),
+    )
+    .await;
+    (
+        report.exit_code,
+        serde_json::from_str(&report.output).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn valid_file_exits_0_with_the_input() {
+    let (exit_code, output) = validate(RequestType::Create, "valid_create.json").await;
+    assert_eq!(exit_code, EXIT_VALID);
+    assert_eq!(output["email"], "chip@example.com");
+    assert_eq!(output["address"]["streetNo"], 1);
+}
+
+#[tokio::test]
+async fn broken_file_exits_2_with_the_json_path_error() {
+    let (exit_code, output) = validate(RequestType::Create, "broken_create.json").await;
+    assert_eq!(exit_code, EXIT_MALFORMED);
+    assert_eq!(
+        output["message"],
+        "JSON path error: pets: EOF while parsing a list at line 2 column 0"
+    );
+    assert_eq!(output["kind"], "eof");
+    assert!(output["errors"].is_null());
+}
+
+#[tokio::test]
+async fn invalid_file_exits_1_with_the_field_errors() {
+    let (exit_code, output) = validate(RequestType::Create, "invalid_create.json").await;
+    assert_eq!(exit_code, EXIT_INVALID);
+    assert_eq!(output["message"], "field errors");
+    let fields: Vec<&str> = output["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["email", "pets[0].name"]);
+    assert_eq!(output["request_id"], "cli");
+}
+
+#[tokio::test]
+async fn type_selects_the_version_the_file_is_read_as() {
+    let (exit_code, _) = validate(RequestType::CreateV2, "valid_create_v2.json").await;
+    assert_eq!(exit_code, EXIT_VALID);
+
+    let (exit_code, output) = validate(RequestType::CreateV2, "valid_create.json").await;
+    assert_eq!(exit_code, EXIT_MALFORMED);
+    assert_eq!(output["errors"][0]["field"], "address.streetNo");
+    assert_eq!(output["errors"][0]["code"], "unknown_field");
+}
+
+/// Runs the binary's `validate` command with `args`, feeding it `stdin`.
+fn run(args: &[&str], stdin: &[u8]) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_example-rust-json-input-validation"))
+        .arg("validate")
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(child.stdin.as_mut().unwrap(), stdin).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn binary_exits_with_the_code_of_each_file() {
+    for (name, expected) in &[
+        ("valid_create.json", EXIT_VALID),
+        ("invalid_create.json", EXIT_INVALID),
+        ("broken_create.json", EXIT_MALFORMED),
+    ] {
+        let path = format!("tests/fixtures/{}", name);
+        let (exit_code, stdout, _) = run(&[&path], b"");
+        assert_eq!(exit_code, *expected, "{}: {}", name, stdout);
+        let report: Value = serde_json::from_str(&stdout).unwrap();
+        assert!(report.is_object(), "{}", name);
+    }
+}
+
+#[test]
+fn binary_reads_stdin_and_takes_a_type() {
+    let v2 = std::fs::read(fixture("valid_create_v2.json")).unwrap();
+    let (exit_code, stdout, _) = run(&["--type", "create-v2", "--stdin"], &v2);
+    assert_eq!(exit_code, EXIT_VALID, "{}", stdout);
+
+    let (exit_code, _, _) = run(&["--stdin"], &v2);
+    assert_eq!(exit_code, EXIT_MALFORMED);
+}
+
+#[test]
+fn usage_errors_exit_2_on_stderr() {
+    let (exit_code, stdout, stderr) = run(&[], b"");
+    assert_eq!(exit_code, EXIT_MALFORMED);
+    assert!(stdout.is_empty());
+    assert!(
+        stderr.contains("missing --stdin or a file to validate"),
+        "{}",
+        stderr
+    );
+
+    let (exit_code, _, stderr) = run(&["--type", "update", "--stdin"], b"");
+    assert_eq!(exit_code, EXIT_MALFORMED);
+    assert!(
+        stderr.contains("--type must be create or create-v2"),
+        "{}",
+        stderr
+    );
+
+    let (exit_code, _, stderr) = run(&["tests/fixtures/missing.json"], b"");
+    assert_eq!(exit_code, EXIT_MALFORMED);
+    assert!(
+        stderr.contains("can't read tests/fixtures/missing.json"),
+        "{}",
+        stderr
+    );
+}
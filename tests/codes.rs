@@ -0,0 +1,322 @@
+//! The stable code of every rule on a create request, its address and its pets, each triggered
+//! on its own, and checked against the rules `GET /rules/create` publishes.
+//! `pet_name.whitespace` is missing: names are trimmed before they're validated.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, send, valid_create};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// `count` distinct valid pets.
+fn pets(count: usize) -> Value {
+    (0..count)
+        .map(|i| json!({ "name": format!("Pet {}", (b'a' + i as u8) as char) }))
+        .collect()
+}
+
+/// Five letters, each under sixty combining marks that don't compose with it.
+fn heavy_name() -> String {
+    (0..5)
+        .map(|_| format!("a{}", "\u{316}".repeat(60)))
+        .collect()
+}
+
+type Case = (
+    &'static str,
+    fn(&mut Value),
+    &'static [(&'static str, &'static str)],
+);
+
+const CASES: &[Case] = &[
+    (
+        "all strings together too long",
+        |r| {
+            let url = format!("https://example.com/{}.png", "a".repeat(4000));
+            r["pets"] = (0..5)
+                .map(|i| json!({ "name": format!("Pet {}", (b'a' + i) as char), "photoUrl": url }))
+                .collect();
+        },
+        &[("", "max_total_bytes_exceeded")],
+    ),
+    (
+        "missing email",
+        |r| {
+            r.as_object_mut().unwrap().remove("email");
+        },
+        &[("email", "required_when")],
+    ),
+    (
+        "invalid email",
+        |r| r["email"] = "chip".into(),
+        &[("email", "email.invalid")],
+    ),
+    (
+        "email too many bytes",
+        |r| r["email"] = format!("{}@example.com", "a".repeat(320)).into(),
+        &[("email", "max_bytes_exceeded")],
+    ),
+    (
+        "missing phone",
+        |r| {
+            r["contactMethod"] = "phone".into();
+        },
+        &[("phone", "required_when")],
+    ),
+    (
+        "invalid phone",
+        |r| r["phone"] = "call-me-maybe".into(),
+        &[("phone", "phone.invalid")],
+    ),
+    (
+        "phone too many bytes",
+        |r| r["phone"] = format!("+49{}", "1".repeat(4096)).into(),
+        &[("phone", "max_bytes_exceeded")],
+    ),
+    (
+        "html bio",
+        |r| r["bio"] = "<b>hi</b>".into(),
+        &[("bio", "html.not_allowed")],
+    ),
+    (
+        "bio too many bytes",
+        |r| r["bio"] = "a".repeat(4097).into(),
+        &[("bio", "max_bytes_exceeded")],
+    ),
+    (
+        "owner born in the future",
+        |r| r["birthdate"] = "2999-01-01".into(),
+        &[("birthdate", "birthdate.future")],
+    ),
+    (
+        "short password",
+        |r| r["password"] = "abc123".into(),
+        &[("password", "password_too_short")],
+    ),
+    (
+        "weak password",
+        |r| r["password"] = "abcdefghijklmnop".into(),
+        &[("password", "password_too_weak")],
+    ),
+    (
+        "password with the email",
+        |r| r["password"] = "chip12345678".into(),
+        &[("password", "password_contains_email")],
+    ),
+    (
+        "password too many bytes",
+        |r| r["password"] = format!("a1{}", "b".repeat(4095)).into(),
+        &[("password", "max_bytes_exceeded")],
+    ),
+    (
+        "short street",
+        |r| r["address"]["street"] = "w".into(),
+        &[("address.street", "length_min")],
+    ),
+    (
+        "long street",
+        |r| r["address"]["street"] = "warpstreets".into(),
+        &[("address.street", "length_max")],
+    ),
+    (
+        "street too many bytes",
+        |r| r["address"]["street"] = heavy_name().into(),
+        &[("address.street", "max_bytes_exceeded")],
+    ),
+    (
+        "street number not a number",
+        |r| r["address"]["streetNo"] = "one".into(),
+        &[("address.streetNo", "invalid_number_string")],
+    ),
+    (
+        "street number zero",
+        |r| r["address"]["streetNo"] = 0.into(),
+        &[("address.streetNo", "range_min")],
+    ),
+    (
+        "street number missing for many pets",
+        |r| {
+            r["address"]["streetNo"] = 0.into();
+            r["pets"] = pets(4);
+        },
+        &[
+            ("address.streetNo", "range_min"),
+            ("address.streetNo", "street_no_required"),
+        ],
+    ),
+    (
+        "invalid address phone",
+        |r| r["address"]["phone"] = "call-me-maybe".into(),
+        &[("address.phone", "phone.invalid")],
+    ),
+    (
+        "address phone too many bytes",
+        |r| r["address"]["phone"] = format!("+49{}", "1".repeat(4096)).into(),
+        &[("address.phone", "max_bytes_exceeded")],
+    ),
+    (
+        "no pets",
+        |r| r["pets"] = json!([]),
+        &[("pets", "pets_count")],
+    ),
+    (
+        "too many pets",
+        |r| r["pets"] = pets(11),
+        &[("pets", "too_many_elements")],
+    ),
+    (
+        "short pet name",
+        |r| r["pets"][0]["name"] = "C".into(),
+        &[("pets[0].name", "length_min")],
+    ),
+    (
+        "long pet name",
+        |r| r["pets"][0]["name"] = "Chip".repeat(6).into(),
+        &[("pets[0].name", "length_max")],
+    ),
+    (
+        "pet name with digits",
+        |r| r["pets"][0]["name"] = "Chip2".into(),
+        &[("pets[0].name", "pet_name.charset")],
+    ),
+    (
+        "reserved pet name",
+        |r| r["pets"][0]["name"] = "Null".into(),
+        &[("pets[0].name", "pet_name.reserved")],
+    ),
+    (
+        "pet name too many bytes",
+        |r| r["pets"][0]["name"] = heavy_name().into(),
+        &[("pets[0].name", "max_bytes_exceeded")],
+    ),
+    (
+        "email domain in a pet name",
+        |r| r["pets"][0]["name"] = "Example".into(),
+        &[("pets[0].name", "email_domain_in_pet_name")],
+    ),
+    (
+        "malformed photo url",
+        |r| r["pets"][0]["photoUrl"] = "chip.png".into(),
+        &[("pets[0].photoUrl", "url.invalid")],
+    ),
+    (
+        "photo url of a page",
+        |r| r["pets"][0]["photoUrl"] = "https://example.com/chip.html".into(),
+        &[("pets[0].photoUrl", "url.not_an_image")],
+    ),
+    (
+        "photo url too many bytes",
+        |r| {
+            r["pets"][0]["photoUrl"] =
+                format!("https://example.com/{}.png", "a".repeat(4096)).into()
+        },
+        &[("pets[0].photoUrl", "max_bytes_exceeded")],
+    ),
+    (
+        "pet born in the future",
+        |r| r["pets"][0]["birthdate"] = "2999-01-01".into(),
+        &[("pets[0].birthdate", "birthdate.future")],
+    ),
+    (
+        "pet born too long ago",
+        |r| r["pets"][0]["birthdate"] = "1900-01-01".into(),
+        &[("pets[0].birthdate", "birthdate.too_old")],
+    ),
+];
+
+#[tokio::test]
+async fn every_rule_reports_its_code() {
+    let routes = routes();
+    let mut failures = Vec::new();
+    for (name, change, expected) in CASES {
+        let mut request = valid_create();
+        change(&mut request);
+        let response = post_json(&routes, "/create-validator", &request).await;
+        let body = json_body(&response);
+        let codes = if body["errors"].is_array() {
+            field_codes(&body)
+        } else {
+            vec![(response.status().to_string(), body.to_string())]
+        };
+        if codes != pairs(expected) {
+            failures.push(format!("{}: {:?}", name, codes));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// `field` with its list indices as `[]`, the way `GET /rules/create` keys fields.
+fn rule_path(field: &str) -> String {
+    let mut path = String::new();
+    let mut in_index = false;
+    for c in field.chars() {
+        match c {
+            '[' => in_index = true,
+            ']' => {
+                in_index = false;
+                path.push_str("[]");
+            }
+            _ if in_index => {}
+            _ => path.push(c),
+        }
+    }
+    path
+}
+
+/// Every `(field, code)` of `GET /rules/create`. `required_when` is listed under
+/// `contactMethod`, but reported on the field the contact method requires.
+async fn registered_codes() -> BTreeSet<(String, String)> {
+    let routes = routes();
+    let response = send(&routes, warp::test::request().path("/rules/create")).await;
+    let mut codes = BTreeSet::new();
+    for (field, rules) in json_body(&response).as_object().unwrap() {
+        for rule in rules.as_array().unwrap() {
+            for code in rule["codes"].as_array().unwrap() {
+                let code = code.as_str().unwrap();
+                if code == "required_when" {
+                    codes.insert(("email".to_string(), code.to_string()));
+                    codes.insert(("phone".to_string(), code.to_string()));
+                } else {
+                    codes.insert((field.clone(), code.to_string()));
+                }
+            }
+        }
+    }
+    codes
+}
+
+/// A validator whose codes aren't in the registry fails here as soon as a case triggers it.
+#[tokio::test]
+async fn every_reported_code_is_registered_under_its_field() {
+    let registered = registered_codes().await;
+    let routes = routes();
+    let mut unregistered = Vec::new();
+    for (name, change, _) in CASES {
+        let mut request = valid_create();
+        change(&mut request);
+        let response = post_json(&routes, "/create-validator", &request).await;
+        for (field, code) in field_codes(&json_body(&response)) {
+            if !registered.contains(&(rule_path(&field), code.clone())) {
+                unregistered.push(format!("{}: {} {}", name, field, code));
+            }
+        }
+    }
+    assert!(unregistered.is_empty(), "{}", unregistered.join("\n"));
+}
+
+/// A rule added to the registry needs a case here, so the one above covers its validator.
+#[tokio::test]
+async fn every_registered_code_is_triggered_by_a_case() {
+    let triggered: BTreeSet<(String, String)> = CASES
+        .iter()
+        .flat_map(|(_, _, expected)| expected.iter())
+        .map(|(field, code)| (rule_path(field), code.to_string()))
+        .collect();
+    let untriggered: Vec<_> = registered_codes()
+        .await
+        .into_iter()
+        .filter(|pair| !triggered.contains(pair))
+        .filter(|(_, code)| code != "pet_name.whitespace")
+        .collect();
+    assert!(untriggered.is_empty(), "{:?}", untriggered);
+}
@@ -0,0 +1,145 @@
+//! `address.streetNo` sent as a string: a number in it is taken and reported in `X-Coercions`,
+//! anything else is an `invalid_number_string` field error. Strict numbers are in
+//! `strict_numbers.rs`, since the installed configuration is global.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, send, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+/// `valid_create` for `email` with `street_no` as its street number.
+fn with_street_no(email: &str, street_no: Value) -> Value {
+    let mut request = valid_create();
+    request["email"] = email.into();
+    request["address"]["streetNo"] = street_no;
+    request
+}
+
+#[tokio::test]
+async fn number_in_a_string_is_taken_and_reported_as_coerced() {
+    let routes = routes();
+    for (i, street_no) in ["4", " 4 ", "\t4\n"].iter().copied().enumerate() {
+        let email = format!("chip{}@example.com", i);
+        let response = post_json(
+            &routes,
+            "/create-validator",
+            &with_street_no(&email, street_no.into()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{:?}", street_no);
+        assert_eq!(
+            response.headers()["x-coercions"],
+            "address.streetNo",
+            "{:?}",
+            street_no
+        );
+
+        let location = response.headers()["location"].to_str().unwrap();
+        let stored = send(&routes, warp::test::request().path(location)).await;
+        assert_eq!(
+            json_body(&stored)["address"]["streetNo"],
+            4,
+            "{:?}",
+            street_no
+        );
+    }
+}
+
+#[tokio::test]
+async fn dry_run_reports_the_coercion_too() {
+    let routes = routes();
+    let request = with_street_no("chip@example.com", "4".into());
+    let response = post_json(&routes, "/create-validator?dry_run=true", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-coercions"], "address.streetNo");
+}
+
+#[tokio::test]
+async fn number_is_taken_unchanged_without_a_coercion() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-validator",
+        &with_street_no("chip@example.com", json!(4)),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(response.headers().get("x-coercions").is_none());
+}
+
+#[tokio::test]
+async fn string_without_a_number_is_a_field_error_next_to_the_others() {
+    let routes = routes();
+    for street_no in ["four", "4.5", "-1", "", "4 5"] {
+        let mut request = with_street_no("chip@example.com", street_no.into());
+        request["pets"][0]["name"] = "C".into();
+        for path in [
+            "/create-basic",
+            "/create-path",
+            "/create-validator",
+            "/create-fast",
+        ] {
+            let response = post_json(&routes, path, &request).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::BAD_REQUEST,
+                "{} {:?}",
+                path,
+                street_no
+            );
+            assert!(response.headers().get("x-coercions").is_none());
+            let body = json_body(&response);
+            assert_eq!(
+                field_codes(&body),
+                pairs(&[
+                    ("address.streetNo", "invalid_number_string"),
+                    ("pets[0].name", "length_min"),
+                ]),
+                "{} {:?}",
+                path,
+                street_no
+            );
+            let error = error_for(&body, "address.streetNo");
+            assert_eq!(error["rejected_value"], street_no);
+            assert_eq!(
+                error["field_errors"][0],
+                format!("streetNo must be a number, not {}", street_no)
+            );
+        }
+    }
+}
+
+/// A coerced number is validated like any other.
+#[tokio::test]
+async fn coerced_number_is_still_validated() {
+    let routes = routes();
+    let request = with_street_no("chip@example.com", "0".into());
+    let response = post_json(&routes, "/create-validator", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.streetNo", "range_min")])
+    );
+    assert_eq!(error_for(&body, "address.streetNo")["rejected_value"], 0);
+}
+
+/// Form bodies have nothing but strings, so their numbers aren't coercions.
+#[tokio::test]
+async fn form_body_numbers_are_not_reported_as_coerced() {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("email=chip%40example.com&address[street]=warpstreet&address[streetNo]=4&pets[0][name]=Chip");
+    let response = send(&routes, request).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::CREATED,
+        "{:?}",
+        response.body()
+    );
+    assert!(response.headers().get("x-coercions").is_none());
+}
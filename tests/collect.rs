@@ -0,0 +1,108 @@
+//! `POST /create-collect`, which reports every missing or invalid field of a body at once where
+//! the other create routes stop at the first one serde trips over.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+/// Sorted, since the comparison is about which errors come back rather than their order.
+fn sorted_codes(body: &Value) -> Vec<(String, String)> {
+    let mut codes = field_codes(body);
+    codes.sort();
+    codes
+}
+
+#[tokio::test]
+async fn every_problem_of_a_body_is_reported_at_once() {
+    let routes = routes();
+    let request = json!({
+        "address": { "street": "w", "streetNo": "x" },
+        "pets": [{ "name": 3 }],
+        "emial": "chip@example.com",
+    });
+    let response = post_json(&routes, "/create-collect", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        sorted_codes(&json_body(&response)),
+        pairs(&[
+            ("address.street", "length_min"),
+            ("address.streetNo", "invalid_number_string"),
+            ("email", "required_when"),
+            ("emial", "unknown_field"),
+            ("pets[0].name", "invalid_type"),
+        ])
+    );
+
+    // the other routes stop at the first field serde can't read
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("emial", "unknown_field")])
+    );
+}
+
+#[tokio::test]
+async fn single_problem_is_reported_like_on_the_other_routes() {
+    let routes = routes();
+    let mut request = valid_create();
+    request.as_object_mut().unwrap().remove("email");
+    let collected = json_body(&post_json(&routes, "/create-collect", &request).await);
+    let first = json_body(&post_json(&routes, "/create-path", &request).await);
+    assert_eq!(
+        field_codes(&collected),
+        pairs(&[("email", "required_when")])
+    );
+    assert_eq!(collected["errors"], first["errors"]);
+}
+
+#[tokio::test]
+async fn valid_body_is_created() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-collect", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn type_and_format_errors_come_back_in_one_response() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["streetNo"] = "five".into();
+    request["email"] = "nope".into();
+    let response = post_json(&routes, "/create-collect", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        sorted_codes(&json_body(&response)),
+        pairs(&[
+            ("address.streetNo", "invalid_number_string"),
+            ("email", "email.invalid"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn mistyped_field_is_not_also_required_or_invalid() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"] = "warpstreet 1".into();
+    request["email"] = "nope".into();
+    let response = post_json(&routes, "/create-collect", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        sorted_codes(&body),
+        pairs(&[("address", "invalid_type"), ("email", "email.invalid")])
+    );
+    assert_eq!(body["errors"][0]["params"]["expected"], "object");
+}
+
+#[tokio::test]
+async fn malformed_json_is_still_a_syntax_error() {
+    let routes = routes();
+    let response = common::post_raw(&routes, "/create-collect", r#"{"email": nope"#).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "syntax");
+    assert_eq!(body["path"], "email");
+    assert!(body["errors"].is_null());
+}
@@ -0,0 +1,253 @@
+//! Shared setup for the integration tests: the routes the server runs, built from a given
+//! environment, and helpers to send them requests through `warp::test`.
+#![allow(dead_code)]
+
+use bytes::Bytes;
+use example_rust_json_input_validation::error::{DefaultErrorFormatter, ErrorFormatter};
+use example_rust_json_input_validation::health::Readiness;
+use example_rust_json_input_validation::routes_with_state;
+use example_rust_json_input_validation::store::CreateStore;
+use example_rust_json_input_validation::validation::ValidationContext;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, PoisonError};
+use warp::http::Response;
+use warp::test::RequestBuilder;
+use warp::Filter;
+
+/// Held while the environment is changed and the routes read it, tests run on several threads.
+static ENV: Mutex<()> = Mutex::new(());
+
+/// The routes with a fresh store, configured from the environment as it is.
+pub fn routes(
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    routes_with_env(&[])
+}
+
+/// The routes with a fresh store, configured with `vars` set for as long as they're built.
+pub fn routes_with_env(
+    vars: &[(&str, &str)],
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    with_env(vars, || {
+        routes_from(
+            Arc::new(CreateStore::default()),
+            Arc::new(ValidationContext::from_env()),
+        )
+    })
+}
+
+/// The routes serving `store`, which a test may have seeded.
+pub fn routes_with_store(
+    store: Arc<CreateStore>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    with_env(&[], || {
+        routes_from(store, Arc::new(ValidationContext::from_env()))
+    })
+}
+
+/// The routes with a fresh store, validating against `context`, which a test may change.
+pub fn routes_with_context(
+    context: Arc<ValidationContext>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    with_env(&[], || {
+        routes_from(Arc::new(CreateStore::default()), context)
+    })
+}
+
+/// The routes with a fresh store, reporting ready once `readiness` says so.
+pub fn routes_with_readiness(
+    readiness: Arc<Readiness>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    with_env(&[], || {
+        routes_with_state(
+            Arc::new(CreateStore::default()),
+            Arc::new(ValidationContext::from_env()),
+            readiness,
+            Arc::new(DefaultErrorFormatter),
+        )
+    })
+}
+
+/// The routes with a fresh store, shaping error bodies with `formatter`.
+pub fn routes_with_formatter(
+    formatter: Arc<dyn ErrorFormatter>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    with_env(&[], || {
+        routes_with_state(
+            Arc::new(CreateStore::default()),
+            Arc::new(ValidationContext::from_env()),
+            Arc::new(Readiness::new(&[])),
+            formatter,
+        )
+    })
+}
+
+fn routes_from(
+    store: Arc<CreateStore>,
+    context: Arc<ValidationContext>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static {
+    routes_with_state(
+        store,
+        context,
+        Arc::new(Readiness::new(&[])),
+        Arc::new(DefaultErrorFormatter),
+    )
+}
+
+/// Runs `f` with `vars` set, removing them afterwards.
+pub fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+    let _env = ENV.lock().unwrap_or_else(PoisonError::into_inner);
+    for (name, value) in vars {
+        std::env::set_var(name, value);
+    }
+    let result = f();
+    for (name, _) in vars {
+        std::env::remove_var(name);
+    }
+    result
+}
+
+/// Sends `request` to `routes`.
+pub async fn send<F>(routes: &F, request: RequestBuilder) -> Response<Bytes>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    request.reply(routes).await
+}
+
+/// Posts `body` to `path` as JSON.
+pub async fn post_json<F>(routes: &F, path: &str, body: &Value) -> Response<Bytes>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    post_raw(routes, path, &body.to_string()).await
+}
+
+/// Posts `body` to `path` labelled as JSON, whether or not it is.
+pub async fn post_raw<F>(routes: &F, path: &str, body: &str) -> Response<Bytes>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    let request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json")
+        .body(body);
+    send(routes, request).await
+}
+
+/// The body of `response` as JSON.
+pub fn json_body(response: &Response<Bytes>) -> Value {
+    serde_json::from_slice(response.body()).unwrap_or_else(|err| {
+        panic!(
+            "response body is not JSON ({}): {}",
+            err,
+            String::from_utf8_lossy(response.body())
+        )
+    })
+}
+
+/// The body of `response` as JSON without its request ID, which differs between any two
+/// requests.
+pub fn without_request_id(response: &Response<Bytes>) -> Value {
+    let mut body = json_body(response);
+    assert!(body["request_id"].is_string(), "{}", body);
+    body.as_object_mut().unwrap().remove("request_id");
+    body
+}
+
+/// The body of `response` as sent, byte for byte, without its `request_id` member.
+pub fn body_without_request_id(response: &Response<Bytes>) -> String {
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    let id = json_body(response)["request_id"]
+        .as_str()
+        .unwrap_or_else(|| panic!("no request_id in {}", body))
+        .to_owned();
+    let member = format!(r#","request_id":"{}""#, id);
+    assert!(body.contains(&member), "{}", body);
+    body.replace(&member, "")
+}
+
+/// A create request passing every rule.
+pub fn valid_create() -> Value {
+    json!({
+        "email": "chip@example.com",
+        "address": { "street": "warpstreet", "streetNo": 1 },
+        "pets": [{ "name": "Chip" }]
+    })
+}
+
+/// A create request that deserializes but fails two rules, on `email` and `address.street`.
+pub fn invalid_create() -> Value {
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["address"]["street"] = "w".into();
+    request
+}
+
+/// The `field` and `code` of every error in an error response body, in order.
+pub fn field_codes(body: &Value) -> Vec<(String, String)> {
+    body["errors"]
+        .as_array()
+        .unwrap_or_else(|| panic!("no errors in {}", body))
+        .iter()
+        .map(|error| {
+            (
+                error["field"].as_str().unwrap_or_default().to_owned(),
+                error["code"].as_str().unwrap_or_default().to_owned(),
+            )
+        })
+        .collect()
+}
+
+/// The error for `field` in an error response body.
+pub fn error_for<'a>(body: &'a Value, field: &str) -> &'a Value {
+    body["errors"]
+        .as_array()
+        .and_then(|errors| errors.iter().find(|error| error["field"] == field))
+        .unwrap_or_else(|| panic!("no error for {} in {}", field, body))
+}
+
+/// What was logged through `tracing` since `capture_logs`.
+#[derive(Clone, Default)]
+pub struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    pub fn contents(&self) -> String {
+        let buffer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+impl std::io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut buffer = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Collects everything logged on the current thread, down to debug level, until the guard is
+/// dropped. `#[tokio::test]` runs a test and the routes on one thread.
+pub fn capture_logs() -> (Logs, tracing::subscriber::DefaultGuard) {
+    let logs = Logs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    (logs, tracing::subscriber::set_default(subscriber))
+}
+
+/// `(field, code)` pairs from string slices, to compare against [`field_codes`].
+pub fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|(field, code)| (field.to_string(), code.to_string()))
+        .collect()
+}
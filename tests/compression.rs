@@ -0,0 +1,88 @@
+//! Compressed request bodies: gzip and deflate are decoded, up to `DECODED_BODY_LIMIT_BYTES`,
+//! before they're parsed.
+
+mod common;
+
+use common::{json_body, routes_with_env, send, valid_create};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::Write;
+use warp::http::StatusCode;
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn deflate(body: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn create_encoded(encoding: &str, body: Vec<u8>) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path("/create-path")
+        .header("content-type", "application/json")
+        .header("content-encoding", encoding)
+        .body(body)
+}
+
+const LIMITED: &[(&str, &str)] = &[("DECODED_BODY_LIMIT_BYTES", "1000")];
+
+#[tokio::test]
+async fn gzip_and_deflate_bodies_are_created() {
+    let routes = routes_with_env(LIMITED);
+    let body = valid_create().to_string();
+    for (encoding, encoded) in &[
+        ("gzip", gzip(body.as_bytes())),
+        ("deflate", deflate(body.as_bytes())),
+        ("identity", body.clone().into_bytes()),
+    ] {
+        let response = send(&routes, create_encoded(encoding, encoded.clone())).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", encoding);
+        assert_eq!(json_body(&response)["email"], "chip@example.com");
+    }
+}
+
+#[tokio::test]
+async fn corrupt_gzip_is_a_bad_request() {
+    let routes = routes_with_env(LIMITED);
+    let mut corrupt = gzip(valid_create().to_string().as_bytes());
+    corrupt.truncate(corrupt.len() / 2);
+    let response = send(&routes, create_encoded("gzip", corrupt)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "could not decode the gzip body: incomplete deflate stream"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#content-encoding");
+}
+
+#[tokio::test]
+async fn gzip_expanding_past_the_limit_is_a_bad_request() {
+    let routes = routes_with_env(LIMITED);
+    let bomb = gzip(&[b' '; 100_000]);
+    assert!(bomb.len() < 1000);
+    let response = send(&routes, create_encoded("gzip", bomb)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(&response)["message"],
+        "could not decode the gzip body: it decompresses to more than 1000 bytes"
+    );
+}
+
+#[tokio::test]
+async fn unknown_encoding_is_unsupported() {
+    let routes = routes_with_env(LIMITED);
+    let body = valid_create().to_string().into_bytes();
+    let response = send(&routes, create_encoded("br", body)).await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        json_body(&response)["message"],
+        r#"unsupported Content-Encoding "br", expected gzip, deflate or identity"#
+    );
+}
@@ -0,0 +1,47 @@
+//! The server configuration read at startup, and binding with it.
+
+use example_rust_json_input_validation::bind;
+use example_rust_json_input_validation::config::{ConfigError, ServerConfig};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+#[test]
+fn unset_host_and_port_default_to_localhost_8080() {
+    let config = ServerConfig::parse(None, None).unwrap();
+    assert_eq!(config.addr(), SocketAddr::from(([127, 0, 0, 1], 8080)));
+    assert_eq!(config.tls, None);
+}
+
+#[test]
+fn host_and_port_are_parsed() {
+    let config = ServerConfig::parse(Some("0.0.0.0"), Some(" 3000 ")).unwrap();
+    assert_eq!(config.host, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    assert_eq!(config.port, 3000);
+}
+
+#[test]
+fn invalid_port_is_a_config_error() {
+    for port in &["65536", "-1", "http", ""] {
+        let error = ServerConfig::parse(None, Some(port)).unwrap_err();
+        assert_eq!(error, ConfigError::InvalidPort(port.to_string()));
+    }
+    assert_eq!(
+        ServerConfig::parse(None, Some("80x"))
+            .unwrap_err()
+            .to_string(),
+        "invalid APP_PORT `80x`: expected a port number between 0 and 65535"
+    );
+}
+
+#[test]
+fn invalid_host_is_a_config_error() {
+    let error = ServerConfig::parse(Some("localhost"), None).unwrap_err();
+    assert_eq!(error, ConfigError::InvalidHost("localhost".to_string()));
+}
+
+#[tokio::test]
+async fn port_0_binds_to_a_free_port_and_reports_it() {
+    let config = ServerConfig::parse(None, Some("0")).unwrap();
+    let (addr, _server) = bind(config.addr(), async {}).unwrap();
+    assert_eq!(addr.ip(), config.host);
+    assert_ne!(addr.port(), 0);
+}
@@ -0,0 +1,70 @@
+//! The media types the body routes take, and the `415` for the others.
+
+mod common;
+
+use bytes::Bytes;
+use common::{json_body, routes, send, valid_create};
+use warp::http::{Response, StatusCode};
+
+async fn post_with(path: &str, content_type: Option<&str>) -> Response<Bytes> {
+    let routes = routes();
+    let mut request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .body(valid_create().to_string());
+    if let Some(content_type) = content_type {
+        request = request.header("content-type", content_type);
+    }
+    send(&routes, request).await
+}
+
+async fn assert_unsupported(content_type: Option<&str>, named: &str) {
+    let response = post_with("/create-basic", content_type).await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        format!(
+            "unsupported media type {}, expected application/json",
+            named
+        )
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#unsupported-media-type");
+}
+
+#[tokio::test]
+async fn missing_content_type_is_unsupported() {
+    assert_unsupported(None, "(none)").await;
+}
+
+#[tokio::test]
+async fn text_plain_is_unsupported() {
+    assert_unsupported(Some("text/plain"), "text/plain").await;
+}
+
+#[tokio::test]
+async fn json_with_a_charset_is_accepted() {
+    let response = post_with("/create-basic", Some("application/json;charset=utf-8")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn unknown_vendor_type_is_unsupported() {
+    assert_unsupported(
+        Some("application/vnd.acme+json"),
+        "application/vnd.acme+json",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn unsupported_type_lists_what_the_route_reads() {
+    let response = post_with("/create-validator", Some("text/plain")).await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let message = json_body(&response)["message"].as_str().unwrap().to_owned();
+    assert!(
+        message.ends_with("application/yaml or application/x-yaml or application/msgpack"),
+        "{}",
+        message
+    );
+}
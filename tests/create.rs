@@ -0,0 +1,340 @@
+//! The create routes end to end: what they answer for valid bodies, for bodies that aren't JSON
+//! and for bodies failing validation, and what an unknown route answers.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, post_raw, routes, valid_create};
+use warp::http::StatusCode;
+
+const CREATE_ROUTES: &[&str] = &["/create-basic", "/create-path", "/create-validator"];
+
+#[tokio::test]
+async fn valid_body_is_created_on_every_create_route() {
+    let routes = routes();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &valid_create()).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["email"], "chip@example.com", "{}", path);
+        assert_eq!(body["pets"][0]["name"], "Chip", "{}", path);
+        assert!(body["id"].is_string(), "{}: {}", path, body);
+    }
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_bad_request_without_field_errors() {
+    let routes = routes();
+    for path in CREATE_ROUTES {
+        let response = post_raw(&routes, path, r#"{"email":"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        let message = body["message"].as_str().unwrap();
+        assert!(
+            message.contains("EOF while parsing"),
+            "{}: {}",
+            path,
+            message
+        );
+        assert!(body["errors"].is_null(), "{}: {}", path, body);
+    }
+}
+
+#[tokio::test]
+async fn malformed_json_names_the_path_on_create_path() {
+    let routes = routes();
+    let response = post_raw(&routes, "/create-path", r#"{"email":"#).await;
+    let body = json_body(&response);
+    assert_eq!(body["path"], "email");
+    assert_eq!(body["kind"], "eof");
+}
+
+#[tokio::test]
+async fn invalid_email_is_reported_on_email() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["message"], "field errors", "{}", path);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("email", "email.invalid")]),
+            "{}",
+            path
+        );
+        let error = error_for(&body, "email");
+        assert_eq!(
+            error["field_errors"][0],
+            "chip is not a valid email address"
+        );
+        assert_eq!(error["rejected_value"], "chip");
+    }
+}
+
+#[tokio::test]
+async fn invalid_address_is_reported_on_its_nested_path() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = "w".into();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["message"], "field errors", "{}", path);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("address.street", "length_min")]),
+            "{}",
+            path
+        );
+        let error = error_for(&body, "address.street");
+        assert_eq!(
+            error["field_errors"][0],
+            "street must be between 2 and 10 characters"
+        );
+        assert_eq!(error["params"]["min"], 2);
+        assert_eq!(body["failed_sections"], serde_json::json!(["address"]));
+    }
+}
+
+#[tokio::test]
+async fn invalid_pet_is_reported_with_its_index() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = serde_json::json!([{ "name": "Chip" }, { "name": "C" }]);
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["message"], "field errors", "{}", path);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("pets[1].name", "length_min")]),
+            "{}",
+            path
+        );
+        let error = error_for(&body, "pets[1].name");
+        assert_eq!(
+            error["field_errors"][0],
+            "pet name must be between 3 and 20 characters"
+        );
+        assert_eq!(error["rejected_value"], "C");
+    }
+}
+
+#[tokio::test]
+async fn unknown_route_is_not_found() {
+    let routes = routes();
+    let request = warp::test::request().method("GET").path("/nope");
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "Not Found");
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn email_street_and_street_number_fail_together_on_their_paths() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["address"]["street"] = "w".into();
+    request["address"]["streetNo"] = 0.into();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        let fields: Vec<String> = field_codes(&json_body(&response))
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        assert_eq!(
+            fields,
+            ["email", "address.street", "address.streetNo"],
+            "{}",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn syntax_error_reports_its_line_and_column() {
+    let routes = routes();
+    let body =
+        "{\n  \"email\": \"chip@example.com\",\n  \"address\": { \"street\" \"warpstreet\" }\n}";
+    let response = post_raw(&routes, "/create-path", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "syntax");
+    assert_eq!(body["line"], 3);
+    assert_eq!(body["column"], 25);
+    assert_eq!(body["path"], "address");
+}
+
+#[tokio::test]
+async fn type_mismatch_reports_its_path() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["streetNo"] = serde_json::json!([1]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "data");
+    assert_eq!(body["path"], "address.streetNo");
+    assert_eq!(body["line"], 1);
+    assert_eq!(body["column"], 46);
+}
+
+#[tokio::test]
+async fn unknown_fields_are_reported_where_they_were_sent() {
+    let routes = routes();
+    let mut top_level = valid_create();
+    top_level["emial"] = "chip@example.com".into();
+    let mut in_address = valid_create();
+    in_address["address"]["zip"] = "12345".into();
+    let mut in_pet = valid_create();
+    in_pet["pets"][0]["colour"] = "brown".into();
+    for (request, field) in &[
+        (top_level, "emial"),
+        (in_address, "address.zip"),
+        (in_pet, "pets[0].colour"),
+    ] {
+        let response = post_json(&routes, "/create-path", request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", field);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(&[(*field, "unknown_field")]));
+        let key = field.rsplit('.').next().unwrap();
+        let message = error_for(&body, field)["field_errors"][0].as_str().unwrap();
+        assert!(
+            message.contains(&format!("unknown field `{}`", key)),
+            "{}",
+            message
+        );
+    }
+}
+
+/// The shape of a `201 Created` body, and nothing else.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Created {
+    id: String,
+    email: String,
+    pets: Vec<example_rust_json_input_validation::models::Pet>,
+}
+
+#[tokio::test]
+async fn created_body_is_json_with_an_id_the_email_and_the_pets() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = " Chip@Example.COM ".into();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/json",
+            "{}",
+            path
+        );
+        let created: Created = serde_json::from_slice(response.body()).unwrap();
+        assert!(uuid::Uuid::parse_str(&created.id).is_ok(), "{}", path);
+        assert_eq!(created.email, "chip@example.com", "{}", path);
+        assert_eq!(created.pets.len(), 1, "{}", path);
+        assert_eq!(created.pets[0].name, "Chip", "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn type_error_in_a_pet_names_its_segments() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = serde_json::json!([{ "name": "Chip" }, { "name": 7 }]);
+    for path in &["/create-path", "/create-validator"] {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            body["segments"],
+            serde_json::json!(["pets", 1, "name"]),
+            "{}",
+            path
+        );
+        assert_eq!(body["path"], "pets[1].name", "{}", path);
+    }
+}
+
+/// `valid_create` with eight pets, serialized.
+fn eight_pets() -> String {
+    let mut request = valid_create();
+    request["pets"] = (0..8)
+        .map(|i| serde_json::json!({ "name": format!("Pet {}", i) }))
+        .collect();
+    request.to_string()
+}
+
+#[tokio::test]
+async fn truncated_body_names_the_path_it_stopped_in() {
+    let routes = routes();
+    let full = eight_pets();
+    let truncated = &full[..full.len() - 20];
+    for path in &["/create-path", "/create-validator"] {
+        let response = post_raw(&routes, path, truncated).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["kind"], "eof", "{}", path);
+        assert_eq!(
+            body["segments"],
+            serde_json::json!(["pets", 6, null]),
+            "{}",
+            path
+        );
+        assert_eq!(body["column"], truncated.len(), "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn syntax_error_deep_in_the_body_names_its_path() {
+    let routes = routes();
+    let broken = eight_pets().replacen(r#""name":"Pet 5""#, r#""name" "Pet 5""#, 1);
+    for path in &["/create-path", "/create-validator"] {
+        let response = post_raw(&routes, path, &broken).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["kind"], "syntax", "{}", path);
+        assert_eq!(body["path"], "pets[5]", "{}", path);
+        assert_eq!(
+            body["message"], "JSON path error: pets[5]: expected `:` at line 1 column 177",
+            "{}",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn short_street_long_pet_name_and_bad_email_get_readable_messages() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip@".into();
+    request["address"]["street"] = "w".into();
+    request["pets"][0]["name"] = "Chip the very good boy".into();
+    for path in CREATE_ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        let body = json_body(&response);
+        let messages: Vec<&str> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["field_errors"][0].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "chip@ is not a valid email address",
+                "street must be between 2 and 10 characters",
+                "pet name must be between 3 and 20 characters",
+            ],
+            "{}",
+            path
+        );
+    }
+}
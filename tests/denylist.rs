@@ -0,0 +1,71 @@
+//! The email domain deny-list `/create-validator` checks asynchronously next to the regular
+//! validation.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes_with_context, valid_create};
+use example_rust_json_input_validation::validation::ValidationContext;
+use serde_json::Value;
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+fn with_email(email: &str) -> Value {
+    let mut request = valid_create();
+    request["email"] = email.into();
+    request
+}
+
+#[tokio::test]
+async fn domain_blocked_at_runtime_is_rejected_from_then_on() {
+    let context = Arc::new(ValidationContext::default());
+    let routes = routes_with_context(context.clone());
+    let response = post_json(
+        &routes,
+        "/create-validator",
+        &with_email("chip@spam.example"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    context.set_blocked_email_domains(vec!["Spam.Example".to_string()]);
+    let response = post_json(
+        &routes,
+        "/create-validator",
+        &with_email("nacho@spam.example"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("email", "email_domain_blocked")])
+    );
+
+    context.set_blocked_email_domains(Vec::new());
+    let response = post_json(
+        &routes,
+        "/create-validator",
+        &with_email("nacho@spam.example"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn blocked_domain_is_reported_next_to_other_errors() {
+    let context = Arc::new(ValidationContext::default());
+    context.set_blocked_email_domains(vec!["spam.example".to_string()]);
+    let routes = routes_with_context(context);
+    let mut request = with_email("chip@spam.example");
+    request["address"]["street"] = "w".into();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    let mut codes = field_codes(&json_body(&response));
+    codes.sort();
+    assert_eq!(
+        codes,
+        pairs(&[
+            ("address.street", "length_min"),
+            ("email", "email_domain_blocked"),
+        ])
+    );
+}
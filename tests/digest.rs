@@ -0,0 +1,171 @@
+//! `Digest` and `Content-MD5`: bodies are checked against them as they were received, and with
+//! `DIGEST_REQUIRED=true` have to come with one.
+
+mod common;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use common::{field_codes, json_body, pairs, routes, routes_with_env, send, valid_create};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use warp::http::{Response, StatusCode};
+
+/// The routes reading the body through each of the filters that check digests.
+const ROUTES: &[&str] = &["/create-validator", "/create-fast"];
+
+fn sha_256(body: &[u8]) -> String {
+    format!("sha-256={}", STANDARD.encode(Sha256::digest(body)))
+}
+
+fn body() -> String {
+    valid_create().to_string()
+}
+
+async fn post(path: &str, headers: &[(&str, &str)], body: impl AsRef<[u8]>) -> Response<Bytes> {
+    post_with_env(&[], path, headers, body).await
+}
+
+async fn post_with_env(
+    vars: &[(&str, &str)],
+    path: &str,
+    headers: &[(&str, &str)],
+    body: impl AsRef<[u8]>,
+) -> Response<Bytes> {
+    let routes = routes_with_env(vars);
+    let mut request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json")
+        .body(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    send(&routes, request).await
+}
+
+#[tokio::test]
+async fn body_matching_its_sha_256_digest_is_created() {
+    for path in ROUTES {
+        let digest = sha_256(body().as_bytes());
+        let response = post(path, &[("digest", &digest)], body()).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn body_matching_its_content_md5_is_created() {
+    assert_eq!(
+        body(),
+        r#"{"address":{"street":"warpstreet","streetNo":1},"email":"chip@example.com","pets":[{"name":"Chip"}]}"#
+    );
+    let response = post(
+        "/create-validator",
+        &[("content-md5", "EnFE3RxEMMOUy9NLho0OMA==")],
+        body(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn tampered_body_is_a_digest_mismatch_with_both_digests() {
+    let digest = sha_256(body().as_bytes());
+    let tampered = body().replace("Chip\"}", "Chap\"}");
+    for path in ROUTES {
+        let response = post(path, &[("digest", &digest)], &tampered).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(&[("Digest", "digest_mismatch")]));
+        assert_eq!(
+            body["errors"][0]["params"],
+            json!({
+                "algorithm": "sha-256",
+                "expected": digest.trim_start_matches("sha-256="),
+                "computed": sha_256(tampered.as_bytes()).trim_start_matches("sha-256="),
+            })
+        );
+        assert_eq!(body["docs_url"], "/docs/validation#digest");
+    }
+}
+
+#[tokio::test]
+async fn malformed_digest_headers_are_digest_malformed() {
+    for &(header, value, reason) in &[
+        (
+            "digest",
+            "sha-256",
+            "malformed Digest header: expected algorithm=digest pairs like sha-256=...",
+        ),
+        (
+            "digest",
+            "sha-256=!!!",
+            "malformed Digest header: the digest is not base64",
+        ),
+        (
+            "digest",
+            "sha-1=abc",
+            "malformed Digest header: no supported algorithm, expected sha-256, sha-512 or md5",
+        ),
+        (
+            "content-md5",
+            "!!!",
+            "malformed Content-MD5 header: the digest is not base64",
+        ),
+    ] {
+        let response = post("/create-validator", &[(header, value)], body()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", value);
+        let body = json_body(&response);
+        assert_eq!(body["message"], reason);
+        assert_eq!(body["errors"][0]["code"], "digest_malformed");
+    }
+}
+
+#[tokio::test]
+async fn digest_is_of_the_body_as_received_before_decompressing() {
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    gzip.write_all(body().as_bytes()).unwrap();
+    let compressed = gzip.finish().unwrap();
+    let digest = sha_256(&compressed);
+    let headers = [("digest", digest.as_str()), ("content-encoding", "gzip")];
+    let response = post("/create-validator", &headers, &compressed).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let plain_digest = sha_256(body().as_bytes());
+    let headers = [
+        ("digest", plain_digest.as_str()),
+        ("content-encoding", "gzip"),
+    ];
+    let response = post("/create-validator", &headers, &compressed).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn absent_digest_is_fine_unless_required() {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .body(body());
+    assert_eq!(send(&routes, request).await.status(), StatusCode::CREATED);
+
+    let required = [("DIGEST_REQUIRED", "true")];
+    for path in ROUTES {
+        let response = post_with_env(&required, path, &[], body()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(&[("Digest", "digest_required")]));
+        assert_eq!(
+            body["message"],
+            "missing Digest header, request bodies have to come with a digest like sha-256=..."
+        );
+
+        let digest = sha_256(self::body().as_bytes());
+        let response = post_with_env(&required, path, &[("digest", &digest)], self::body()).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+    }
+}
@@ -0,0 +1,21 @@
+//! Error docs hosted elsewhere, with `DOCS_BASE_URL`. The base is read once per process, so every
+//! test of this binary sets the same one.
+
+mod common;
+
+use common::{json_body, post_json, valid_create};
+
+#[tokio::test]
+async fn docs_urls_start_with_the_configured_base() {
+    std::env::set_var("DOCS_BASE_URL", "https://docs.example.com/errors");
+    let routes = common::routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        body["errors"][0]["docs_url"],
+        "https://docs.example.com/errors#email"
+    );
+    assert_eq!(body["docs_url"], "https://docs.example.com/errors#email");
+}
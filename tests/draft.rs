@@ -0,0 +1,78 @@
+//! `POST /create-draft`: the address and the pets are optional, validated in full when they're
+//! there and not at all when they're left out or `null`.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+async fn draft(body: &Value) -> warp::http::Response<bytes::Bytes> {
+    post_json(&routes(), "/create-draft", body).await
+}
+
+#[tokio::test]
+async fn absent_address_and_pets_are_valid() {
+    let response = draft(&json!({ "email": "chip@example.com" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = draft(&json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn null_address_and_pets_are_taken_for_absent_ones() {
+    let response = draft(&json!({ "address": null, "pets": null })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8_lossy(response.body()).into_owned();
+    assert!(body.contains("address: None, pets: None"), "{}", body);
+}
+
+#[tokio::test]
+async fn present_address_with_a_short_street_is_reported_on_address_street() {
+    let response = draft(&json!({ "address": { "street": "w", "streetNo": 1 } })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_min")])
+    );
+    assert_eq!(body["failed_sections"], json!(["address"]));
+}
+
+#[tokio::test]
+async fn present_address_is_deserialized_in_full() {
+    let response = draft(&json!({ "address": { "street": "warpstreet" } })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["path"], "address");
+    assert_eq!(
+        body["message"],
+        "JSON path error: address: missing field `streetNo` at line 1 column 34"
+    );
+}
+
+/// An empty list is a list of pets that's too short, not a missing one.
+#[tokio::test]
+async fn present_but_empty_pets_fail_pets_count() {
+    let response = draft(&json!({ "pets": [] })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets", "pets_count")]));
+    assert_eq!(body["errors"][0]["params"]["count"], 0);
+}
+
+#[tokio::test]
+async fn errors_in_present_address_and_pets_are_reported_together() {
+    let response = draft(&json!({
+        "address": { "street": "warpstreet", "streetNo": 0 },
+        "pets": [{ "name": "Chip" }, { "name": "C" }],
+    }))
+    .await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[
+            ("address.streetNo", "range_min"),
+            ("pets[1].name", "length_min"),
+        ])
+    );
+}
@@ -0,0 +1,104 @@
+//! `?dry_run=true` on `/create-validator`: the whole pipeline runs, but nothing is stored.
+
+mod common;
+
+use common::{
+    invalid_create, json_body, post_json, routes_with_store, send, valid_create, without_request_id,
+};
+use example_rust_json_input_validation::store::CreateStore;
+use serde_json::Value;
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+const KEY: &str = "0d4b9a52-7c1e-4f3a-9b6d-2e8f1a3c5b7d";
+
+#[tokio::test]
+async fn valid_dry_run_is_200_and_stores_nothing() {
+    let store = Arc::new(CreateStore::default());
+    let routes = routes_with_store(store.clone());
+    let response = post_json(&routes, "/create-validator?dry_run=true", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("location").is_none());
+    assert_eq!(json_body(&response), serde_json::json!({ "valid": true }));
+    assert_eq!(store.list(0, 10).len(), 0);
+}
+
+#[tokio::test]
+async fn invalid_dry_run_gets_the_same_errors_as_a_create() {
+    let routes = routes_with_store(Arc::new(CreateStore::default()));
+    let dry = post_json(&routes, "/create-validator?dry_run=true", &invalid_create()).await;
+    let real = post_json(&routes, "/create-validator", &invalid_create()).await;
+    assert_eq!(dry.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(real.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(without_request_id(&dry), without_request_id(&real));
+}
+
+#[tokio::test]
+async fn create_without_the_flag_or_with_false_stores_it() {
+    let store = Arc::new(CreateStore::default());
+    let routes = routes_with_store(store.clone());
+    let response = post_json(&routes, "/create-validator", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let mut other = valid_create();
+    other["email"] = "nacho@example.com".into();
+    let response = post_json(&routes, "/create-validator?dry_run=false", &other).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(store.list(0, 10).len(), 2);
+}
+
+#[tokio::test]
+async fn flag_values_other_than_true_or_false_are_rejected_on_dry_run() {
+    let routes = routes_with_store(Arc::new(CreateStore::default()));
+    for value in &["yes", "1", ""] {
+        let path = format!("/create-validator?dry_run={}", value);
+        let response = post_json(&routes, &path, &valid_create()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", value);
+        let body = json_body(&response);
+        assert_eq!(body["errors"][0]["field"], "dry_run");
+        assert_eq!(body["errors"][0]["code"], "invalid_flag");
+        assert_eq!(
+            body["errors"][0]["field_errors"][0],
+            format!(
+                "invalid_flag: dry_run must be true or false, got `{}`",
+                value
+            )
+        );
+    }
+}
+
+#[tokio::test]
+async fn dry_run_reports_a_taken_email_without_taking_it() {
+    let store = Arc::new(CreateStore::default());
+    let routes = routes_with_store(store.clone());
+    let response = post_json(&routes, "/create-validator?dry_run=true", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = post_json(&routes, "/create-validator", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = post_json(&routes, "/create-validator?dry_run=true", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(json_body(&response)["errors"][0]["code"], "unique");
+    assert_eq!(store.list(0, 10).len(), 1);
+}
+
+#[tokio::test]
+async fn dry_run_leaves_the_idempotency_key_unused() {
+    let routes = routes_with_store(Arc::new(CreateStore::default()));
+    let create = |path: &str, body: &Value| {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("content-type", "application/json")
+            .header("idempotency-key", KEY)
+            .body(body.to_string())
+    };
+    let mut other = valid_create();
+    other["email"] = "nacho@example.com".into();
+    let response = send(&routes, create("/create-validator?dry_run=true", &other)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Another body under the same key would be a 422 had the dry run used it.
+    let response = send(&routes, create("/create-validator", &valid_create())).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(response.headers().get("idempotency-replayed").is_none());
+}
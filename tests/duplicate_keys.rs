@@ -0,0 +1,62 @@
+//! Duplicate keys on `/create-validator`: each duplicated key is reported on its path instead of
+//! the last value silently winning.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_raw, routes, valid_create};
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn duplicate_email_is_reported() {
+    let routes = routes();
+    let body = r#"{"email":"good@example.com","email":"evil","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let response = post_raw(&routes, "/create-validator", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "field errors");
+    assert_eq!(field_codes(&body), pairs(&[("email", "duplicate_key")]));
+    assert_eq!(
+        error_for(&body, "email")["field_errors"][0],
+        "duplicate_key: `email` appears more than once"
+    );
+}
+
+#[tokio::test]
+async fn duplicates_in_the_address_and_a_pet_are_reported_on_their_paths() {
+    let routes = routes();
+    let body = r#"{"email":"chip@example.com","address":{"street":"warpstreet","street":"w","streetNo":1},"pets":[{"name":"Chip"},{"name":"Chap","name":"C"}]}"#;
+    let response = post_raw(&routes, "/create-validator", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[
+            ("address.street", "duplicate_key"),
+            ("pets[1].name", "duplicate_key"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn key_repeated_three_times_is_reported_once() {
+    let routes = routes();
+    let body = r#"{"email":"a@example.com","email":"b@example.com","email":"c@example.com","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let response = post_raw(&routes, "/create-validator", body).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("email", "duplicate_key")])
+    );
+}
+
+#[tokio::test]
+async fn same_key_in_different_objects_is_no_duplicate() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = serde_json::json!([{ "name": "Chip" }, { "name": "Chap" }]);
+    let response = post_raw(&routes, "/create-validator", &request.to_string()).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::CREATED,
+        "{}",
+        String::from_utf8_lossy(response.body())
+    );
+}
@@ -0,0 +1,64 @@
+//! `POST /echo`: what the create-validator pipeline understood of a request, as canonical JSON,
+//! or exactly the errors create-validator answers with.
+
+mod common;
+
+use common::{body_without_request_id, json_body, post_json, post_raw, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn messy_but_valid_request_is_echoed_normalized_with_sorted_keys_and_defaults() {
+    let routes = routes();
+    let messy = json!({
+        "pets": [{ "name": "  Chip " }],
+        "address": { "streetNo": 1, "street": " warpstreet " },
+        "email": " Chip@Example.COM "
+    });
+    let response = post_json(&routes, "/echo", &messy).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(
+        response.body(),
+        r#"{"address":{"phone":null,"street":"warpstreet","streetNo":1},"bio":null,"email":"chip@example.com","password":null,"pets":[{"birthdate":null,"name":"Chip","photoUrl":null,"species":null}],"phone":null}"#
+    );
+}
+
+#[tokio::test]
+async fn echoing_an_echo_gives_the_same_bytes() {
+    let routes = routes();
+    let first = post_json(&routes, "/echo", &valid_create()).await;
+    let echoed: Value = json_body(&first);
+    let second = post_json(&routes, "/echo", &echoed).await;
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_eq!(second.body(), first.body());
+}
+
+#[tokio::test]
+async fn invalid_request_gets_the_errors_of_create_validator_byte_for_byte() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["pets"] = json!([{ "name": "C" }]);
+    let echo = post_json(&routes, "/echo", &request).await;
+    let create = post_json(&routes, "/create-validator", &request).await;
+    assert_eq!(echo.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(echo.status(), create.status());
+    assert_eq!(
+        body_without_request_id(&echo),
+        body_without_request_id(&create)
+    );
+}
+
+#[tokio::test]
+async fn malformed_request_gets_the_errors_of_create_validator_byte_for_byte() {
+    let routes = routes();
+    let body = r#"{"email":"chip@example.com","address":{"street" "warpstreet"}}"#;
+    let echo = post_raw(&routes, "/echo", body).await;
+    let create = post_raw(&routes, "/create-validator", body).await;
+    assert_eq!(echo.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body_without_request_id(&echo),
+        body_without_request_id(&create)
+    );
+}
@@ -0,0 +1,138 @@
+//! The encodings `/create-validator` reads besides plain JSON, each answered like the JSON body
+//! it stands for.
+
+mod common;
+
+use bytes::Bytes;
+use common::{json_body, post_json, routes, send, valid_create, without_request_id};
+use serde_json::Value;
+use warp::http::{Response, StatusCode};
+
+async fn post_as<B: AsRef<[u8]>>(content_type: &str, body: B) -> Response<Bytes> {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", content_type)
+        .body(body);
+    send(&routes, request).await
+}
+
+const FORM: &str = "application/x-www-form-urlencoded";
+
+#[tokio::test]
+async fn valid_form_is_created_like_its_json() {
+    let form = "email=chip%40example.com&address[street]=warpstreet&address[streetNo]=1&pets[0][name]=Chip";
+    let response = post_as(FORM, form).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = json_body(&response);
+    let routes = routes();
+    let expected = json_body(&post_json(&routes, "/create-validator", &valid_create()).await);
+    for key in &["email", "address", "pets"] {
+        assert_eq!(created[key], expected[key], "{}", key);
+    }
+}
+
+#[tokio::test]
+async fn invalid_form_gets_the_errors_of_its_json() {
+    let form =
+        "email=chip&address[street]=w&address[streetNo]=1&pets[0][name]=Chip&pets[1][name]=C";
+    let response = post_as(FORM, form).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let from_form = without_request_id(&response);
+
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["address"]["street"] = "w".into();
+    request["pets"] = serde_json::json!([{ "name": "Chip" }, { "name": "C" }]);
+    let routes = routes();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    let from_json = without_request_id(&response);
+    assert_eq!(from_form, from_json);
+}
+
+const YAML: &str = "application/yaml";
+
+#[tokio::test]
+async fn valid_yaml_is_created_like_its_json() {
+    let yaml = "email: chip@example.com\naddress:\n  street: warpstreet\n  streetNo: 1\npets:\n  - name: Chip\n";
+    for content_type in &[YAML, "application/x-yaml"] {
+        let response = post_as(content_type, yaml).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", content_type);
+        let created = json_body(&response);
+        assert_eq!(created["email"], "chip@example.com");
+        assert_eq!(created["pets"][0]["name"], "Chip");
+    }
+}
+
+#[tokio::test]
+async fn invalid_yaml_is_a_bad_request_with_its_location() {
+    let response = post_as(YAML, "email: 'chip\n").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.starts_with("YAML path error: "), "{}", message);
+    assert!(message.contains("at line 2 column 1"), "{}", message);
+    assert!(body["errors"].is_null());
+    assert_eq!(body["docs_url"], "/docs/validation#malformed-body");
+}
+
+#[tokio::test]
+async fn invalid_yaml_email_gets_the_errors_of_its_json() {
+    let yaml =
+        "email: chip\naddress:\n  street: warpstreet\n  streetNo: 1\npets:\n  - name: Chip\n";
+    let response = post_as(YAML, yaml).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let from_yaml = without_request_id(&response);
+
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let routes = routes();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    assert_eq!(from_yaml, without_request_id(&response));
+}
+
+const MSGPACK: &str = "application/msgpack";
+
+#[tokio::test]
+async fn msgpack_round_trips_when_msgpack_is_accepted() {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", MSGPACK)
+        .header("accept", MSGPACK)
+        .body(rmp_serde::to_vec_named(&valid_create()).unwrap());
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], MSGPACK);
+    let created: Value = rmp_serde::from_slice(response.body()).unwrap();
+    assert_eq!(created["email"], "chip@example.com");
+    assert_eq!(created["pets"][0]["name"], "Chip");
+    assert!(created["id"].is_string());
+}
+
+#[tokio::test]
+async fn msgpack_is_answered_with_json_by_default() {
+    let body = rmp_serde::to_vec_named(&valid_create()).unwrap();
+    let response = post_as(MSGPACK, body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(json_body(&response)["email"], "chip@example.com");
+}
+
+#[tokio::test]
+async fn msgpack_with_a_mistyped_street_number_names_its_path() {
+    let mut request = valid_create();
+    request["address"]["streetNo"] = true.into();
+    let response = post_as(MSGPACK, rmp_serde::to_vec_named(&request).unwrap()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "MessagePack path error: address.streetNo: invalid type: boolean `true`, \
+         expected a non-negative integer, or a string holding one"
+    );
+    assert!(body["errors"].is_null());
+    assert_eq!(body["docs_url"], "/docs/validation#malformed-body");
+}
@@ -0,0 +1,51 @@
+//! Create requests wrapped in a versioned envelope, `{"version": 1, "payload": {...}}`.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn supported_version_is_created() {
+    let routes = routes();
+    let request = json!({ "version": 1, "payload": valid_create() });
+    let response = post_json(&routes, "/create-envelope", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["email"], "chip@example.com");
+}
+
+#[tokio::test]
+async fn unsupported_version_is_rejected_before_the_payload() {
+    let routes = routes();
+    let request = json!({ "version": 7, "payload": { "email": 1 } });
+    let response = post_json(&routes, "/create-envelope", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "bad request: unsupported envelope version 7"
+    );
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn payload_errors_are_reported_like_those_of_a_bare_request() {
+    let routes = routes();
+    let mut not_a_number = valid_create();
+    not_a_number["address"]["streetNo"] = "one".into();
+    let mut invalid_email = valid_create();
+    invalid_email["email"] = "chip".into();
+    for (payload, expected) in &[
+        (not_a_number, ("address.streetNo", "invalid_number_string")),
+        (invalid_email, ("email", "email.invalid")),
+    ] {
+        let request = json!({ "version": 1, "payload": payload });
+        let response = post_json(&routes, "/create-envelope", &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(field_codes(&json_body(&response)), pairs(&[*expected]));
+
+        let response = post_json(&routes, "/create-path", payload).await;
+        assert_eq!(field_codes(&json_body(&response)), pairs(&[*expected]));
+    }
+}
@@ -0,0 +1,127 @@
+//! `X-Error-Detail`: `full` error responses carry messages, params and rejected values,
+//! `minimal` ones only field names and codes. `ERROR_DETAIL` sets the default.
+
+mod common;
+
+use common::{invalid_create, json_body, routes, routes_with_env, send};
+use serde_json::Value;
+use warp::http::StatusCode;
+
+/// Posts `body` to `/create-path`, asking for `detail` if given.
+async fn create<F>(routes: &F, body: &str, detail: Option<&str>) -> Value
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + 'static,
+{
+    let mut request = warp::test::request()
+        .method("POST")
+        .path("/create-path")
+        .header("content-type", "application/json")
+        .body(body);
+    if let Some(detail) = detail {
+        request = request.header("x-error-detail", detail);
+    }
+    let response = send(routes, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    json_body(&response)
+}
+
+/// The member names of every error in `body`.
+fn error_keys(body: &Value) -> Vec<Vec<String>> {
+    body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error.as_object().unwrap().keys().cloned().collect())
+        .collect()
+}
+
+/// The member names of a field error at the `full` detail level, with `docs_url` only if
+/// `ERROR_DOC_URLS` leaves it on.
+fn full_keys(docs_urls: bool) -> Vec<&'static str> {
+    let mut keys = vec![
+        "code",
+        "field",
+        "field_errors",
+        "params",
+        "rejected_value",
+        "severity",
+    ];
+    if docs_urls {
+        keys.insert(1, "docs_url");
+    }
+    keys
+}
+
+#[tokio::test]
+async fn full_detail_carries_messages_params_and_rejected_values() {
+    let routes = routes();
+    for detail in &[None, Some("full"), Some("FULL"), Some("everything")] {
+        let body = create(&routes, &invalid_create().to_string(), *detail).await;
+        assert_eq!(
+            error_keys(&body),
+            [full_keys(true), full_keys(true)],
+            "{:?}",
+            detail
+        );
+        assert_eq!(body["errors"][1]["rejected_value"], "w");
+        assert_eq!(body["errors"][1]["params"]["min"], 2);
+    }
+}
+
+#[tokio::test]
+async fn minimal_detail_carries_only_fields_and_codes() {
+    let routes = routes();
+    let body = create(&routes, &invalid_create().to_string(), Some("minimal")).await;
+    assert_eq!(
+        body["errors"],
+        serde_json::json!([
+            { "field": "email", "code": "email.invalid" },
+            { "field": "address.street", "code": "length_min" },
+        ])
+    );
+    assert_eq!(body["message"], "field errors");
+}
+
+#[tokio::test]
+async fn minimal_detail_hides_serde_messages_behind_the_path() {
+    let routes = routes();
+    for malformed in &[r#"{"email":"#, r#"{"email":1}"#] {
+        let body = create(&routes, malformed, Some("minimal")).await;
+        assert_eq!(body["message"], "malformed JSON at email", "{}", malformed);
+        for member in &["path", "segments", "line", "column", "kind"] {
+            assert!(body.get(member).is_none(), "{} in {}", member, body);
+        }
+    }
+
+    let body = create(&routes, r#"{"email":1}"#, Some("full")).await;
+    assert_eq!(
+        body["message"],
+        "JSON path error: email: invalid type: integer `1`, expected a string at line 1 column 10"
+    );
+    assert_eq!(body["path"], "email");
+}
+
+#[tokio::test]
+async fn error_detail_sets_the_default_and_the_header_overrides_it() {
+    let routes = routes_with_env(&[("ERROR_DETAIL", "minimal")]);
+    for detail in &[None, Some("minimal"), Some("neither")] {
+        let body = create(&routes, &invalid_create().to_string(), *detail).await;
+        assert_eq!(
+            error_keys(&body),
+            [["code", "field"], ["code", "field"]],
+            "{:?}",
+            detail
+        );
+    }
+    let body = create(&routes, &invalid_create().to_string(), Some("full")).await;
+    assert_eq!(error_keys(&body), [full_keys(true), full_keys(true)]);
+}
+
+#[tokio::test]
+async fn full_detail_leaves_out_docs_urls_when_they_are_off() {
+    let routes = routes_with_env(&[("ERROR_DOC_URLS", "false")]);
+    let body = create(&routes, &invalid_create().to_string(), Some("full")).await;
+    assert_eq!(error_keys(&body), [full_keys(false), full_keys(false)]);
+}
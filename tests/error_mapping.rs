@@ -0,0 +1,129 @@
+//! Each error answers with the status, message and field errors it reports itself, through
+//! the same handling the routes use, and the bodies of the routes' own errors stay as they were.
+
+mod common;
+
+use bytes::Bytes;
+use common::{body_without_request_id, json_body, post_json, post_raw, routes, valid_create};
+use example_rust_json_input_validation::error::{
+    with_request_handling, DefaultErrorFormatter, Error, ErrorConfig, Language,
+};
+use example_rust_json_input_validation::metrics::Metrics;
+use serde_json::Value;
+use std::sync::Arc;
+use warp::http::{Response, StatusCode};
+use warp::Filter;
+
+/// What a route failing with `error()` answers, with `config`.
+async fn answer(error: fn() -> Error, config: ErrorConfig) -> Response<Bytes> {
+    let failing = warp::any()
+        .and_then(move || async move { Err::<warp::reply::Response, _>(error().into_rejection()) });
+    let routes = with_request_handling(
+        Arc::new(Metrics::default()),
+        config,
+        Arc::new(DefaultErrorFormatter),
+        failing,
+    );
+    warp::test::request().reply(&routes).await
+}
+
+fn codes(errors: &Value) -> Vec<&str> {
+    errors
+        .as_array()
+        .map(|errors| {
+            errors
+                .iter()
+                .map(|error| error["code"].as_str().unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn errors_answer_with_what_they_report() {
+    let cases: Vec<fn() -> Error> = vec![
+        || Error::EmptyBody,
+        || Error::InvalidBodyType {
+            expected: "object",
+            received: "array",
+        },
+        || Error::JsonTooDeep { max: 128 },
+        || Error::TrailingContent { offset: 7 },
+        || Error::MissingApiKey,
+        || Error::Forbidden("no".to_string()),
+        || Error::PayloadTooLarge {
+            route: "create-basic",
+            limit: 10,
+        },
+        || Error::TooManyRequests {
+            retry_after_secs: 3,
+        },
+        || Error::Timeout { limit_ms: 50 },
+        || Error::NotFound {
+            resource: "create",
+            id: "7".to_string(),
+        },
+        || Error::Conflict {
+            field: "email",
+            value: "chip@example.com".to_string(),
+        },
+        || Error::InvalidIdempotencyKey("k".to_string()),
+        || Error::BadRequest("bad".to_string()),
+    ];
+    let config = ErrorConfig::default();
+    for make in cases {
+        let error = make();
+        let response = answer(make, config).await;
+        let name = format!("{:?}", error);
+        assert_eq!(response.status(), error.status(config), "{}", name);
+        let body = json_body(&response);
+        assert_eq!(body["message"], error.message(), "{}", name);
+        let expected = serde_json::to_value(error.field_errors(Language::En)).unwrap();
+        assert_eq!(codes(&body["errors"]), codes(&expected), "{}", name);
+        assert!(
+            body["docs_url"]
+                .as_str()
+                .unwrap()
+                .starts_with("/docs/validation#"),
+            "{}",
+            name
+        );
+    }
+}
+
+#[tokio::test]
+async fn status_of_validation_errors_follows_the_config() {
+    let config = ErrorConfig {
+        validation_status: StatusCode::UNPROCESSABLE_ENTITY,
+        ..ErrorConfig::default()
+    };
+    let invalid = || Error::ValidationError(validator::ValidationErrors::new());
+    assert_eq!(invalid().status(config), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        answer(invalid, config).await.status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert_eq!(Error::EmptyBody.status(config), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn json_path_error_body_is_unchanged() {
+    let routes = routes();
+    let response = post_raw(&routes, "/create-path", r#"{"email":1}"#).await;
+    assert_eq!(
+        body_without_request_id(&response),
+        r#"{"message":"JSON path error: email: invalid type: integer `1`, expected a string at line 1 column 10","errors":null,"docs_url":"/docs/validation#malformed-body","path":"email","segments":["email"],"line":1,"column":10,"kind":"data"}"#
+    );
+}
+
+#[tokio::test]
+async fn validation_error_body_is_unchanged() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(
+        body_without_request_id(&response),
+        r#"{"message":"field errors","errors":[{"field":"email","severity":"error","code":"email.invalid","field_errors":["chip is not a valid email address"],"params":{},"rejected_value":"chip","docs_url":"/docs/validation#email"}],"docs_url":"/docs/validation#email","failed_sections":["email"]}"#
+    );
+}
@@ -0,0 +1,317 @@
+//! The shape of error responses: what every error carries besides its message.
+
+mod common;
+
+use common::{error_for, json_body, post_json, routes, routes_with_env, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn docs_urls_off_leaves_the_body_as_it_was_before_field_errors_linked_theirs() {
+    let routes = routes_with_env(&[("ERROR_DOC_URLS", "false")]);
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let request_id = json_body(&response)["request_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        std::str::from_utf8(response.body()).unwrap(),
+        r#"{"message":"field errors","errors":[{"field":"email","severity":"error","code":"email.invalid","field_errors":["chip is not a valid email address"],"params":{},"rejected_value":"chip"}],"docs_url":"/docs/validation#email","failed_sections":["email"],"request_id":"REQUEST_ID"}"#
+            .replace("REQUEST_ID", &request_id)
+    );
+
+    let response = common::post_raw(&routes, "/create-path", "{").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(json_body(&response).get("docs_url").is_none());
+}
+
+#[tokio::test]
+async fn validation_errors_link_their_docs() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = "w".into();
+    request["contactMethod"] = "phone".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(body["docs_url"], body["errors"][0]["docs_url"]);
+    assert_eq!(
+        error_for(&body, "phone")["docs_url"],
+        "/docs/validation#required-when"
+    );
+    assert_eq!(
+        error_for(&body, "address.street")["docs_url"],
+        "/docs/validation#length"
+    );
+}
+
+#[tokio::test]
+async fn other_errors_link_the_docs_of_their_code() {
+    let routes = routes();
+    let request = warp::test::request().method("GET").path("/nope");
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        json_body(&response)["docs_url"],
+        "/docs/validation#not-found"
+    );
+}
+
+#[tokio::test]
+async fn failed_sections_list_the_sections_with_errors() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"][0]["name"] = "C".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(json_body(&response)["failed_sections"], json!(["pets"]));
+
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(
+        json_body(&response)["failed_sections"],
+        json!(["email", "pets"])
+    );
+}
+
+#[tokio::test]
+async fn errors_on_the_whole_body_are_in_the_root_section() {
+    let routes = routes();
+    let request = json!({ "name": "P", "minGuests": 5, "maxGuests": 3 });
+    let response = post_json(&routes, "/create-booking", &request).await;
+    assert_eq!(
+        json_body(&response)["failed_sections"],
+        json!(["root", "name"])
+    );
+}
+
+#[tokio::test]
+async fn parameter_and_header_errors_have_no_section() {
+    let routes = routes_with_env(&[("MIN_CLIENT_VERSION", "1.4.0")]);
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator?dry_run=yes")
+        .header("content-type", "application/json")
+        .header("x-client-version", "1.4.0")
+        .body(valid_create().to_string());
+    let body = json_body(&common::send(&routes, request).await);
+    assert_eq!(body["errors"][0]["field"], "dry_run");
+    assert!(body.get("failed_sections").is_none(), "{}", body);
+
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .header("x-client-version", "1.0.0")
+        .body(valid_create().to_string());
+    let body = json_body(&common::send(&routes, request).await);
+    assert_eq!(body["errors"][0]["field"], "X-Client-Version");
+    assert!(body.get("failed_sections").is_none(), "{}", body);
+}
+
+#[tokio::test]
+async fn explain_mode_lists_the_validators_of_each_field() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-validator?explain=true", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["validators"]["email"],
+        json!(["email", "validate_email_bytes"])
+    );
+    assert_eq!(
+        body["validators"]["address.street"],
+        json!(["validate_street_length", "validate_street_bytes"])
+    );
+}
+
+#[tokio::test]
+async fn validators_are_only_listed_in_explain_mode() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    assert!(json_body(&response).get("validators").is_none());
+
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator?explain=true")
+        .header("content-type", "application/json")
+        .header("x-error-detail", "minimal")
+        .body(request.to_string());
+    let response = common::send(&routes, request).await;
+    assert!(json_body(&response).get("validators").is_none());
+}
+
+#[tokio::test]
+async fn email_failure_links_the_email_section() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        error_for(&body, "email")["docs_url"],
+        "/docs/validation#email"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#email");
+}
+
+#[tokio::test]
+async fn messages_follow_accept_language() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"][0]["name"] = "Chip2".into();
+    request["address"]["streetNo"] = 0.into();
+    let mut messages = Vec::new();
+    for language in &["en", "de-DE,de;q=0.9", "fr"] {
+        let request = warp::test::request()
+            .method("POST")
+            .path("/create-path")
+            .header("content-type", "application/json")
+            .header("accept-language", *language)
+            .body(request.to_string());
+        let body = json_body(&common::send(&routes, request).await);
+        messages.push((
+            error_for(&body, "pets[0].name")["field_errors"][0].clone(),
+            error_for(&body, "address.streetNo")["field_errors"][0].clone(),
+        ));
+    }
+    let english = (
+        json!("name may only contain letters, spaces and hyphens, not 2"),
+        json!("street number must be at least 1"),
+    );
+    let german = (
+        json!("name darf nur Buchstaben, Leerzeichen und Bindestriche enthalten, nicht 2"),
+        json!("streetNo muss mindestens 1 sein"),
+    );
+    // languages without translations get English
+    assert_eq!(messages, [english.clone(), german, english]);
+}
+
+#[tokio::test]
+async fn validation_status_is_configurable_while_parse_errors_stay_400() {
+    let mut invalid = valid_create();
+    invalid["email"] = "chip".into();
+    for (setting, expected) in &[
+        (None, StatusCode::BAD_REQUEST),
+        (Some("400"), StatusCode::BAD_REQUEST),
+        (Some("422"), StatusCode::UNPROCESSABLE_ENTITY),
+    ] {
+        let routes = match setting {
+            Some(status) => routes_with_env(&[("VALIDATION_ERROR_STATUS", status)]),
+            None => routes_with_env(&[]),
+        };
+        let response = post_json(&routes, "/create-path", &invalid).await;
+        assert_eq!(response.status(), *expected, "{:?}", setting);
+        assert_eq!(json_body(&response)["errors"][0]["field"], "email");
+
+        let response = common::post_raw(&routes, "/create-path", r#"{"email":"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?}", setting);
+    }
+}
+
+fn create_accepting(accept: Option<&str>, body: &str) -> warp::test::RequestBuilder {
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-path")
+        .header("content-type", "application/json")
+        .body(body);
+    match accept {
+        Some(accept) => request.header("accept", accept),
+        None => request,
+    }
+}
+
+/// The members the default format and problem details share, without the request ID.
+fn shared_members(mut body: serde_json::Value) -> serde_json::Value {
+    let body = body.as_object_mut().unwrap();
+    body.remove("request_id");
+    for problem_member in &["type", "title", "status", "detail", "message"] {
+        body.remove(*problem_member);
+    }
+    serde_json::Value::Object(body.clone())
+}
+
+#[tokio::test]
+async fn problem_details_carry_what_the_default_format_does() {
+    let routes = routes();
+    let mut invalid = valid_create();
+    invalid["email"] = "chip".into();
+    let body = invalid.to_string();
+
+    let plain = common::send(&routes, create_accepting(None, &body)).await;
+    assert_eq!(plain.headers()["content-type"], "application/json");
+    let plain = json_body(&plain);
+    assert!(plain.get("type").is_none());
+
+    let problem = common::send(
+        &routes,
+        create_accepting(Some("application/problem+json"), &body),
+    )
+    .await;
+    assert_eq!(problem.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        problem.headers()["content-type"],
+        "application/problem+json"
+    );
+    let problem = json_body(&problem);
+    assert_eq!(problem["type"], "/problems/validation-error");
+    assert_eq!(problem["title"], "Bad Request");
+    assert_eq!(problem["status"], 400);
+    assert_eq!(problem["detail"], plain["message"]);
+    assert_eq!(shared_members(problem), shared_members(plain));
+}
+
+#[tokio::test]
+async fn problem_type_names_the_category_of_the_error() {
+    let routes = routes();
+    let accept = Some("application/problem+json");
+    let response = common::send(&routes, create_accepting(accept, "{")).await;
+    assert_eq!(json_body(&response)["type"], "/problems/parse-error");
+
+    let request = warp::test::request()
+        .path("/nope")
+        .header("accept", "application/problem+json");
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = json_body(&response);
+    assert_eq!(body["type"], "/problems/not-found");
+    assert_eq!(body["status"], 404);
+}
+
+#[tokio::test]
+async fn errors_carry_the_rejected_value_and_the_params_of_their_rule() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = "w".into();
+    request["pets"][0]["name"] = "Chip the very good boy".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+
+    let pet_name = error_for(&body, "pets[0].name");
+    assert_eq!(pet_name["code"], "length_max");
+    assert_eq!(pet_name["params"]["max"], 20);
+    assert_eq!(pet_name["params"]["length"], 22);
+    assert_eq!(pet_name["rejected_value"], "Chip the very good boy");
+
+    let street = error_for(&body, "address.street");
+    assert_eq!(street["params"]["min"], 2);
+    assert_eq!(street["rejected_value"], "w");
+}
+
+#[tokio::test]
+async fn rejected_password_is_not_echoed() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["password"] = "short1".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    let password = error_for(&body, "password");
+    assert_eq!(password["code"], "password_too_short");
+    assert_eq!(password["params"], json!({ "min": 12 }));
+    assert!(password["rejected_value"].is_null());
+    assert!(!body.to_string().contains("short1"), "{}", body);
+}
@@ -0,0 +1,179 @@
+//! `ETag`s on `GET /creates/{id}` and `GET /creates`, and the `304 Not Modified` a matching
+//! `If-None-Match` gets.
+
+mod common;
+
+use bytes::Bytes;
+use common::{json_body, post_json, routes, routes_with_env, send, valid_create};
+use example_rust_json_input_validation::etag::{if_none_match, strong_etag, weak_etag};
+use std::convert::Infallible;
+use warp::http::{Response, StatusCode};
+use warp::Filter;
+
+/// `GET` of `path`, with `If-None-Match: <tags>` if given.
+async fn get<F>(routes: &F, path: &str, tags: Option<&str>) -> Response<Bytes>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    let mut request = warp::test::request().path(path);
+    if let Some(tags) = tags {
+        request = request.header("if-none-match", tags);
+    }
+    send(routes, request).await
+}
+
+fn etag(response: &Response<Bytes>) -> String {
+    response.headers()["etag"].to_str().unwrap().to_owned()
+}
+
+/// Creates `valid_create` for `email` and answers where it was stored.
+async fn create<F>(routes: &F, email: &str) -> String
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    let mut request = valid_create();
+    request["email"] = email.into();
+    let response = post_json(routes, "/create-validator", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    response.headers()["location"].to_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn stored_create_has_a_strong_tag_of_its_body() {
+    let routes = routes();
+    let location = create(&routes, "chip@example.com").await;
+    let response = get(&routes, &location, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let tag = etag(&response);
+    assert!(!tag.starts_with("W/"), "{}", tag);
+    assert_eq!(tag, strong_etag(response.body()));
+    assert_eq!(json_body(&response)["email"], "chip@example.com");
+
+    let again = get(&routes, &location, None).await;
+    assert_eq!(etag(&again), tag);
+}
+
+#[tokio::test]
+async fn matching_if_none_match_gets_an_empty_304_with_the_tag() {
+    let routes = routes();
+    let location = create(&routes, "chip@example.com").await;
+    let tag = etag(&get(&routes, &location, None).await);
+    let weak = format!("W/{}", tag);
+    let listed = format!("\"other\", {}, \"more\"", tag);
+    for tags in [tag.as_str(), weak.as_str(), "*", listed.as_str()] {
+        let response = get(&routes, &location, Some(tags)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", tags);
+        assert!(response.body().is_empty(), "{}", tags);
+        assert_eq!(etag(&response), tag, "{}", tags);
+    }
+}
+
+#[tokio::test]
+async fn mismatching_or_malformed_if_none_match_gets_the_body() {
+    let routes = routes();
+    let location = create(&routes, "chip@example.com").await;
+    let tag = etag(&get(&routes, &location, None).await);
+    let unquoted = tag.trim_matches('"').to_owned();
+    let broken_list = format!("{}, nonsense", tag);
+    for tags in [
+        "\"other\"",
+        unquoted.as_str(),
+        broken_list.as_str(),
+        "",
+        "W/",
+    ] {
+        let response = get(&routes, &location, Some(tags)).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", tags);
+        assert_eq!(etag(&response), tag, "{:?}", tags);
+        assert_eq!(json_body(&response)["email"], "chip@example.com");
+    }
+}
+
+/// Nothing updates a stored create, a re-create is a new one with a body and tag of its own.
+#[tokio::test]
+async fn recreated_body_gets_a_new_tag_and_the_old_one_no_longer_matches() {
+    let routes = routes();
+    let first = create(&routes, "chip@example.com").await;
+    let old = etag(&get(&routes, &first, None).await);
+    let second = create(&routes, "chip2@example.com").await;
+    let response = get(&routes, &second, Some(&old)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(etag(&response), old);
+    assert_eq!(json_body(&response)["email"], "chip2@example.com");
+}
+
+#[tokio::test]
+async fn list_has_a_weak_tag_that_changes_with_an_insert() {
+    let routes = routes();
+    create(&routes, "chip@example.com").await;
+    let response = get(&routes, "/creates", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let tag = etag(&response);
+    assert!(tag.starts_with("W/\""), "{}", tag);
+    assert_eq!(
+        get(&routes, "/creates", Some(&tag)).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+
+    create(&routes, "chip2@example.com").await;
+    let response = get(&routes, "/creates", Some(&tag)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(etag(&response), tag);
+    assert_eq!(json_body(&response).as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn list_pages_have_tags_of_their_own() {
+    let routes = routes();
+    create(&routes, "chip@example.com").await;
+    let first = etag(&get(&routes, "/creates?limit=1", None).await);
+    let second = get(&routes, "/creates?limit=1&offset=1", Some(&first)).await;
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_ne!(etag(&second), first);
+}
+
+#[tokio::test]
+async fn missing_create_is_not_found_whatever_the_tags() {
+    let routes = routes();
+    let path = format!("/creates/{}", uuid::Uuid::new_v4());
+    let response = get(&routes, &path, Some("*")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn cors_lets_browsers_send_if_none_match_and_read_the_tag() {
+    const APP: &str = "https://app.example.com";
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let location = create(&routes, "chip@example.com").await;
+    let request = warp::test::request().path(&location).header("origin", APP);
+    let response = send(&routes, request).await;
+    let exposed = response.headers()["access-control-expose-headers"]
+        .to_str()
+        .unwrap();
+    assert!(exposed.split(", ").any(|h| h == "etag"), "{}", exposed);
+
+    let preflight = warp::test::request()
+        .method("OPTIONS")
+        .path(&location)
+        .header("origin", APP)
+        .header("access-control-request-method", "GET")
+        .header("access-control-request-headers", "if-none-match");
+    let response = send(&routes, preflight).await;
+    let allowed = response.headers()["access-control-allow-headers"]
+        .to_str()
+        .unwrap();
+    assert!(
+        allowed.split(", ").any(|h| h == "if-none-match"),
+        "{}",
+        allowed
+    );
+}
+
+#[test]
+fn strong_tag_changes_with_any_byte_of_the_body() {
+    let body = br#"{"email":"chip@example.com"}"#;
+    let tag = strong_etag(body);
+    assert_eq!(tag, strong_etag(body));
+    assert_ne!(tag, strong_etag(br#"{"email":"chiq@example.com"}"#));
+    assert!(if_none_match(Some(&weak_etag(tag.trim_matches('"'))), &tag));
+}
@@ -0,0 +1,38 @@
+//! `POST /create-fast`, the route the benchmarks justified: a body deserialized straight from
+//! its bytes answers like `/create-path`.
+
+mod common;
+
+use common::{field_codes, json_body, post_json, post_raw, routes, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn valid_body_is_created() {
+    let response = post_json(&routes(), "/create-fast", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["email"], "chip@example.com");
+}
+
+#[tokio::test]
+async fn invalid_body_gets_the_errors_of_create_path() {
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["pets"] = json!([{ "name": "C" }]);
+    let fast = json_body(&post_json(&routes(), "/create-fast", &request).await);
+    let path = json_body(&post_json(&routes(), "/create-path", &request).await);
+    assert_eq!(field_codes(&fast), field_codes(&path));
+    assert_eq!(fast["errors"], path["errors"]);
+}
+
+#[tokio::test]
+async fn malformed_body_names_its_path_like_create_path() {
+    let body = r#"{"email":"chip@example.com","pets":[{"name":7}]}"#;
+    let fast = post_raw(&routes(), "/create-fast", body).await;
+    assert_eq!(fast.status(), StatusCode::BAD_REQUEST);
+    let fast = json_body(&fast);
+    let path = json_body(&post_raw(&routes(), "/create-path", body).await);
+    assert_eq!(fast["path"], "pets[0].name");
+    assert_eq!(fast["message"], path["message"]);
+    assert_eq!(fast["segments"], path["segments"]);
+}
@@ -0,0 +1,125 @@
+//! The fields of the owner in a create request and how each one is checked.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::Value;
+use warp::http::StatusCode;
+
+fn with_bio(bio: &str) -> Value {
+    let mut request = valid_create();
+    request["bio"] = bio.into();
+    request
+}
+
+#[tokio::test]
+async fn script_tag_in_bio_is_rejected() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_bio("hi <script>alert(1)</script>"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("bio", "html.not_allowed")]));
+    assert_eq!(
+        error_for(&body, "bio")["field_errors"][0],
+        "bio must not contain HTML"
+    );
+}
+
+#[tokio::test]
+async fn comparison_in_bio_is_not_html() {
+    let routes = routes();
+    for bio in &["a < b", "x <3 cats", "1 </ 2"] {
+        let response = post_json(&routes, "/create-path", &with_bio(bio)).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", bio);
+    }
+}
+
+#[tokio::test]
+async fn tag_like_text_in_bio_is_rejected() {
+    let routes = routes();
+    for bio in &["a<b", "</div>", "<!-- hidden -->"] {
+        let response = post_json(&routes, "/create-path", &with_bio(bio)).await;
+        let body = json_body(&response);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("bio", "html.not_allowed")]),
+            "{}",
+            bio
+        );
+    }
+}
+
+#[tokio::test]
+async fn plain_text_bio_is_accepted() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_bio("Chip's owner, loves walks"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+fn with_address_phone(phone: &str) -> Value {
+    let mut request = valid_create();
+    request["address"]["phone"] = phone.into();
+    request
+}
+
+#[tokio::test]
+async fn address_without_a_phone_is_fine() {
+    let routes = routes();
+    let response = post_json(&routes, "/echo", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(json_body(&response)["address"]["phone"].is_null());
+}
+
+#[tokio::test]
+async fn e164_address_phone_is_accepted() {
+    let routes = routes();
+    let response = post_json(&routes, "/echo", &with_address_phone("+4915112345678")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["address"]["phone"], "+4915112345678");
+}
+
+#[tokio::test]
+async fn national_address_phone_is_accepted_once_normalized() {
+    let routes = routes();
+    for phone in &["0151 1234 5678", "0151-1234-5678"] {
+        let response = post_json(&routes, "/echo", &with_address_phone(phone)).await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", phone);
+        assert_eq!(
+            json_body(&response)["address"]["phone"],
+            "015112345678",
+            "{}",
+            phone
+        );
+    }
+}
+
+#[tokio::test]
+async fn address_phone_with_letters_is_rejected_on_its_path() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_address_phone("call-me-maybe"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.phone", "phone.invalid")])
+    );
+    assert_eq!(
+        error_for(&body, "address.phone")["field_errors"][0],
+        "phone must be a phone number like +4915112345678 or 0151 12345678"
+    );
+}
@@ -0,0 +1,124 @@
+//! Error formatters: the same rejections shaped by the default formatter, the flat one and one
+//! of a service's own.
+
+mod common;
+
+use common::{invalid_create, json_body, post_json, post_raw, routes_with_formatter, with_env};
+use example_rust_json_input_validation::error::{
+    DefaultErrorFormatter, Error, ErrorBody, ErrorContext, ErrorFormatter, FlatErrorFormatter,
+};
+use serde_json::json;
+use std::sync::Arc;
+use validator::ValidationErrors;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn default_formatter_answers_with_the_error_response() {
+    let routes = routes_with_formatter(Arc::new(DefaultErrorFormatter));
+    let response = post_json(&routes, "/create-validator", &invalid_create()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "field errors");
+    assert_eq!(body["errors"][0]["field"], "email");
+    assert_eq!(body["errors"][1]["field"], "address.street");
+    assert!(body["request_id"].is_string());
+}
+
+#[tokio::test]
+async fn flat_formatter_maps_fields_to_their_messages() {
+    let routes = routes_with_formatter(Arc::new(FlatErrorFormatter));
+    let response = post_json(&routes, "/create-validator", &invalid_create()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert!(response.headers().contains_key("x-request-id"));
+    assert_eq!(
+        json_body(&response),
+        json!({ "errors": {
+            "address.street": ["street must be between 2 and 10 characters"],
+            "email": ["chip is not a valid email address"],
+        } })
+    );
+}
+
+#[tokio::test]
+async fn flat_formatter_puts_parse_errors_on_their_path() {
+    let routes = routes_with_formatter(Arc::new(FlatErrorFormatter));
+    let response = post_raw(&routes, "/create-path", r#"{"email":1}"#).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(&response),
+        json!({ "errors": { "email": [
+            "JSON path error: email: invalid type: integer `1`, expected a string at line 1 column 10"
+        ] } })
+    );
+
+    let request = warp::test::request().method("GET").path("/nope");
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        json_body(&response),
+        json!({ "errors": { "": ["Not Found"] } })
+    );
+}
+
+#[tokio::test]
+async fn error_formatter_selects_the_flat_formatter() {
+    let routes = with_env(&[("ERROR_FORMATTER", "flat")], || {
+        example_rust_json_input_validation::routes()
+    });
+    let response = post_json(&routes, "/create-validator", &invalid_create()).await;
+    assert!(json_body(&response)["errors"]["email"].is_array());
+}
+
+/// Answers everything with a `418` naming the kind of error, to show any formatter is used.
+struct Teapot;
+
+impl ErrorFormatter for Teapot {
+    fn format_validation(
+        &self,
+        errors: &ValidationErrors,
+        _: &ErrorContext,
+    ) -> (StatusCode, ErrorBody) {
+        let mut fields: Vec<&str> = errors.errors().keys().copied().collect();
+        fields.sort_unstable();
+        (
+            StatusCode::IM_A_TEAPOT,
+            ErrorBody::json(&json!({ "kind": "validation", "fields": fields })),
+        )
+    }
+
+    fn format_parse(&self, error: &Error, _: &ErrorContext) -> (StatusCode, ErrorBody) {
+        (
+            StatusCode::IM_A_TEAPOT,
+            ErrorBody::json(&json!({ "kind": "parse", "code": error.code() })),
+        )
+    }
+
+    fn format_other(&self, _: &ErrorContext) -> (StatusCode, ErrorBody) {
+        (
+            StatusCode::IM_A_TEAPOT,
+            ErrorBody::json(&json!({ "kind": "other" })),
+        )
+    }
+}
+
+#[tokio::test]
+async fn custom_formatter_gets_each_kind_of_error() {
+    let routes = routes_with_formatter(Arc::new(Teapot));
+    let response = post_json(&routes, "/create-validator", &invalid_create()).await;
+    assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+    assert_eq!(
+        json_body(&response),
+        json!({ "kind": "validation", "fields": ["address", "email"] })
+    );
+
+    let response = post_raw(&routes, "/create-path", r#"{"email":1}"#).await;
+    assert_eq!(
+        json_body(&response),
+        json!({ "kind": "parse", "code": "json_path_error" })
+    );
+
+    let request = warp::test::request().method("GET").path("/nope");
+    let response = common::send(&routes, request).await;
+    assert_eq!(json_body(&response), json!({ "kind": "other" }));
+}
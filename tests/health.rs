@@ -0,0 +1,73 @@
+//! `GET /health/live` and `GET /health/ready`, answered ahead of the API key and rate limit
+//! checks of the other routes.
+
+mod common;
+
+use common::{json_body, routes_with_env, routes_with_readiness, send};
+use example_rust_json_input_validation::health::{Readiness, STARTUP_COMPONENTS};
+use serde_json::json;
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+/// Building the routes marks the startup components ready, so these stand in for tasks that
+/// finish later.
+const LATE_COMPONENTS: &[&str] = &["warm_cache", "migrations"];
+
+fn get(path: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("GET")
+        .path(path)
+        .remote_addr(([10, 0, 0, 1], 4000).into())
+}
+
+#[tokio::test]
+async fn liveness_is_ok_even_while_starting_up() {
+    let readiness = Arc::new(Readiness::new(LATE_COMPONENTS));
+    let routes = routes_with_readiness(readiness);
+    let response = send(&routes, get("/health/live")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(response.body().as_ref(), br#"{"status":"ok"}"#);
+}
+
+#[tokio::test]
+async fn readiness_is_503_until_every_component_is_ready() {
+    let readiness = Arc::new(Readiness::new(LATE_COMPONENTS));
+    let routes = routes_with_readiness(readiness.clone());
+    let response = send(&routes, get("/health/ready")).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        json_body(&response),
+        json!({ "status": "unavailable", "unready": ["migrations", "warm_cache"] })
+    );
+
+    readiness.mark_ready("migrations");
+    let response = send(&routes, get("/health/ready")).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json_body(&response)["unready"], json!(["warm_cache"]));
+
+    readiness.mark_ready("warm_cache");
+    let response = send(&routes, get("/health/ready")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response), json!({ "status": "ok" }));
+}
+
+#[tokio::test]
+async fn startup_components_are_ready_once_the_routes_are_built() {
+    let routes = routes_with_readiness(Arc::new(Readiness::new(STARTUP_COMPONENTS)));
+    let response = send(&routes, get("/health/ready")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn health_needs_no_api_key_and_is_not_rate_limited() {
+    let routes = routes_with_env(&[("API_KEYS", "good-key"), ("RATE_LIMIT_PER_MINUTE", "1")]);
+    for _ in 0..3 {
+        for path in &["/health/live", "/health/ready"] {
+            let response = send(&routes, get(path)).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        }
+    }
+    let response = send(&routes, get("/creates")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
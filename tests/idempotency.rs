@@ -0,0 +1,86 @@
+//! `Idempotency-Key` on `/create-validator`: repeats of a successful create get the original
+//! response, other requests under the same key a `422`.
+
+mod common;
+
+use common::{json_body, routes, routes_with_env, send, valid_create};
+use serde_json::Value;
+use warp::http::StatusCode;
+use warp::test::RequestBuilder;
+
+const KEY: &str = "6f1c2d3e-4b5a-4c7d-8e9f-0a1b2c3d4e5f";
+
+fn create_with_key(key: &str, body: &Value) -> RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .header("idempotency-key", key)
+        .body(body.to_string())
+}
+
+fn other_create() -> Value {
+    let mut request = valid_create();
+    request["email"] = "nacho@example.com".into();
+    request
+}
+
+#[tokio::test]
+async fn repeat_gets_the_original_response() {
+    let routes = routes();
+    let first = send(&routes, create_with_key(KEY, &valid_create())).await;
+    assert_eq!(first.status(), StatusCode::CREATED);
+    assert!(first.headers().get("idempotency-replayed").is_none());
+
+    let replay = send(&routes, create_with_key(KEY, &valid_create())).await;
+    assert_eq!(replay.status(), StatusCode::CREATED);
+    assert_eq!(replay.headers()["idempotency-replayed"], "true");
+    assert_eq!(replay.body(), first.body());
+}
+
+#[tokio::test]
+async fn different_request_under_a_used_key_is_unprocessable() {
+    let routes = routes();
+    send(&routes, create_with_key(KEY, &valid_create())).await;
+    let response = send(&routes, create_with_key(KEY, &other_create())).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        json_body(&response)["message"],
+        format!(
+            "Idempotency-Key {} was already used for a different request",
+            KEY
+        )
+    );
+}
+
+#[tokio::test]
+async fn expired_key_is_free_again() {
+    let routes = routes_with_env(&[("IDEMPOTENCY_TTL_SECS", "0")]);
+    send(&routes, create_with_key(KEY, &valid_create())).await;
+    let response = send(&routes, create_with_key(KEY, &other_create())).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(response.headers().get("idempotency-replayed").is_none());
+    assert_eq!(json_body(&response)["email"], "nacho@example.com");
+}
+
+#[tokio::test]
+async fn failed_request_is_not_remembered() {
+    let routes = routes();
+    let mut invalid = valid_create();
+    invalid["email"] = "chip".into();
+    let response = send(&routes, create_with_key(KEY, &invalid)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = send(&routes, create_with_key(KEY, &other_create())).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn key_that_is_no_uuid_is_a_bad_request() {
+    let routes = routes();
+    let response = send(&routes, create_with_key("nope", &valid_create())).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(&response)["message"],
+        r#"Idempotency-Key must be a UUID, got "nope""#
+    );
+}
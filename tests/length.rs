@@ -0,0 +1,110 @@
+//! How the lengths of street and pet names are counted: in grapheme clusters, what a reader
+//! sees as one character, by default.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+/// Three family emoji, each five code points joined with zero width joiners.
+const THREE_FAMILIES: &str = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\
+                              \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\
+                              \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+
+fn with_pet_name(name: &str) -> Value {
+    let mut request = valid_create();
+    request["pets"] = json!([{ "name": name }]);
+    request
+}
+
+fn with_street(street: &str) -> Value {
+    let mut request = valid_create();
+    request["address"]["street"] = street.into();
+    request
+}
+
+#[tokio::test]
+async fn zwj_sequences_count_as_one_character_each() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-path", &with_street(THREE_FAMILIES)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let one_family: String = THREE_FAMILIES.chars().take(5).collect();
+    let response = post_json(&routes, "/create-path", &with_street(&one_family)).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_min")])
+    );
+    let params = &error_for(&body, "address.street")["params"];
+    assert_eq!(params["length"], 1);
+    assert_eq!(params["mode"], "graphemes");
+}
+
+#[tokio::test]
+async fn combining_accents_count_in_pet_names() {
+    let routes = routes();
+    // Three letters, four code points
+    let response = post_json(&routes, "/create-path", &with_pet_name("Zoe\u{308}")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Two letters, three code points, one short of the minimum
+    let response = post_json(&routes, "/create-path", &with_pet_name("Jo\u{308}")).await;
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets[0].name", "length_min")]));
+    assert_eq!(error_for(&body, "pets[0].name")["params"]["length"], 2);
+}
+
+#[tokio::test]
+async fn combining_accents_belong_to_their_letter() {
+    let routes = routes();
+    // Ten letters, eleven code points: the U carries a combining diaeresis
+    let street = "U\u{308}berstra\u{df}e";
+    let response = post_json(&routes, "/create-path", &with_street(street)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_street(&format!("{}n", street)),
+    )
+    .await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_max")])
+    );
+    assert_eq!(error_for(&body, "address.street")["params"]["length"], 11);
+}
+
+#[tokio::test]
+async fn ascii_counts_one_per_byte() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-path", &with_street("abcdefghij")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = post_json(&routes, "/create-path", &with_street("abcdefghijk")).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_max")])
+    );
+    let params = &error_for(&body, "address.street")["params"];
+    assert_eq!(params["length"], 11);
+    assert_eq!(params["max"], 10);
+}
+
+#[tokio::test]
+async fn one_sided_bounds_get_their_own_message() {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("GET")
+        .path("/creates?offset=20000");
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    let error = error_for(&body, "offset");
+    assert_eq!(error["code"], "range_max");
+    assert_eq!(error["field_errors"][0], "offset must be at most 10000");
+}
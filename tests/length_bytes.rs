@@ -0,0 +1,43 @@
+//! Lengths counted in UTF-8 bytes, with `LENGTH_MODE=bytes`. The mode is read once per process,
+//! so every test of this binary sets the same one.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, valid_create};
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn routes(
+) -> impl Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible> + Clone + 'static
+{
+    std::env::set_var("LENGTH_MODE", "bytes");
+    common::routes()
+}
+
+#[tokio::test]
+async fn multi_byte_street_within_the_char_limit_is_too_many_bytes() {
+    let routes = routes();
+    let mut request = valid_create();
+    // ten code points in thirteen bytes
+    request["address"]["street"] = "\u{dc}bergr\u{f6}\u{df}en".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_max")])
+    );
+    let params = &error_for(&body, "address.street")["params"];
+    assert_eq!(params["length"], 13);
+    assert_eq!(params["max"], 10);
+    assert_eq!(params["mode"], "bytes");
+}
+
+#[tokio::test]
+async fn ascii_street_counts_the_same_in_bytes() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = "abcdefghij".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
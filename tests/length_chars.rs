@@ -0,0 +1,55 @@
+//! Lengths counted in code points, with `LENGTH_MODE=chars`. The mode is read once per process,
+//! so every test of this binary sets the same one.
+
+mod common;
+
+use common::{json_body, post_json, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::Filter;
+
+fn routes(
+) -> impl Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible> + Clone + 'static
+{
+    std::env::set_var("LENGTH_MODE", "chars");
+    common::routes()
+}
+
+#[tokio::test]
+async fn decomposed_name_is_counted_after_composing_it() {
+    let routes = routes();
+    // 22 code points as sent, above the maximum of 20, and 11 once composed
+    let decomposed = "e\u{301}".repeat(11);
+    let mut request = valid_create();
+    request["pets"] = json!([{ "name": decomposed }]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["pets"][0]["name"], "\u{e9}".repeat(11));
+}
+
+/// Ten code points in thirteen bytes.
+const UMLAUT_STREET: &str = "\u{dc}bergr\u{f6}\u{df}en";
+
+#[tokio::test]
+async fn multi_byte_street_within_the_char_limit_passes() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = UMLAUT_STREET.into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn zwj_family_counts_each_code_point() {
+    let routes = routes();
+    let mut request = valid_create();
+    // two families of five code points each and one more character
+    request["address"]["street"] =
+        "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}x"
+            .into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    let params = &common::error_for(&body, "address.street")["params"];
+    assert_eq!(params["length"], 11);
+    assert_eq!(params["mode"], "chars");
+}
@@ -0,0 +1,72 @@
+//! Lenient JSON on `/create-validator`: comments and trailing commas are allowed with
+//! `?lenient=true` or `application/json5`, and rejected by the default strict parsing.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, routes, send};
+use warp::http::StatusCode;
+
+const COMMENTED: &str = "{
+  // the owner
+  \"email\": \"chip@example.com\",
+  \"address\": { \"street\": \"warpstreet\", \"streetNo\": 1, },
+  /* pets */ \"pets\": [{ \"name\": \"Chip\" },],
+}";
+
+async fn create(path: &str, content_type: &str, body: &str) -> warp::http::Response<bytes::Bytes> {
+    let request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", content_type)
+        .body(body);
+    send(&routes(), request).await
+}
+
+#[tokio::test]
+async fn strict_parsing_rejects_comments() {
+    let response = create("/create-validator", "application/json", COMMENTED).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "syntax");
+    assert_eq!(body["line"], 2);
+    assert_eq!(body["column"], 3);
+}
+
+#[tokio::test]
+async fn lenient_parsing_accepts_comments_and_trailing_commas() {
+    for (path, content_type) in &[
+        ("/create-validator?lenient=true", "application/json"),
+        ("/create-validator", "application/json5"),
+    ] {
+        let response = create(path, content_type, COMMENTED).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::CREATED,
+            "{} {}",
+            path,
+            content_type
+        );
+        assert_eq!(json_body(&response)["email"], "chip@example.com");
+    }
+}
+
+#[tokio::test]
+async fn lenient_body_is_still_validated() {
+    let body = COMMENTED.replace("chip@example.com", "chip");
+    let response = create("/create-validator?lenient=true", "application/json", &body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("email", "email.invalid")])
+    );
+}
+
+#[tokio::test]
+async fn lenient_syntax_errors_point_into_the_original_body() {
+    let body = "{\n  // the owner\n  \"email\": \"chip@example.com\" \"x\",\n}";
+    let response = create("/create-validator?lenient=true", "application/json", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["line"], 3);
+    assert_eq!(body["column"], 31);
+}
@@ -0,0 +1,49 @@
+//! The library's public entry points, used the way another service would use them.
+
+use example_rust_json_input_validation::error::{validation_field_errors, Language};
+use example_rust_json_input_validation::models::CreateRequest;
+use example_rust_json_input_validation::routes;
+use validator::Validate;
+use warp::http::StatusCode;
+
+const INVALID_EMAIL: &str =
+    r#"{"email":"chip","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+
+#[tokio::test]
+async fn routes_answer_an_invalid_create_byte_for_byte() {
+    let response = warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("content-type", "application/json")
+        .body(INVALID_EMAIL)
+        .reply(&routes())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    let expected = format!(
+        concat!(
+            r#"{{"message":"field errors","errors":[{{"field":"email","severity":"error","#,
+            r#""code":"email.invalid","field_errors":["chip is not a valid email address"],"#,
+            r#""params":{{}},"rejected_value":"chip","docs_url":"/docs/validation#email"}}],"#,
+            r#""docs_url":"/docs/validation#email","failed_sections":["email"],"#,
+            r#""request_id":"{}"}}"#
+        ),
+        request_id
+    );
+    assert_eq!(String::from_utf8_lossy(response.body()), expected);
+}
+
+#[test]
+fn validation_errors_convert_outside_the_routes() {
+    let request: CreateRequest = serde_json::from_str(INVALID_EMAIL).unwrap();
+    let errors = request.validate().unwrap_err();
+    let field_errors = validation_field_errors(&errors, Language::En);
+    assert_eq!(field_errors.len(), 1);
+    assert_eq!(field_errors[0].field, "email");
+    assert_eq!(field_errors[0].code, "email.invalid");
+    assert_eq!(
+        field_errors[0].field_errors,
+        ["chip is not a valid email address"]
+    );
+}
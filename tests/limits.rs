@@ -0,0 +1,88 @@
+//! The limits on the size and shape of request bodies.
+
+mod common;
+
+use common::{json_body, post_raw, routes_with_env, valid_create};
+use serde_json::json;
+use warp::http::StatusCode;
+
+/// `valid_create` padded with trailing whitespace to `len` bytes.
+fn create_of_len(len: usize) -> String {
+    let body = valid_create().to_string();
+    format!("{:len$}", body, len = len)
+}
+
+#[tokio::test]
+async fn body_accepted_on_the_batch_route_is_too_large_for_a_single_create() {
+    let routes = routes_with_env(&[
+        ("BODY_LIMIT_BYTES", "200"),
+        ("BODY_LIMIT_BYTES_CREATE_BATCH", "4096"),
+    ]);
+    let batch = json!([valid_create(), valid_create()]).to_string();
+    assert!(batch.len() > 200);
+    let response = post_raw(&routes, "/create-batch", &batch).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+
+    let response = post_raw(&routes, "/create-validator", &create_of_len(batch.len())).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        json_body(&response)["message"],
+        "payload too large: the body exceeds the 200 byte limit of /create-validator"
+    );
+}
+
+#[tokio::test]
+async fn body_at_the_limit_is_accepted_and_one_byte_more_is_a_json_413() {
+    let routes = routes_with_env(&[("BODY_LIMIT_BYTES_CREATE_VALIDATOR", "300")]);
+    let response = post_raw(&routes, "/create-validator", &create_of_len(300)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = post_raw(&routes, "/create-validator", &create_of_len(301)).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "payload too large: the body exceeds the 300 byte limit of /create-validator"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#payload-too-large");
+    assert!(body["request_id"].is_string());
+}
+
+#[tokio::test]
+async fn thousand_nested_arrays_are_too_deep() {
+    let routes = routes_with_env(&[]);
+    let nested = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+    for path in &["/create-path", "/create-validator", "/create-batch"] {
+        let response = post_raw(&routes, path, &nested).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            body["message"], "JSON nesting too deep (max 128)",
+            "{}",
+            path
+        );
+        assert_eq!(
+            body["docs_url"], "/docs/validation#json-too-deep",
+            "{}",
+            path
+        );
+        assert!(body["errors"].is_null(), "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn nesting_up_to_the_limit_is_parsed() {
+    let routes = routes_with_env(&[]);
+    let response = post_raw(&routes, "/create-path", &valid_create().to_string()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // 128 levels aren't too deep, they're just not a create request.
+    let nested = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    let response = post_raw(&routes, "/create-path", &nested).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_ne!(
+        json_body(&response)["docs_url"],
+        "/docs/validation#json-too-deep"
+    );
+}
@@ -0,0 +1,101 @@
+//! `GET /metrics`: request, deserialization failure and per-field validation failure counters.
+
+mod common;
+
+use common::{post_json, post_raw, routes, send, valid_create};
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// The value of the `series` line of the metrics `routes` render, 0 if it's missing.
+async fn counter<F>(routes: &F, series: &str) -> u64
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + 'static,
+{
+    let response = send(routes, warp::test::request().path("/metrics")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let metrics = String::from_utf8_lossy(response.body()).into_owned();
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map_or(0, |value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn validation_failures_are_counted_per_field() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    post_json(&routes, "/create-basic", &request).await;
+    request["address"]["street"] = "w".into();
+    request["pets"][0]["name"] = "C".into();
+    post_json(&routes, "/create-path", &request).await;
+
+    let email = r#"validation_failures_total{field="email"}"#;
+    assert_eq!(counter(&routes, email).await, 2);
+    let street = r#"validation_failures_total{field="address.street"}"#;
+    assert_eq!(counter(&routes, street).await, 1);
+    let pet_name = r#"validation_failures_total{field="pets[*].name"}"#;
+    assert_eq!(counter(&routes, pet_name).await, 1);
+    let street_no = r#"validation_failures_total{field="address.streetNo"}"#;
+    assert_eq!(counter(&routes, street_no).await, 0);
+}
+
+#[tokio::test]
+async fn deserialization_failures_are_counted() {
+    let routes = routes();
+    assert_eq!(counter(&routes, "deserialization_failures_total").await, 0);
+    post_raw(&routes, "/create-basic", "{").await;
+    post_raw(&routes, "/create-path", r#"{"email":1}"#).await;
+    assert_eq!(counter(&routes, "deserialization_failures_total").await, 2);
+}
+
+#[tokio::test]
+async fn requests_are_counted_per_endpoint() {
+    let routes = routes();
+    for _ in 0..3 {
+        post_json(&routes, "/create-basic", &valid_create()).await;
+    }
+    post_raw(&routes, "/create-validator", "{").await;
+    let basic = r#"requests_total{endpoint="/create-basic"}"#;
+    assert_eq!(counter(&routes, basic).await, 3);
+    let validator = r#"requests_total{endpoint="/create-validator"}"#;
+    assert_eq!(counter(&routes, validator).await, 1);
+}
+
+#[tokio::test]
+async fn requests_with_ids_are_counted_per_route() {
+    let routes = routes();
+    for id in &["6f2c1d9e-0000-4000-8000-000000000001", "not-a-uuid"] {
+        send(
+            &routes,
+            warp::test::request().path(&format!("/creates/{}", id)),
+        )
+        .await;
+        send(
+            &routes,
+            warp::test::request().path(&format!("/pets/{}", id)),
+        )
+        .await;
+    }
+    send(
+        &routes,
+        warp::test::request().method("POST").path("/pets/import"),
+    )
+    .await;
+    send(&routes, warp::test::request().path("/wp-login.php")).await;
+
+    let creates = r#"requests_total{endpoint="/creates/{id}"}"#;
+    assert_eq!(counter(&routes, creates).await, 2);
+    let pets = r#"requests_total{endpoint="/pets/{id}"}"#;
+    assert_eq!(counter(&routes, pets).await, 2);
+    let import = r#"requests_total{endpoint="/pets/import"}"#;
+    assert_eq!(counter(&routes, import).await, 1);
+    let unmatched = r#"requests_total{endpoint="unmatched"}"#;
+    assert_eq!(counter(&routes, unmatched).await, 1);
+
+    let response = send(&routes, warp::test::request().path("/metrics")).await;
+    let metrics = String::from_utf8_lossy(response.body()).into_owned();
+    assert!(!metrics.contains("not-a-uuid"), "{}", metrics);
+}
@@ -0,0 +1,420 @@
+//! What the filters around the routes answer before a handler runs: CORS, rate limits and the
+//! other checks on headers and query strings.
+
+mod common;
+
+use common::{json_body, routes_with_env, send, valid_create};
+use example_rust_json_input_validation::filters::ApiKeys;
+use warp::http::StatusCode;
+
+const APP: &str = "https://app.example.com";
+
+fn create_from(origin: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("origin", origin)
+        .header("content-type", "application/json")
+        .body(valid_create().to_string())
+}
+
+fn preflight_from(origin: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("OPTIONS")
+        .path("/create-basic")
+        .header("origin", origin)
+        .header("access-control-request-method", "POST")
+}
+
+#[tokio::test]
+async fn disallowed_origin_gets_a_json_403() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let response = send(&routes, create_from("https://evil.example.com")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "origin https://evil.example.com is not allowed to call this API, see CORS_ALLOWED_ORIGINS"
+    );
+    assert!(body["request_id"].is_string());
+}
+
+#[tokio::test]
+async fn disallowed_preflight_gets_a_json_403() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let response = send(&routes, preflight_from("https://evil.example.com")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let message = json_body(&response)["message"].as_str().unwrap().to_owned();
+    assert!(message.contains("is not allowed"), "{}", message);
+}
+
+#[tokio::test]
+async fn preflight_for_a_disallowed_method_gets_a_json_403() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let request = preflight_from(APP).header("access-control-request-method", "DELETE");
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let message = json_body(&response)["message"].as_str().unwrap().to_owned();
+    assert!(message.contains("DELETE is not allowed"), "{}", message);
+}
+
+#[tokio::test]
+async fn allowed_origin_gets_cors_headers() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let response = send(&routes, create_from(APP)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["access-control-allow-origin"], APP);
+
+    let response = send(&routes, preflight_from(APP)).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()["access-control-allow-origin"], APP);
+}
+
+#[tokio::test]
+async fn preflight_allows_post_and_content_type_for_a_while() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let request = preflight_from(APP).header("access-control-request-headers", "content-type");
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let headers = response.headers();
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.split(", ").any(|m| m == "POST"), "{}", methods);
+    let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(
+        allowed.split(", ").any(|h| h == "content-type"),
+        "{}",
+        allowed
+    );
+    assert_eq!(headers["access-control-max-age"], "600");
+}
+
+#[tokio::test]
+async fn preflight_allows_the_headers_the_filters_read() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let request = preflight_from(APP).header("access-control-request-headers", "x-api-version");
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let allowed = response.headers()["access-control-allow-headers"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    for header in &[
+        "x-api-version",
+        "x-allow-partial",
+        "x-error-detail",
+        "digest",
+        "content-md5",
+    ] {
+        assert!(
+            allowed.split(", ").any(|h| h == *header),
+            "{} in {}",
+            header,
+            allowed
+        );
+    }
+}
+
+#[tokio::test]
+async fn validation_errors_carry_cors_headers_for_allowed_origins() {
+    let routes = routes_with_env(&[("CORS_ALLOWED_ORIGINS", APP)]);
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let request = create_from(APP).body(request.to_string());
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.headers()["access-control-allow-origin"], APP);
+    assert_eq!(json_body(&response)["errors"][0]["field"], "email");
+}
+
+#[tokio::test]
+async fn origins_are_not_checked_without_allowed_origins() {
+    let routes = routes_with_env(&[]);
+    let response = send(&routes, create_from("https://evil.example.com")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+}
+
+fn create_with(remote: [u8; 4], header: (&str, &str)) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path("/create-validator?dry_run=true")
+        .remote_addr((remote, 4000).into())
+        .header("content-type", "application/json")
+        .header(header.0, header.1)
+        .body(valid_create().to_string())
+}
+
+const RATE_LIMITED: &[(&str, &str)] = &[("RATE_LIMIT_PER_MINUTE", "2"), ("API_KEYS", "good-key")];
+
+#[tokio::test]
+async fn unknown_credentials_are_rate_limited_by_ip() {
+    let routes = routes_with_env(RATE_LIMITED);
+    for token in &["Bearer made-up-1", "Bearer made-up-2"] {
+        let response = send(
+            &routes,
+            create_with([10, 0, 0, 1], ("authorization", token)),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = send(
+        &routes,
+        create_with([10, 0, 0, 1], ("x-api-key", "made-up-3")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+
+    let response = send(
+        &routes,
+        create_with([10, 0, 0, 2], ("x-api-key", "made-up-4")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn known_keys_are_rate_limited_by_key() {
+    let routes = routes_with_env(RATE_LIMITED);
+    for remote in &[[10, 0, 1, 1], [10, 0, 1, 2]] {
+        let response = send(&routes, create_with(*remote, ("x-api-key", "good-key"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = send(
+        &routes,
+        create_with([10, 0, 1, 3], ("x-api-key", "good-key")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let response = send(
+        &routes,
+        create_with([10, 0, 1, 1], ("x-api-key", "made-up")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+fn create_from_ip(remote: [u8; 4]) -> warp::test::RequestBuilder {
+    create_with(remote, ("user-agent", "curl/8.0"))
+}
+
+#[tokio::test]
+async fn ip_past_its_limit_gets_a_429_with_retry_after() {
+    let routes = routes_with_env(&[("RATE_LIMIT_PER_MINUTE", "2")]);
+    for _ in 0..2 {
+        let response = send(&routes, create_from_ip([10, 0, 2, 1])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = send(&routes, create_from_ip([10, 0, 2, 1])).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after), "{}", retry_after);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        format!("too many requests, retry in {} seconds", retry_after)
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#rate-limit");
+
+    let response = send(&routes, create_from_ip([10, 0, 2, 2])).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn forwarded_for_is_only_trusted_when_configured() {
+    let forwarded = |ip: &str| {
+        create_from_ip([10, 0, 3, 1]).header("x-forwarded-for", format!("203.0.113.9, {}", ip))
+    };
+    let routes = routes_with_env(&[("RATE_LIMIT_PER_MINUTE", "1")]);
+    send(&routes, forwarded("198.51.100.1")).await;
+    let response = send(&routes, forwarded("198.51.100.2")).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let routes = routes_with_env(&[
+        ("RATE_LIMIT_PER_MINUTE", "1"),
+        ("RATE_LIMIT_TRUST_FORWARDED_FOR", "true"),
+    ]);
+    send(&routes, forwarded("198.51.100.1")).await;
+    let response = send(&routes, forwarded("198.51.100.2")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send(&routes, forwarded("198.51.100.1")).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn api_keys_are_read_from_the_file_next_to_the_env() {
+    let path = std::env::temp_dir().join("api-keys-from-file");
+    std::fs::write(&path, "file-key\n\n").unwrap();
+    let routes = routes_with_env(&[
+        ("API_KEYS", "env-key"),
+        ("API_KEYS_FILE", path.to_str().unwrap()),
+    ]);
+    for (key, status) in &[
+        ("env-key", StatusCode::CREATED),
+        ("file-key", StatusCode::CREATED),
+        ("other-key", StatusCode::FORBIDDEN),
+    ] {
+        let request = create_from(APP).header("x-api-key", *key);
+        assert_eq!(send(&routes, request).await.status(), *status, "{}", key);
+    }
+}
+
+#[test]
+fn unreadable_api_keys_file_is_a_config_error() {
+    let path = "/nonexistent/api-keys";
+    let result = common::with_env(&[("API_KEYS_FILE", path)], ApiKeys::from_env);
+    let message = result.err().expect("API_KEYS_FILE was read").to_string();
+    assert!(
+        message.starts_with("failed to read API_KEYS_FILE /nonexistent/api-keys: "),
+        "{}",
+        message
+    );
+}
+
+fn create_as(user_agent: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("user-agent", user_agent)
+        .header("content-type", "application/json")
+        .body(valid_create().to_string())
+}
+
+#[tokio::test]
+async fn denylisted_user_agent_is_forbidden() {
+    let routes = routes_with_env(&[]);
+    let response = send(&routes, create_as("sqlmap/1.7.2#stable")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "forbidden: user agent matches denylisted pattern 'sqlmap'"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#authorization");
+}
+
+#[tokio::test]
+async fn other_user_agents_are_allowed() {
+    let routes = routes_with_env(&[]);
+    let response = send(&routes, create_as("curl/8.4.0")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn user_agent_denylist_is_configurable_and_ignores_case() {
+    let routes = routes_with_env(&[("USER_AGENT_DENYLIST", "badbot, crawler")]);
+    let response = send(&routes, create_as("Mozilla/5.0 (compatible; BadBot/2.1)")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = send(&routes, create_as("sqlmap/1.7.2#stable")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+fn create_with_query(query: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("POST")
+        .path(&format!("/create-basic?{}", query))
+        .header("content-type", "application/json")
+        .body(valid_create().to_string())
+}
+
+#[tokio::test]
+async fn unknown_query_params_are_ignored_by_default() {
+    let routes = routes_with_env(&[]);
+    let (logs, _guard) = common::capture_logs();
+    let response = send(&routes, create_with_query("debug=1")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert!(!logs.contents().contains("unknown query parameters"));
+}
+
+#[tokio::test]
+async fn unknown_query_params_are_logged_in_warn_mode() {
+    let routes = routes_with_env(&[("UNKNOWN_QUERY_PARAMS", "warn")]);
+    let (logs, _guard) = common::capture_logs();
+    let response = send(&routes, create_with_query("debug=1&verbose=2")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let logs = logs.contents();
+    assert!(logs.contains("WARN"), "{}", logs);
+    assert!(
+        logs.contains("unknown query parameters: debug, verbose"),
+        "{}",
+        logs
+    );
+    assert!(logs.contains("path=/create-basic"), "{}", logs);
+}
+
+#[tokio::test]
+async fn unknown_query_params_are_rejected_in_reject_mode() {
+    let routes = routes_with_env(&[("UNKNOWN_QUERY_PARAMS", "reject")]);
+    let response = send(&routes, create_with_query("debug=1&utm_source=news")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(&response)["message"],
+        "unknown query parameters: debug"
+    );
+
+    let response = send(&routes, create_with_query("utm_source=news")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+fn create_as_version(version: Option<&str>) -> warp::test::RequestBuilder {
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("content-type", "application/json")
+        .body(valid_create().to_string());
+    match version {
+        Some(version) => request.header("x-client-version", version),
+        None => request,
+    }
+}
+
+#[tokio::test]
+async fn client_versions_are_checked_against_the_minimum() {
+    let routes = routes_with_env(&[("MIN_CLIENT_VERSION", "1.2.0")]);
+    for (version, status, code) in &[
+        (None, StatusCode::BAD_REQUEST, "client_version_missing"),
+        (
+            Some("banana"),
+            StatusCode::BAD_REQUEST,
+            "client_version_invalid",
+        ),
+        (
+            Some("1.1.9"),
+            StatusCode::UPGRADE_REQUIRED,
+            "client_version_too_old",
+        ),
+    ] {
+        let response = send(&routes, create_as_version(*version)).await;
+        assert_eq!(response.status(), *status, "{:?}", version);
+        let body = json_body(&response);
+        let error = &body["errors"][0];
+        assert_eq!(error["field"], "X-Client-Version", "{:?}", version);
+        assert_eq!(error["code"], *code, "{:?}", version);
+        assert_eq!(error["params"]["minimum"], "1.2.0", "{:?}", version);
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("1.2.0"), "{}", message);
+    }
+    for version in &["1.2.0", "2.0.0"] {
+        let response = send(&routes, create_as_version(Some(version))).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", version);
+    }
+}
+
+#[tokio::test]
+async fn client_version_is_not_checked_without_a_minimum() {
+    let routes = routes_with_env(&[]);
+    let response = send(&routes, create_as_version(None)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
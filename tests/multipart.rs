@@ -0,0 +1,140 @@
+//! `POST /create-multipart`: a create request in a `payload` part, with an optional `photo`
+//! part for the first pet.
+
+mod common;
+
+use bytes::Bytes;
+use common::{field_codes, json_body, pairs, routes_with_env, send, valid_create};
+use serde_json::json;
+use warp::http::{Response, StatusCode};
+
+const BOUNDARY: &str = "chip-boundary";
+
+/// A part named `name`, with `content_type` if given.
+fn part(name: &str, content_type: Option<&str>, body: &[u8]) -> Vec<u8> {
+    let mut part = format!(
+        "--{}\r\ncontent-disposition: form-data; name=\"{}\"\r\n",
+        BOUNDARY, name
+    )
+    .into_bytes();
+    if let Some(content_type) = content_type {
+        part.extend(format!("content-type: {}\r\n", content_type).bytes());
+    }
+    part.extend(b"\r\n");
+    part.extend(body);
+    part.extend(b"\r\n");
+    part
+}
+
+async fn post_form(parts: Vec<Vec<u8>>) -> Response<Bytes> {
+    let mut body = parts.concat();
+    body.extend(format!("--{}--\r\n", BOUNDARY).bytes());
+    let routes = routes_with_env(&[("PHOTO_MAX_BYTES", "100")]);
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-multipart")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(body);
+    send(&routes, request).await
+}
+
+fn payload() -> Vec<u8> {
+    part(
+        "payload",
+        Some("application/json"),
+        valid_create().to_string().as_bytes(),
+    )
+}
+
+#[tokio::test]
+async fn payload_and_photo_are_created_with_the_photo_reported() {
+    let response = post_form(vec![payload(), part("photo", Some("image/png"), &[1; 50])]).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = json_body(&response);
+    assert_eq!(body["email"], "chip@example.com");
+    assert_eq!(
+        body["photo"],
+        json!({ "received": true, "size": 50, "contentType": "image/png" })
+    );
+}
+
+#[tokio::test]
+async fn payload_alone_is_created_without_a_photo() {
+    let response = post_form(vec![payload()]).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["photo"], json!({ "received": false }));
+}
+
+#[tokio::test]
+async fn missing_payload_is_required() {
+    let response = post_form(vec![part("photo", Some("image/jpeg"), &[1; 50])]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("payload", "required")]));
+    assert_eq!(
+        body["errors"][0]["field_errors"][0],
+        "required: the payload part with the create request is missing"
+    );
+}
+
+#[tokio::test]
+async fn invalid_json_in_the_payload_is_a_path_error() {
+    let response = post_form(vec![part("payload", None, br#"{"email":"#)]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "eof");
+    assert_eq!(body["path"], "email");
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn invalid_payload_is_reported_on_its_fields() {
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    let response = post_form(vec![part("payload", None, request.to_string().as_bytes())]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("email", "email.invalid")])
+    );
+}
+
+#[tokio::test]
+async fn oversized_photo_is_too_large() {
+    let response = post_form(vec![payload(), part("photo", Some("image/png"), &[1; 500])]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("photo", "photo.too_large")]));
+    assert_eq!(
+        body["errors"][0]["params"],
+        json!({ "limit": 100, "size": 500 })
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#multipart");
+}
+
+#[tokio::test]
+async fn photo_of_another_type_is_unsupported() {
+    let response = post_form(vec![payload(), part("photo", Some("image/gif"), &[1; 5])]).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("photo", "photo.unsupported_type")])
+    );
+    assert_eq!(
+        body["errors"][0]["params"],
+        json!({ "allowed": ["image/png", "image/jpeg"], "received": "image/gif" })
+    );
+}
+
+#[tokio::test]
+async fn duplicate_and_unknown_parts_are_reported_together() {
+    let response = post_form(vec![payload(), payload(), part("extra", None, b"x")]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("payload", "duplicate_part"), ("extra", "unknown_field")])
+    );
+}
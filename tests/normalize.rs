@@ -0,0 +1,108 @@
+//! Normalization before validation: emails are trimmed and lowercased, streets and pet names
+//! trimmed and streets' inner runs of spaces collapsed. Validation and the answer see the
+//! normalized values.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::Value;
+use warp::http::StatusCode;
+
+fn create(email: &str, street: &str, pet_name: &str) -> Value {
+    let mut request = valid_create();
+    request["email"] = email.into();
+    request["address"]["street"] = street.into();
+    request["pets"][0]["name"] = pet_name.into();
+    request
+}
+
+#[tokio::test]
+async fn padded_values_are_validated_and_echoed_normalized() {
+    let routes = routes();
+    let request = create(" Foo@Example.COM ", "  main    st ", " Chip  ");
+    let response = post_json(&routes, "/echo", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let echoed = json_body(&response);
+    assert_eq!(echoed["email"], "foo@example.com");
+    assert_eq!(echoed["address"]["street"], "main st");
+    assert_eq!(echoed["pets"][0]["name"], "Chip");
+
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = json_body(&response);
+    assert_eq!(created["email"], "foo@example.com");
+    assert_eq!(created["pets"][0]["name"], "Chip");
+}
+
+/// `"ab "` is two characters once trimmed, just enough for a street.
+#[tokio::test]
+async fn street_is_measured_after_trimming() {
+    let routes = routes();
+    let response = post_json(&routes, "/echo", &create("chip@example.com", "ab ", "Chip")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["address"]["street"], "ab");
+
+    let response = post_json(&routes, "/echo", &create("chip@example.com", " a ", "Chip")).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_min")])
+    );
+    assert_eq!(body["errors"][0]["rejected_value"], "a");
+    assert_eq!(body["errors"][0]["params"]["length"], 1);
+}
+
+/// Collapsing only removes spaces, so a street that fits once collapsed stays accepted and one
+/// that doesn't is still too long.
+#[tokio::test]
+async fn street_is_measured_after_collapsing_spaces() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/echo",
+        &create("chip@example.com", "warp    st", "Chip"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["address"]["street"], "warp st");
+
+    let response = post_json(
+        &routes,
+        "/echo",
+        &create("chip@example.com", "warp  street", "Chip"),
+    )
+    .await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_max")])
+    );
+    assert_eq!(body["errors"][0]["rejected_value"], "warp street");
+}
+
+#[tokio::test]
+async fn pet_name_is_measured_after_trimming() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/echo",
+        &create("chip@example.com", "warpstreet", "  Ab "),
+    )
+    .await;
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets[0].name", "length_min")]));
+    assert_eq!(body["errors"][0]["rejected_value"], "Ab");
+}
+
+#[tokio::test]
+async fn padded_email_is_valid_once_trimmed() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/echo",
+        &create("  chip@example.com\t", "warpstreet", "Chip"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["email"], "chip@example.com");
+}
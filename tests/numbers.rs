@@ -0,0 +1,79 @@
+//! Street numbers serde can't read as a count: negative, fractional and too large ones are
+//! field errors on `address.streetNo`, with a code of their own, rather than serde's message.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_raw, routes};
+use warp::http::StatusCode;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator"];
+
+/// A create request with `street_no` written into the body as is.
+fn with_street_no(street_no: &str) -> String {
+    format!(
+        r#"{{"email":"chip@example.com","address":{{"street":"warpstreet","streetNo":{}}},"pets":[{{"name":"Chip"}}]}}"#,
+        street_no
+    )
+}
+
+async fn assert_street_no_error(street_no: &str, code: &str, message: &str) {
+    for path in ROUTES {
+        let response = post_raw(&routes(), path, &with_street_no(street_no)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["message"], "field errors", "{}", path);
+        assert_eq!(field_codes(&body), pairs(&[("address.streetNo", code)]));
+        let error = error_for(&body, "address.streetNo");
+        assert_eq!(error["field_errors"][0], message, "{}", path);
+        assert_eq!(error["docs_url"], "/docs/validation#numbers", "{}", path);
+        assert_eq!(body["failed_sections"], serde_json::json!(["address"]));
+    }
+}
+
+#[tokio::test]
+async fn negative_street_number_is_not_allowed() {
+    assert_street_no_error(
+        "-3",
+        "negative_not_allowed",
+        "negative_not_allowed: negative number `-3`, expected a non-negative integer",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn fractional_street_number_is_not_an_integer() {
+    assert_street_no_error(
+        "1.5",
+        "not_an_integer",
+        "not_an_integer: fractional number `1.5`, expected an integer",
+    )
+    .await;
+}
+
+/// Integers past `u64` are parsed as floats, so the number quoted is the float it became.
+#[tokio::test]
+async fn street_number_past_u64_is_out_of_range() {
+    assert_street_no_error(
+        "18446744073709551616",
+        "number_out_of_range",
+        "number_out_of_range: out of range number `18446744073709552000`, expected at most \
+         18446744073709551615",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn whole_numbers_are_taken_however_they_are_written() {
+    for street_no in &["7", "7.0", "1e3"] {
+        for path in ROUTES {
+            let response = post_raw(&routes(), path, &with_street_no(street_no)).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::CREATED,
+                "{} {}",
+                path,
+                street_no
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Errors come in the order the fields are declared, list elements by index and the errors of
+//! one field by code, the same on every request.
+
+mod common;
+
+use common::{
+    body_without_request_id, field_codes, json_body, pairs, post_json, routes, valid_create,
+};
+use serde_json::{json, Value};
+
+/// A create request failing on every field it has.
+fn failing_everywhere() -> Value {
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["phone"] = "12".into();
+    request["address"] = json!({ "street": "w", "streetNo": 0, "phone": "1" });
+    request["pets"] = json!([
+        { "name": "Ca" },
+        { "name": "Pet b", "photoUrl": "nope" },
+        { "name": "C" },
+        { "name": "Pet d" },
+    ]);
+    request["bio"] = "<b>Chip's owner</b>".into();
+    request["password"] = "a".into();
+    request
+}
+
+#[tokio::test]
+async fn errors_are_in_declaration_then_index_then_code_order() {
+    let response = post_json(&routes(), "/create-validator", &failing_everywhere()).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[
+            ("email", "email.invalid"),
+            ("phone", "phone.invalid"),
+            ("address.street", "length_min"),
+            ("address.streetNo", "range_min"),
+            ("address.streetNo", "street_no_required"),
+            ("address.phone", "phone.invalid"),
+            ("pets[0].name", "length_min"),
+            ("pets[1].photoUrl", "url.invalid"),
+            ("pets[2].name", "length_min"),
+            ("bio", "html.not_allowed"),
+            ("password", "password_too_short"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn error_body_is_identical_ten_times_over() {
+    for path in &["/create-path", "/create-validator"] {
+        let first = post_json(&routes(), path, &failing_everywhere()).await;
+        let first = body_without_request_id(&first);
+        for _ in 0..10 {
+            // Fresh routes, so each run validates with state of its own.
+            let response = post_json(&routes(), path, &failing_everywhere()).await;
+            assert_eq!(body_without_request_id(&response), first, "{}", path);
+        }
+    }
+}
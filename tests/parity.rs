@@ -0,0 +1,175 @@
+//! create-basic, create-path and create-validator validate alike: a body that deserializes gets
+//! the same answer from all three, byte for byte but the request id.
+//!
+//! They differ on purpose only in how a body that doesn't deserialize is reported.
+//! create-basic uses `warp::body::json`, so it only gets serde's message. It finds the field of
+//! an unknown or missing key in it, but not the path, only the bare key. The other two name the
+//! path, the line and the column.
+
+mod common;
+
+use common::{
+    field_codes, json_body, pairs, post_json, post_raw, routes, valid_create, without_request_id,
+};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const CREATE_ROUTES: [&str; 3] = ["/create-basic", "/create-path", "/create-validator"];
+
+/// Invalid requests that deserialize, each failing a different kind of rule.
+fn invalid_requests() -> Vec<Value> {
+    let mut short_street = valid_create();
+    short_street["address"]["street"] = "w".into();
+    let mut several = valid_create();
+    several["email"] = "chip".into();
+    several["address"]["streetNo"] = 0.into();
+    several["pets"] = json!([{ "name": "Chip" }, { "name": "Chip2" }]);
+    let mut struct_level = valid_create();
+    struct_level["contactMethod"] = "phone".into();
+    struct_level["pets"] = json!([]);
+    let mut number_string = valid_create();
+    number_string["address"]["streetNo"] = "x".into();
+    vec![short_street, several, struct_level, number_string]
+}
+
+#[tokio::test]
+async fn invalid_requests_get_the_same_answer_from_every_route() {
+    let routes = routes();
+    for request in invalid_requests() {
+        let mut answers = Vec::new();
+        for path in CREATE_ROUTES {
+            let response = post_json(&routes, path, &request).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::BAD_REQUEST,
+                "{}: {}",
+                path,
+                request
+            );
+            answers.push(without_request_id(&response));
+        }
+        assert_eq!(answers[0], answers[1], "{}", request);
+        assert_eq!(answers[1], answers[2], "{}", request);
+        assert_eq!(answers[0]["message"], "field errors");
+    }
+}
+
+/// The street that was "valid" on create-basic before it validated.
+#[tokio::test]
+async fn one_character_street_is_rejected_by_create_basic() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["street"] = "w".into();
+    let response = post_json(&routes, "/create-basic", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("address.street", "length_min")])
+    );
+}
+
+#[tokio::test]
+async fn valid_request_is_created_by_every_route() {
+    let routes = routes();
+    for (i, path) in CREATE_ROUTES.iter().enumerate() {
+        let mut request = valid_create();
+        request["email"] = format!("chip{}@example.com", i).into();
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn unknown_field_is_reported_by_its_bare_key_on_create_basic() {
+    let routes = routes();
+    let mut top_level = valid_create();
+    top_level["emial"] = "x".into();
+    let mut in_pet = valid_create();
+    in_pet["pets"][0]["colour"] = "brown".into();
+    for (request, key, path) in [
+        (top_level, "emial", "emial"),
+        (in_pet, "colour", "pets[0].colour"),
+    ] {
+        let basic = post_json(&routes, "/create-basic", &request).await;
+        assert_eq!(basic.status(), StatusCode::BAD_REQUEST);
+        let basic = json_body(&basic);
+        assert_eq!(field_codes(&basic), pairs(&[(key, "unknown_field")]));
+        let message = basic["message"].as_str().unwrap();
+        assert!(
+            message.starts_with(&format!("unknown field `{}`", key)),
+            "{}",
+            message
+        );
+
+        for route in ["/create-path", "/create-validator"] {
+            let response = post_json(&routes, route, &request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", route);
+            let body = json_body(&response);
+            assert_eq!(
+                field_codes(&body),
+                pairs(&[(path, "unknown_field")]),
+                "{}",
+                route
+            );
+            assert_eq!(body["message"], "field errors", "{}", route);
+            assert_eq!(
+                body["errors"][0]["field_errors"],
+                basic["errors"][0]["field_errors"]
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn missing_field_is_required_on_create_basic_and_a_path_error_elsewhere() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]
+        .as_object_mut()
+        .unwrap()
+        .remove("streetNo");
+    let basic = post_json(&routes, "/create-basic", &request).await;
+    assert_eq!(basic.status(), StatusCode::BAD_REQUEST);
+    let basic = json_body(&basic);
+    assert_eq!(field_codes(&basic), pairs(&[("streetNo", "required")]));
+    assert_eq!(
+        basic["errors"][0]["field_errors"][0],
+        "required: missing field `streetNo`"
+    );
+
+    for route in ["/create-path", "/create-validator"] {
+        let response = post_json(&routes, route, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", route);
+        let body = json_body(&response);
+        assert!(body["errors"].is_null(), "{}: {}", route, body);
+        assert_eq!(body["path"], "address", "{}", route);
+        assert_eq!(body["kind"], "data", "{}", route);
+    }
+}
+
+#[tokio::test]
+async fn syntax_error_is_only_a_message_on_create_basic() {
+    let routes = routes();
+    let basic = post_raw(&routes, "/create-basic", r#"{"email":"#).await;
+    assert_eq!(basic.status(), StatusCode::BAD_REQUEST);
+    let basic = without_request_id(&basic);
+    assert_eq!(
+        basic,
+        json!({
+            "message": "EOF while parsing a value at line 1 column 9",
+            "errors": null,
+            "docs_url": "/docs/validation#malformed-body",
+        })
+    );
+
+    for route in ["/create-path", "/create-validator"] {
+        let response = post_raw(&routes, route, r#"{"email":"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", route);
+        let body = json_body(&response);
+        assert_eq!(
+            body["message"],
+            "JSON path error: email: EOF while parsing a value at line 1 column 9"
+        );
+        assert_eq!((&body["line"], &body["column"]), (&json!(1), &json!(9)));
+    }
+}
@@ -0,0 +1,216 @@
+//! `X-Allow-Partial: true` on create-validator: a request whose only errors are on some of its
+//! pets is created with the valid ones and answered `207 Multi-Status`; anything else is
+//! rejected like without the header.
+
+mod common;
+
+use bytes::Bytes;
+use common::{field_codes, json_body, pairs, routes, send, valid_create};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use warp::http::{Response, StatusCode};
+use warp::Filter;
+
+/// Posts `body` to `path`, with `X-Allow-Partial: <partial>` if given.
+async fn post<F>(routes: &F, path: &str, body: &Value, partial: Option<&str>) -> Response<Bytes>
+where
+    F: Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone + 'static,
+{
+    let mut request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json");
+    if let Some(partial) = partial {
+        request = request.header("x-allow-partial", partial);
+    }
+    send(routes, request.body(body.to_string())).await
+}
+
+/// `valid_create` for `email` with four pets, the second and the fourth invalid.
+fn mixed_pets(email: &str) -> Value {
+    let mut request = valid_create();
+    request["email"] = email.into();
+    request["pets"] = json!([
+        { "name": "Chip" },
+        { "name": "C" },
+        { "name": "Dale" },
+        { "name": "D", "photoUrl": "ftp://x" },
+    ]);
+    request
+}
+
+const MIXED_PET_ERRORS: &[(&str, &str)] = &[
+    ("pets[1].name", "length_min"),
+    ("pets[3].name", "length_min"),
+    ("pets[3].photoUrl", "url.not_an_image"),
+];
+
+fn pet_names(body: &Value) -> Vec<&str> {
+    body["pets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pet| pet["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn all_valid_pets_are_created_as_usual_with_the_header() {
+    let routes = routes();
+    let response = post(&routes, "/create-validator", &valid_create(), Some("true")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = json_body(&response);
+    assert_eq!(pet_names(&body), ["Chip"]);
+    assert!(body.get("accepted").is_none(), "{}", body);
+    assert!(body.get("rejected").is_none(), "{}", body);
+}
+
+#[tokio::test]
+async fn invalid_pets_are_dropped_with_their_errors_and_the_rest_stored() {
+    let routes = routes();
+    let response = post(
+        &routes,
+        "/create-validator",
+        &mixed_pets("chip@example.com"),
+        Some("true"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = json_body(&response);
+    assert_eq!(pet_names(&body), ["Chip", "Dale"]);
+    assert_eq!(body["accepted"], json!([0, 2]));
+
+    let rejected = body["rejected"].as_array().unwrap();
+    assert_eq!(rejected.len(), 2, "{}", body);
+    assert_eq!(rejected[0]["index"], 1);
+    assert_eq!(
+        field_codes(&rejected[0]),
+        pairs(&[("pets[1].name", "length_min")])
+    );
+    assert_eq!(
+        rejected[0]["errors"][0]["field_errors"][0],
+        "pet name must be between 3 and 20 characters"
+    );
+    assert_eq!(rejected[0]["errors"][0]["rejected_value"], "C");
+    assert_eq!(rejected[1]["index"], 3);
+    assert_eq!(
+        field_codes(&rejected[1]),
+        pairs(&[
+            ("pets[3].name", "length_min"),
+            ("pets[3].photoUrl", "url.not_an_image"),
+        ])
+    );
+
+    let location = response.headers()["location"].to_str().unwrap();
+    assert_eq!(
+        location,
+        format!("/creates/{}", body["id"].as_str().unwrap())
+    );
+    let stored = send(&routes, warp::test::request().path(location)).await;
+    assert_eq!(stored.status(), StatusCode::OK);
+    assert_eq!(pet_names(&json_body(&stored)), ["Chip", "Dale"]);
+}
+
+#[tokio::test]
+async fn invalid_pets_reject_the_whole_request_without_the_header() {
+    let routes = routes();
+    for partial in [None, Some("false"), Some("yes")] {
+        let response = post(
+            &routes,
+            "/create-validator",
+            &mixed_pets("chip@example.com"),
+            partial,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?}", partial);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(MIXED_PET_ERRORS), "{:?}", partial);
+        assert!(body.get("rejected").is_none(), "{:?}: {}", partial, body);
+    }
+}
+
+#[tokio::test]
+async fn invalid_email_rejects_the_request_with_all_errors_despite_the_header() {
+    let routes = routes();
+    let response = post(
+        &routes,
+        "/create-validator",
+        &mixed_pets("chip"),
+        Some("true"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    let mut expected = vec![("email", "email.invalid")];
+    expected.extend_from_slice(MIXED_PET_ERRORS);
+    assert_eq!(field_codes(&body), pairs(&expected));
+    assert_eq!(body["failed_sections"], json!(["email", "pets"]));
+}
+
+#[tokio::test]
+async fn invalid_address_rejects_the_request_despite_the_header() {
+    let routes = routes();
+    let mut request = mixed_pets("chip@example.com");
+    request["address"]["street"] = "w".into();
+    let response = post(&routes, "/create-validator", &request, Some("true")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body)[0],
+        ("address.street".into(), "length_min".into())
+    );
+}
+
+/// `pets_count` applies to the pets that are kept, so dropping every pet rejects the request.
+#[tokio::test]
+async fn dropping_every_pet_fails_the_pets_count_of_what_is_left() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = json!([{ "name": "C" }, { "name": "D" }]);
+    let response = post(&routes, "/create-validator", &request, Some("true")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("pets[0].name", "length_min"),
+            ("pets[1].name", "length_min")
+        ])
+    );
+
+    // Nothing was stored, so the email isn't taken
+    let response = post(&routes, "/create-validator", &valid_create(), None).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn dry_run_answers_multi_status_without_storing() {
+    let routes = routes();
+    let request = mixed_pets("dale@example.com");
+    let response = post(
+        &routes,
+        "/create-validator?dry_run=true",
+        &request,
+        Some("true"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let body = json_body(&response);
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["accepted"], json!([0, 2]));
+    assert_eq!(body["rejected"][1]["index"], 3);
+
+    let response = post(&routes, "/create-validator", &request, Some("true")).await;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+}
+
+#[tokio::test]
+async fn other_create_routes_ignore_the_header() {
+    let routes = routes();
+    for path in ["/create-path", "/create-fast"] {
+        let response = post(&routes, path, &mixed_pets("chip@example.com"), Some("true")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(MIXED_PET_ERRORS), "{}", path);
+    }
+}
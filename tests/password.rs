@@ -0,0 +1,108 @@
+//! The optional `password` of a create request: its rules, and that nothing of it is ever sent
+//! back or printed.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use example_rust_json_input_validation::models::CreateRequest;
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator", "/create-fast", "/echo"];
+
+/// `valid_create` for `fred@example.com` with `password`.
+fn with_password(password: &str) -> Value {
+    let mut request = valid_create();
+    request["email"] = "fred@example.com".into();
+    request["password"] = password.into();
+    request
+}
+
+/// Panics if `body` contains four or more consecutive characters of `password`.
+fn assert_no_fragment_of(password: &str, body: &[u8]) {
+    let body = String::from_utf8_lossy(body);
+    let chars: Vec<char> = password.chars().collect();
+    for fragment in chars.windows(4) {
+        let fragment: String = fragment.iter().collect();
+        assert!(
+            !body.contains(&fragment),
+            "{:?} of the password in {}",
+            fragment,
+            body
+        );
+    }
+}
+
+#[tokio::test]
+async fn each_weak_password_fails_with_its_code_and_nothing_of_it_is_answered() {
+    let routes = routes();
+    for (password, code, params) in [
+        ("Zq7xk", "password_too_short", json!({ "min": 12 })),
+        ("zqxjkvwzqxjkv", "password_too_weak", json!({})),
+        ("937104628461", "password_too_weak", json!({})),
+        (
+            "xxFRED99zqzq",
+            "password_contains_email",
+            json!({ "field": "password", "fields": ["password", "email"] }),
+        ),
+    ] {
+        for path in ROUTES {
+            let response = post_json(&routes, path, &with_password(password)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+            let body = json_body(&response);
+            assert_eq!(field_codes(&body), pairs(&[("password", code)]), "{}", path);
+            let error = error_for(&body, "password");
+            assert_eq!(error["params"], params, "{} {}", path, password);
+            assert!(error["rejected_value"].is_null(), "{}", path);
+            assert_no_fragment_of(password, response.body());
+        }
+    }
+}
+
+#[tokio::test]
+async fn password_equal_to_the_email_local_part_fails() {
+    let mut request = with_password("Fred12345678");
+    request["email"] = "fred12345678@example.com".into();
+    let response = post_json(&routes(), "/create-validator", &request).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("password", "password_contains_email")])
+    );
+}
+
+#[tokio::test]
+async fn strong_password_is_created_without_being_answered() {
+    let routes = routes();
+    for path in &["/create-path", "/create-validator", "/create-fast"] {
+        let response = post_json(&routes, path, &with_password("Correct-Horse-9")).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+        assert_no_fragment_of("Correct-Horse-9", response.body());
+    }
+}
+
+#[tokio::test]
+async fn echo_shows_the_password_as_stars() {
+    let response = post_json(&routes(), "/echo", &with_password("Correct-Horse-9")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["password"], "***");
+    assert_no_fragment_of("Correct-Horse-9", response.body());
+}
+
+#[tokio::test]
+async fn validate_field_answers_nothing_of_the_password() {
+    let request = json!({ "field": "password", "value": "zqxjkvwzqxjkv" });
+    let response = post_json(&routes(), "/validate-field", &request).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[("password", "password_too_weak")])
+    );
+    assert_no_fragment_of("zqxjkvwzqxjkv", response.body());
+}
+
+#[test]
+fn debug_shows_the_password_as_stars() {
+    let request: CreateRequest = serde_json::from_value(with_password("Correct-Horse-9")).unwrap();
+    let debug = format!("{:?}", request);
+    assert!(debug.contains(r#"password: Some("***")"#), "{}", debug);
+    assert_no_fragment_of("Correct-Horse-9", debug.as_bytes());
+}
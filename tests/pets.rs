@@ -0,0 +1,337 @@
+//! The fields of a pet and how each one is checked.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+fn with_pet(pet: Value) -> Value {
+    let mut request = valid_create();
+    request["pets"] = json!([pet]);
+    request
+}
+
+#[tokio::test]
+async fn image_photo_url_is_accepted() {
+    let routes = routes();
+    let request =
+        with_pet(json!({ "name": "Chip", "photoUrl": "https://example.com/chip.JPG?w=200" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        json_body(&response)["pets"][0]["photoUrl"],
+        "https://example.com/chip.JPG?w=200"
+    );
+}
+
+#[tokio::test]
+async fn photo_url_of_something_else_is_not_an_image() {
+    let routes = routes();
+    let request = with_pet(json!({ "name": "Chip", "photoUrl": "https://example.com/chip.html" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].photoUrl", "url.not_an_image")])
+    );
+    let error = error_for(&body, "pets[0].photoUrl");
+    assert_eq!(error["field_errors"][0], "photoUrl must point to an image");
+    assert!(error["params"]["allowed_extensions"]
+        .as_array()
+        .unwrap()
+        .contains(&json!(".png")));
+}
+
+#[tokio::test]
+async fn malformed_photo_url_is_invalid() {
+    let routes = routes();
+    let request = with_pet(json!({ "name": "Chip", "photoUrl": "chip.png" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].photoUrl", "url.invalid")])
+    );
+    assert_eq!(
+        error_for(&body, "pets[0].photoUrl")["field_errors"][0],
+        "photoUrl must be a valid URL"
+    );
+}
+
+#[tokio::test]
+async fn failing_pets_are_reported_on_their_own_index() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = json!([{ "name": "C" }, { "name": "Chip" }, { "name": "Rex2" }]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("pets[0].name", "length_min"),
+            ("pets[2].name", "pet_name.charset"),
+        ])
+    );
+    assert_eq!(body["errors"][0]["rejected_value"], "C");
+    assert_eq!(body["errors"][1]["rejected_value"], "Rex2");
+}
+
+async fn get(path: &str) -> warp::http::Response<bytes::Bytes> {
+    let routes = routes();
+    common::send(&routes, warp::test::request().method("GET").path(path)).await
+}
+
+#[tokio::test]
+async fn valid_pet_query_pages_and_filters() {
+    let response = get("/pets?limit=2&offset=1&nameContains=a").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let names: Vec<Value> = json_body(&response)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pet| pet["name"].clone())
+        .collect();
+    assert_eq!(names, [json!("bella"), json!("nala")]);
+
+    let response = get("/pets?name_contains=chi").await;
+    assert_eq!(json_body(&response)[0]["name"], "chip");
+}
+
+#[tokio::test]
+async fn out_of_range_pet_query_is_reported_per_parameter() {
+    let response = get("/pets?limit=0&offset=20000&nameContains=").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "field errors");
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("limit", "range_min"),
+            ("nameContains", "length_min"),
+            ("offset", "range_max"),
+        ])
+    );
+    assert_eq!(
+        error_for(&body, "limit")["field_errors"][0],
+        "limit must be between 1 and 100"
+    );
+}
+
+#[tokio::test]
+async fn unparseable_pet_query_names_the_parameter() {
+    let response = get("/pets?limit=many").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "invalid query string: limit: invalid digit found in string"
+    );
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn pet_name_with_digits_names_them() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_pet(json!({ "name": "R2D2" })),
+    )
+    .await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].name", "pet_name.charset")])
+    );
+    let error = error_for(&body, "pets[0].name");
+    assert_eq!(error["params"]["invalid_chars"], "22");
+    assert_eq!(
+        error["field_errors"][0],
+        "name may only contain letters, spaces and hyphens, not 22"
+    );
+}
+
+/// Names are trimmed before they're validated, so `pet_name.whitespace` never comes back.
+#[tokio::test]
+async fn pet_name_with_surrounding_spaces_is_trimmed() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_pet(json!({ "name": "  Chip " })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["pets"][0]["name"], "Chip");
+}
+
+#[tokio::test]
+async fn reserved_pet_name_is_rejected_ignoring_case() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_pet(json!({ "name": "UNDEFINED" })),
+    )
+    .await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].name", "pet_name.reserved")])
+    );
+    let error = error_for(&body, "pets[0].name");
+    assert_eq!(error["field_errors"][0], "name must not be UNDEFINED");
+    assert!(error["params"]["reserved"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("undefined")));
+}
+
+#[tokio::test]
+async fn hyphenated_pet_name_is_accepted() {
+    let routes = routes();
+    let response = post_json(
+        &routes,
+        "/create-path",
+        &with_pet(json!({ "name": "Mary-Lou Ann" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+async fn import<F>(routes: &F, csv: &str) -> warp::http::Response<bytes::Bytes>
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + 'static,
+{
+    let request = warp::test::request()
+        .method("POST")
+        .path("/pets/import")
+        .header("content-type", "text/csv")
+        .body(csv);
+    common::send(routes, request).await
+}
+
+#[tokio::test]
+async fn import_reports_each_invalid_or_malformed_row() {
+    let routes = routes();
+    let response = import(&routes, "name\nChip\nC\nNacho,extra\nBella\n").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(&response);
+    assert_eq!(body["valid"], 2);
+    let errors = json!({ "errors": body["errors"] });
+    assert_eq!(
+        field_codes(&errors),
+        pairs(&[("row[2].name", "length_min"), ("row[3]", "csv.malformed")])
+    );
+    let short = error_for(&errors, "row[2].name");
+    assert_eq!(
+        short["field_errors"][0],
+        "pet name must be between 3 and 20 characters"
+    );
+    assert_eq!(short["params"]["min"], 3);
+    assert_eq!(short["rejected_value"], "C");
+    let malformed = error_for(&errors, "row[3]")["field_errors"][0]
+        .as_str()
+        .unwrap();
+    assert!(
+        malformed.contains("found record with 2 fields"),
+        "{}",
+        malformed
+    );
+}
+
+#[tokio::test]
+async fn import_past_the_row_limit_is_too_large() {
+    let routes = common::routes_with_env(&[("PET_IMPORT_MAX_ROWS", "2")]);
+    let response = import(&routes, "name\nChip\nNacho\n").await;
+    assert_eq!(json_body(&response)["valid"], 2);
+    let response = import(&routes, "name\nChip\nNacho\nBella\n").await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        json_body(&response)["message"],
+        "too many rows: imports are limited to 2 rows"
+    );
+}
+
+#[tokio::test]
+async fn known_species_is_accepted() {
+    let routes = routes();
+    let request = with_pet(json!({ "name": "Chip", "species": "cat" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["pets"][0]["species"], "cat");
+}
+
+#[tokio::test]
+async fn unknown_species_lists_the_allowed_ones() {
+    let routes = routes();
+    let request = with_pet(json!({ "name": "Chip", "species": "dragon" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].species", "invalid_enum_value")])
+    );
+    let error = error_for(&body, "pets[0].species");
+    assert_eq!(error["params"]["allowed"], json!(["dog", "cat", "hamster"]));
+    assert_eq!(
+        error["field_errors"][0],
+        "invalid_enum_value: `dragon` is not one of dog, cat, hamster"
+    );
+    assert_eq!(error["rejected_value"], "dragon");
+}
+
+/// Species are matched case-sensitively, as the README says, so `DOG` is no dog.
+#[tokio::test]
+async fn species_in_another_case_is_unknown() {
+    let routes = routes();
+    let request = with_pet(json!({ "name": "Chip", "species": "DOG" }));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[0].species", "invalid_enum_value")])
+    );
+    assert_eq!(error_for(&body, "pets[0].species")["rejected_value"], "DOG");
+}
+
+#[tokio::test]
+async fn malformed_pet_id_is_an_invalid_id() {
+    for id in &["abc", "0", "-1", "1.5"] {
+        let response = get(&format!("/pets/{}", id)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", id);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(&[("id", "invalid_id")]), "{}", id);
+        let error = error_for(&body, "id");
+        assert_eq!(error["rejected_value"], *id);
+        assert_eq!(
+            error["field_errors"][0],
+            format!("invalid_id: id must be a positive integer, got `{}`", id)
+        );
+    }
+}
+
+#[tokio::test]
+async fn unknown_pet_id_is_not_found() {
+    let response = get("/pets/999").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = json_body(&response);
+    assert_eq!(body["message"], "pet 999 not found");
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn pet_id_counts_from_one() {
+    let response = get("/pets/1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["name"], "nacho");
+    let response = get("/pets/6").await;
+    assert_eq!(json_body(&response)["name"], "nala");
+}
@@ -0,0 +1,147 @@
+//! The framework-free pipeline on plain byte slices, and that the warp routes built on it answer
+//! the same errors.
+
+mod common;
+
+use common::{json_body, post_raw, routes};
+use example_rust_json_input_validation::models::CreateRequest;
+use example_rust_json_input_validation::pipeline::{parse_and_validate, ApiError};
+use serde_json::{json, Value};
+
+const VALID: &[u8] =
+    br#"{"email":"chip@example.com","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+
+const INVALID: &str =
+    r#"{"email":"chip","address":{"street":"w","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+
+fn parse(body: &[u8]) -> Result<CreateRequest, ApiError> {
+    parse_and_validate(body)
+}
+
+fn response_json(error: &ApiError) -> Value {
+    serde_json::to_value(error.to_error_response()).unwrap()
+}
+
+#[test]
+fn valid_body_is_deserialized_and_normalized() {
+    assert!(parse(VALID).is_ok());
+    let body = br#"{"email":" Chip@Example.COM ","address":{"street":" warpstreet ","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+    let request = parse(body).unwrap();
+    assert_eq!(request.email.as_deref(), Some("chip@example.com"));
+    assert_eq!(request.address.street, "warpstreet");
+}
+
+#[test]
+fn invalid_body_is_a_validation_error_per_field() {
+    let error = parse(INVALID.as_bytes()).unwrap_err();
+    assert_eq!(error.status_code(), 400);
+    let errors = match &error {
+        ApiError::Validation(errors) => errors,
+        other => panic!("{:?}", other),
+    };
+    let codes: Vec<_> = errors
+        .iter()
+        .map(|e| (e.field.as_str(), e.code.as_str()))
+        .collect();
+    assert_eq!(
+        codes,
+        [("email", "email.invalid"), ("address.street", "length_min")]
+    );
+    let body = response_json(&error);
+    assert_eq!(body["message"], "field errors");
+    assert_eq!(body["failed_sections"], json!(["email", "address"]));
+    assert!(body.get("request_id").is_none(), "{}", body);
+}
+
+#[test]
+fn syntax_error_names_its_path_line_and_column() {
+    match parse(br#"{"email":"#).unwrap_err() {
+        ApiError::Parse {
+            code,
+            path,
+            line,
+            column,
+            message,
+        } => {
+            assert_eq!(code, "json_path_error");
+            assert_eq!(path, "email");
+            assert_eq!((line, column), (Some(1), Some(9)));
+            assert_eq!(
+                message,
+                "JSON path error: email: EOF while parsing a value at line 1 column 9"
+            );
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn parse_errors_are_listed_as_a_field_error_at_their_path() {
+    let error = parse(br#"{"email":1}"#).unwrap_err();
+    assert_eq!(error.status_code(), 400);
+    let body = response_json(&error);
+    assert_eq!(body["errors"][0]["field"], "email");
+    assert_eq!(body["errors"][0]["code"], "json_path_error");
+    assert_eq!(
+        body["errors"][0]["params"],
+        json!({ "line": 1, "column": 10 })
+    );
+    assert_eq!(
+        body["errors"][0]["docs_url"],
+        "/docs/validation#malformed-body"
+    );
+}
+
+#[test]
+fn errors_about_the_body_as_a_whole_have_an_empty_path() {
+    for (body, code) in [
+        (&b""[..], "empty_body"),
+        (b"[]", "invalid_body_type"),
+        (b"\xff\xfe{", "invalid_encoding"),
+    ] {
+        match parse(body).unwrap_err() {
+            ApiError::Parse {
+                code: actual, path, ..
+            } => {
+                assert_eq!(actual, code);
+                assert_eq!(path, "");
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
+
+#[test]
+fn unknown_field_is_a_parse_error_at_the_field() {
+    match parse(br#"{"emial":"x"}"#).unwrap_err() {
+        ApiError::Parse { code, path, .. } => {
+            assert_eq!(code, "unknown_field");
+            assert_eq!(path, "emial");
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn warp_routes_answer_the_field_errors_of_the_core() {
+    let core = response_json(&parse(INVALID.as_bytes()).unwrap_err());
+    for path in &["/create-fast", "/create-path", "/create-validator"] {
+        let response = post_raw(&routes(), path, INVALID).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(body["message"], core["message"], "{}", path);
+        assert_eq!(body["errors"], core["errors"], "{}", path);
+        assert_eq!(body["failed_sections"], core["failed_sections"], "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn warp_routes_answer_the_parse_message_of_the_core() {
+    let body = r#"{"email":1}"#;
+    let core = response_json(&parse(body.as_bytes()).unwrap_err());
+    let response = json_body(&post_raw(&routes(), "/create-fast", body).await);
+    assert_eq!(response["message"], core["message"]);
+    assert_eq!(response["path"], "email");
+    assert_eq!(response["line"], 1);
+    assert_eq!(response["column"], 10);
+}
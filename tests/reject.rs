@@ -0,0 +1,95 @@
+//! `reject` and `Error::into_rejection`: what they build is found as our `Error` when the
+//! rejection is handled, and answered like the routes answer it.
+
+mod common;
+
+use bytes::Bytes;
+use common::{json_body, post_json, post_raw, routes, valid_create, without_request_id};
+use example_rust_json_input_validation::error::{
+    reject, with_request_handling, DefaultErrorFormatter, Error, ErrorConfig,
+};
+use example_rust_json_input_validation::metrics::Metrics;
+use example_rust_json_input_validation::models::CreateRequest;
+use serde_json::Value;
+use std::sync::Arc;
+use validator::{Validate, ValidationErrors};
+use warp::http::Response;
+use warp::{Filter, Rejection};
+
+fn deserialize_create(
+    body: &str,
+) -> Result<CreateRequest, serde_path_to_error::Error<serde_json::Error>> {
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(body))
+}
+
+fn validation_errors(request: &Value) -> ValidationErrors {
+    let request: CreateRequest = serde_json::from_value(request.clone()).unwrap();
+    request.validate().unwrap_err()
+}
+
+fn invalid_email() -> Value {
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request
+}
+
+/// What a route failing with `rejection()` answers.
+async fn answer(rejection: fn() -> Rejection) -> Response<Bytes> {
+    let failing =
+        warp::any().and_then(move || async move { Err::<warp::reply::Response, _>(rejection()) });
+    let routes = with_request_handling(
+        Arc::new(Metrics::default()),
+        ErrorConfig::default(),
+        Arc::new(DefaultErrorFormatter),
+        failing,
+    );
+    warp::test::request().reply(&routes).await
+}
+
+#[test]
+fn serde_errors_are_rejected_as_json_path_errors() {
+    let rejection = reject(deserialize_create(r#"{"email":1}"#).unwrap_err());
+    match rejection.find::<Error>() {
+        Some(Error::JSONPathError(_)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn validation_errors_are_rejected_as_validation_errors() {
+    let rejection = reject(validation_errors(&invalid_email()));
+    match rejection.find::<Error>() {
+        Some(Error::ValidationError(errors)) => {
+            assert!(errors.field_errors().contains_key("email"))
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn into_rejection_keeps_the_error() {
+    let rejection = Error::JsonTooDeep { max: 128 }.into_rejection();
+    assert!(matches!(
+        rejection.find::<Error>(),
+        Some(Error::JsonTooDeep { max: 128 })
+    ));
+}
+
+#[tokio::test]
+async fn rejected_serde_error_is_answered_like_the_route_answers_it() {
+    let direct = answer(|| reject(deserialize_create(r#"{"email":1}"#).unwrap_err())).await;
+    let routes = routes();
+    let route = post_raw(&routes, "/create-path", r#"{"email":1}"#).await;
+    assert_eq!(direct.status(), route.status());
+    assert_eq!(without_request_id(&direct), without_request_id(&route));
+    assert_eq!(json_body(&direct)["path"], "email");
+}
+
+#[tokio::test]
+async fn rejected_validation_errors_are_answered_like_the_route_answers_them() {
+    let direct = answer(|| reject(validation_errors(&invalid_email()))).await;
+    let routes = routes();
+    let route = post_json(&routes, "/create-path", &invalid_email()).await;
+    assert_eq!(direct.status(), route.status());
+    assert_eq!(without_request_id(&direct), without_request_id(&route));
+}
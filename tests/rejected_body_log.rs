@@ -0,0 +1,107 @@
+//! `LOG_REJECTED_BODIES`: with the flag, rejected bodies are logged at debug level redacted,
+//! without it they aren't logged at all.
+
+mod common;
+
+use common::{capture_logs, post_json, post_raw, routes_with_env, valid_create};
+use serde_json::Value;
+use warp::http::StatusCode;
+
+/// A request failing on its pet, with an email, a password and a phone number to redact.
+fn failing() -> Value {
+    let mut request = valid_create();
+    request["email"] = "fred@example.com".into();
+    request["pets"][0]["name"] = "C".into();
+    request["password"] = "hunter2hunter2x".into();
+    request["phone"] = "+4915112345678".into();
+    request
+}
+
+/// The `rejected body` lines of `logs`.
+fn rejected_body_lines(logs: &str) -> Vec<&str> {
+    logs.lines()
+        .filter(|line| line.contains("rejected body"))
+        .collect()
+}
+
+#[tokio::test]
+async fn rejected_body_is_logged_with_the_email_masked_and_secrets_replaced() {
+    let routes = routes_with_env(&[("LOG_REJECTED_BODIES", "true")]);
+    let (logs, _guard) = capture_logs();
+    for path in &["/create-validator", "/create-fast"] {
+        let response = post_json(&routes, path, &failing()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+    }
+    let logs = logs.contents();
+    let lines = rejected_body_lines(&logs);
+    assert_eq!(lines.len(), 2, "{}", logs);
+    for (line, path) in lines.iter().zip(&["/create-validator", "/create-fast"]) {
+        assert!(line.contains("DEBUG"), "{}", line);
+        assert!(line.contains(&format!("path=\"{}\"", path)), "{}", line);
+        assert!(line.contains(r#"error="validation_error""#), "{}", line);
+        assert!(
+            line.contains(r#"field_errors=[{"field":"pets[0].name","code":"length_min"}]"#),
+            "{}",
+            line
+        );
+        assert!(
+            line.contains(r#"\"email\":\"f***@example.com\""#),
+            "{}",
+            line
+        );
+        assert!(line.contains(r#"\"password\":\"[redacted]\""#), "{}", line);
+        assert!(line.contains(r#"\"phone\":\"[redacted]\""#), "{}", line);
+        assert!(!line.contains("fred@"), "{}", line);
+        assert!(!line.contains("hunter2"), "{}", line);
+        assert!(!line.contains("15112345678"), "{}", line);
+    }
+}
+
+#[tokio::test]
+async fn rejected_body_is_cut_to_the_configured_bytes() {
+    let routes = routes_with_env(&[
+        ("LOG_REJECTED_BODIES", "true"),
+        ("LOG_REJECTED_BODY_BYTES", "40"),
+    ]);
+    let (logs, _guard) = capture_logs();
+    post_json(&routes, "/create-validator", &failing()).await;
+    let logs = logs.contents();
+    let lines = rejected_body_lines(&logs);
+    assert_eq!(lines.len(), 1, "{}", logs);
+    assert!(
+        lines[0].ends_with(r#"body="{\"address\":{\"street\":\"warpstreet\",\"stree...""#),
+        "{}",
+        lines[0]
+    );
+}
+
+#[tokio::test]
+async fn body_that_isnt_json_is_only_logged_by_its_size() {
+    let routes = routes_with_env(&[("LOG_REJECTED_BODIES", "true")]);
+    let (logs, _guard) = capture_logs();
+    let body = r#"{"email":"fred@example.com","#;
+    post_raw(&routes, "/create-validator", body).await;
+    let logs = logs.contents();
+    let lines = rejected_body_lines(&logs);
+    assert_eq!(lines.len(), 1, "{}", logs);
+    assert!(
+        lines[0].contains(&format!("body=\"<{} bytes, not JSON>\"", body.len())),
+        "{}",
+        lines[0]
+    );
+    assert!(!lines[0].contains("fred@"), "{}", lines[0]);
+}
+
+#[tokio::test]
+async fn body_is_not_logged_without_the_flag() {
+    let routes = routes_with_env(&[]);
+    let (logs, _guard) = capture_logs();
+    for path in &["/create-validator", "/create-fast"] {
+        let response = post_json(&routes, path, &failing()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+    }
+    let logs = logs.contents();
+    assert!(rejected_body_lines(&logs).is_empty(), "{}", logs);
+    assert!(!logs.contains("example.com"), "{}", logs);
+    assert!(logs.contains(r#"error="validation_error""#), "{}", logs);
+}
@@ -0,0 +1,108 @@
+//! warp's own rejections, answered with a status and a JSON error body naming what was wrong.
+
+mod common;
+
+use common::{json_body, routes, send};
+use example_rust_json_input_validation::error::builtin_rejection_status;
+use serde::Deserialize;
+use warp::http::{Method, StatusCode};
+use warp::Rejection;
+
+#[derive(Deserialize, Debug)]
+struct Page {
+    #[allow(dead_code)]
+    limit: u32,
+}
+
+/// What `$filter` rejects `$request` with.
+macro_rules! rejection {
+    ($filter:expr, $request:expr $(,)?) => {
+        $request.filter(&$filter).await.unwrap_err()
+    };
+}
+
+#[tokio::test]
+async fn builtin_rejections_get_their_status_and_message() {
+    let get = || warp::test::request().method("GET");
+    let post = || warp::test::request().method("POST");
+    let cases: Vec<(&str, Rejection, StatusCode, &str)> = vec![
+        (
+            "method not allowed",
+            rejection!(warp::post(), get()),
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method GET is not allowed on this route",
+        ),
+        (
+            "unsupported media type",
+            rejection!(
+                warp::body::json::<serde_json::Value>(),
+                post().header("content-type", "text/plain").body("{}"),
+            ),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported media type",
+        ),
+        (
+            "invalid query",
+            rejection!(warp::query::<Page>(), get().path("/?limit=abc")),
+            StatusCode::BAD_REQUEST,
+            "invalid query string",
+        ),
+        (
+            "length required",
+            rejection!(warp::body::content_length_limit(10), post()),
+            StatusCode::LENGTH_REQUIRED,
+            "A content-length header is required",
+        ),
+        (
+            "payload too large",
+            rejection!(warp::body::content_length_limit(1), post().body("xx")),
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "The request payload is too large",
+        ),
+        (
+            "missing header",
+            rejection!(warp::header::<String>("x-client-version"), get()),
+            StatusCode::BAD_REQUEST,
+            "missing header x-client-version",
+        ),
+        (
+            "invalid header",
+            rejection!(
+                warp::header::<u32>("x-count"),
+                get().header("x-count", "many"),
+            ),
+            StatusCode::BAD_REQUEST,
+            "invalid header x-count",
+        ),
+    ];
+    for (name, rejection, status, message) in cases {
+        let answer = builtin_rejection_status(&rejection, &Method::GET);
+        assert_eq!(answer, Some((status, message.to_string())), "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn get_on_a_create_route_is_a_json_405() {
+    let routes = routes();
+    let response = send(&routes, warp::test::request().path("/create-basic")).await;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = json_body(&response);
+    assert_eq!(body["message"], "method GET is not allowed on this route");
+    assert_eq!(body["docs_url"], "/docs/validation#method-not-allowed");
+}
+
+#[tokio::test]
+async fn create_without_a_body_is_a_json_411() {
+    let routes = routes();
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-basic")
+        .header("content-type", "application/json");
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::LENGTH_REQUIRED);
+    assert_eq!(
+        json_body(&response)["message"],
+        "A content-length header is required"
+    );
+}
@@ -0,0 +1,170 @@
+//! The example routes whose rules relate several fields: guest ranges, payment methods and
+//! schedules.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn booking_with_min_above_max_guests_is_rejected() {
+    let routes = routes();
+    let request = json!({ "name": "Party", "minGuests": 5, "maxGuests": 3 });
+    let response = post_json(&routes, "/create-booking", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("__all__", "min_guests_greater_than_max_guests")])
+    );
+    assert_eq!(body["errors"][0]["params"]["min_guests"], 5);
+    assert_eq!(body["errors"][0]["params"]["max_guests"], 3);
+}
+
+#[tokio::test]
+async fn booking_with_equal_guests_is_accepted() {
+    let routes = routes();
+    let request = json!({ "name": "Party", "minGuests": 4, "maxGuests": 4 });
+    let response = post_json(&routes, "/create-booking", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn booking_with_ordered_guests_is_accepted() {
+    let routes = routes();
+    let request = json!({ "name": "Party", "minGuests": 2, "maxGuests": 6 });
+    let response = post_json(&routes, "/create-booking", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn guest_range_is_reported_next_to_field_errors() {
+    let routes = routes();
+    let request = json!({ "name": "P", "minGuests": 5, "maxGuests": 3 });
+    let response = post_json(&routes, "/create-booking", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("__all__", "min_guests_greater_than_max_guests"),
+            ("name", "length_min"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn card_payment_with_card_number_is_accepted() {
+    let routes = routes();
+    let request = json!({ "payment": { "method": "card", "cardNumber": "4111111111111111" } });
+    let response = post_json(&routes, "/create-payment", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn card_payment_without_card_number_is_rejected() {
+    let routes = routes();
+    let request = json!({ "payment": { "method": "card" } });
+    let response = post_json(&routes, "/create-payment", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("payment.__all__", "card_number_required_for_card")])
+    );
+    assert_eq!(body["errors"][0]["params"]["missing_field"], "cardNumber");
+}
+
+#[tokio::test]
+async fn paypal_payment_with_paypal_email_is_accepted() {
+    let routes = routes();
+    let request = json!({ "payment": { "method": "paypal", "paypalEmail": "chip@example.com" } });
+    let response = post_json(&routes, "/create-payment", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn paypal_payment_without_paypal_email_is_rejected() {
+    let routes = routes();
+    let request = json!({ "payment": { "method": "paypal" } });
+    let response = post_json(&routes, "/create-payment", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("payment.__all__", "paypal_email_required_for_paypal")])
+    );
+    assert_eq!(body["errors"][0]["params"]["missing_field"], "paypalEmail");
+}
+
+#[tokio::test]
+async fn missing_payment_field_is_reported_next_to_field_errors() {
+    let routes = routes();
+    let request = json!({ "payment": { "method": "paypal", "cardNumber": "41" } });
+    let response = post_json(&routes, "/create-payment", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("payment.__all__", "paypal_email_required_for_paypal"),
+            ("payment.cardNumber", "length_min"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn chronological_schedule_is_accepted() {
+    let routes = routes();
+    let request = json!({ "schedule": [
+        { "startsAt": 100, "durationMinutes": 30 },
+        { "startsAt": 200, "durationMinutes": 30 },
+    ] });
+    let response = post_json(&routes, "/create-schedule", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn out_of_order_schedule_reports_the_first_late_slot() {
+    let routes = routes();
+    let request = json!({ "schedule": [
+        { "startsAt": 300, "durationMinutes": 30 },
+        { "startsAt": 200, "durationMinutes": 30 },
+    ] });
+    let response = post_json(&routes, "/create-schedule", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("__all__", "schedule_not_chronological")])
+    );
+    let params = &body["errors"][0]["params"];
+    assert_eq!(params["index"], 1);
+    assert_eq!(params["starts_at"], 200);
+    assert_eq!(params["previous_starts_at"], 300);
+}
+
+#[tokio::test]
+async fn empty_schedule_is_accepted() {
+    let routes = routes();
+    let request = json!({ "schedule": [] });
+    let response = post_json(&routes, "/create-schedule", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn schedule_order_is_reported_next_to_slot_errors() {
+    let routes = routes();
+    let request = json!({ "schedule": [
+        { "startsAt": 300, "durationMinutes": 1 },
+        { "startsAt": 200, "durationMinutes": 30 },
+    ] });
+    let response = post_json(&routes, "/create-schedule", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("__all__", "schedule_not_chronological"),
+            ("schedule[0].durationMinutes", "range_min"),
+        ])
+    );
+}
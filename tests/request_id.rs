@@ -0,0 +1,66 @@
+//! Request IDs: every response carries one in `X-Request-Id`, error bodies repeat it as
+//! `request_id`, and the request log line names it.
+
+mod common;
+
+use common::{capture_logs, json_body, post_json, post_raw, routes, send, valid_create};
+use warp::http::StatusCode;
+
+fn request_id(response: &warp::http::Response<bytes::Bytes>) -> String {
+    let id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(!id.is_empty());
+    id.to_owned()
+}
+
+#[tokio::test]
+async fn success_carries_a_request_id() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-basic", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    request_id(&response);
+}
+
+#[tokio::test]
+async fn error_body_repeats_the_request_id_of_the_header() {
+    let routes = routes();
+    let response = post_raw(&routes, "/create-basic", "{").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let id = request_id(&response);
+    assert_eq!(json_body(&response)["request_id"], id);
+}
+
+#[tokio::test]
+async fn every_request_gets_its_own_id() {
+    let routes = routes();
+    let first = post_raw(&routes, "/create-basic", "{").await;
+    let second = post_raw(&routes, "/create-basic", "{").await;
+    assert_ne!(request_id(&first), request_id(&second));
+}
+
+#[tokio::test]
+async fn request_id_sent_by_a_proxy_is_kept() {
+    let routes = routes();
+    let request = warp::test::request()
+        .path("/nope")
+        .header("x-request-id", "abc-123");
+    let response = send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(request_id(&response), "abc-123");
+    assert_eq!(json_body(&response)["request_id"], "abc-123");
+}
+
+#[tokio::test]
+async fn request_is_logged_with_its_id_method_path_and_status() {
+    let routes = routes();
+    let (logs, _guard) = capture_logs();
+    let response = post_raw(&routes, "/create-basic", "{").await;
+    let logs = logs.contents();
+    let line = logs
+        .lines()
+        .find(|line| line.contains(&format!("request_id={}", request_id(&response))))
+        .unwrap_or_else(|| panic!("no request line in {}", logs));
+    assert!(line.contains("method=POST"), "{}", line);
+    assert!(line.contains(r#"path="/create-basic""#), "{}", line);
+    assert!(line.contains("status=400"), "{}", line);
+    assert!(line.contains("latency_ms="), "{}", line);
+}
@@ -0,0 +1,156 @@
+//! `GET /rules/create`, the validation rules of a create request per field for clients that
+//! mirror them: which fields it covers and that its bounds are the ones enforced.
+
+mod common;
+
+use common::{json_body, post_json, routes, send, valid_create};
+use example_rust_json_input_validation::models::CREATE_FIELD_RULES;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use warp::http::StatusCode;
+
+/// Fields that are only checked by their type while the body is read, so they have no rules.
+const TYPE_ONLY: &[&str] = &["pets[].species"];
+
+async fn get_json(path: &str) -> Value {
+    let routes = routes();
+    let response = send(&routes, warp::test::request().path(path)).await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", path);
+    json_body(&response)
+}
+
+/// The rule named `rule` of `field`.
+fn rule<'a>(rules: &'a Value, field: &str, rule: &str) -> &'a Value {
+    rules[field]
+        .as_array()
+        .and_then(|rules| rules.iter().find(|entry| entry["rule"] == rule))
+        .unwrap_or_else(|| panic!("no {} rule for {} in {}", rule, field, rules))
+}
+
+/// The path of every property of `schema` below `prefix` that isn't an object, with list items
+/// as `[]`.
+fn schema_fields(schema: &Value, prefix: &str, fields: &mut BTreeSet<String>) {
+    let Some(properties) = schema["properties"].as_object() else {
+        return;
+    };
+    for (name, property) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        if property["type"] != "object" {
+            fields.insert(path.clone());
+        }
+        schema_fields(property, &path, fields);
+        schema_fields(&property["items"], &format!("{}[]", path), fields);
+    }
+}
+
+fn rule_fields(rules: &Value) -> BTreeSet<String> {
+    rules.as_object().unwrap().keys().cloned().collect()
+}
+
+#[tokio::test]
+async fn rules_are_listed_per_field_with_their_bounds_and_codes() {
+    let rules = get_json("/rules/create").await;
+    assert_eq!(
+        rule(&rules, "email", "email"),
+        &json!({ "rule": "email", "codes": ["email.invalid"], "description": "an email address" })
+    );
+    let street = rule(&rules, "address.street", "length");
+    assert_eq!((&street["min"], &street["max"]), (&json!(2), &json!(10)));
+    assert_eq!(street["codes"], json!(["length_min", "length_max"]));
+    assert_eq!(rule(&rules, "address.streetNo", "range")["min"], 1);
+    let name = rule(&rules, "pets[].name", "length");
+    assert_eq!((&name["min"], &name["max"]), (&json!(3), &json!(20)));
+
+    let charset = rule(&rules, "pets[].name", "pet_name");
+    assert_eq!(
+        charset["codes"],
+        json!([
+            "pet_name.whitespace",
+            "pet_name.charset",
+            "pet_name.reserved"
+        ])
+    );
+    assert!(charset["description"].as_str().unwrap().contains("letters"));
+    assert_eq!(
+        rule(&rules, "password", "password")["codes"],
+        json!(["password_too_short", "password_too_weak"])
+    );
+}
+
+/// Every field of the request either has rules or is checked by its type alone, so a field
+/// added to the request has to be registered.
+#[tokio::test]
+async fn every_field_of_the_schema_has_rules() {
+    let rules = get_json("/rules/create").await;
+    let mut fields = BTreeSet::new();
+    schema_fields(&get_json("/schema/create").await, "", &mut fields);
+    let mut expected = rule_fields(&rules);
+    expected.remove("");
+    expected.extend(TYPE_ONLY.iter().map(|field| field.to_string()));
+    assert_eq!(fields, expected);
+}
+
+#[tokio::test]
+async fn every_field_validate_field_knows_has_rules() {
+    let rules = get_json("/rules/create").await;
+    for (field, _) in CREATE_FIELD_RULES {
+        assert!(
+            rules.get(*field).is_some() || TYPE_ONLY.contains(field),
+            "{} has no rules",
+            field
+        );
+    }
+}
+
+#[tokio::test]
+async fn bounds_agree_with_the_published_schema() {
+    let rules = get_json("/rules/create").await;
+    let schema = get_json("/schema/create").await;
+    let properties = &schema["properties"];
+    let street = &properties["address"]["properties"]["street"];
+    let rule_street = rule(&rules, "address.street", "length");
+    assert_eq!(rule_street["min"], street["minLength"]);
+    assert_eq!(rule_street["max"], street["maxLength"]);
+    assert_eq!(
+        rule(&rules, "address.streetNo", "range")["min"],
+        properties["address"]["properties"]["streetNo"]["minimum"]
+    );
+    let name = &properties["pets"]["items"]["properties"]["name"];
+    let rule_name = rule(&rules, "pets[].name", "length");
+    assert_eq!(rule_name["min"], name["minLength"]);
+    assert_eq!(rule_name["max"], name["maxLength"]);
+    let count = rule(&rules, "pets", "count");
+    assert_eq!(count["min"], properties["pets"]["minItems"]);
+    assert_eq!(count["max"], properties["pets"]["maxItems"]);
+    assert_eq!(
+        rule(&rules, "pets", "max_elements")["max"],
+        properties["pets"]["maxItems"]
+    );
+    assert_eq!(
+        rule(&rules, "password", "password")["min"],
+        properties["password"]["minLength"]
+    );
+}
+
+/// `?explain=true` lists the validators of the same registry, every field but those whose only
+/// rules are struct-level.
+#[tokio::test]
+async fn explained_validators_cover_the_same_fields() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-validator?explain=true", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let validators: BTreeSet<String> = json_body(&response)["validators"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    let mut expected = rule_fields(&get_json("/rules/create").await);
+    expected.remove("");
+    expected.remove("contactMethod");
+    assert_eq!(validators, expected);
+}
@@ -0,0 +1,105 @@
+//! The JSON Schemas the service validates against or publishes.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn missing_required_property_fails_the_supplied_schema() {
+    let routes = routes();
+    let request = json!({
+        "schema": { "type": "object", "required": ["email"] },
+        "data": { "name": "chip" },
+    });
+    let response = post_json(&routes, "/validate-against-schema", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("email", "schema.required")]));
+    assert_eq!(
+        body["errors"][0]["field_errors"][0],
+        r#"required: "email" is a required property"#
+    );
+}
+
+#[tokio::test]
+async fn schema_errors_are_reported_on_the_path_of_the_data() {
+    let routes = routes();
+    let request = json!({
+        "schema": {
+            "type": "object",
+            "properties": {
+                "pets": { "type": "array", "items": { "type": "object", "required": ["name"] } },
+            },
+        },
+        "data": { "pets": [{ "name": "Chip" }, {}] },
+    });
+    let response = post_json(&routes, "/validate-against-schema", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[1].name", "schema.required")])
+    );
+}
+
+#[tokio::test]
+async fn data_matching_the_supplied_schema_passes() {
+    let routes = routes();
+    let request = json!({
+        "schema": { "type": "object", "required": ["email"] },
+        "data": { "email": "chip@example.com" },
+    });
+    let response = post_json(&routes, "/validate-against-schema", &request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// `GET` of `path` as JSON.
+async fn get_json(path: &str) -> serde_json::Value {
+    let routes = routes();
+    let request = warp::test::request().method("GET").path(path);
+    let response = common::send(&routes, request).await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", path);
+    json_body(&response)
+}
+
+#[tokio::test]
+async fn create_schema_carries_the_constraints_of_every_field() {
+    let schema = get_json("/schema/create").await;
+    let properties = &schema["properties"];
+    assert_eq!(properties["email"]["format"], "email");
+    let address = &properties["address"]["properties"];
+    assert_eq!(address["street"]["minLength"], 2);
+    assert_eq!(address["street"]["maxLength"], 10);
+    assert_eq!(address["streetNo"]["minimum"], 1);
+    let pet_name = &properties["pets"]["items"]["properties"]["name"];
+    assert_eq!(pet_name["minLength"], 3);
+    assert_eq!(pet_name["maxLength"], 20);
+}
+
+#[tokio::test]
+async fn openapi_document_describes_the_create_routes_and_their_errors() {
+    let document = get_json("/openapi.json").await;
+    assert_eq!(document["openapi"], "3.1.0");
+    for path in &["/create-basic", "/create-path", "/create-validator"] {
+        let post = &document["paths"][path]["post"];
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/CreateRequest",
+            "{}",
+            path
+        );
+        assert_eq!(
+            post["responses"]["400"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse",
+            "{}",
+            path
+        );
+    }
+    let schemas = &document["components"]["schemas"];
+    assert_eq!(
+        schemas["ErrorResponse"]["properties"]["errors"]["items"]["$ref"],
+        "#/components/schemas/FieldError"
+    );
+    assert!(schemas["FieldError"]["properties"]["code"].is_object());
+}
@@ -0,0 +1,70 @@
+//! Graceful shutdown: the server stops once its shutdown future resolves, after finishing the
+//! requests it's already serving.
+
+use example_rust_json_input_validation::bind;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const BODY: &str = r#"{"email":"chip@example.com","address":{"street":"warpstreet","streetNo":1},"pets":[{"name":"Chip"}]}"#;
+
+#[tokio::test]
+async fn server_stops_when_signalled() {
+    let (signal, shutdown) = oneshot::channel::<()>();
+    let (_, server) = bind(([127, 0, 0, 1], 0).into(), async {
+        shutdown.await.ok();
+    })
+    .unwrap();
+    let server = tokio::spawn(server);
+    signal.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server still running after shutdown")
+        .unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn request_in_flight_completes_before_the_server_stops() {
+    let (signal, shutdown) = oneshot::channel::<()>();
+    let (addr, server) = bind(([127, 0, 0, 1], 0).into(), async {
+        shutdown.await.ok();
+    })
+    .unwrap();
+    let server = tokio::spawn(server);
+
+    let (headers_sent, headers_received) = std::sync::mpsc::channel();
+    let (resume, resumed) = std::sync::mpsc::channel::<()>();
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let (head, tail) = BODY.split_at(10);
+        write!(
+            stream,
+            "POST /create-basic HTTP/1.1\r\nhost: localhost\r\ncontent-type: application/json\r\n\
+             content-length: {}\r\n\r\n{}",
+            BODY.len(),
+            head
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        headers_sent.send(()).unwrap();
+        resumed.recv().unwrap();
+        stream.write_all(tail.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+
+    headers_received.recv().unwrap();
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+    signal.send(()).unwrap();
+    tokio::time::delay_for(Duration::from_millis(50)).await;
+    resume.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server still running after shutdown")
+        .unwrap();
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+}
@@ -0,0 +1,138 @@
+//! The creates `/create-validator` keeps, and reading them back through `GET /creates`.
+
+mod common;
+
+use common::{json_body, post_json, routes, routes_with_store, send, valid_create};
+use example_rust_json_input_validation::models::{CreateInput, CreateRequest};
+use example_rust_json_input_validation::store::CreateStore;
+use serde_json::Value;
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+fn with_email(email: &str) -> Value {
+    let mut request = valid_create();
+    request["email"] = email.into();
+    request
+}
+
+async fn get<F>(routes: &F, path: &str) -> warp::http::Response<bytes::Bytes>
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + 'static,
+{
+    send(routes, warp::test::request().path(path)).await
+}
+
+#[tokio::test]
+async fn created_request_is_read_back_by_its_id() {
+    let routes = routes();
+    let response = post_json(&routes, "/create-validator", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let id = json_body(&response)["id"].as_str().unwrap().to_owned();
+    let location = format!("/creates/{}", id);
+    assert_eq!(response.headers()["location"], location.as_str());
+
+    let response = get(&routes, &location).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let stored = json_body(&response);
+    assert_eq!(stored["id"], id.as_str());
+    assert_eq!(stored["email"], "chip@example.com");
+    assert_eq!(stored["address"]["street"], "warpstreet");
+    assert_eq!(stored["pets"][0]["name"], "Chip");
+    assert!(stored["createdAt"].is_string());
+}
+
+#[tokio::test]
+async fn unknown_id_is_a_json_404() {
+    let routes = routes();
+    let id = "0e1c2d3e-4b5a-4c7d-8e9f-0a1b2c3d4e5f";
+    let response = get(&routes, &format!("/creates/{}", id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        json_body(&response)["message"],
+        format!("create {} not found", id)
+    );
+}
+
+#[tokio::test]
+async fn id_that_is_no_uuid_is_an_invalid_id() {
+    let routes = routes();
+    let response = get(&routes, "/creates/abc").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(&response)["errors"][0]["code"], "invalid_id");
+}
+
+#[tokio::test]
+async fn creates_are_listed_oldest_first_and_paged() {
+    let routes = routes();
+    let emails = ["a@example.com", "b@example.com", "c@example.com"];
+    for email in &emails {
+        let response = post_json(&routes, "/create-validator", &with_email(email)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    let listed = |body: Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|stored| stored["email"].as_str().unwrap().to_owned())
+            .collect()
+    };
+    let response = get(&routes, "/creates").await;
+    assert_eq!(listed(json_body(&response)), emails);
+    let response = get(&routes, "/creates?limit=1&offset=1").await;
+    assert_eq!(listed(json_body(&response)), ["b@example.com"]);
+}
+
+#[tokio::test]
+async fn seeded_store_is_served() {
+    let store = Arc::new(CreateStore::default());
+    let request: CreateRequest = serde_json::from_value(with_email("seed@example.com")).unwrap();
+    let seeded = store.insert(CreateInput::from(request)).unwrap();
+    let routes = routes_with_store(store);
+    let response = get(&routes, &format!("/creates/{}", seeded.id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(&response)["email"], "seed@example.com");
+}
+
+#[tokio::test]
+async fn second_create_with_a_taken_email_is_a_conflict() {
+    let routes = routes();
+    post_json(&routes, "/create-validator", &valid_create()).await;
+    let response = post_json(
+        &routes,
+        "/create-validator",
+        &with_email(" CHIP@example.com"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body = json_body(&response);
+    assert_eq!(
+        common::field_codes(&body),
+        common::pairs(&[("email", "unique")])
+    );
+    assert_eq!(body["message"], "email chip@example.com is already taken");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn of_two_simultaneous_creates_with_one_email_only_one_is_stored() {
+    let routes = routes();
+    let creates: Vec<_> = (0..2)
+        .map(|_| {
+            let routes = routes.clone();
+            tokio::spawn(async move {
+                post_json(&routes, "/create-validator", &valid_create())
+                    .await
+                    .status()
+            })
+        })
+        .collect();
+    let mut statuses = Vec::new();
+    for create in creates {
+        statuses.push(create.await.unwrap());
+    }
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+    let response = get(&routes, "/creates").await;
+    assert_eq!(json_body(&response).as_array().unwrap().len(), 1);
+}
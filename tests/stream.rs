@@ -0,0 +1,155 @@
+//! `POST /create-stream`: newline-delimited create requests, answered with one NDJSON record per
+//! input line however the body is split into chunks.
+
+mod common;
+
+use common::{routes, routes_with_env, send, valid_create};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use warp::http::StatusCode;
+
+/// The records of an NDJSON response body.
+fn records(body: &[u8]) -> Vec<Value> {
+    std::str::from_utf8(body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+async fn post_stream<F>(routes: &F, body: &str) -> warp::http::Response<bytes::Bytes>
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + 'static,
+{
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-stream")
+        .header("content-type", "application/x-ndjson")
+        .body(body);
+    send(routes, request).await
+}
+
+/// A valid line, a line that isn't JSON, one failing validation and a valid last line without
+/// a newline.
+fn mixed_body() -> String {
+    let mut invalid = valid_create();
+    invalid["email"] = "chip".into();
+    format!(
+        "{}\n{{\"email\":\n{}\n{}",
+        valid_create(),
+        invalid,
+        valid_create()
+    )
+}
+
+/// Asserts `records` describe the lines of `mixed_body`, in order.
+fn assert_mixed_records(records: &[Value]) {
+    assert_eq!(records.len(), 4, "{:?}", records);
+    assert_eq!(records[0]["status"], "created");
+    assert_eq!(records[0]["line"], 1);
+    assert!(records[0]["id"].is_string());
+
+    assert_eq!(records[1]["status"], "invalid");
+    assert_eq!(records[1]["line"], 2);
+    assert_eq!(records[1]["errors"][0]["code"], "json.malformed");
+    assert_eq!(records[1]["errors"][0]["field"], "email");
+
+    assert_eq!(records[2]["status"], "invalid");
+    assert_eq!(records[2]["line"], 3);
+    assert_eq!(records[2]["errors"][0]["field"], "email");
+    assert_eq!(records[2]["errors"][0]["code"], "email.invalid");
+
+    assert_eq!(records[3]["status"], "created");
+    assert_eq!(records[3]["line"], 4);
+}
+
+#[tokio::test]
+async fn every_line_gets_a_record_and_bad_lines_do_not_stop_the_stream() {
+    let routes = routes();
+    let response = post_stream(&routes, &mixed_body()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    assert_mixed_records(&records(response.body()));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn lines_split_across_chunks_are_put_back_together() {
+    let (addr, server) = warp::serve(routes()).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let body = mixed_body();
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /create-stream HTTP/1.1\r\nhost: localhost\r\n\
+             content-type: application/x-ndjson\r\ntransfer-encoding: chunked\r\n\
+             connection: close\r\n\r\n"
+        )
+        .unwrap();
+        // Chunks of 7 bytes end in the middle of every line and of the newlines' neighbours.
+        for chunk in body.as_bytes().chunks(7) {
+            write!(stream, "{:x}\r\n", chunk.len()).unwrap();
+            stream.write_all(chunk).unwrap();
+            stream.write_all(b"\r\n").unwrap();
+            stream.flush().unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        stream.write_all(b"0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+    let response = tokio::task::spawn_blocking(move || client.join().unwrap())
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    let (_, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+    // The records are answered in one piece, so only chunk sizes sit between them.
+    let records: Vec<Value> = body
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_mixed_records(&records);
+}
+
+#[tokio::test]
+async fn line_past_the_line_limit_aborts_the_stream() {
+    let routes = routes_with_env(&[("STREAM_MAX_LINES", "2")]);
+    let response = post_stream(&routes, &mixed_body()).await;
+    let records = records(response.body());
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1]["line"], 2);
+    assert_eq!(
+        records[2],
+        serde_json::json!({
+            "status": "aborted",
+            "line": 3,
+            "message": "the stream exceeds the limit of 2 lines",
+        })
+    );
+}
+
+#[tokio::test]
+async fn line_over_the_length_limit_aborts_the_stream() {
+    let routes = routes_with_env(&[("STREAM_MAX_LINE_BYTES", "120")]);
+    let mut long = valid_create();
+    long["bio"] = "x".repeat(100).into();
+    let body = format!("{}\n{}\n{}\n", valid_create(), long, valid_create());
+    let response = post_stream(&routes, &body).await;
+    let records = records(response.body());
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["status"], "created");
+    assert_eq!(
+        records[1],
+        serde_json::json!({
+            "status": "aborted",
+            "line": 2,
+            "message": "line exceeds the limit of 120 bytes",
+        })
+    );
+}
@@ -0,0 +1,43 @@
+//! Street number 0 with `street_no_min` 0 installed, in a binary of its own since the
+//! configuration is global.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, valid_create};
+use example_rust_json_input_validation::config::ValidationConfig;
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+fn install_street_no_min_zero() {
+    ValidationConfig {
+        street_no_min: 0,
+        ..ValidationConfig::default()
+    }
+    .install();
+}
+
+fn with_pets(count: usize) -> Value {
+    let mut request = valid_create();
+    request["address"]["streetNo"] = 0.into();
+    request["pets"] = (0..count)
+        .map(|i| json!({ "name": format!("Pet {}", (b'a' + i as u8) as char) }))
+        .collect();
+    request
+}
+
+#[tokio::test]
+async fn street_number_0_needs_at_most_three_pets() {
+    install_street_no_min_zero();
+    let routes = routes();
+
+    let response = post_json(&routes, "/create-path", &with_pets(3)).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = post_json(&routes, "/create-path", &with_pets(4)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.streetNo", "street_no_required")])
+    );
+}
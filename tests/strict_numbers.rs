@@ -0,0 +1,43 @@
+//! `strict_numbers` turns the coercion of `address.streetNo` off: a number in a string is a
+//! deserialization error again. In a binary of its own since the installed configuration is
+//! global.
+
+mod common;
+
+use common::{json_body, post_json, routes, send, valid_create};
+use example_rust_json_input_validation::config::ValidationConfig;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn strict_numbers_reject_numbers_in_strings() {
+    ValidationConfig {
+        strict_numbers: true,
+        ..ValidationConfig::DEFAULT
+    }
+    .install();
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["streetNo"] = "4".into();
+    for path in ["/create-path", "/create-validator"] {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        assert!(response.headers().get("x-coercions").is_none());
+        let body = json_body(&response);
+        assert!(body["errors"].is_null(), "{}: {}", path, body);
+        assert_eq!(body["path"], "address.streetNo", "{}", path);
+        assert_eq!(body["kind"], "data", "{}", path);
+    }
+
+    request["address"]["streetNo"] = 4.into();
+    let response = post_json(&routes, "/create-validator", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Form bodies can't send numbers any other way
+    let form = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("email=form%40example.com&address[street]=warpstreet&address[streetNo]=4&pets[0][name]=Chip");
+    assert_eq!(send(&routes, form).await.status(), StatusCode::CREATED);
+    ValidationConfig::default().install();
+}
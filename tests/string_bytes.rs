@@ -0,0 +1,123 @@
+//! Byte limits on string fields, on top of their length rules, and on all the strings of a
+//! request together.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator"];
+
+/// `count` combining low lines, two bytes each. Unlike accents they don't compose with the
+/// letter before them when the value is normalized, which would change its byte count.
+fn marks(count: usize) -> String {
+    "\u{332}".repeat(count)
+}
+
+async fn answer(request: &Value) -> Vec<Value> {
+    let routes = routes();
+    let mut bodies = Vec::new();
+    for path in ROUTES {
+        let response = post_json(&routes, path, request).await;
+        bodies.push(json_body(&response));
+    }
+    bodies
+}
+
+#[tokio::test]
+async fn combining_mark_bomb_within_the_length_limit_exceeds_the_bytes() {
+    let mut request = valid_create();
+    // Ten graphemes, as many as a street may have, of 61 bytes each.
+    request["address"]["street"] = "warpstreet"
+        .chars()
+        .map(|c| format!("{}{}", c, marks(30)))
+        .collect::<String>()
+        .into();
+    for body in answer(&request).await {
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("address.street", "max_bytes_exceeded")])
+        );
+        let error = error_for(&body, "address.street");
+        assert_eq!(error["params"], json!({ "max": 256, "bytes": 610 }));
+        assert_eq!(
+            error["field_errors"][0],
+            "street must be at most 256 bytes long, not 610"
+        );
+        assert_eq!(body["docs_url"], "/docs/validation#byte-limits");
+    }
+}
+
+#[tokio::test]
+async fn value_failing_length_and_bytes_reports_both() {
+    let mut request = valid_create();
+    request["address"]["street"] = format!("w{}", marks(300)).into();
+    for body in answer(&request).await {
+        let error = error_for(&body, "address.street");
+        assert_eq!(error["code"], "length_min");
+        assert_eq!(
+            error["field_errors"],
+            json!([
+                "street must be between 2 and 10 characters",
+                "street must be at most 256 bytes long, not 601"
+            ])
+        );
+    }
+}
+
+#[tokio::test]
+async fn email_and_other_strings_have_limits_of_their_own() {
+    let mut request = valid_create();
+    request["email"] = format!("{}@example.com", "a".repeat(330)).into();
+    request["bio"] = "a".repeat(5000).into();
+    for body in answer(&request).await {
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[
+                ("email", "max_bytes_exceeded"),
+                ("bio", "max_bytes_exceeded")
+            ])
+        );
+        assert_eq!(
+            error_for(&body, "email")["params"],
+            json!({ "max": 320, "bytes": 342 })
+        );
+        assert_eq!(
+            error_for(&body, "bio")["params"],
+            json!({ "max": 4096, "bytes": 5000 })
+        );
+    }
+}
+
+#[tokio::test]
+async fn long_but_legal_values_are_created() {
+    let mut request = valid_create();
+    request["address"]["street"] = "Bahnhofstr".into();
+    request["pets"][0]["name"] = "Chip the very goodie".into();
+    request["bio"] = "a".repeat(4096).into();
+    let routes = routes();
+    for path in ROUTES {
+        let response = post_json(&routes, path, &request).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn strings_legal_on_their_own_can_exceed_the_request_cap_together() {
+    let url = format!("https://example.com/{}.png", "a".repeat(3900));
+    let mut request = valid_create();
+    request["bio"] = "a".repeat(4000).into();
+    request["pets"] = (0..4)
+        .map(|i| json!({ "name": format!("Pet {}", ["a", "b", "c", "d"][i]), "photoUrl": url }))
+        .collect();
+    for body in answer(&request).await {
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[("", "max_total_bytes_exceeded")])
+        );
+        let params = &body["errors"][0]["params"];
+        assert_eq!(params["max"], 16384);
+        assert!(params["bytes"].as_u64().unwrap() > 16384, "{}", params);
+    }
+}
@@ -0,0 +1,257 @@
+//! Rules spanning several fields of a request: every one that fails is reported, next to the
+//! errors of the fields themselves.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+const PHONE: &str = "+4915112345678";
+
+/// `valid_create` reaching the owner by `contact_method`, with only the given channels.
+fn contact(contact_method: &str, email: Option<&str>, phone: Option<&str>) -> Value {
+    let mut request = valid_create();
+    request["contactMethod"] = contact_method.into();
+    request["email"] = email.map_or(Value::Null, Value::from);
+    if let Some(phone) = phone {
+        request["phone"] = phone.into();
+    }
+    request
+}
+
+#[tokio::test]
+async fn contact_method_email_with_email_is_created() {
+    let routes = routes();
+    let request = contact("email", Some("chip@example.com"), None);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn contact_method_email_without_email_requires_email() {
+    let routes = routes();
+    let request = contact("email", None, Some(PHONE));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("email", "required_when")]));
+    let error = error_for(&body, "email");
+    assert_eq!(error["params"]["contact_method"], "email");
+    assert_eq!(
+        error["field_errors"][0],
+        "email is required when contactMethod is email"
+    );
+}
+
+#[tokio::test]
+async fn contact_method_phone_with_phone_is_created() {
+    let routes = routes();
+    let request = contact("phone", None, Some(PHONE));
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["phone"], PHONE);
+}
+
+#[tokio::test]
+async fn contact_method_phone_without_phone_requires_phone() {
+    let routes = routes();
+    let request = contact("phone", Some("chip@example.com"), None);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("phone", "required_when")]));
+    assert_eq!(
+        error_for(&body, "phone")["params"]["contact_method"],
+        "phone"
+    );
+}
+
+#[tokio::test]
+async fn blank_phone_is_missing_rather_than_too_short() {
+    let routes = routes();
+    let request = contact("phone", None, Some(""));
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("phone", "required_when")]));
+}
+
+#[tokio::test]
+async fn missing_channel_is_reported_next_to_field_errors() {
+    let routes = routes();
+    let mut request = contact("phone", None, None);
+    request["bio"] = "<b>Chip's owner</b>".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("phone", "required_when"), ("bio", "html.not_allowed")])
+    );
+}
+
+#[tokio::test]
+async fn every_failing_struct_rule_is_reported() {
+    let routes = routes();
+    let mut request = contact("phone", None, None);
+    request["pets"] = json!([]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("phone", "required_when"), ("pets", "pets_count")])
+    );
+}
+
+/// `count` distinct valid pets.
+fn pets(count: usize) -> Value {
+    (0..count)
+        .map(|i| json!({ "name": format!("Pet {}", (b'a' + i as u8) as char) }))
+        .collect()
+}
+
+#[tokio::test]
+async fn empty_pets_fail_pets_count() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = json!([]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets", "pets_count")]));
+    let error = error_for(&body, "pets");
+    assert_eq!(
+        error["field_errors"][0],
+        "pets must contain between 1 and 10 pets"
+    );
+    assert_eq!(error["params"]["count"], 0);
+}
+
+#[tokio::test]
+async fn eleven_pets_are_too_many() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = pets(11);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets", "too_many_elements")]));
+    assert_eq!(error_for(&body, "pets")["params"]["max"], 10);
+}
+
+/// Lists over `pets_max` are turned away while they're read, so their elements are never
+/// validated: only the count is reported, however many of them are invalid.
+#[tokio::test]
+async fn eleven_pets_with_an_invalid_one_only_report_the_count() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = pets(11);
+    request["pets"][3]["name"] = "C".into();
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets", "too_many_elements")]));
+}
+
+#[tokio::test]
+async fn empty_pets_are_reported_next_to_an_invalid_email() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip".into();
+    request["pets"] = json!([]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("email", "email.invalid"), ("pets", "pets_count")])
+    );
+}
+
+#[tokio::test]
+async fn email_domain_in_a_pet_name_is_reported_on_the_pet() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["email"] = "chip@nacho.com".into();
+    request["pets"] = json!([{ "name": "Chip" }, { "name": "Big Nacho" }]);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("pets[1].name", "email_domain_in_pet_name")])
+    );
+    let error = error_for(&body, "pets[1].name");
+    assert_eq!(error["params"]["domain"], "nacho");
+    assert_eq!(error["params"]["fields"], json!(["email", "pets[1].name"]));
+}
+
+#[tokio::test]
+async fn more_than_three_pets_need_a_street_number() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]["streetNo"] = 0.into();
+    request["pets"] = pets(4);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("address.streetNo", "range_min"),
+            ("address.streetNo", "street_no_required"),
+        ])
+    );
+    let error = &body["errors"][1];
+    assert_eq!(
+        error["field_errors"][0],
+        "address.streetNo must be greater than 0 for more than 3 pets"
+    );
+    assert_eq!(error["params"]["pets"], 4);
+    assert_eq!(error["params"]["max_pets"], 3);
+    assert_eq!(
+        error["params"]["fields"],
+        json!(["pets", "address.streetNo"])
+    );
+}
+
+#[tokio::test]
+async fn three_pets_or_a_street_number_pass() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["pets"] = pets(4);
+    let response = post_json(&routes, "/create-path", &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    request["address"]["streetNo"] = 0.into();
+    request["pets"] = pets(3);
+    let response = post_json(&routes, "/create-path", &request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.streetNo", "range_min")])
+    );
+}
+
+#[tokio::test]
+async fn version_2_needs_a_house_number_for_more_than_three_pets() {
+    let routes = routes();
+    let request = json!({
+        "email": "chip@example.com",
+        "address": { "street": "warpstreet", "houseNumber": 0 },
+        "pets": pets(4),
+    });
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator?dry_run=true")
+        .header("content-type", "application/json")
+        .header("x-api-version", "2")
+        .body(request.to_string());
+    let response = common::send(&routes, request).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("address.houseNumber", "range_min"),
+            ("address.houseNumber", "street_no_required"),
+        ])
+    );
+}
@@ -0,0 +1,120 @@
+//! `REQUEST_TIMEOUT_MS`: work that isn't done by the deadline is dropped and answered with a
+//! `408` naming the limit.
+
+mod common;
+
+use common::{json_body, post_json, routes_with_env, valid_create, with_env};
+use example_rust_json_input_validation::bind;
+use example_rust_json_input_validation::error::{
+    with_request_handling, DefaultErrorFormatter, ErrorConfig,
+};
+use example_rust_json_input_validation::filters::{with_deadline, Deadline};
+use example_rust_json_input_validation::metrics::Metrics;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// Sets its flag when dropped, to tell that the work holding it was cancelled.
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn slow_handler_is_cancelled_and_answered_with_a_408() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let flag = dropped.clone();
+    let slow = with_env(&[("REQUEST_TIMEOUT_MS", "50")], with_deadline).and_then(
+        move |deadline: Deadline| {
+            let guard = DropFlag(flag.clone());
+            deadline.run(async move {
+                tokio::time::delay_for(Duration::from_secs(10)).await;
+                drop(guard);
+                Ok(warp::reply::Response::new("done".into()))
+            })
+        },
+    );
+    let routes = with_request_handling(
+        Arc::new(Metrics::default()),
+        ErrorConfig::default(),
+        Arc::new(DefaultErrorFormatter),
+        slow,
+    );
+
+    let started = Instant::now();
+    let response = warp::test::request().reply(&routes).await;
+    let elapsed = started.elapsed();
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    assert!(
+        elapsed >= Duration::from_millis(50) && elapsed < Duration::from_secs(2),
+        "{:?}",
+        elapsed
+    );
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "request timeout: the request didn't complete within the 50 ms limit"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#timeout");
+    assert!(body["request_id"].is_string());
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn request_within_the_limit_is_created() {
+    let routes = routes_with_env(&[("REQUEST_TIMEOUT_MS", "1000")]);
+    let response = post_json(&routes, "/create-validator", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn body_trickling_in_past_the_limit_is_a_408() {
+    let (stop, stopped) = oneshot::channel::<()>();
+    let (addr, server) = with_env(&[("REQUEST_TIMEOUT_MS", "100")], || {
+        bind(([127, 0, 0, 1], 0).into(), async {
+            stopped.await.ok();
+        })
+        .unwrap()
+    });
+    let server = tokio::spawn(server);
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let body = valid_create().to_string();
+        write!(
+            stream,
+            "POST /create-validator HTTP/1.1\r\nhost: localhost\r\n\
+             content-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            &body[..10]
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut response = vec![0; 4096];
+        let read = stream.read(&mut response).unwrap();
+        String::from_utf8_lossy(&response[..read]).into_owned()
+    });
+    let response = tokio::task::spawn_blocking(move || client.join().unwrap())
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+    assert!(
+        response.contains(
+            r#""message":"request timeout: the request didn't complete within the 100 ms limit""#
+        ),
+        "{}",
+        response
+    );
+    stop.send(()).unwrap();
+    server.await.unwrap();
+}
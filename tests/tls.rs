@@ -0,0 +1,96 @@
+//! HTTPS with `TLS_CERT_PATH` and `TLS_KEY_PATH`: the files are checked when they're loaded, and
+//! the server answers over TLS. The fixtures are a self-signed certificate for `localhost` with
+//! its key, and an unrelated key.
+
+use example_rust_json_input_validation::bind_tls;
+use example_rust_json_input_validation::config::{ConfigError, TlsConfig};
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn tls_config(cert: &str, key: &str) -> TlsConfig {
+    TlsConfig {
+        cert_path: fixture(cert),
+        key_path: fixture(key),
+    }
+}
+
+/// `GET`s `path` over TLS from `addr`, trusting only the fixture certificate.
+fn https_get(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut config = rustls::ClientConfig::new();
+    let cert = std::fs::File::open(fixture("localhost.crt")).unwrap();
+    config
+        .root_store
+        .add_pem_file(&mut BufReader::new(cert))
+        .unwrap();
+    let name = webpki::DNSNameRef::try_from_ascii_str("localhost").unwrap();
+    let mut session = rustls::ClientSession::new(&Arc::new(config), name);
+    let mut socket = TcpStream::connect(addr).unwrap();
+    let mut stream = rustls::Stream::new(&mut session, &mut socket);
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+        path
+    )
+    .unwrap();
+    let mut response = Vec::new();
+    if let Err(e) = stream.read_to_end(&mut response) {
+        assert_eq!(e.kind(), std::io::ErrorKind::ConnectionAborted, "{}", e);
+    }
+    String::from_utf8(response).unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn server_answers_over_https() {
+    let identity = tls_config("localhost.crt", "localhost.key").load().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let (addr, server) = bind_tls(([127, 0, 0, 1], 0).into(), identity, async {
+        stopped.await.ok();
+    });
+    let server = tokio::spawn(server);
+    let response = tokio::task::spawn_blocking(move || https_get(addr, "/health/live"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.ends_with(r#"{"status":"ok"}"#), "{}", response);
+    stop.send(()).unwrap();
+    server.await.unwrap();
+}
+
+#[test]
+fn key_of_another_certificate_is_rejected() {
+    let error = tls_config("localhost.crt", "other.key")
+        .load()
+        .err()
+        .unwrap();
+    assert!(matches!(error, ConfigError::InvalidTls(_)), "{:?}", error);
+}
+
+#[test]
+fn missing_file_is_named() {
+    let error = tls_config("missing.crt", "localhost.key")
+        .load()
+        .err()
+        .unwrap();
+    match error {
+        ConfigError::UnreadableTlsFile { path, .. } => assert!(path.ends_with("missing.crt")),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn file_that_is_no_pem_is_rejected() {
+    let error = tls_config("localhost.key", "localhost.key")
+        .load()
+        .err()
+        .unwrap();
+    assert!(matches!(error, ConfigError::InvalidTls(_)), "{:?}", error);
+}
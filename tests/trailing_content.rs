@@ -0,0 +1,73 @@
+//! Content after the JSON document: whitespace is fine, anything else is rejected with the byte
+//! offset it starts at.
+
+mod common;
+
+use common::{json_body, post_raw, routes, valid_create};
+use warp::http::StatusCode;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator"];
+
+#[tokio::test]
+async fn trailing_newline_and_whitespace_are_accepted() {
+    for tail in &["\n", " \r\n\t "] {
+        for path in ROUTES {
+            let routes = routes();
+            let body = format!("{}{}", valid_create(), tail);
+            let response = post_raw(&routes, path, &body).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::CREATED,
+                "{} {:?}",
+                path,
+                tail
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn second_json_object_is_rejected_at_its_offset() {
+    let routes = routes();
+    let document = valid_create().to_string();
+    let body = format!("{}{}", document, r#"{"email":"evil@example.com"}"#);
+    for path in ROUTES {
+        let response = post_raw(&routes, path, &body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            body["message"],
+            format!(
+                "unexpected content after the JSON document at byte {}",
+                document.len()
+            ),
+            "{}",
+            path
+        );
+        assert_eq!(
+            body["docs_url"], "/docs/validation#malformed-body",
+            "{}",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn text_after_whitespace_is_rejected_where_it_starts() {
+    let routes = routes();
+    let document = valid_create().to_string();
+    let body = format!("{}\n  GARBAGE", document);
+    for path in ROUTES {
+        let response = post_raw(&routes, path, &body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        assert_eq!(
+            json_body(&response)["message"],
+            format!(
+                "unexpected content after the JSON document at byte {}",
+                document.len() + 3
+            ),
+            "{}",
+            path
+        );
+    }
+}
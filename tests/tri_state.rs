@@ -0,0 +1,166 @@
+//! The fields of `PATCH /update` are left out, sent as `null` or sent with a value, and each of
+//! the three is told apart, validated and serialized on its own.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, send};
+use example_rust_json_input_validation::models::{Maybe, UpdateRequest};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+async fn patch(body: &Value) -> warp::http::Response<bytes::Bytes> {
+    let routes = common::routes();
+    let request = warp::test::request()
+        .method("PATCH")
+        .path("/update")
+        .header("content-type", "application/json")
+        .body(body.to_string());
+    send(&routes, request).await
+}
+
+/// The `Debug` of the `UpdateRequest` the route was called with.
+async fn called_with(body: &Value) -> String {
+    let response = patch(body).await;
+    assert_eq!(response.status(), StatusCode::OK, "{}", body);
+    String::from_utf8_lossy(response.body()).into_owned()
+}
+
+fn update(body: Value) -> UpdateRequest {
+    serde_json::from_value(body).unwrap()
+}
+
+#[tokio::test]
+async fn absent_fields_are_absent() {
+    assert_eq!(
+        called_with(&json!({})).await,
+        "called with: UpdateRequest { email: Absent, address: Absent, pets: Absent, bio: Absent }"
+    );
+}
+
+#[tokio::test]
+async fn null_is_refused_for_every_field_but_bio() {
+    let body = json!({ "email": null, "address": null, "pets": null, "bio": null });
+    let response = patch(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("email", "cannot_be_null"),
+            ("address", "cannot_be_null"),
+            ("pets", "cannot_be_null"),
+        ])
+    );
+    assert_eq!(
+        error_for(&body, "pets")["field_errors"][0],
+        "pets can't be cleared, leave it out to keep it unchanged"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#partial-updates");
+}
+
+#[tokio::test]
+async fn null_bio_clears_it() {
+    assert_eq!(
+        called_with(&json!({ "bio": null })).await,
+        "called with: UpdateRequest { email: Absent, address: Absent, pets: Absent, bio: Null }"
+    );
+}
+
+#[tokio::test]
+async fn values_are_validated() {
+    for (body, field, code) in [
+        (json!({ "email": "chip" }), "email", "email.invalid"),
+        (json!({ "pets": [] }), "pets", "pets_count"),
+        (
+            json!({ "pets": [{ "name": "C" }] }),
+            "pets[0].name",
+            "length_min",
+        ),
+        (json!({ "bio": "<b>" }), "bio", "html.not_allowed"),
+    ] {
+        let response = patch(&body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(field_codes(&json_body(&response)), pairs(&[(field, code)]));
+    }
+}
+
+#[tokio::test]
+async fn valid_values_are_taken() {
+    for (body, debug) in [
+        (
+            json!({ "email": "chip@example.com" }),
+            r#"email: Value("chip@example.com")"#,
+        ),
+        (
+            json!({ "pets": [{ "name": "Chip" }] }),
+            r#"pets: Value(BoundedVec { elements: [Pet { name: "Chip""#,
+        ),
+        (json!({ "bio": "hi" }), r#"bio: Value("hi")"#),
+    ] {
+        let called = called_with(&body).await;
+        assert!(called.contains(debug), "{}", called);
+    }
+}
+
+#[tokio::test]
+async fn address_in_the_wrapper_is_validated_as_a_whole_struct() {
+    let called =
+        called_with(&json!({ "address": { "street": "warpstreet", "streetNo": 1 } })).await;
+    assert!(
+        called.contains(r#"address: Value(Address { street: "warpstreet", street_no: Value(1)"#),
+        "{}",
+        called
+    );
+
+    let response = patch(&json!({ "address": { "street": "w", "streetNo": 0 } })).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[
+            ("address.street", "length_min"),
+            ("address.streetNo", "range_min")
+        ])
+    );
+
+    let response = patch(&json!({ "address": { "street": "warpstreet" } })).await;
+    let body = json_body(&response);
+    assert_eq!(body["path"], "address");
+    assert_eq!(
+        body["message"],
+        "JSON path error: address: missing field `streetNo` at line 1 column 34"
+    );
+}
+
+#[tokio::test]
+async fn null_and_nested_errors_are_reported_together() {
+    let response =
+        patch(&json!({ "email": null, "address": { "street": "w", "streetNo": 1 } })).await;
+    assert_eq!(
+        field_codes(&json_body(&response)),
+        pairs(&[
+            ("email", "cannot_be_null"),
+            ("address.street", "length_min")
+        ])
+    );
+}
+
+#[test]
+fn each_state_deserializes_apart() {
+    let request = update(json!({ "email": null, "bio": "hi" }));
+    assert_eq!(request.email, Maybe::Null);
+    assert_eq!(request.bio, Maybe::Value("hi".to_string()));
+    assert!(request.address.is_absent());
+    assert!(request.pets.is_absent());
+}
+
+#[test]
+fn absent_is_left_out_and_null_kept_when_serialized() {
+    for body in [
+        json!({}),
+        json!({ "bio": null }),
+        json!({ "email": "chip@example.com", "bio": null }),
+        json!({ "address": { "street": "warpstreet", "streetNo": 1, "phone": null } }),
+    ] {
+        let serialized = serde_json::to_value(update(body.clone())).unwrap();
+        assert_eq!(serialized, body);
+    }
+}
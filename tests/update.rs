@@ -0,0 +1,118 @@
+//! `PATCH /update`, which takes any subset of the fields of a create request and validates the
+//! ones it gets.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, post_raw, send, valid_create};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+async fn patch(body: &Value) -> warp::http::Response<bytes::Bytes> {
+    let routes = common::routes();
+    let request = warp::test::request()
+        .method("PATCH")
+        .path("/update")
+        .header("content-type", "application/json")
+        .body(body.to_string());
+    send(&routes, request).await
+}
+
+#[tokio::test]
+async fn valid_update_passes_the_validated_json_filter() {
+    let response = patch(&json!({ "email": "chip@example.com" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8_lossy(response.body()).into_owned();
+    assert!(body.starts_with("called with: UpdateRequest"), "{}", body);
+}
+
+#[tokio::test]
+async fn update_that_does_not_deserialize_names_the_path() {
+    let response = patch(&json!({ "address": { "street": "warpstreet", "streetNo": [] } })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["path"], "address.streetNo");
+    assert_eq!(body["kind"], "data");
+    assert!(body["errors"].is_null());
+}
+
+#[tokio::test]
+async fn invalid_update_fails_validation_in_the_filter() {
+    let response = patch(&json!({ "email": "chip" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("email", "email.invalid")]));
+}
+
+/// The filter isn't tied to updates: bookings go through the same one.
+#[tokio::test]
+async fn other_request_types_share_the_filter() {
+    let routes = common::routes();
+    let response = post_raw(
+        &routes,
+        "/create-booking",
+        r#"{"name":"Party","minGuests":"many","maxGuests":3}"#,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(json_body(&response)["path"], "minGuests");
+}
+
+#[tokio::test]
+async fn empty_update_is_valid() {
+    let response = patch(&json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8_lossy(response.body()).into_owned();
+    assert!(body.contains("email: Absent, address: Absent"), "{}", body);
+}
+
+#[tokio::test]
+async fn update_with_only_a_bad_email_reports_only_the_email() {
+    let response = patch(&json!({ "email": "not-an-email" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("email", "email.invalid")]));
+    assert_eq!(body["errors"][0]["rejected_value"], "not-an-email");
+}
+
+#[tokio::test]
+async fn update_reports_email_and_bio_errors_like_create() {
+    let routes = common::routes();
+    let long = "a".repeat(5000);
+    let long_email = format!("{}@example.com", "a".repeat(320));
+    let cases = [
+        ("email", "nope"),
+        ("email", long_email.as_str()),
+        ("bio", "<b>hi</b>"),
+        ("bio", long.as_str()),
+    ];
+    for &(field, value) in &cases {
+        let mut create = valid_create();
+        create[field] = value.into();
+        let created = json_body(&post_json(&routes, "/create-path", &create).await);
+        let updated = json_body(&patch(&json!({ field: value })).await);
+        assert_eq!(updated["errors"], created["errors"], "{}", field);
+    }
+}
+
+#[tokio::test]
+async fn address_error_in_an_update_is_reported_on_its_nested_path() {
+    let response = patch(&json!({ "address": { "street": "w", "streetNo": 1 } })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.street", "length_min")])
+    );
+    assert_eq!(
+        body["errors"][0]["field_errors"][0],
+        "street must be between 2 and 10 characters"
+    );
+}
+
+#[tokio::test]
+async fn null_email_is_not_taken_for_an_absent_one() {
+    let response = patch(&json!({ "email": null })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("email", "cannot_be_null")]));
+}
@@ -0,0 +1,122 @@
+//! JSON bodies must be UTF-8: a leading UTF-8 byte order mark is stripped, anything else is
+//! rejected with `invalid_encoding` saying what's wrong, on every route reading the body itself.
+
+mod common;
+
+use bytes::Bytes;
+use common::{capture_logs, field_codes, json_body, pairs, routes, send, valid_create};
+use serde_json::json;
+use warp::http::{Response, StatusCode};
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator", "/create-fast"];
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+async fn post_bytes(path: &str, body: Vec<u8>) -> Response<Bytes> {
+    let request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-type", "application/json")
+        .body(body);
+    send(&routes(), request).await
+}
+
+/// `valid_create` with a street in latin-1, whose `ß` is at byte 26.
+fn latin_1() -> Vec<u8> {
+    valid_create()
+        .to_string()
+        .replace("warpstreet", "Stra\u{df}e")
+        .chars()
+        .map(|c| c as u32 as u8)
+        .collect()
+}
+
+fn utf_16(little_endian: bool, bom: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    if bom {
+        body.extend(if little_endian {
+            [0xFF, 0xFE]
+        } else {
+            [0xFE, 0xFF]
+        });
+    }
+    for unit in valid_create().to_string().encode_utf16() {
+        body.extend(if little_endian {
+            unit.to_le_bytes()
+        } else {
+            unit.to_be_bytes()
+        });
+    }
+    body
+}
+
+#[tokio::test]
+async fn body_with_a_utf_8_bom_is_created() {
+    let (logs, _guard) = capture_logs();
+    for path in ROUTES {
+        let body = [BOM, valid_create().to_string().as_bytes()].concat();
+        let response = post_bytes(path, body).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", path);
+        assert_eq!(json_body(&response)["email"], "chip@example.com");
+    }
+    let logs = logs.contents();
+    assert_eq!(
+        logs.matches("stripped the UTF-8 byte order mark of a JSON body")
+            .count(),
+        ROUTES.len(),
+        "{}",
+        logs
+    );
+}
+
+#[tokio::test]
+async fn latin_1_is_rejected_at_its_first_invalid_byte() {
+    for path in ROUTES {
+        let response = post_bytes(path, latin_1()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+        let body = json_body(&response);
+        assert_eq!(
+            body["message"], "the body is not valid UTF-8: invalid byte sequence at byte 26",
+            "{}",
+            path
+        );
+        assert_eq!(field_codes(&body), pairs(&[("", "invalid_encoding")]));
+        assert_eq!(body["errors"][0]["params"], json!({ "offset": 26 }));
+        assert_eq!(body["docs_url"], "/docs/validation#invalid-encoding");
+    }
+}
+
+#[tokio::test]
+async fn offset_counts_a_stripped_bom() {
+    let response = post_bytes("/create-path", [BOM, &latin_1()].concat()).await;
+    let body = json_body(&response);
+    assert_eq!(body["errors"][0]["params"], json!({ "offset": 29 }));
+}
+
+#[tokio::test]
+async fn utf_16_is_rejected_naming_it_with_or_without_a_bom() {
+    for path in ROUTES {
+        for &(little_endian, bom, encoding) in &[
+            (true, true, "UTF-16LE"),
+            (false, true, "UTF-16BE"),
+            (true, false, "UTF-16LE"),
+            (false, false, "UTF-16BE"),
+        ] {
+            let response = post_bytes(path, utf_16(little_endian, bom)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+            let body = json_body(&response);
+            assert_eq!(
+                body["message"],
+                format!(
+                    "the body is encoded as {}, JSON bodies must be UTF-8",
+                    encoding
+                ),
+                "{} {}",
+                path,
+                bom
+            );
+            assert_eq!(field_codes(&body), pairs(&[("", "invalid_encoding")]));
+            assert_eq!(body["errors"][0]["params"], json!({ "encoding": encoding }));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! `POST /validate-field`: every field of a create request with rules of its own is checked
+//! alone with the errors a full validation gives it, and the fields it can't check alone are
+//! refused.
+
+mod common;
+
+use common::{error_for, field_codes, json_body, pairs, post_json, routes, valid_create};
+use example_rust_json_input_validation::models::{FieldRules, CREATE_FIELD_RULES};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+/// A valid and an invalid value, with the code it fails with, for each field checked alone.
+fn cases() -> Vec<(&'static str, Value, Value, &'static str)> {
+    vec![
+        (
+            "email",
+            json!("chip@example.com"),
+            json!("chip"),
+            "email.invalid",
+        ),
+        (
+            "phone",
+            json!("+4915112345678"),
+            json!("12"),
+            "phone.invalid",
+        ),
+        (
+            "address.street",
+            json!("warpstreet"),
+            json!("w"),
+            "length_min",
+        ),
+        ("address.streetNo", json!(1), json!(0), "range_min"),
+        (
+            "address.phone",
+            json!("+4915112345678"),
+            json!("x"),
+            "phone.invalid",
+        ),
+        ("pets[0].name", json!("Chip"), json!("C"), "length_min"),
+        (
+            "pets[0].photoUrl",
+            json!("https://example.com/a.png"),
+            json!("nope"),
+            "url.invalid",
+        ),
+        (
+            "pets[0].species",
+            json!("dog"),
+            json!("dragon"),
+            "invalid_enum_value",
+        ),
+        (
+            "pets[0].birthdate",
+            json!("2020-01-01"),
+            json!("2999-01-01"),
+            "birthdate.future",
+        ),
+        ("bio", json!("hello"), json!("<b>x</b>"), "html.not_allowed"),
+        (
+            "birthdate",
+            json!("1990-01-01"),
+            json!("2999-01-01"),
+            "birthdate.future",
+        ),
+        (
+            "password",
+            json!("Correct-Horse-9"),
+            json!("a"),
+            "password_too_short",
+        ),
+    ]
+}
+
+async fn validate(field: &str, value: &Value) -> warp::http::Response<bytes::Bytes> {
+    let request = json!({ "field": field, "value": value });
+    post_json(&routes(), "/validate-field", &request).await
+}
+
+/// `request` with `value` at a dotted path like `pets[0].name`.
+fn set(request: &mut Value, path: &str, value: Value) {
+    let mut target = request;
+    for segment in path.split('.') {
+        target = match segment.strip_suffix(']') {
+            Some(indexed) => {
+                let (name, index) = indexed.split_at(indexed.find('[').unwrap());
+                &mut target[name][index[1..].parse::<usize>().unwrap()]
+            }
+            None => &mut target[segment],
+        };
+    }
+    *target = value;
+}
+
+#[test]
+fn every_field_with_rules_of_its_own_has_a_case() {
+    let own: Vec<String> = CREATE_FIELD_RULES
+        .iter()
+        .filter(|(_, rules)| matches!(rules, FieldRules::Own))
+        .map(|(path, _)| path.replace("[]", "[0]"))
+        .collect();
+    let covered: Vec<String> = cases().iter().map(|case| case.0.to_owned()).collect();
+    assert_eq!(covered, own);
+}
+
+#[tokio::test]
+async fn valid_values_are_valid() {
+    for (field, valid, _, _) in cases() {
+        let response = validate(field, &valid).await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", field);
+        assert_eq!(json_body(&response), json!({ "valid": true }), "{}", field);
+    }
+}
+
+#[tokio::test]
+async fn invalid_values_get_the_error_a_full_validation_gives_them() {
+    for (field, _, invalid, code) in cases() {
+        let response = validate(field, &invalid).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", field);
+        let alone = json_body(&response);
+        assert_eq!(field_codes(&alone), pairs(&[(field, code)]));
+
+        let mut request = valid_create();
+        set(&mut request, field, invalid);
+        let full = json_body(&post_json(&routes(), "/create-validator", &request).await);
+        assert_eq!(
+            error_for(&alone, field),
+            error_for(&full, field),
+            "{}",
+            field
+        );
+    }
+}
+
+#[tokio::test]
+async fn pet_fields_are_reported_at_the_index_asked_for() {
+    let response = validate("pets[2].name", &json!("C")).await;
+    let body = json_body(&response);
+    assert_eq!(field_codes(&body), pairs(&[("pets[2].name", "length_min")]));
+    assert_eq!(body["failed_sections"], json!(["pets"]));
+}
+
+#[tokio::test]
+async fn fields_only_checked_by_struct_rules_require_full_validation() {
+    for (field, rule) in &[("contactMethod", "required_when"), ("pets", "pets_count")] {
+        let response = validate(field, &json!("email")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", field);
+        let body = json_body(&response);
+        assert_eq!(
+            field_codes(&body),
+            pairs(&[(field, "requires_full_validation")])
+        );
+        assert_eq!(error_for(&body, field)["params"]["rules"], json!([rule]));
+        assert_eq!(body["docs_url"], "/docs/validation#validate-field");
+    }
+}
+
+#[tokio::test]
+async fn unknown_paths_are_unknown_field_paths() {
+    for field in &["nope", "address.zip", "pets.name"] {
+        let response = validate(field, &json!(1)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", field);
+        let body = json_body(&response);
+        assert_eq!(field_codes(&body), pairs(&[(field, "unknown_field_path")]));
+        assert_eq!(
+            body["message"],
+            format!(
+                "unknown field path {}, expected a field of a create request like \
+                 address.street or pets[0].name",
+                field
+            )
+        );
+    }
+}
+
+#[tokio::test]
+async fn value_of_the_wrong_type_is_a_path_error_on_the_field() {
+    let response = validate("email", &json!(7)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(body["kind"], "data");
+    assert_eq!(body["path"], "email");
+    assert_eq!(
+        body["message"],
+        "JSON path error: email: invalid type: integer `7`, expected a string"
+    );
+}
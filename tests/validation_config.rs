@@ -0,0 +1,85 @@
+//! Validation bounds from `VALIDATION_CONFIG_FILE`: the same request passes with one file and
+//! fails with another, and files nothing could pass are refused. In a binary of its own since the
+//! installed configuration is global.
+
+mod common;
+
+use common::{field_codes, json_body, pairs, post_json, routes, valid_create};
+use example_rust_json_input_validation::config::{ConfigError, ValidationConfig};
+use std::path::PathBuf;
+use warp::http::StatusCode;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[tokio::test]
+async fn same_request_passes_the_defaults_and_fails_a_stricter_config() {
+    ValidationConfig::default().install();
+    let routes = routes();
+    let response = post_json(&routes, "/create-path", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let strict = ValidationConfig::load(&fixture("strict_validation.json")).unwrap();
+    assert_eq!(strict.street_max_length, 8);
+    assert_eq!(strict.pet_name_max_length, 20);
+    strict.install();
+    let response = post_json(&routes, "/create-path", &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[
+            ("address.street", "length_max"),
+            ("address.streetNo", "range_min"),
+            ("pets", "pets_count"),
+            ("pets[0].name", "length_min"),
+        ])
+    );
+    assert_eq!(
+        body["errors"][0]["field_errors"][0],
+        "street must be between 2 and 8 characters"
+    );
+    ValidationConfig::default().install();
+}
+
+#[test]
+fn minimum_above_maximum_is_refused() {
+    let error = ValidationConfig::load(&fixture("street_min_above_max.json")).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .ends_with("street_min_length (12) is greater than street_max_length (10)"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn zero_maximum_is_refused() {
+    let error = ValidationConfig::load(&fixture("zero_pets_max.json")).unwrap_err();
+    assert!(
+        matches!(&error, ConfigError::InvalidValidationConfig { reason, .. }
+            if reason == "pets_max must be greater than 0"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn unknown_keys_and_missing_files_are_refused() {
+    let error = ValidationConfig::load(&fixture("valid_create.json")).unwrap_err();
+    assert!(
+        error.to_string().contains("unknown field `email`"),
+        "{}",
+        error
+    );
+    let error = ValidationConfig::load(&fixture("missing.json")).unwrap_err();
+    assert!(
+        matches!(error, ConfigError::UnreadableValidationConfig { .. }),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,102 @@
+//! `X-Api-Version` on `/create-validator`: version 1 and 2 bodies and the field names each
+//! reports errors under.
+
+mod common;
+
+use bytes::Bytes;
+use common::{field_codes, json_body, pairs, routes, send, valid_create};
+use serde_json::{json, Value};
+use warp::http::{Response, StatusCode};
+
+/// Posts `body` to fresh routes, so the email isn't taken yet, as `version` if there is one.
+async fn create_as(version: Option<&str>, body: &Value) -> Response<Bytes> {
+    let request = warp::test::request()
+        .method("POST")
+        .path("/create-validator")
+        .header("content-type", "application/json")
+        .body(body.to_string());
+    let request = match version {
+        Some(version) => request.header("x-api-version", version),
+        None => request,
+    };
+    send(&routes(), request).await
+}
+
+/// `valid_create` as version 2 sends it.
+fn valid_create_v2() -> Value {
+    json!({
+        "email": "chip@example.com",
+        "address": { "street": "warpstreet", "houseNumber": 1 },
+        "pets": [{ "name": "Chip" }]
+    })
+}
+
+#[tokio::test]
+async fn same_request_is_created_under_both_versions() {
+    for (version, body) in &[
+        (None, valid_create()),
+        (Some("1"), valid_create()),
+        (Some("2"), valid_create_v2()),
+    ] {
+        let response = create_as(*version, body).await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{:?}", version);
+        let created = json_body(&response);
+        assert_eq!(created["email"], "chip@example.com", "{:?}", version);
+        assert_eq!(created["pets"][0]["name"], "Chip", "{:?}", version);
+    }
+}
+
+#[tokio::test]
+async fn version_2_phone_is_accepted() {
+    let mut request = valid_create_v2();
+    request["phone"] = "+4915112345678".into();
+    let response = create_as(Some("2"), &request).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(json_body(&response)["phone"], "+4915112345678");
+}
+
+#[tokio::test]
+async fn errors_name_the_fields_of_the_version_sent() {
+    let mut request = valid_create_v2();
+    request["address"]["houseNumber"] = 0.into();
+    let response = create_as(Some("2"), &request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.houseNumber", "range_min")])
+    );
+}
+
+#[tokio::test]
+async fn unknown_version_lists_the_supported_ones() {
+    let response = create_as(Some("3"), &valid_create()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        body["message"],
+        "unsupported API version 3, supported versions are 1, 2"
+    );
+    assert_eq!(body["docs_url"], "/docs/validation#api-version");
+}
+
+#[tokio::test]
+async fn version_2_field_is_unknown_under_version_1() {
+    let response = create_as(Some("1"), &valid_create_v2()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.houseNumber", "unknown_field")])
+    );
+}
+
+#[tokio::test]
+async fn version_1_field_is_unknown_under_version_2() {
+    let response = create_as(Some("2"), &valid_create()).await;
+    let body = json_body(&response);
+    assert_eq!(
+        field_codes(&body),
+        pairs(&[("address.streetNo", "unknown_field")])
+    );
+}
@@ -0,0 +1,101 @@
+//! `GET /ws/create`: every text message is a create request, answered in order with the created
+//! ID or the error response `/create-validator` would have sent.
+
+mod common;
+
+use common::{routes, routes_with_env, valid_create};
+use serde_json::Value;
+use warp::test::WsClient;
+use warp::ws::Message;
+
+async fn connect<F>(routes: F) -> WsClient
+where
+    F: warp::Filter<Extract = (warp::reply::Response,), Error = std::convert::Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    warp::test::ws()
+        .path("/ws/create")
+        .handshake(routes)
+        .await
+        .unwrap()
+}
+
+/// The next message on `client`, which must be a JSON text message.
+async fn reply(client: &mut WsClient) -> Value {
+    let message = client.recv().await.unwrap();
+    let text = message
+        .to_str()
+        .unwrap_or_else(|()| panic!("not a text message: {:?}", message));
+    serde_json::from_str(text).unwrap()
+}
+
+#[tokio::test]
+async fn each_message_gets_its_reply_in_order() {
+    let mut client = connect(routes()).await;
+    let mut invalid = valid_create();
+    invalid["email"] = "chip".into();
+    client.send_text(valid_create().to_string()).await;
+    client.send_text(r#"{"email":"#).await;
+    client.send_text(invalid.to_string()).await;
+
+    let created = reply(&mut client).await;
+    assert_eq!(created["ok"], true);
+    assert!(uuid::Uuid::parse_str(created["id"].as_str().unwrap()).is_ok());
+
+    let malformed = reply(&mut client).await;
+    assert_eq!(
+        malformed["message"],
+        "JSON path error: email: EOF while parsing a value at line 1 column 9"
+    );
+    assert_eq!(malformed["path"], "email");
+
+    let failed = reply(&mut client).await;
+    assert_eq!(failed["message"], "field errors");
+    assert_eq!(failed["errors"][0]["field"], "email");
+    assert_eq!(failed["errors"][0]["code"], "email.invalid");
+    assert_eq!(failed["request_id"], malformed["request_id"]);
+}
+
+#[tokio::test]
+async fn binary_and_oversized_messages_get_error_replies_and_the_socket_stays_open() {
+    let mut client = connect(routes_with_env(&[("WS_MAX_MESSAGE_BYTES", "200")])).await;
+    client.send(Message::binary(vec![1, 2, 3])).await;
+    client
+        .send_text(format!("{:300}", valid_create().to_string()))
+        .await;
+    client.send_text(valid_create().to_string()).await;
+
+    assert_eq!(
+        reply(&mut client).await["message"],
+        "unsupported media type binary message, expected application/json"
+    );
+    let too_large = reply(&mut client).await;
+    assert_eq!(
+        too_large["message"],
+        "payload too large: the body exceeds the 200 byte limit of /ws/create"
+    );
+    assert_eq!(too_large["docs_url"], "/docs/validation#payload-too-large");
+    assert_eq!(reply(&mut client).await["ok"], true);
+}
+
+#[tokio::test]
+async fn ping_is_ponged() {
+    let mut client = connect(routes()).await;
+    client.send(Message::ping(b"still there?".to_vec())).await;
+    let pong = client.recv().await.unwrap();
+    assert!(pong.is_pong(), "{:?}", pong);
+    assert_eq!(pong.as_bytes(), b"still there?");
+}
+
+#[tokio::test]
+async fn socket_closes_when_the_client_closes_it() {
+    let mut client = connect(routes()).await;
+    client.send_text(valid_create().to_string()).await;
+    assert_eq!(reply(&mut client).await["ok"], true);
+    client.send(Message::close()).await;
+    assert!(client.recv().await.is_err());
+    client.recv_closed().await.unwrap();
+}
@@ -0,0 +1,87 @@
+//! Fields are camelCase on the wire, and errors name them that way whether serde or a validator
+//! reported them.
+
+mod common;
+
+use common::{json_body, post_json, routes, valid_create};
+use serde_json::Value;
+
+const ROUTES: &[&str] = &["/create-path", "/create-validator"];
+
+/// The path a body failing to deserialize is reported on.
+async fn deserialization_path(request: &Value) -> Vec<String> {
+    let routes = routes();
+    let mut paths = Vec::new();
+    for path in ROUTES {
+        let body = json_body(&post_json(&routes, path, request).await);
+        assert_eq!(body["kind"], "data", "{}: {}", path, body);
+        paths.push(body["path"].as_str().unwrap().to_owned());
+    }
+    paths
+}
+
+/// The field the single validation error of a body is reported on.
+async fn validation_field(request: &Value) -> Vec<String> {
+    let routes = routes();
+    let mut fields = Vec::new();
+    for path in ROUTES {
+        let body = json_body(&post_json(&routes, path, request).await);
+        assert_eq!(body["message"], "field errors", "{}: {}", path, body);
+        assert_eq!(body["errors"].as_array().unwrap().len(), 1, "{}", body);
+        fields.push(body["errors"][0]["field"].as_str().unwrap().to_owned());
+    }
+    fields
+}
+
+#[tokio::test]
+async fn street_number_is_address_street_no_for_serde_and_validator() {
+    let mut mistyped = valid_create();
+    mistyped["address"]["streetNo"] = serde_json::json!([1]);
+    let mut out_of_range = valid_create();
+    out_of_range["address"]["streetNo"] = 0.into();
+
+    let expected = ["address.streetNo", "address.streetNo"];
+    assert_eq!(deserialization_path(&mistyped).await, expected);
+    assert_eq!(validation_field(&out_of_range).await, expected);
+}
+
+#[tokio::test]
+async fn photo_url_is_pets_index_photo_url_for_serde_and_validator() {
+    let mut mistyped = valid_create();
+    mistyped["pets"][0]["photoUrl"] = 5.into();
+    let mut invalid = valid_create();
+    invalid["pets"][0]["photoUrl"] = "nope".into();
+
+    let expected = ["pets[0].photoUrl", "pets[0].photoUrl"];
+    assert_eq!(deserialization_path(&mistyped).await, expected);
+    assert_eq!(validation_field(&invalid).await, expected);
+}
+
+#[tokio::test]
+async fn snake_case_names_are_unknown_fields() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["address"]
+        .as_object_mut()
+        .unwrap()
+        .remove("streetNo");
+    request["address"]["street_no"] = 1.into();
+    for path in ROUTES {
+        let body = json_body(&post_json(&routes, path, &request).await);
+        assert_eq!(body["errors"][0]["field"], "address.street_no", "{}", path);
+        assert_eq!(body["errors"][0]["code"], "unknown_field", "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn struct_rules_name_the_wire_fields_they_relate() {
+    let routes = routes();
+    let mut request = valid_create();
+    request["contactMethod"] = "phone".into();
+    let body = json_body(&post_json(&routes, "/create-validator", &request).await);
+    assert_eq!(body["errors"][0]["field"], "phone");
+    assert_eq!(
+        body["errors"][0]["params"]["fields"],
+        serde_json::json!(["contactMethod", "phone"])
+    );
+}